@@ -0,0 +1,142 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, BufRead},
+    ops::Bound,
+};
+
+use crate::complete::{Completer, Suggestion};
+
+/// Alternative spellings and abbreviations of words, each standing for a
+/// canonical form, e.g. "colour" for "color": the dictionary stores only the
+/// canonical forms, and queries are routed to them.
+///
+/// ```
+/// use prefix_tree::{Aliases, Completer, Trie};
+///
+/// let aliases = Aliases::read("colour=color\nfavourite=favorite".as_bytes()).unwrap();
+/// assert_eq!(aliases.canonical("colour"), "color");
+///
+/// let trie: Trie = ["color", "colorful", "favorite"].into_iter().collect();
+/// let words = aliases.over(&trie).show_alias(true).complete("colou", 10);
+/// assert_eq!(words[0].word, "colour");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Aliases {
+    // Sorted, so the aliases starting with a prefix are a range.
+    canonical: BTreeMap<String, String>,
+}
+
+impl Aliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads `alias=canonical` lines; blank lines and lines starting with `#`
+    /// are skipped, as are lines without a `=`.
+    pub fn read(input: impl BufRead) -> io::Result<Self> {
+        let mut aliases = Self::new();
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            if let Some((alias, canonical)) = line.split_once('=') {
+                aliases.insert(alias.trim(), canonical.trim());
+            }
+        }
+        Ok(aliases)
+    }
+
+    pub fn insert(&mut self, alias: &str, canonical: &str) {
+        if !alias.is_empty() && !canonical.is_empty() {
+            self.canonical
+                .insert(alias.to_string(), canonical.to_string());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.canonical.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.canonical.is_empty()
+    }
+
+    /// The canonical form of `word`, `word` itself if it isn't an alias.
+    pub fn canonical<'a>(&'a self, word: &'a str) -> &'a str {
+        self.canonical.get(word).map_or(word, String::as_str)
+    }
+
+    /// The aliases starting with `prefix`, with their canonical forms.
+    pub fn starting_with<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.canonical
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(move |(alias, _)| alias.starts_with(prefix))
+            .map(|(alias, canonical)| (alias.as_str(), canonical.as_str()))
+    }
+
+    /// Completions of `completer` that also complete the aliases, with their
+    /// canonical forms.
+    pub fn over<'a>(&'a self, completer: &'a dyn Completer) -> Aliased<'a> {
+        Aliased {
+            completer,
+            aliases: self,
+            show_alias: false,
+        }
+    }
+}
+
+/// Completions routed through [`Aliases`], see [`Aliases::over`].
+pub struct Aliased<'a> {
+    completer: &'a dyn Completer,
+    aliases: &'a Aliases,
+    show_alias: bool,
+}
+
+impl Aliased<'_> {
+    /// Shows a word completing an alias as the alias the user was typing,
+    /// instead of its canonical form.
+    pub fn show_alias(mut self, show_alias: bool) -> Self {
+        self.show_alias = show_alias;
+        self
+    }
+}
+
+impl Completer for Aliased<'_> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        let mut suggestions = self
+            .completer
+            .complete(self.aliases.canonical(prefix), limit);
+        for (alias, canonical) in self.aliases.starting_with(prefix) {
+            // Only aliases of stored words are offered, at their weight.
+            let stored = self
+                .completer
+                .complete(canonical, usize::MAX)
+                .into_iter()
+                .find(|suggestion| suggestion.word == canonical);
+            if let Some(mut suggestion) = stored {
+                if self.show_alias {
+                    suggestion.word = alias.to_string();
+                }
+                suggestions.push(suggestion);
+            }
+        }
+        // An alias may complete to a word the prefix itself completes.
+        let mut weights: HashMap<String, u64> = HashMap::new();
+        for suggestion in suggestions {
+            let weight = weights.entry(suggestion.word).or_default();
+            *weight = (*weight).max(suggestion.weight);
+        }
+        let mut suggestions: Vec<Suggestion> = weights
+            .into_iter()
+            .map(|(word, weight)| Suggestion { word, weight })
+            .collect();
+        suggestions.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.word.cmp(&b.word)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
@@ -0,0 +1,103 @@
+//! What the heap goes through, for profiling: [`Counting`] wraps the system
+//! allocator and counts every allocation passing through it, and
+//! [`measure`] tells what a piece of code allocated.
+//!
+//! Nothing is counted unless [`Counting`] is the global allocator, which the
+//! `prefix-tree` binary makes it when built with the `alloc-stats` feature:
+//!
+//! ```
+//! use prefix_tree::alloc_stats::{self, Counting};
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: Counting = Counting;
+//!
+//! let (words, usage) = alloc_stats::measure(|| vec![0u8; 4096]);
+//! assert_eq!(words.len(), 4096);
+//! assert!(usage.allocations >= 1 && usage.peak >= 4096);
+//! ```
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering::Relaxed},
+};
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting.
+pub struct Counting;
+
+// A reallocation counts as one allocation, of what the block grew by.
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            allocated(layout.size(), 0);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            allocated(layout.size(), 0);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            allocated(new_size, layout.size());
+        }
+        new
+    }
+}
+
+// Counts a block of `size` bytes replacing one of `old`.
+fn allocated(size: usize, old: usize) {
+    ALLOCATIONS.fetch_add(1, Relaxed);
+    if size > old {
+        let grown = size - old;
+        BYTES.fetch_add(grown as u64, Relaxed);
+        let current = CURRENT.fetch_add(grown, Relaxed) + grown;
+        PEAK.fetch_max(current, Relaxed);
+    } else {
+        CURRENT.fetch_sub(old - size, Relaxed);
+    }
+}
+
+/// What [`measure`] saw allocated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub allocations: u64,
+    /// Bytes allocated, whether freed again or not.
+    pub bytes: u64,
+    /// The most the heap held above what it held at the start.
+    pub peak: usize,
+}
+
+/// Runs `f`, returning what it allocated along with its result. All zeros
+/// unless [`Counting`] is the global allocator. Allocations of other threads
+/// meanwhile are counted too, and measuring inside a measured `f` leaves the
+/// outer peak short.
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, Usage) {
+    let start = CURRENT.load(Relaxed);
+    PEAK.store(start, Relaxed);
+    let allocations = ALLOCATIONS.load(Relaxed);
+    let bytes = BYTES.load(Relaxed);
+    let result = f();
+    let usage = Usage {
+        allocations: ALLOCATIONS.load(Relaxed) - allocations,
+        bytes: BYTES.load(Relaxed) - bytes,
+        peak: PEAK.load(Relaxed).saturating_sub(start),
+    };
+    (result, usage)
+}
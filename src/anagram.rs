@@ -0,0 +1,102 @@
+//! Anagram lookups for word games: an auxiliary trie keyed by each word's
+//! letters in sorted order, so words made of the same letters share a node
+//! and the words made of some of them are the nodes a walk can spell.
+
+use crate::trie::{Node, Trie};
+
+/// Maps the sorted letters of words to the dictionary words made of them.
+pub struct AnagramIndex {
+    trie: Trie<char, Vec<String>>,
+}
+
+impl Default for AnagramIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnagramIndex {
+    pub fn new() -> Self {
+        Self { trie: Trie::new() }
+    }
+
+    /// Adds `word`, unless it has no letters at all.
+    pub fn insert(&mut self, word: String) {
+        let key = sorted_letters(&word);
+        if !key.is_empty() {
+            self.trie.entry(&key).or_default().push(word);
+        }
+    }
+
+    /// The words made of exactly `letters`, case and anything but letters
+    /// aside, in the order they were added.
+    ///
+    /// ```
+    /// use prefix_tree::anagram::AnagramIndex;
+    ///
+    /// let mut index = AnagramIndex::new();
+    /// for word in ["listen", "silent", "tinsel", "list"] {
+    ///     index.insert(word.to_string());
+    /// }
+    /// assert_eq!(index.anagrams("Enlist"), ["listen", "silent", "tinsel"]);
+    /// ```
+    pub fn anagrams(&self, letters: &str) -> &[String] {
+        self.trie
+            .get(&sorted_letters(letters))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The words made of some of `letters`, each used at most as often as
+    /// it's given, longest first and then in sorted order.
+    ///
+    /// ```
+    /// use prefix_tree::anagram::AnagramIndex;
+    ///
+    /// let mut index = AnagramIndex::new();
+    /// for word in ["tea", "eat", "ate", "at", "tee", "a"] {
+    ///     index.insert(word.to_string());
+    /// }
+    /// assert_eq!(index.sub_anagrams("tae"), ["ate", "eat", "tea", "at", "a"]);
+    /// ```
+    pub fn sub_anagrams(&self, letters: &str) -> Vec<String> {
+        let key: Vec<char> = sorted_letters(letters).chars().collect();
+        let mut found = vec![];
+        within(&self.trie.root, &key, &mut found);
+        found.sort_unstable_by(|a, b| {
+            b.chars()
+                .count()
+                .cmp(&a.chars().count())
+                .then_with(|| a.cmp(b))
+        });
+        found
+    }
+}
+
+// Collects the words of `node` and of every node below it spelled by a
+// sub-multiset of `letters`, which are sorted like the edges of a path.
+fn within(node: &Node<char, Vec<String>>, letters: &[char], found: &mut Vec<String>) {
+    if let Some(words) = node.value() {
+        found.extend(words.iter().cloned());
+    }
+    for (i, &letter) in letters.iter().enumerate() {
+        // A repeated letter is only branched on from its first copy.
+        if i > 0 && letters[i - 1] == letter {
+            continue;
+        }
+        if let Some(child) = node.children.get(&letter) {
+            // Paths spell letters in sorted order, so nothing before this
+            // one can follow it.
+            within(child, &letters[i + 1..], found);
+        }
+    }
+}
+
+fn sorted_letters(word: &str) -> String {
+    let mut letters: Vec<char> = word
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect();
+    letters.sort_unstable();
+    letters.into_iter().collect()
+}
@@ -0,0 +1,13 @@
+//! Checking the tokens clients present to the servers' mutating commands.
+
+/// Whether `given` is `token`, taking as long whichever byte they first
+/// differ at, so the time an answer takes doesn't reveal a token byte by
+/// byte.
+pub(crate) fn token_matches(given: &str, token: &str) -> bool {
+    let (given, token) = (given.as_bytes(), token.as_bytes());
+    let differences = given
+        .iter()
+        .zip(token)
+        .fold(0, |differences, (a, b)| differences | (a ^ b));
+    given.len() == token.len() && differences == 0
+}
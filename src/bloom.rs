@@ -0,0 +1,80 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use crate::{key::TrieKey, trie::Trie};
+
+/// A Bloom filter over a trie's words, made with [`Trie::bloom`], to turn
+/// away unknown words before the trie is walked at all.
+///
+/// The filter never rejects a stored word, but lets through a small share of
+/// the others, the false positive rate it was sized for, which the trie then
+/// answers. When scanning a corpus for unknown words, most of the lookups
+/// that fail cost a few bit tests instead of a walk down the trie.
+///
+/// ```
+/// use prefix_tree::Trie;
+///
+/// let trie: Trie = ["apple", "apricot", "banana"].into_iter().collect();
+/// let bloom = trie.bloom(0.01);
+/// assert!(bloom.may_contain("apple"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Bloom {
+    bits: Vec<u64>,
+    // Bits set per word.
+    hashes: u32,
+}
+
+impl<K: TrieKey, V> Trie<K, V> {
+    /// A [`Bloom`] filter of the words, letting through about
+    /// `false_positive_rate` of the words not stored.
+    pub fn bloom(&self, false_positive_rate: f64) -> Bloom {
+        let mut bloom = Bloom::new(self.len(), false_positive_rate);
+        self.for_each_word(|word, _| bloom.insert(word));
+        bloom
+    }
+}
+
+impl Bloom {
+    /// An empty filter sized for `words` words at `false_positive_rate`.
+    pub fn new(words: usize, false_positive_rate: f64) -> Self {
+        let words = words.max(1) as f64;
+        let rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-words * rate.ln() / (ln2 * ln2)).ceil().max(64.0);
+        let hashes = (bits / words * ln2).round().clamp(1.0, 32.0) as u32;
+        Self {
+            bits: vec![0; (bits as usize).div_ceil(64)],
+            hashes,
+        }
+    }
+
+    pub fn insert(&mut self, word: &str) {
+        for bit in self.bits_of(word) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Whether `word` may have been inserted; `false` means it certainly
+    /// wasn't.
+    pub fn may_contain(&self, word: &str) -> bool {
+        self.bits_of(word)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    // The bits of `word`, from two hashes combined as h1 + i * h2.
+    fn bits_of(&self, word: &str) -> impl Iterator<Item = usize> {
+        let hash = |seed: u64| {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            word.hash(&mut hasher);
+            hasher.finish()
+        };
+        let (h1, h2) = (hash(0), hash(1) | 1);
+        let len = self.bits.len() as u64 * 64;
+        (0..u64::from(self.hashes))
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+}
@@ -0,0 +1,201 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::{
+    key::TrieKey,
+    trie::{Entry, Node, Trie, WordInfo, DEFAULT_WEIGHT},
+};
+
+/// Which original casing of a case-folded word completions display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DisplayCase {
+    /// The folded, lowercase word itself.
+    Folded,
+    /// The casing with the highest total weight, the first one among equals.
+    #[default]
+    Frequent,
+    /// The casing the word first appeared with.
+    First,
+}
+
+impl DisplayCase {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "folded" => Some(Self::Folded),
+            "frequent" => Some(Self::Frequent),
+            "first" => Some(Self::First),
+            _ => None,
+        }
+    }
+}
+
+/// Builds a [`Trie`] from a stream of entries, much faster than repeated
+/// [`Trie::insert_entry`] calls when the entries come sorted.
+///
+/// The builder owns the nodes along the path of the last word and only hands
+/// a node to its parent once a word leaves its subtree, so consecutive words
+/// sharing a prefix never re-walk it from the root. Unsorted input is still
+/// handled correctly: a word out of order takes the subtree it falls in back
+/// from its parent, so its lines fold in as if they had come in order.
+///
+/// ```
+/// use prefix_tree::{Entry, TrieBuilder};
+///
+/// let mut builder = TrieBuilder::<char>::new().fold_case(true);
+/// for word in ["Apple", "apple", "apricot"] {
+///     builder.push(&Entry::parse(word));
+/// }
+/// let trie = builder.build();
+/// assert!(trie.contains("apple"));
+/// ```
+pub struct TrieBuilder<K = char> {
+    root: Node<K>,
+    path: Vec<(K, Node<K>)>,
+    fold_case: bool,
+    display_case: DisplayCase,
+    dedup: bool,
+    // The original casings of every folded word, with their total weight, in
+    // order of appearance.
+    forms: HashMap<String, Vec<(String, u64)>>,
+}
+
+impl<K: TrieKey> Default for TrieBuilder<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: TrieKey> TrieBuilder<K> {
+    pub fn new() -> Self {
+        Self {
+            root: Node::new(),
+            path: Vec::new(),
+            fold_case: false,
+            display_case: DisplayCase::default(),
+            dedup: false,
+            forms: HashMap::new(),
+        }
+    }
+
+    /// Lowercases words before inserting them, so "Apple" and "apple" are
+    /// stored once.
+    pub fn fold_case(mut self, fold_case: bool) -> Self {
+        self.fold_case = fold_case;
+        self
+    }
+
+    /// Which original casing of a folded word its [`WordInfo::display`] is set
+    /// to, [`DisplayCase::Frequent`] by default.
+    pub fn display_case(mut self, display_case: DisplayCase) -> Self {
+        self.display_case = display_case;
+        self
+    }
+
+    /// Keeps the first entry of a repeated word and ignores the others,
+    /// instead of merging their weights and tags.
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Adds a dictionary line. The lines of a word fold in as
+    /// [`WordInfo::apply`] folds them, whether other words come between them
+    /// or not.
+    ///
+    /// ```
+    /// use prefix_tree::{Entry, TrieBuilder};
+    ///
+    /// for lines in [
+    ///     ["apple\t5", "apple", "banana"],
+    ///     ["apple\t5", "banana", "apple"],
+    ///     ["apple", "banana", "apple\t5"],
+    /// ] {
+    ///     let mut builder = TrieBuilder::<char>::new();
+    ///     for line in lines {
+    ///         builder.push(&Entry::parse(line));
+    ///     }
+    ///     assert_eq!(builder.build().get("apple").unwrap().weight, 5);
+    /// }
+    /// ```
+    pub fn push(&mut self, entry: &Entry) {
+        let word = if self.fold_case {
+            Cow::Owned(entry.word.to_lowercase())
+        } else {
+            Cow::Borrowed(entry.word)
+        };
+        if self.fold_case && self.display_case != DisplayCase::Folded {
+            let forms = self.forms.entry(word.to_string()).or_default();
+            let weight = entry.weight.unwrap_or(DEFAULT_WEIGHT);
+            match forms.iter_mut().find(|(form, _)| form == entry.word) {
+                Some((_, total)) => *total += weight,
+                None => forms.push((entry.word.to_string(), weight)),
+            }
+        }
+        let keys = K::segment(&word);
+        let common = self
+            .path
+            .iter()
+            .zip(&keys)
+            .take_while(|((key, _), next)| key == *next)
+            .count();
+        while self.path.len() > common {
+            self.pop();
+        }
+        for key in keys.into_iter().skip(common) {
+            let parent = match self.path.last_mut() {
+                Some((_, node)) => node,
+                None => &mut self.root,
+            };
+            let node = parent.children.remove(&key).unwrap_or_else(Node::new);
+            self.path.push((key, node));
+        }
+
+        let dedup = self.dedup;
+        let node = match self.path.last_mut() {
+            Some((_, node)) => node,
+            None => &mut self.root,
+        };
+        if !(dedup && node.value.is_some()) {
+            node.value
+                .get_or_insert_with(WordInfo::default)
+                .apply(entry);
+        }
+    }
+
+    pub fn build(mut self) -> Trie<K> {
+        while !self.path.is_empty() {
+            self.pop();
+        }
+        let mut trie = Trie::from_root(self.root);
+        for (word, forms) in self.forms {
+            let form = match self.display_case {
+                DisplayCase::Folded => continue,
+                // max_by_key keeps the last of equals, so go backwards.
+                DisplayCase::Frequent => forms.iter().rev().max_by_key(|(_, weight)| *weight),
+                DisplayCase::First => forms.first(),
+            };
+            if let (Some((form, _)), Some(info)) = (form, trie.get_mut(&word)) {
+                if *form != word {
+                    info.display = Some(form.clone());
+                }
+            }
+        }
+        trie
+    }
+
+    fn pop(&mut self) {
+        let (key, node) = self.path.pop().expect("path is not empty");
+        let parent = match self.path.last_mut() {
+            Some((_, parent)) => parent,
+            None => &mut self.root,
+        };
+        parent.children.insert(key, node);
+    }
+}
+
+impl<'a, K: TrieKey> Extend<&'a Entry<'a>> for TrieBuilder<K> {
+    fn extend<I: IntoIterator<Item = &'a Entry<'a>>>(&mut self, entries: I) {
+        for entry in entries {
+            self.push(entry);
+        }
+    }
+}
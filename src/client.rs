@@ -0,0 +1,317 @@
+//! A client for the [`resp`](crate::resp) server, so Rust services using a
+//! resident trie don't each speak the protocol by hand: [`PrefixTreeClient`]
+//! blocks, and `AsyncPrefixTreeClient`, with the `async-client` feature,
+//! runs on tokio.
+//!
+//! ```
+//! use std::{net::TcpListener, sync::RwLock, thread};
+//!
+//! use prefix_tree::{client::PrefixTreeClient, resp, shutdown::Shutdown, Trie};
+//!
+//! let listener = TcpListener::bind("127.0.0.1:0")?;
+//! let address = listener.local_addr()?;
+//! thread::spawn(move || {
+//!     let trie: RwLock<Trie> = RwLock::new(["apple"].into_iter().collect());
+//!     resp::serve(listener, &trie, 10, None, &Shutdown::new())
+//! });
+//!
+//! let mut client = PrefixTreeClient::connect(address)?;
+//! assert!(client.add("apply", Some(5))?);
+//! let words: Vec<_> = client.complete("ap", 10)?.into_iter().map(|s| s.word).collect();
+//! assert_eq!(words, ["apply", "apple"]);
+//! assert!(client.remove("apple")?);
+//! assert!(!client.contains("apple")?);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Errors the server answers come back as [`io::Error`]s of their own, of
+//! kind [`PermissionDenied`](ErrorKind::PermissionDenied) for a write made
+//! without the server's token or with the wrong one, and
+//! [`Other`](ErrorKind::Other) for the rest.
+
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use crate::complete::Suggestion;
+
+/// A connection to a RESP server, answering a call at a time.
+pub struct PrefixTreeClient {
+    stream: TcpStream,
+    // What was read of replies still incomplete.
+    buffer: Vec<u8>,
+}
+
+impl PrefixTreeClient {
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            stream: TcpStream::connect(address)?,
+            buffer: vec![],
+        })
+    }
+
+    /// Connects within `timeout`, after which calls fail too if the server
+    /// doesn't answer them.
+    pub fn connect_timeout(address: &SocketAddr, timeout: Duration) -> io::Result<Self> {
+        let stream = TcpStream::connect_timeout(address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        Ok(Self {
+            stream,
+            buffer: vec![],
+        })
+    }
+
+    /// Sends the server's token, which it takes writes with.
+    pub fn auth(&mut self, token: &str) -> io::Result<()> {
+        self.call(&["AUTH", token]).map(drop)
+    }
+
+    /// Up to `limit` completions of `prefix`, best first.
+    pub fn complete(&mut self, prefix: &str, limit: usize) -> io::Result<Vec<Suggestion>> {
+        let limit = limit.to_string();
+        suggestions(self.call(&["PT.COMPLETE", prefix, &limit, "WITHWEIGHTS"])?)
+    }
+
+    /// Whether `word` itself is stored.
+    pub fn contains(&mut self, word: &str) -> io::Result<bool> {
+        flag(self.call(&["PT.EXISTS", word])?)
+    }
+
+    /// Stores `word`, with `weight` if given, and returns whether it wasn't
+    /// stored already.
+    pub fn add(&mut self, word: &str, weight: Option<u64>) -> io::Result<bool> {
+        let weight = weight.map(|weight| weight.to_string());
+        let mut command = vec!["PT.ADD", word];
+        command.extend(weight.as_deref());
+        flag(self.call(&command)?)
+    }
+
+    /// Removes `word`, and returns whether it was stored.
+    pub fn remove(&mut self, word: &str) -> io::Result<bool> {
+        flag(self.call(&["PT.DEL", word])?)
+    }
+
+    fn call(&mut self, command: &[&str]) -> io::Result<Reply> {
+        self.stream.write_all(&encode(command))?;
+        loop {
+            if let Some((reply, len)) = parse(&self.buffer)? {
+                self.buffer.drain(..len);
+                return reply.ok();
+            }
+            let mut chunk = [0; 8 * 1024];
+            let read = self.stream.read(&mut chunk)?;
+            if read == 0 {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
+
+/// [`PrefixTreeClient`] for tokio.
+///
+/// ```
+/// use prefix_tree::{client::AsyncPrefixTreeClient, Suggestion};
+///
+/// async fn suggest(prefix: &str) -> std::io::Result<Vec<Suggestion>> {
+///     let mut client = AsyncPrefixTreeClient::connect("127.0.0.1:6380").await?;
+///     client.complete(prefix, 5).await
+/// }
+/// ```
+#[cfg(feature = "async-client")]
+pub struct AsyncPrefixTreeClient {
+    stream: tokio::net::TcpStream,
+    buffer: Vec<u8>,
+}
+
+#[cfg(feature = "async-client")]
+impl AsyncPrefixTreeClient {
+    pub async fn connect(address: impl tokio::net::ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            stream: tokio::net::TcpStream::connect(address).await?,
+            buffer: vec![],
+        })
+    }
+
+    pub async fn auth(&mut self, token: &str) -> io::Result<()> {
+        self.call(&["AUTH", token]).await.map(drop)
+    }
+
+    pub async fn complete(&mut self, prefix: &str, limit: usize) -> io::Result<Vec<Suggestion>> {
+        let limit = limit.to_string();
+        suggestions(
+            self.call(&["PT.COMPLETE", prefix, &limit, "WITHWEIGHTS"])
+                .await?,
+        )
+    }
+
+    pub async fn contains(&mut self, word: &str) -> io::Result<bool> {
+        flag(self.call(&["PT.EXISTS", word]).await?)
+    }
+
+    pub async fn add(&mut self, word: &str, weight: Option<u64>) -> io::Result<bool> {
+        let weight = weight.map(|weight| weight.to_string());
+        let mut command = vec!["PT.ADD", word];
+        command.extend(weight.as_deref());
+        flag(self.call(&command).await?)
+    }
+
+    pub async fn remove(&mut self, word: &str) -> io::Result<bool> {
+        flag(self.call(&["PT.DEL", word]).await?)
+    }
+
+    async fn call(&mut self, command: &[&str]) -> io::Result<Reply> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        self.stream.write_all(&encode(command)).await?;
+        loop {
+            if let Some((reply, len)) = parse(&self.buffer)? {
+                self.buffer.drain(..len);
+                return reply.ok();
+            }
+            let mut chunk = [0; 8 * 1024];
+            let read = self.stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
+
+// A reply as the server sends them, which is all RESP has but nil.
+enum Reply {
+    Status,
+    Error(String),
+    Integer(i64),
+    Bulk,
+    Array(Vec<String>),
+}
+
+impl Reply {
+    // The reply, or the error the server answered.
+    fn ok(self) -> io::Result<Self> {
+        match self {
+            Reply::Error(message) => {
+                let kind = match message.starts_with("NOAUTH") || message.starts_with("WRONGPASS") {
+                    true => ErrorKind::PermissionDenied,
+                    false => ErrorKind::Other,
+                };
+                Err(io::Error::new(kind, message))
+            }
+            reply => Ok(reply),
+        }
+    }
+}
+
+fn flag(reply: Reply) -> io::Result<bool> {
+    match reply {
+        Reply::Integer(n) => Ok(n != 0),
+        _ => Err(invalid("expected an integer reply")),
+    }
+}
+
+// Completions from the words and weights `PT.COMPLETE ... WITHWEIGHTS`
+// answers in turn.
+fn suggestions(reply: Reply) -> io::Result<Vec<Suggestion>> {
+    let Reply::Array(items) = reply else {
+        return Err(invalid("expected an array reply"));
+    };
+    let mut items = items.into_iter();
+    let mut suggestions = vec![];
+    while let Some(word) = items.next() {
+        let weight = items
+            .next()
+            .and_then(|weight| weight.parse().ok())
+            .ok_or_else(|| invalid("expected a weight after each word"))?;
+        suggestions.push(Suggestion { word, weight });
+    }
+    Ok(suggestions)
+}
+
+// A command as an array of bulk strings, as client libraries send them.
+fn encode(command: &[&str]) -> Vec<u8> {
+    let mut encoded = format!("*{}\r\n", command.len()).into_bytes();
+    for arg in command {
+        encoded.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        encoded.extend_from_slice(arg.as_bytes());
+        encoded.extend_from_slice(b"\r\n");
+    }
+    encoded
+}
+
+// The reply `buffer` starts with and its length, `None` while it isn't all
+// there yet.
+fn parse(buffer: &[u8]) -> io::Result<Option<(Reply, usize)>> {
+    let mut at = 0;
+    let Some(header) = line(buffer, &mut at)? else {
+        return Ok(None);
+    };
+    let reply = match header.split_at_checked(1) {
+        Some(("+", _)) => Reply::Status,
+        Some(("-", message)) => Reply::Error(message.to_string()),
+        Some((":", n)) => Reply::Integer(n.parse().map_err(|_| invalid("invalid integer"))?),
+        Some(("$", len)) => match bulk(buffer, &mut at, len)? {
+            Some(_) => Reply::Bulk,
+            None => return Ok(None),
+        },
+        Some(("*", count)) => {
+            let count: usize = count
+                .parse()
+                .map_err(|_| invalid("invalid multibulk length"))?;
+            let mut items = Vec::with_capacity(count.min(1024));
+            for _ in 0..count {
+                let Some(header) = line(buffer, &mut at)? else {
+                    return Ok(None);
+                };
+                let len = header
+                    .strip_prefix('$')
+                    .ok_or_else(|| invalid("expected a bulk string"))?;
+                match bulk(buffer, &mut at, len)? {
+                    Some(item) => items.push(item),
+                    None => return Ok(None),
+                }
+            }
+            Reply::Array(items)
+        }
+        _ => return Err(invalid("unknown reply type")),
+    };
+    Ok(Some((reply, at)))
+}
+
+// The line at `at`, without its CRLF, moving `at` past it.
+fn line<'b>(buffer: &'b [u8], at: &mut usize) -> io::Result<Option<&'b str>> {
+    let rest = &buffer[*at..];
+    let Some(end) = rest.windows(2).position(|pair| pair == b"\r\n") else {
+        return Ok(None);
+    };
+    *at += end + 2;
+    match std::str::from_utf8(&rest[..end]) {
+        Ok(line) => Ok(Some(line)),
+        Err(_) => Err(invalid("reply isn't UTF-8")),
+    }
+}
+
+// The bulk string of `len` bytes at `at`, moving `at` past it.
+fn bulk(buffer: &[u8], at: &mut usize, len: &str) -> io::Result<Option<String>> {
+    let len: usize = len.parse().map_err(|_| invalid("invalid bulk length"))?;
+    let end = at
+        .checked_add(len)
+        .and_then(|end| end.checked_add(2))
+        .ok_or_else(|| invalid("invalid bulk length"))?;
+    let Some(data) = buffer.get(*at..end) else {
+        return Ok(None);
+    };
+    *at = end;
+    match String::from_utf8(data[..len].to_vec()) {
+        Ok(text) => Ok(Some(text)),
+        Err(_) => Err(invalid("reply isn't UTF-8")),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
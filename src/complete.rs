@@ -0,0 +1,621 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    key::TrieKey,
+    order::ChildOrder,
+    trie::{Node, Trie, WordInfo, DEFAULT_WEIGHT},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub word: String,
+    pub weight: u64,
+}
+
+/// A source of completions. The CLI is written against this trait, so other
+/// sources (shell history, project files, ...) can be offered alongside or
+/// instead of the dictionary trie.
+pub trait Completer {
+    /// Up to `limit` words starting with `prefix`, best first.
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion>;
+
+    /// Like [`Completer::complete`], also giving the earliest expiry time of
+    /// the words completed, in seconds since the Unix epoch, after which the
+    /// completions may change. `None` unless the completer says otherwise.
+    fn complete_until(&self, prefix: &str, limit: usize) -> (Vec<Suggestion>, Option<u64>) {
+        (self.complete(prefix, limit), None)
+    }
+}
+
+impl<K: TrieKey> Trie<K> {
+    /// Restricts completions to words carrying `tag`.
+    pub fn tagged<'a>(&'a self, tag: &'a str) -> Tagged<'a, K> {
+        Tagged {
+            trie: self,
+            tag,
+            min_len: 0,
+        }
+    }
+
+    /// Leaves out words of fewer than `min_len` keys. They are skipped as the
+    /// walk passes them, so a limit still fills with longer words.
+    pub fn min_len(&self, min_len: usize) -> MinLen<'_, K> {
+        MinLen {
+            trie: self,
+            min_len,
+        }
+    }
+
+    /// Like [`Completer::complete`], also reporting where the time went.
+    pub fn complete_explained(&self, prefix: &str, limit: usize) -> (Vec<Suggestion>, Explanation) {
+        self.complete_explained_where(prefix, limit, 0, |_| true)
+    }
+
+    fn complete_where(
+        &self,
+        prefix: &str,
+        limit: usize,
+        min_len: usize,
+        filter: impl Fn(&WordInfo) -> bool,
+    ) -> (Vec<Suggestion>, Option<u64>) {
+        let Some(node) = self.get_node(prefix) else {
+            return (vec![], None);
+        };
+        complete_below(
+            node,
+            &self.order,
+            K::segment(prefix),
+            limit,
+            min_len,
+            filter,
+        )
+    }
+
+    fn complete_explained_where(
+        &self,
+        prefix: &str,
+        limit: usize,
+        min_len: usize,
+        filter: impl Fn(&WordInfo) -> bool,
+    ) -> (Vec<Suggestion>, Explanation) {
+        let mut explanation = Explanation::default();
+        let start = Instant::now();
+        let keys = K::segment(prefix);
+        let mut node = Some(&self.root);
+        for key in &keys {
+            node = node.and_then(|node| node.children.get(key));
+            explanation.prefix_nodes += usize::from(node.is_some());
+        }
+        let walked = Instant::now();
+        explanation.prefix_walk = walked - start;
+        let Some(node) = node else {
+            return (vec![], explanation);
+        };
+        let mut found = vec![];
+        enumerate(
+            node,
+            &self.order,
+            &mut keys.clone(),
+            min_len,
+            &filter,
+            &mut found,
+            &mut explanation.nodes_enumerated,
+        );
+        explanation.words = found.len();
+        let enumerated = Instant::now();
+        explanation.enumeration = enumerated - walked;
+        rank(
+            &mut found,
+            limit,
+            self.order.is_keys(),
+            |(suggestion, _)| suggestion,
+        );
+        explanation.ranking = enumerated.elapsed();
+        (until(found).0, explanation)
+    }
+
+    /// Starts a [`CompletionSession`] with nothing typed yet.
+    pub fn session(&self) -> CompletionSession<'_, K> {
+        CompletionSession {
+            text: String::new(),
+            keys: Vec::new(),
+            path: vec![&self.root],
+            order: &self.order,
+        }
+    }
+}
+
+// The completions of the words below `node`, whose path is `keys`, with
+// when the first of them expires.
+fn complete_below<K: TrieKey>(
+    node: &Node<K>,
+    order: &ChildOrder<K>,
+    mut keys: Vec<K>,
+    limit: usize,
+    min_len: usize,
+    filter: impl Fn(&WordInfo) -> bool,
+) -> (Vec<Suggestion>, Option<u64>) {
+    let mut found = vec![];
+    enumerate(node, order, &mut keys, min_len, &filter, &mut found, &mut 0);
+    rank(&mut found, limit, order.is_keys(), |(suggestion, _)| {
+        suggestion
+    });
+    until(found)
+}
+
+// The suggestions of `found`, each with when its word expires, and when the
+// first of them does.
+pub(crate) fn until(found: Vec<(Suggestion, Option<u64>)>) -> (Vec<Suggestion>, Option<u64>) {
+    let expires = found.iter().filter_map(|(_, expires)| *expires).min();
+    let suggestions = found.into_iter().map(|(suggestion, _)| suggestion);
+    (suggestions.collect(), expires)
+}
+
+// Collects the words of at least `min_len` keys at and below `node` passing
+// `filter`, with when they expire, counting the nodes visited, in `order`
+// unless that is key order, which ranking restores. The walk keeps its own stack, one iterator per
+// level, so a long word can't overflow the thread's.
+fn enumerate<K: TrieKey>(
+    node: &Node<K>,
+    order: &ChildOrder<K>,
+    buffer: &mut Vec<K>,
+    min_len: usize,
+    filter: &impl Fn(&WordInfo) -> bool,
+    out: &mut Vec<(Suggestion, Option<u64>)>,
+    visited: &mut usize,
+) {
+    let mut collect = |node: &Node<K>, buffer: &[K]| {
+        *visited += 1;
+        let long_enough = buffer.len() >= min_len;
+        if let Some(info) = node
+            .value
+            .as_ref()
+            .filter(|info| long_enough && !info.expired() && filter(info))
+        {
+            let suggestion = Suggestion {
+                word: info
+                    .display
+                    .as_deref()
+                    .unwrap_or(&K::join(buffer))
+                    .to_string(),
+                weight: info.weight,
+            };
+            out.push((suggestion, info.expires));
+        }
+    };
+    let base = buffer.len();
+    collect(node, buffer);
+    let mut stack = vec![order.visit(node)];
+    while let Some(children) = stack.last_mut() {
+        if let Some((key, child)) = children.next() {
+            buffer.push(key.clone());
+            collect(child, buffer);
+            stack.push(order.visit(child));
+        } else {
+            stack.pop();
+            buffer.truncate(base + stack.len().saturating_sub(1));
+        }
+    }
+}
+
+// Heaviest words first, alphabetical among equals so the output doesn't
+// depend on the trie's iteration order, unless the trie has a child order of
+// its own (`keyed` is false), which equals keep as they were enumerated in.
+// `found` holds the suggestions, or something `suggestion` finds one in.
+pub(crate) fn rank<T>(
+    found: &mut Vec<T>,
+    limit: usize,
+    keyed: bool,
+    suggestion: impl Fn(&T) -> &Suggestion,
+) {
+    if keyed {
+        found.sort_by(|a, b| {
+            let (a, b) = (suggestion(a), suggestion(b));
+            b.weight.cmp(&a.weight).then_with(|| a.word.cmp(&b.word))
+        });
+    } else {
+        found.sort_by_key(|found| Reverse(suggestion(found).weight));
+    }
+    found.truncate(limit);
+}
+
+/// Completion as the user types, one character at a time, see
+/// [`Trie::session`].
+///
+/// The session remembers the node reached by the text so far, so a
+/// keystroke only walks the keys it changed instead of the whole prefix
+/// from the root.
+///
+/// ```
+/// use prefix_tree::Trie;
+///
+/// let trie: Trie = ["apple", "apricot", "banana"].into_iter().collect();
+/// let mut session = trie.session();
+/// session.push_char('a');
+/// session.push_char('p');
+/// assert_eq!(session.suggestions(10).len(), 2);
+/// session.push_char('p');
+/// assert_eq!(session.suggestions(10)[0].word, "apple");
+/// session.pop_char();
+/// assert_eq!(session.text(), "ap");
+/// ```
+pub struct CompletionSession<'a, K = char> {
+    text: String,
+    keys: Vec<K>,
+    // The node reached by each prefix of `keys`, the root first, for as long
+    // as the trie has one.
+    path: Vec<&'a Node<K>>,
+    order: &'a ChildOrder<K>,
+}
+
+impl<'a, K: TrieKey> CompletionSession<'a, K> {
+    /// The text typed so far.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn push_char(&mut self, ch: char) {
+        self.text.push(ch);
+        self.resegment();
+    }
+
+    /// Deletes the last character typed, returning it.
+    pub fn pop_char(&mut self) -> Option<char> {
+        let ch = self.text.pop()?;
+        self.resegment();
+        Some(ch)
+    }
+
+    /// Up to `limit` completions of the text typed so far, best first.
+    pub fn suggestions(&self, limit: usize) -> Vec<Suggestion> {
+        if self.path.len() <= self.keys.len() {
+            return vec![];
+        }
+        let node = self.path[self.path.len() - 1];
+        complete_below(node, self.order, self.keys.clone(), limit, 0, |_| true).0
+    }
+
+    // Keys other than `char` may merge or split as characters come and go,
+    // so the text is segmented again, but the trie is only walked from the
+    // first key that changed.
+    fn resegment(&mut self) {
+        let keys = K::segment(&self.text);
+        let common = self
+            .keys
+            .iter()
+            .zip(&keys)
+            .take_while(|(a, b)| a == b)
+            .count();
+        self.path.truncate(common + 1);
+        self.keys = keys;
+        while let Some(key) = self.keys.get(self.path.len() - 1) {
+            match self.path[self.path.len() - 1].children.get(key) {
+                Some(child) => self.path.push(child),
+                None => break,
+            }
+        }
+    }
+}
+
+/// Completing walks the trie on a stack of its own rather than the
+/// thread's, so words of any length complete, and drop, with the trie:
+///
+/// ```
+/// use prefix_tree::{Completer, Trie};
+///
+/// let long = "a".repeat(1_000_000);
+/// let trie: Trie = [long.as_str(), "ab"].into_iter().collect();
+/// let words: Vec<_> = trie.complete("a", 2).into_iter().map(|s| s.word).collect();
+/// assert_eq!(words, [&long, "ab"]);
+/// ```
+///
+/// [`Completer::complete_until`] tells when the first word completed
+/// expires, as it does for the trie restricted by [`Trie::tagged`] or
+/// [`Trie::min_len`]:
+///
+/// ```
+/// use prefix_tree::{Completer, Entry, Trie};
+///
+/// let mut trie: Trie = Trie::new();
+/// trie.insert_entry(&Entry::parse("session-1\t2\texpires=4102444800"));
+/// trie.insert_entry(&Entry::parse("session-2\t1\texpires=4000000000\tadmin"));
+/// trie.insert_entry(&Entry::parse("sess"));
+/// assert_eq!(trie.complete_until("session", 1).1, Some(4102444800));
+/// assert_eq!(trie.complete_until("s", 3).1, Some(4000000000));
+/// assert_eq!(trie.complete_until("sess", 1).1, Some(4102444800));
+/// assert_eq!(trie.tagged("admin").complete_until("s", 3).1, Some(4000000000));
+/// assert_eq!(trie.min_len(5).complete_until("s", 3).1, Some(4000000000));
+/// assert_eq!(trie.min_len(5).complete_until("sess", 3).0.len(), 2);
+/// assert_eq!(trie.complete_until("other", 3), (vec![], None));
+/// ```
+impl<K: TrieKey> Completer for Trie<K> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        self.complete_until(prefix, limit).0
+    }
+
+    fn complete_until(&self, prefix: &str, limit: usize) -> (Vec<Suggestion>, Option<u64>) {
+        self.complete_where(prefix, limit, 0, |_| true)
+    }
+}
+
+/// Completions of a trie limited to words of a minimum length, see
+/// [`Trie::min_len`].
+pub struct MinLen<'a, K> {
+    trie: &'a Trie<K>,
+    min_len: usize,
+}
+
+impl<K: TrieKey> MinLen<'_, K> {
+    /// Like [`Completer::complete`], also reporting where the time went.
+    pub fn complete_explained(&self, prefix: &str, limit: usize) -> (Vec<Suggestion>, Explanation) {
+        self.trie
+            .complete_explained_where(prefix, limit, self.min_len, |_| true)
+    }
+}
+
+impl<K: TrieKey> Completer for MinLen<'_, K> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        self.complete_until(prefix, limit).0
+    }
+
+    fn complete_until(&self, prefix: &str, limit: usize) -> (Vec<Suggestion>, Option<u64>) {
+        self.trie
+            .complete_where(prefix, limit, self.min_len, |_| true)
+    }
+}
+
+/// Completions of a trie limited to the words carrying a tag, see
+/// [`Trie::tagged`].
+pub struct Tagged<'a, K> {
+    trie: &'a Trie<K>,
+    tag: &'a str,
+    min_len: usize,
+}
+
+impl<K: TrieKey> Tagged<'_, K> {
+    /// Also leaves out words of fewer than `min_len` keys, see
+    /// [`Trie::min_len`].
+    pub fn min_len(mut self, min_len: usize) -> Self {
+        self.min_len = min_len;
+        self
+    }
+
+    /// Like [`Completer::complete`], also reporting where the time went.
+    pub fn complete_explained(&self, prefix: &str, limit: usize) -> (Vec<Suggestion>, Explanation) {
+        self.trie
+            .complete_explained_where(prefix, limit, self.min_len, |info| info.has_tag(self.tag))
+    }
+}
+
+/// Where a completion spent its time, see [`Trie::complete_explained`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Explanation {
+    /// Nodes walked down to the prefix, fewer than its keys if it isn't in
+    /// the trie.
+    pub prefix_nodes: usize,
+    pub prefix_walk: Duration,
+    /// Nodes below the prefix visited to collect the words.
+    pub nodes_enumerated: usize,
+    /// Words collected, before the limit.
+    pub words: usize,
+    pub enumeration: Duration,
+    pub ranking: Duration,
+}
+
+impl<K: TrieKey> Completer for Tagged<'_, K> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        self.complete_until(prefix, limit).0
+    }
+
+    fn complete_until(&self, prefix: &str, limit: usize) -> (Vec<Suggestion>, Option<u64>) {
+        self.trie
+            .complete_where(prefix, limit, self.min_len, |info| info.has_tag(self.tag))
+    }
+}
+
+impl<C: Completer + ?Sized> Completer for &C {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        (**self).complete(prefix, limit)
+    }
+
+    fn complete_until(&self, prefix: &str, limit: usize) -> (Vec<Suggestion>, Option<u64>) {
+        (**self).complete_until(prefix, limit)
+    }
+}
+
+/// Completions of several sources interleaved by weight rather than listed
+/// one source after the other, each source contributing at most its cap. A
+/// word offered by more than one source is listed once, with its highest
+/// weight.
+///
+/// ```
+/// use prefix_tree::{Completer, Merged, Trie};
+///
+/// let dictionary: Trie = ["apple", "apricot"].into_iter().collect();
+/// let history = vec!["apt install", "apt update"];
+/// let merged = Merged::new().source(&dictionary, usize::MAX).source(&history, 1);
+/// assert_eq!(merged.complete("ap", 10).len(), 3);
+/// ```
+#[derive(Default)]
+pub struct Merged<'a> {
+    sources: Vec<(&'a dyn Completer, usize)>,
+}
+
+impl<'a> Merged<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn source(mut self, completer: &'a dyn Completer, cap: usize) -> Self {
+        self.sources.push((completer, cap));
+        self
+    }
+}
+
+impl Completer for Merged<'_> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        let mut suggestions: Vec<Suggestion> = vec![];
+        // Index of every word in `suggestions`.
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for &(source, cap) in &self.sources {
+            for suggestion in source.complete(prefix, cap.min(limit)) {
+                match positions.get(&suggestion.word) {
+                    Some(&i) => {
+                        let merged = &mut suggestions[i];
+                        merged.weight = merged.weight.max(suggestion.weight);
+                    }
+                    None => {
+                        positions.insert(suggestion.word.clone(), suggestions.len());
+                        suggestions.push(suggestion);
+                    }
+                }
+            }
+        }
+        suggestions.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.word.cmp(&b.word)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+/// A plain list of words, e.g. history entries, completed in list order.
+impl<S: AsRef<str>> Completer for Vec<S> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        self.iter()
+            .map(AsRef::as_ref)
+            .filter(|word| word.starts_with(prefix))
+            .take(limit)
+            .map(|word| Suggestion {
+                word: word.to_string(),
+                weight: DEFAULT_WEIGHT,
+            })
+            .collect()
+    }
+}
+
+/// Remembers the completions of the most recently asked prefixes, for
+/// servers whose traffic keeps coming back to the same few. The cache lives
+/// and dies with the completer it wraps, so replacing that (e.g. on reload)
+/// clears it too. Completions are asked for again once one of the words in
+/// them expires, see [`Completer::complete_until`].
+///
+/// ```
+/// use std::cell::Cell;
+/// use prefix_tree::{Cached, Completer, Suggestion};
+///
+/// // Counts its calls, and says its completions have already expired.
+/// struct Expiring(Cell<usize>);
+///
+/// impl Completer for Expiring {
+///     fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+///         self.complete_until(prefix, limit).0
+///     }
+///
+///     fn complete_until(&self, prefix: &str, _: usize) -> (Vec<Suggestion>, Option<u64>) {
+///         self.0.set(self.0.get() + 1);
+///         let word = format!("{}-1", prefix);
+///         (vec![Suggestion { word, weight: 1 }], Some(0))
+///     }
+/// }
+///
+/// let cached = Cached::new(Expiring(Cell::new(0)), 10);
+/// cached.complete("session", 10);
+/// cached.complete("session", 10);
+/// assert_eq!(cached.get_ref().0.get(), 2);
+///
+/// let cached = Cached::new(vec!["session-1", "session-2"], 10);
+/// cached.complete("session", 10);
+/// assert_eq!(cached.complete("session", 10).len(), 2);
+/// ```
+pub struct Cached<C> {
+    completer: C,
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+// A prefix and a limit.
+type CacheKey = (String, usize);
+
+#[derive(Default)]
+struct CacheState {
+    clock: u64,
+    // The completions of each (prefix, limit), with when they were last used
+    // and when the first of their words expires.
+    entries: HashMap<CacheKey, (u64, Option<u64>, Vec<Suggestion>)>,
+    // The same keys, least recently used first.
+    by_age: BTreeMap<u64, CacheKey>,
+}
+
+impl<C: Completer> Cached<C> {
+    /// Caches up to `capacity` queries; with 0, nothing is cached.
+    pub fn new(completer: C, capacity: usize) -> Self {
+        Self {
+            completer,
+            capacity,
+            state: Mutex::default(),
+        }
+    }
+
+    /// The completer whose completions are cached.
+    pub fn get_ref(&self) -> &C {
+        &self.completer
+    }
+}
+
+impl<C: Completer> Completer for Cached<C> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        self.complete_until(prefix, limit).0
+    }
+
+    fn complete_until(&self, prefix: &str, limit: usize) -> (Vec<Suggestion>, Option<u64>) {
+        if self.capacity == 0 {
+            return self.completer.complete_until(prefix, limit);
+        }
+        let key = (prefix.to_string(), limit);
+        {
+            let mut state = self.state.lock().unwrap();
+            state.clock += 1;
+            let now = state.clock;
+            match state.entries.get_mut(&key) {
+                // A word in them has expired, so they are completed again.
+                Some((used, Some(expires), _)) if *expires <= crate::trie::now() => {
+                    let last_used = *used;
+                    state.by_age.remove(&last_used);
+                    state.entries.remove(&key);
+                }
+                Some((used, expires, suggestions)) => {
+                    let last_used = std::mem::replace(used, now);
+                    let found = (suggestions.clone(), *expires);
+                    state.by_age.remove(&last_used);
+                    state.by_age.insert(now, key);
+                    return found;
+                }
+                None => {}
+            }
+        }
+        // Not holding the lock, so other queries needn't wait for this one.
+        let (suggestions, expires) = self.completer.complete_until(prefix, limit);
+        let mut state = self.state.lock().unwrap();
+        state.clock += 1;
+        let now = state.clock;
+        if let Some((last_used, _, _)) = state
+            .entries
+            .insert(key.clone(), (now, expires, suggestions.clone()))
+        {
+            // Completed by another query in the meantime.
+            state.by_age.remove(&last_used);
+        }
+        state.by_age.insert(now, key);
+        while state.entries.len() > self.capacity {
+            let Some((_, oldest)) = state.by_age.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+        (suggestions, expires)
+    }
+}
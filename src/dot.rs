@@ -0,0 +1,368 @@
+use std::io::{self, Write};
+
+use crate::{key::TrieKey, order::ChildOrder, trie::Node, trie::Trie};
+
+/// How [`write_dot_with`] draws a trie.
+#[derive(Debug)]
+pub struct DotStyle {
+    /// Draws the subtree of every first key as a cluster, see
+    /// [`write_dot_clustered`].
+    pub clusters: bool,
+    /// The Graphviz shape of the nodes where a word ends, `None` to draw them
+    /// like every other node.
+    pub word_shape: Option<String>,
+    /// Labels the nodes where a word ends with the whole word rather than
+    /// their last key.
+    pub word_labels: bool,
+    /// Draws only the nodes at most this many keys below the root, the ones
+    /// with children left out dashed, see [`depth_within`].
+    pub max_depth: Option<usize>,
+    /// Leaves the labels off the edges, which only repeat the key of the node
+    /// they lead to, and numbers the nodes in the order they are written
+    /// rather than naming them after their path. The tooltips still tell the
+    /// prefix of every node.
+    pub compact: bool,
+    /// Draws a chain of single children ending in a leaf as one node,
+    /// labelled with the rest of the word.
+    pub merge_chains: bool,
+}
+
+impl Default for DotStyle {
+    fn default() -> Self {
+        Self {
+            clusters: false,
+            word_shape: Some("doublecircle".to_string()),
+            word_labels: false,
+            max_depth: None,
+            compact: false,
+            merge_chains: false,
+        }
+    }
+}
+
+/// Writes the trie as a Graphviz digraph, one node per key, with the nodes
+/// where a word ends drawn as double circles.
+///
+/// Every node has a tooltip with its prefix and the number of words below
+/// it, which SVG renderings show on hover.
+///
+/// A node is named after the path to it, `"/ap"` for the node of "ap" and
+/// `"/"` for the root, and children are written in key order, or the trie's
+/// [child order](Trie::with_child_order), so the same dictionary always gives
+/// the same file and two dictionaries' files diff node by node.
+///
+/// The nodes are visited on a stack of their own, so a word's length is no
+/// matter to the thread's, though naming every node after its path makes the
+/// file grow with the square of it:
+///
+/// ```
+/// use std::{io::BufRead, thread};
+///
+/// use prefix_tree::{dot, Trie};
+///
+/// let trie: Trie = ["x".repeat(5_000)].iter().map(String::as_str).collect();
+/// let tiny_stack = thread::Builder::new().stack_size(64 * 1024);
+/// let out = tiny_stack
+///     .spawn(move || {
+///         let mut out = vec![];
+///         dot::write_dot(&mut out, &trie).unwrap();
+///         out
+///     })
+///     .unwrap()
+///     .join()
+///     .unwrap();
+/// // The root, then every node and the edge into it, then the closing brace.
+/// assert_eq!(out.lines().count(), 1 + 1 + 2 * 5_000 + 1);
+/// ```
+pub fn write_dot<W: Write, K: TrieKey, V>(out: &mut W, trie: &Trie<K, V>) -> io::Result<()> {
+    write_dot_with(out, trie, &DotStyle::default())
+}
+
+/// Like [`write_dot`], with the subtree of every first key drawn as a
+/// labelled, colored cluster of its own, which keeps large renderings
+/// navigable.
+pub fn write_dot_clustered<W: Write, K: TrieKey, V>(
+    out: &mut W,
+    trie: &Trie<K, V>,
+) -> io::Result<()> {
+    let style = DotStyle {
+        clusters: true,
+        ..DotStyle::default()
+    };
+    write_dot_with(out, trie, &style)
+}
+
+/// [`write_dot`] drawn in the given style.
+pub fn write_dot_with<W: Write, K: TrieKey, V>(
+    out: &mut W,
+    trie: &Trie<K, V>,
+    style: &DotStyle,
+) -> io::Result<()> {
+    let mut names = Names {
+        compact: style.compact,
+        next: 0,
+    };
+    writeln!(out, "digraph Trie {{")?;
+    let root = names.name::<K>(&[]);
+    write_node(out, &trie.root, &[], 0, &root, "  ", style)?;
+    if style.clusters {
+        write_clusters(out, trie, &root, &mut names, style)?;
+    } else {
+        dump_dot(
+            out,
+            &trie.root,
+            &trie.order,
+            &root,
+            &mut Vec::new(),
+            &mut names,
+            style,
+        )?;
+    }
+    writeln!(out, "}}")
+}
+
+// Names the nodes, quoted as dot needs them.
+struct Names {
+    compact: bool,
+    // The number of the next node named when compact.
+    next: usize,
+}
+
+impl Names {
+    fn name<K: TrieKey>(&mut self, path: &[K]) -> String {
+        if self.compact {
+            self.next += 1;
+            format!("n{}", self.next - 1)
+        } else {
+            format!("\"/{}\"", escape(&K::join(path)))
+        }
+    }
+}
+
+/// The depth to draw `trie` to, see [`DotStyle::max_depth`], for the drawing
+/// to have at most `max_nodes` nodes, along with how many it has then. `None`
+/// if every node fits; the root is drawn whatever `max_nodes`.
+///
+/// ```
+/// use prefix_tree::{dot, Trie};
+///
+/// let trie: Trie = ["ape", "apt", "bee"].into_iter().collect();
+/// // The root, then "a" and "b", then "ap" and "be", then the words.
+/// assert_eq!(dot::depth_within(&trie, 5), Some((2, 5)));
+/// assert_eq!(dot::depth_within(&trie, 8), None);
+/// ```
+pub fn depth_within<K: TrieKey, V>(trie: &Trie<K, V>, max_nodes: usize) -> Option<(usize, usize)> {
+    let (mut depth, mut nodes) = (0, 1);
+    let mut level = vec![&trie.root];
+    loop {
+        let next: Vec<_> = level
+            .iter()
+            .flat_map(|node| node.children.values())
+            .collect();
+        if next.is_empty() {
+            return None;
+        }
+        if nodes + next.len() > max_nodes {
+            return Some((depth, nodes));
+        }
+        depth += 1;
+        nodes += next.len();
+        level = next;
+    }
+}
+
+// Fill colors of the clusters, in turn.
+const CLUSTER_COLORS: &[&str] = &[
+    "lightblue",
+    "lightpink",
+    "palegreen",
+    "khaki",
+    "plum",
+    "lightsalmon",
+    "paleturquoise",
+    "wheat",
+];
+
+fn write_clusters<W: Write, K: TrieKey, V>(
+    out: &mut W,
+    trie: &Trie<K, V>,
+    root: &str,
+    names: &mut Names,
+    style: &DotStyle,
+) -> io::Result<()> {
+    if style.max_depth == Some(0) {
+        return Ok(());
+    }
+    for (i, (key, child)) in trie.order.sorted(&trie.root).into_iter().enumerate() {
+        let mut path = vec![];
+        let id = escape(&K::join(std::slice::from_ref(key)));
+        let label = escape(&key.to_string());
+        writeln!(out, "  subgraph \"cluster_{}\" {{", id)?;
+        writeln!(out, "    label=\"{}\"", label)?;
+        writeln!(out, "    style=filled")?;
+        writeln!(
+            out,
+            "    color={}",
+            CLUSTER_COLORS[i % CLUSTER_COLORS.len()]
+        )?;
+        let (name, below) = write_child(out, key, child, &mut path, "    ", names, style)?;
+        if let Some(node) = below {
+            dump_dot(out, node, &trie.order, &name, &mut path, names, style)?;
+        }
+        writeln!(out, "  }}")?;
+        write_edge(out, "  ", root, &name, key, style)?;
+    }
+    Ok(())
+}
+
+// Writes the nodes below `root`, named `name`, and the edges into them, depth
+// first, indented one level deeper inside clusters. The nodes still to write
+// sit on a stack of our own, with the length of `path` at their parent, so
+// long words don't overflow the thread's.
+fn dump_dot<W: Write, K: TrieKey, V>(
+    file: &mut W,
+    root: &Node<K, V>,
+    order: &ChildOrder<K, V>,
+    name: &str,
+    path: &mut Vec<K>,
+    names: &mut Names,
+    style: &DotStyle,
+) -> io::Result<()> {
+    let indent = if style.clusters { "    " } else { "  " };
+    if style.max_depth.is_some_and(|depth| path.len() >= depth) {
+        return Ok(());
+    }
+    let base = path.len();
+    let mut stack = vec![(name.to_string(), base, order.sorted(root).into_iter())];
+    while let Some((parent, len, children)) = stack.last_mut() {
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        path.truncate(*len);
+        let (name, below) = write_child(file, key, child, path, indent, names, style)?;
+        write_edge(file, indent, parent, &name, key, style)?;
+        if let Some(node) = below {
+            stack.push((name, path.len(), order.sorted(node).into_iter()));
+        }
+    }
+    path.truncate(base);
+    Ok(())
+}
+
+// Writes `child`, reached by `key`, which is pushed onto `path`. With merged
+// chains, a leaf at the end of single children below it is written in its
+// stead, and their keys pushed as well. Returns the name of the node written,
+// with the node whose children are still to be written, if any.
+fn write_child<'a, W: Write, K: TrieKey, V>(
+    out: &mut W,
+    key: &K,
+    child: &'a Node<K, V>,
+    path: &mut Vec<K>,
+    indent: &str,
+    names: &mut Names,
+    style: &DotStyle,
+) -> io::Result<(String, Option<&'a Node<K, V>>)> {
+    path.push(key.clone());
+    let mut shown = 1;
+    let mut node = child;
+    if style.merge_chains {
+        if let Some((keys, leaf)) = leaf_chain(child) {
+            shown += keys.len();
+            path.extend(keys.into_iter().cloned());
+            node = leaf;
+        }
+    }
+    let name = names.name(path);
+    write_node(out, node, path, shown, &name, indent, style)?;
+    let below = style.max_depth.is_none_or(|depth| path.len() < depth);
+    Ok((name, Some(node).filter(|_| below)))
+}
+
+// The keys down from `node` to the leaf its single children lead to, if no
+// word ends on the way and they lead to one at all.
+fn leaf_chain<K: TrieKey, V>(mut node: &Node<K, V>) -> Option<(Vec<&K>, &Node<K, V>)> {
+    let mut keys = vec![];
+    while node.value.is_none() && node.children.len() == 1 {
+        let (key, child) = node.children.iter().next()?;
+        keys.push(key);
+        node = child;
+    }
+    Some((keys, node)).filter(|(keys, node)| !keys.is_empty() && node.children.is_empty())
+}
+
+fn write_edge<W: Write, K: TrieKey>(
+    out: &mut W,
+    indent: &str,
+    from: &str,
+    to: &str,
+    key: &K,
+    style: &DotStyle,
+) -> io::Result<()> {
+    if style.compact {
+        writeln!(out, "{}{} -> {}", indent, from, to)
+    } else {
+        writeln!(
+            out,
+            "{}{} -> {} [label=\"{}\"]",
+            indent,
+            from,
+            to,
+            escape(&key.to_string())
+        )
+    }
+}
+
+// Writes the node at `path` as `name`, labelled with the last `shown` keys of
+// the path and marked as a word end if one ends there.
+fn write_node<W: Write, K: TrieKey, V>(
+    out: &mut W,
+    node: &Node<K, V>,
+    path: &[K],
+    shown: usize,
+    name: &str,
+    indent: &str,
+    style: &DotStyle,
+) -> io::Result<()> {
+    let prefix = K::join(path);
+    let is_word = node.value.is_some();
+    let label = match path.len() {
+        0 => "root".to_string(),
+        _ if is_word && style.word_labels => prefix.clone(),
+        len => path[len - shown..]
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+    };
+    write!(
+        out,
+        "{}{} [label=\"{}\" tooltip=\"{}\"",
+        indent,
+        name,
+        escape(&label),
+        tooltip(&prefix, node.words)
+    )?;
+    if let (true, Some(shape)) = (is_word, &style.word_shape) {
+        write!(out, " shape=\"{}\"", escape(shape))?;
+    }
+    if style.max_depth == Some(path.len()) && !node.children.is_empty() {
+        write!(out, " style=dashed")?;
+    }
+    writeln!(out, "]")
+}
+
+fn tooltip(prefix: &str, words: usize) -> String {
+    let words = match words {
+        1 => "1 word".to_string(),
+        n => format!("{} words", n),
+    };
+    match prefix {
+        "" => escape(&words),
+        _ => escape(&format!("{}: {}", prefix, words)),
+    }
+}
+
+// Escapes text for a double-quoted dot string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
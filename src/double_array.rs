@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+
+use crate::{
+    complete::{Completer, Suggestion},
+    frozen::Frozen,
+    key::TrieKey,
+    trie::WordInfo,
+};
+
+// `check` of a slot no state occupies, and `value` of a state without one.
+const FREE: u32 = u32::MAX;
+const NO_VALUE: u32 = u32::MAX;
+
+/// A read-only trie laid out as a double array, made with
+/// [`Frozen::into_double_array`].
+///
+/// Every key is given a small code, and the state reached from state `s` by
+/// the key coded `c` is `base[s] + c`, valid only if `check` of that slot
+/// names `s` as its parent. A lookup is then a couple of array reads per key
+/// in a few flat vectors, which the cache likes far better than chasing
+/// pointers. Listing a state's children means trying every code, so
+/// completion is best suited to small alphabets such as `char` keys.
+///
+/// ```
+/// use prefix_tree::{Completer, Trie};
+///
+/// let trie: Trie = ["apple", "apricot", "banana"].into_iter().collect();
+/// let array = trie.freeze().into_double_array();
+/// assert!(array.contains("apple"));
+/// assert_eq!(array.complete("ap", 10).len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct DoubleArray<K = char, V = WordInfo> {
+    // Sorted; a key's code is its position plus one.
+    alphabet: Vec<K>,
+    base: Vec<u32>,
+    check: Vec<u32>,
+    // Index into `values` of each state's value.
+    value: Vec<u32>,
+    values: Vec<V>,
+    len: usize,
+}
+
+impl<K: TrieKey, V> Frozen<K, V> {
+    /// Converts the trie to its [`DoubleArray`] form, which visits children
+    /// in key order whatever the [`ChildOrder`](crate::ChildOrder) frozen with.
+    pub fn into_double_array(self) -> DoubleArray<K, V> {
+        let len = self.len();
+        let mut nodes = self.nodes;
+        let mut alphabet: Vec<K> = nodes
+            .iter()
+            .flat_map(|node| node.children.iter().map(|(key, _)| key.clone()))
+            .collect();
+        alphabet.sort();
+        alphabet.dedup();
+        let mut array = DoubleArray {
+            alphabet,
+            base: vec![0],
+            // The root is state 0; no transition leads back to it.
+            check: vec![0],
+            value: vec![NO_VALUE],
+            values: Vec::new(),
+            len,
+        };
+
+        // The state of every frozen node, assigned as its parent is placed.
+        let mut states = vec![0; nodes.len()];
+        let mut queue = VecDeque::from([0]);
+        // Every slot below this one is occupied.
+        let mut free = 1;
+        while let Some(id) = queue.pop_front() {
+            let state = states[id];
+            if let Some(value) = nodes[id].value.take() {
+                array.value[state] = array.values.len() as u32;
+                array.values.push(value);
+            }
+            let children = &nodes[id].children;
+            if children.is_empty() {
+                continue;
+            }
+            let codes: Vec<usize> = children
+                .iter()
+                .map(|(key, _)| array.code(key).expect("key is in the alphabet"))
+                .collect();
+            while !array.is_free(free) {
+                free += 1;
+            }
+            // The children are sorted, so codes[0] is the smallest.
+            let mut base = free.saturating_sub(codes[0]);
+            while !codes.iter().all(|&code| array.is_free(base + code)) {
+                base += 1;
+            }
+            array.base[state] = base as u32;
+            for (&code, (_, child)) in codes.iter().zip(children) {
+                let slot = base + code;
+                array.occupy(slot, state);
+                states[*child as usize] = slot;
+                queue.push_back(*child as usize);
+            }
+        }
+        array
+    }
+}
+
+impl<K: TrieKey, V> DoubleArray<K, V> {
+    /// The number of words stored.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, word: &str) -> Option<&V> {
+        match self.value[self.find(word)?] {
+            NO_VALUE => None,
+            i => Some(&self.values[i as usize]),
+        }
+    }
+
+    /// Whether `word` itself is stored, see [`Trie::contains`](crate::Trie::contains).
+    pub fn contains(&self, word: &str) -> bool {
+        self.get(word).is_some()
+    }
+
+    /// Whether any stored word starts with `prefix`.
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        self.find(prefix).is_some()
+    }
+
+    /// Calls `f` with every word and its value, in key order.
+    pub fn for_each_word(&self, mut f: impl FnMut(&str, &V)) {
+        self.for_each_word_below(0, &mut Vec::new(), &mut f);
+    }
+
+    fn code(&self, key: &K) -> Option<usize> {
+        self.alphabet.binary_search(key).ok().map(|i| i + 1)
+    }
+
+    fn child(&self, state: usize, code: usize) -> Option<usize> {
+        let slot = self.base[state] as usize + code;
+        (self.check.get(slot) == Some(&(state as u32))).then_some(slot)
+    }
+
+    fn find(&self, text: &str) -> Option<usize> {
+        let mut state = 0;
+        for key in K::segment(text) {
+            state = self.child(state, self.code(&key)?)?;
+        }
+        Some(state)
+    }
+
+    fn for_each_word_below(&self, state: usize, buffer: &mut Vec<K>, f: &mut impl FnMut(&str, &V)) {
+        let mut visit = |state: usize, buffer: &[K]| {
+            let value = self.value[state];
+            if value != NO_VALUE {
+                f(&K::join(buffer), &self.values[value as usize]);
+            }
+        };
+        visit(state, buffer);
+        let base = buffer.len();
+        // Each state with the alphabet index to look for its next child from.
+        let mut stack = vec![(state, 0)];
+        while let Some((state, next)) = stack.last_mut() {
+            let found = (*next..self.alphabet.len())
+                .find_map(|i| self.child(*state, i + 1).map(|child| (i, child)));
+            let Some((i, child)) = found else {
+                stack.pop();
+                continue;
+            };
+            *next = i + 1;
+            buffer.truncate(base + stack.len() - 1);
+            buffer.push(self.alphabet[i].clone());
+            visit(child, buffer);
+            stack.push((child, 0));
+        }
+        buffer.truncate(base);
+    }
+
+    fn is_free(&self, slot: usize) -> bool {
+        self.check.get(slot).is_none_or(|&check| check == FREE)
+    }
+
+    fn occupy(&mut self, slot: usize, parent: usize) {
+        if slot >= self.check.len() {
+            self.base.resize(slot + 1, 0);
+            self.check.resize(slot + 1, FREE);
+            self.value.resize(slot + 1, NO_VALUE);
+        }
+        self.check[slot] = parent as u32;
+    }
+}
+
+impl<K: TrieKey> Completer for DoubleArray<K> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        let Some(state) = self.find(prefix) else {
+            return vec![];
+        };
+        let mut suggestions = vec![];
+        self.for_each_word_below(state, &mut K::segment(prefix), &mut |word, info| {
+            if info.expired() {
+                return;
+            }
+            suggestions.push(Suggestion {
+                word: info.display.as_deref().unwrap_or(word).to_string(),
+                weight: info.weight,
+            });
+        });
+        suggestions.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.word.cmp(&b.word)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
@@ -0,0 +1,296 @@
+use std::cmp::Ordering;
+
+use smallvec::SmallVec;
+
+use crate::{
+    complete::{rank, until, Completer, Suggestion},
+    key::TrieKey,
+    order::ChildOrder,
+    trie::{Node, Trie, WordInfo},
+};
+
+/// A read-only [`Trie`] for query-heavy use, made with [`Trie::freeze`].
+///
+/// The nodes live in a single vector, and each one keeps its children as a
+/// short list sorted by key, found by binary search. Most nodes have only a
+/// handful of children, for which that is both smaller and faster than a
+/// `HashMap`. Every node also counts the words below it, which makes
+/// [`Frozen::select`] and [`Frozen::rank`] walks down a single path.
+///
+/// The trie's [`ChildOrder`] comes along: words are visited and equals
+/// completed in it, while what is defined by sorted position, like
+/// [`Frozen::select`], stays in key order, as it does for the trie.
+///
+/// ```
+/// use prefix_tree::{Completer, Trie};
+///
+/// let trie: Trie = ["apple", "apricot", "banana"].into_iter().collect();
+/// let frozen = trie.freeze();
+/// assert!(frozen.contains("apple"));
+/// assert_eq!(frozen.complete("ap", 10).len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct Frozen<K = char, V = WordInfo> {
+    // The root is the first node.
+    pub(crate) nodes: Vec<FrozenNode<K, V>>,
+    // With a child order of the trie's own, the positions in `children` of
+    // each node's children in that order; empty for key order.
+    visits: Vec<SmallVec<[u32; 4]>>,
+    len: usize,
+}
+
+#[derive(Debug)]
+pub(crate) struct FrozenNode<K, V> {
+    pub(crate) value: Option<V>,
+    // Sorted by key, with the index of each child in `nodes`.
+    pub(crate) children: SmallVec<[(K, u32); 4]>,
+    // Words in the subtree, this node's own included.
+    words: usize,
+}
+
+impl<K: TrieKey, V> Trie<K, V> {
+    /// Converts the trie to its read-only [`Frozen`] form, keeping its
+    /// [`ChildOrder`].
+    ///
+    /// ```
+    /// use prefix_tree::{ChildOrder, Completer, Trie};
+    ///
+    /// let trie: Trie = ["apple", "banana", "blueberry", "cherry"].into_iter().collect();
+    /// let trie = trie.with_child_order(ChildOrder::most_words());
+    /// let completed = trie.complete("", 4);
+    /// let frozen = trie.freeze();
+    /// assert_eq!(frozen.complete("", 4), completed);
+    /// assert_eq!(completed[0].word, "banana");
+    /// let mut words = vec![];
+    /// frozen.for_each_word(|word, _| words.push(word.to_string()));
+    /// assert_eq!(words, ["banana", "blueberry", "apple", "cherry"]);
+    /// assert_eq!(frozen.select(0, "").as_deref(), Some("apple"));
+    /// ```
+    pub fn freeze(self) -> Frozen<K, V> {
+        let len = self.len();
+        let mut nodes = Vec::new();
+        let mut visits = Vec::new();
+        freeze(self.root, &self.order, &mut nodes, &mut visits);
+        Frozen { nodes, visits, len }
+    }
+}
+
+impl<K: TrieKey, V> Frozen<K, V> {
+    /// The number of words stored.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, word: &str) -> Option<&V> {
+        self.nodes[self.find(word)?].value.as_ref()
+    }
+
+    /// Whether `word` itself is stored, see [`Trie::contains`].
+    pub fn contains(&self, word: &str) -> bool {
+        self.get(word).is_some()
+    }
+
+    /// Whether any stored word starts with `prefix`.
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        // Frozen from a trie that prunes emptied branches, so every node
+        // leads to a word.
+        self.find(prefix).is_some()
+    }
+
+    /// Calls `f` with every word and its value, in the trie's child order.
+    pub fn for_each_word(&self, mut f: impl FnMut(&str, &V)) {
+        self.for_each_word_below(0, &mut Vec::new(), &mut f);
+    }
+
+    /// The `k`-th word starting with `prefix` in key order, counting from 0,
+    /// found by following the subtree counts down a single path.
+    pub fn select(&self, mut k: usize, prefix: &str) -> Option<String> {
+        let mut id = self.find(prefix)?;
+        let mut keys = K::segment(prefix);
+        if k >= self.nodes[id].words {
+            return None;
+        }
+        loop {
+            let node = &self.nodes[id];
+            if node.value.is_some() {
+                if k == 0 {
+                    return Some(K::join(&keys));
+                }
+                k -= 1;
+            }
+            for (key, child) in &node.children {
+                let words = self.nodes[*child as usize].words;
+                if k < words {
+                    keys.push(key.clone());
+                    id = *child as usize;
+                    break;
+                }
+                k -= words;
+            }
+        }
+    }
+
+    /// How many stored words sort strictly before `word`, which needn't be
+    /// stored itself; the counterpart of [`Frozen::select`].
+    pub fn rank(&self, word: &str) -> usize {
+        let mut rank = 0;
+        let mut id = 0;
+        for key in K::segment(word) {
+            let node = &self.nodes[id];
+            // A proper prefix of the word sorts before it.
+            rank += usize::from(node.value.is_some());
+            let mut next = None;
+            for (k, child) in &node.children {
+                match k.cmp(&key) {
+                    Ordering::Less => rank += self.nodes[*child as usize].words,
+                    Ordering::Equal => next = Some(*child as usize),
+                    Ordering::Greater => break,
+                }
+            }
+            match next {
+                Some(child) => id = child,
+                None => break,
+            }
+        }
+        rank
+    }
+
+    fn find(&self, text: &str) -> Option<usize> {
+        let mut id = 0;
+        for key in K::segment(text) {
+            let children = &self.nodes[id].children;
+            let i = children.binary_search_by(|(k, _)| k.cmp(&key)).ok()?;
+            id = children[i].1 as usize;
+        }
+        Some(id)
+    }
+
+    // The `i`-th child of node `id` in the child order, with its key.
+    fn child(&self, id: usize, i: usize) -> Option<&(K, u32)> {
+        let children = &self.nodes[id].children;
+        match self.visits.get(id) {
+            Some(visits) => children.get(*visits.get(i)? as usize),
+            None => children.get(i),
+        }
+    }
+
+    // The walk keeps its own stack, a node and the next of its children per
+    // level, so a long word can't overflow the thread's.
+    fn for_each_word_below(&self, id: usize, buffer: &mut Vec<K>, f: &mut impl FnMut(&str, &V)) {
+        if let Some(value) = &self.nodes[id].value {
+            f(&K::join(buffer), value);
+        }
+        let base = buffer.len();
+        let mut stack = vec![(id, 0)];
+        while let Some((parent, next)) = stack.last_mut() {
+            let Some((key, child)) = self.child(*parent, *next) else {
+                stack.pop();
+                continue;
+            };
+            *next += 1;
+            let child = *child as usize;
+            buffer.truncate(base + stack.len() - 1);
+            buffer.push(key.clone());
+            if let Some(value) = &self.nodes[child].value {
+                f(&K::join(buffer), value);
+            }
+            stack.push((child, 0));
+        }
+        buffer.truncate(base);
+    }
+}
+
+/// Completes like the trie it was frozen from, telling
+/// [`Completer::complete_until`] when the first word completed expires.
+///
+/// ```
+/// use prefix_tree::{Completer, Entry, Trie};
+///
+/// let mut trie: Trie = Trie::new();
+/// trie.insert_entry(&Entry::parse("session-1\t2\texpires=4102444800"));
+/// trie.insert_entry(&Entry::parse("session-2\t1\texpires=4000000000"));
+/// trie.insert_entry(&Entry::parse("session-3"));
+/// let frozen = trie.freeze();
+/// assert_eq!(frozen.complete_until("session", 1).1, Some(4102444800));
+/// assert_eq!(frozen.complete_until("session", 3).1, Some(4000000000));
+/// assert_eq!(frozen.complete_until("other", 3), (vec![], None));
+/// ```
+impl<K: TrieKey> Completer for Frozen<K> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        self.complete_until(prefix, limit).0
+    }
+
+    fn complete_until(&self, prefix: &str, limit: usize) -> (Vec<Suggestion>, Option<u64>) {
+        let Some(id) = self.find(prefix) else {
+            return (vec![], None);
+        };
+        let mut found = vec![];
+        self.for_each_word_below(id, &mut K::segment(prefix), &mut |word, info| {
+            if info.expired() {
+                return;
+            }
+            let suggestion = Suggestion {
+                word: info.display.as_deref().unwrap_or(word).to_string(),
+                weight: info.weight,
+            };
+            found.push((suggestion, info.expires));
+        });
+        rank(
+            &mut found,
+            limit,
+            self.visits.is_empty(),
+            |(suggestion, _)| suggestion,
+        );
+        until(found)
+    }
+}
+
+// Appends `root` and then its subtree depth first, each node before its
+// children in key order, and unless `order` is key order, where each node's
+// children come in it to `visits`. The children still to append sit on a
+// stack of our own, so a long word can't overflow the thread's.
+fn freeze<K: TrieKey, V>(
+    root: Node<K, V>,
+    order: &ChildOrder<K, V>,
+    nodes: &mut Vec<FrozenNode<K, V>>,
+    visits: &mut Vec<SmallVec<[u32; 4]>>,
+) {
+    // Appends `node`, returning its index and its children, last key first.
+    let mut append = |node: Node<K, V>, nodes: &mut Vec<FrozenNode<K, V>>| {
+        let id = nodes.len();
+        if !order.is_keys() {
+            let mut keys: Vec<&K> = node.children.keys().collect();
+            keys.sort();
+            let positions = order
+                .sorted(&node)
+                .into_iter()
+                .map(|(key, _)| keys.binary_search(&key).expect("a child's key") as u32);
+            visits.push(positions.collect());
+        }
+        let words = node.words;
+        let (value, children) = node.into_parts();
+        nodes.push(FrozenNode {
+            words,
+            value,
+            children: SmallVec::new(),
+        });
+        let mut children: Vec<_> = children.into_iter().collect();
+        children.sort_by(|(a, _), (b, _)| b.cmp(a));
+        (id, children)
+    };
+    let mut stack = vec![append(root, nodes)];
+    while let Some((id, children)) = stack.last_mut() {
+        let id = *id;
+        let Some((key, child)) = children.pop() else {
+            stack.pop();
+            continue;
+        };
+        let (child, below) = append(child, nodes);
+        nodes[id].children.push((key, child as u32));
+        stack.push((child, below));
+    }
+}
@@ -0,0 +1,39 @@
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::{key::TrieKey, trie::Trie};
+
+/// An arbitrary trie is built by a mix of inserts, removals, drains, renames
+/// and sorted extends, so fuzz targets see the shapes those leave behind
+/// rather than only freshly inserted words.
+///
+/// ```
+/// use arbitrary::{Arbitrary, Unstructured};
+/// use prefix_tree::Trie;
+///
+/// let mut u = Unstructured::new(b"\x00\x03app\x01\x00\x03ape\x02");
+/// let trie = Trie::<char, u8>::arbitrary(&mut u).unwrap();
+/// assert!(trie.debug_validate().is_ok());
+/// ```
+impl<'a, K: TrieKey, V: Arbitrary<'a>> Arbitrary<'a> for Trie<K, V> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut trie = Trie::new();
+        for _ in 0..u.arbitrary_len::<(&str, V)>()? {
+            match u.int_in_range(0..=5u8)? {
+                0 | 1 => {
+                    trie.insert(u.arbitrary()?, u.arbitrary()?);
+                }
+                2 => {
+                    trie.remove(u.arbitrary()?);
+                }
+                3 => {
+                    trie.drain_prefix(u.arbitrary()?).for_each(drop);
+                }
+                4 => {
+                    trie.rename_prefix(u.arbitrary()?, u.arbitrary()?);
+                }
+                _ => trie.extend_sorted(u.arbitrary::<Vec<(&str, V)>>()?),
+            }
+        }
+        Ok(trie)
+    }
+}
@@ -0,0 +1,398 @@
+use std::time::Instant;
+
+use crate::{
+    complete::{Completer, Suggestion},
+    key::TrieKey,
+    keyboard::Keyboard,
+    plan::{self, Plan, Strategy},
+    trie::Node,
+    trie::Trie,
+};
+
+// Nodes visited between two looks at the clock.
+const DEADLINE_CHECK_INTERVAL: usize = 1024;
+
+#[derive(Debug)]
+pub struct Match {
+    pub word: String,
+    pub distance: f64,
+    pub weight: u64,
+}
+
+/// Edit costs used by `search`. Every edit costs 1, except substituting a
+/// character for one on a neighbouring key of `keyboard`, which costs
+/// `adjacent_cost`: a fat-fingered "nab" is closer to "nan" than to "nap".
+#[derive(Debug)]
+pub struct Costs {
+    pub keyboard: Option<Keyboard>,
+    pub adjacent_cost: f64,
+    /// Words of fewer keys are never matched; the walk passes them by.
+    pub min_len: usize,
+}
+
+impl Default for Costs {
+    fn default() -> Self {
+        Self {
+            keyboard: Keyboard::layout("qwerty"),
+            adjacent_cost: 0.5,
+            min_len: 0,
+        }
+    }
+}
+
+impl Costs {
+    fn substitution<K: TrieKey>(&self, a: &K, b: &K) -> f64 {
+        let adjacent = |k: &Keyboard| match (a.as_char(), b.as_char()) {
+            (Some(a), Some(b)) => k.adjacent(a, b),
+            _ => false,
+        };
+        if a == b {
+            0.0
+        } else if self.keyboard.as_ref().is_some_and(adjacent) {
+            self.adjacent_cost
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Collects every word within `max_dist` edits of `word`. The distance is the
+/// optimal string alignment distance, so a swap of two adjacent characters
+/// ("teh" -> "the") costs a single edit like it does in spell-checkers.
+///
+/// One row of the edit-distance matrix is computed per trie node and shared by
+/// every word below it; subtrees are pruned as soon as no cell of the row is
+/// within reach.
+pub fn search<K: TrieKey>(trie: &Trie<K>, word: &str, max_dist: f64, costs: &Costs) -> Vec<Match> {
+    search_until(trie, word, max_dist, costs, None).0
+}
+
+/// Like [`search`], but gives up once `deadline` has passed, returning the
+/// matches found so far and whether the search was cut short.
+pub fn search_until<K: TrieKey>(
+    trie: &Trie<K>,
+    word: &str,
+    max_dist: f64,
+    costs: &Costs,
+    deadline: Option<Instant>,
+) -> (Vec<Match>, bool) {
+    let (matches, truncated, _) = search_explained(trie, word, max_dist, costs, deadline);
+    (matches, truncated)
+}
+
+/// How much of the trie a search looked at, see [`search_explained`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    pub nodes_visited: usize,
+    /// Subtrees left out because no word in them could be close enough.
+    pub branches_pruned: usize,
+}
+
+/// Like [`search_until`], also reporting how much of the trie was searched.
+pub fn search_explained<K: TrieKey>(
+    trie: &Trie<K>,
+    word: &str,
+    max_dist: f64,
+    costs: &Costs,
+    deadline: Option<Instant>,
+) -> (Vec<Match>, bool, Stats) {
+    Search::new(word, max_dist, costs, deadline, false).run(trie)
+}
+
+/// Collects every word starting with a prefix within `max_dist` edits of
+/// `prefix`, so a mistyped "pyhto" still completes to "python". A word's
+/// distance is that of its closest prefix.
+pub fn search_prefix<K: TrieKey>(
+    trie: &Trie<K>,
+    prefix: &str,
+    max_dist: f64,
+    costs: &Costs,
+) -> Vec<Match> {
+    Search::new(prefix, max_dist, costs, None, true).run(trie).0
+}
+
+/// What [`search`] for `word` would do, without searching.
+///
+/// ```
+/// use prefix_tree::{fuzzy::{self, Costs}, plan::Strategy, Trie};
+///
+/// let trie: Trie = ["apple", "apply", "banana"].into_iter().collect();
+/// let plan = fuzzy::plan(&trie, "aple", 1.0, &Costs::default());
+/// assert_eq!(plan.strategy, Strategy::Traversal);
+/// assert_eq!(plan.visits, 13);
+/// ```
+pub fn plan<K: TrieKey>(trie: &Trie<K>, word: &str, max_dist: f64, costs: &Costs) -> Plan {
+    plan_search(trie, word, max_dist, costs, false)
+}
+
+/// What [`search_prefix`] for `prefix` would do, without searching.
+pub fn plan_prefix<K: TrieKey>(trie: &Trie<K>, prefix: &str, max_dist: f64, costs: &Costs) -> Plan {
+    plan_search(trie, prefix, max_dist, costs, true)
+}
+
+fn plan_search<K: TrieKey>(
+    trie: &Trie<K>,
+    word: &str,
+    max_dist: f64,
+    costs: &Costs,
+    prefix: bool,
+) -> Plan {
+    let len = K::segment(word).len();
+    let edits = if max_dist == 1.0 { "edit" } else { "edits" };
+    let mut prunes = vec![format!(
+        "subtrees with no prefix within {} {} of {:?}",
+        max_dist, edits, word
+    )];
+    // Every insertion or deletion costs a whole edit, so no word more than
+    // `max_dist` keys longer than `word` can be within reach, nor lead to one.
+    let depth = if prefix {
+        prunes.push("none below a prefix within reach, where every word matches".to_string());
+        usize::MAX
+    } else {
+        len + max_dist.max(0.0) as usize + 1
+    };
+    let reach = plan::reach(&trie.root, depth);
+    if reach.cut {
+        prunes.push(format!("nothing deeper than {} keys", depth));
+    }
+    if costs.min_len > 0 {
+        prunes.push(format!(
+            "words of fewer than {} keys are passed by, not matched",
+            costs.min_len
+        ));
+    }
+    Plan {
+        // Every prefix of up to `max_dist` keys is within reach of a word no
+        // longer than that.
+        strategy: if len as f64 <= max_dist && !reach.cut && !reach.capped {
+            Strategy::FullScan
+        } else {
+            Strategy::Traversal
+        },
+        visits: reach.nodes,
+        capped: reach.capped,
+        prunes,
+    }
+}
+
+impl<K: TrieKey> Trie<K> {
+    /// Completes prefixes typed with up to `max_dist` edits, see
+    /// [`search_prefix`].
+    pub fn fuzzy_prefix(&self, max_dist: f64) -> FuzzyPrefix<'_, K> {
+        FuzzyPrefix {
+            trie: self,
+            max_dist,
+            costs: Costs::default(),
+        }
+    }
+}
+
+/// Completions of a trie forgiving typos in the prefix, see
+/// [`Trie::fuzzy_prefix`]. The closest come first, and the heaviest among
+/// equally close ones.
+pub struct FuzzyPrefix<'a, K> {
+    trie: &'a Trie<K>,
+    max_dist: f64,
+    costs: Costs,
+}
+
+impl<K> FuzzyPrefix<'_, K> {
+    pub fn costs(mut self, costs: Costs) -> Self {
+        self.costs = costs;
+        self
+    }
+}
+
+impl<K: TrieKey> Completer for FuzzyPrefix<'_, K> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        let mut matches = search_prefix(self.trie, prefix, self.max_dist, &self.costs);
+        matches.sort_by(|a, b| {
+            a.distance
+                .total_cmp(&b.distance)
+                .then_with(|| b.weight.cmp(&a.weight))
+                .then_with(|| a.word.cmp(&b.word))
+        });
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|m| Suggestion {
+                word: m.word,
+                weight: m.weight,
+            })
+            .collect()
+    }
+}
+
+struct Search<'a, K> {
+    target: Vec<K>,
+    // Match the words below every node within reach, not only the node.
+    prefix: bool,
+    max_dist: f64,
+    costs: &'a Costs,
+    buffer: Vec<K>,
+    matches: Vec<Match>,
+    deadline: Option<Instant>,
+    visited: usize,
+    pruned: usize,
+    truncated: bool,
+}
+
+impl<'a, K: TrieKey> Search<'a, K> {
+    fn new(
+        word: &str,
+        max_dist: f64,
+        costs: &'a Costs,
+        deadline: Option<Instant>,
+        prefix: bool,
+    ) -> Self {
+        Search {
+            target: K::segment(word),
+            prefix,
+            max_dist,
+            costs,
+            buffer: vec![],
+            matches: vec![],
+            deadline,
+            visited: 0,
+            pruned: 0,
+            truncated: false,
+        }
+    }
+
+    fn run(mut self, trie: &Trie<K>) -> (Vec<Match>, bool, Stats) {
+        let first_row: Vec<f64> = (0..=self.target.len()).map(|i| i as f64).collect();
+        // The nodes on the path to the one visited, each with its children
+        // still to visit, its row and the row above it, and the closest its
+        // prefixes came. The rows don't fit the call stack of a long word.
+        let mut stack = vec![(trie.root.children.iter(), first_row, None, f64::INFINITY)];
+        loop {
+            let depth = stack.len();
+            let Some((children, row, above, closest)) = stack.last_mut() else {
+                break;
+            };
+            let Some((ch, child)) = children.next() else {
+                stack.pop();
+                continue;
+            };
+            self.buffer.truncate(depth - 1);
+            self.buffer.push(ch.clone());
+            if let Some((next, distance)) = self.visit(child, row, above.as_deref(), *closest) {
+                let above = Some(row.clone());
+                stack.push((child.children.iter(), next, above, distance));
+            }
+        }
+        let stats = Stats {
+            nodes_visited: self.visited,
+            branches_pruned: self.pruned,
+        };
+        (self.matches, self.truncated, stats)
+    }
+
+    // Matches the word in `buffer` ending at `node`, returning its row and
+    // distance if the words below it can still match. `closest` is the
+    // least distance of the prefixes above `node`, for prefix searches.
+    fn visit(
+        &mut self,
+        node: &Node<K>,
+        prev_row: &[f64],
+        prev_prev_row: Option<&[f64]>,
+        closest: f64,
+    ) -> Option<(Vec<f64>, f64)> {
+        if self.out_of_time() {
+            return None;
+        }
+        let target = &self.target;
+        let ch = &self.buffer[self.buffer.len() - 1];
+        let prev_ch = self.buffer.len().checked_sub(2).map(|i| &self.buffer[i]);
+        let mut row = vec![prev_row[0] + 1.0; target.len() + 1];
+        for i in 1..=target.len() {
+            let substitution = prev_row[i - 1] + self.costs.substitution(&target[i - 1], ch);
+            row[i] = substitution.min(row[i - 1] + 1.0).min(prev_row[i] + 1.0);
+            if let (Some(prev_prev_row), Some(prev_ch)) = (prev_prev_row, prev_ch) {
+                if i > 1 && &target[i - 1] == prev_ch && &target[i - 2] == ch {
+                    row[i] = row[i].min(prev_prev_row[i - 2] + 1.0);
+                }
+            }
+        }
+
+        let distance = if self.prefix {
+            closest.min(row[target.len()])
+        } else {
+            row[target.len()]
+        };
+        let long_enough = self.buffer.len() >= self.costs.min_len;
+        if let Some(info) = node
+            .value
+            .as_ref()
+            .filter(|_| long_enough && distance <= self.max_dist)
+        {
+            self.matches.push(Match {
+                word: K::join(&self.buffer),
+                distance,
+                weight: info.weight,
+            });
+        }
+
+        // Below a prefix within reach, every word is.
+        let reached = self.prefix && distance <= self.max_dist;
+        if reached || row.iter().any(|&d| d <= self.max_dist) {
+            Some((row, distance))
+        } else {
+            self.pruned += node.children.len();
+            None
+        }
+    }
+
+    fn out_of_time(&mut self) -> bool {
+        if !self.truncated && self.visited.is_multiple_of(DEADLINE_CHECK_INTERVAL) {
+            self.truncated = self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.visited += 1;
+        self.truncated
+    }
+}
+
+/// Blends closeness and commonness into a single score: every edit costs
+/// `distance_weight`, and the word's weight adds `frequency_weight` per unit of
+/// its logarithm, so a far more common word can outrank a slightly closer one.
+#[derive(Debug, Clone, Copy)]
+pub struct Ranking {
+    pub distance_weight: f64,
+    pub frequency_weight: f64,
+    /// Matches scoring below this are dropped by [`Ranking::rank`].
+    pub min_score: f64,
+}
+
+impl Default for Ranking {
+    fn default() -> Self {
+        Self {
+            distance_weight: 1.0,
+            frequency_weight: 0.1,
+            min_score: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl Ranking {
+    pub fn score(&self, m: &Match) -> f64 {
+        self.frequency_weight * (m.weight as f64).ln_1p() - self.distance_weight * m.distance
+    }
+
+    /// Drops the matches scoring below `min_score` and sorts the others.
+    pub fn rank(&self, matches: &mut Vec<Match>) {
+        matches.retain(|m| self.score(m) >= self.min_score);
+        self.sort(matches);
+    }
+
+    /// Best suggestions first; ties are broken alphabetically so the output is
+    /// stable regardless of the trie's iteration order.
+    pub fn sort(&self, matches: &mut [Match]) {
+        matches.sort_by(|a, b| {
+            self.score(b)
+                .total_cmp(&self.score(a))
+                .then_with(|| a.word.cmp(&b.word))
+        });
+    }
+}
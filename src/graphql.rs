@@ -0,0 +1,498 @@
+//! A GraphQL endpoint over HTTP, for frontends that already speak GraphQL:
+//!
+//! ```graphql
+//! type Query {
+//!   complete(prefix: String!, limit: Int = 10): [Completion!]!
+//!   contains(word: String!): Boolean!
+//!   stats: Stats!
+//! }
+//! ```
+//!
+//! Queries are POSTed as the usual `{"query", "variables", "operationName"}`
+//! JSON to any path, and answered one after the other.
+//!
+//! Clients with no use for GraphQL can `GET /complete?q=<prefix>` instead,
+//! with an optional `limit` (default 10) and the `version` of the
+//! [`response`](crate::response) envelope answered in (default 1). Those
+//! completing many prefixes at once `POST /complete/batch` a JSON array of
+//! them, up to [`MAX_BATCH`], taking the same `limit` and `version`, and are
+//! answered an array of the envelopes, in order. A POST body over
+//! [`MAX_BODY`] bytes is refused with 413, unread past that.
+//!
+//! For orchestrators, `GET /healthz` answers as soon as the server listens,
+//! and `GET /readyz` only once the dictionary is loaded, with its version and
+//! word count, and not while the last reload failed. Until then everything
+//! else is answered with 503, unless the [`Dictionary`] is already
+//! [`loading`](Dictionary::loading) shard by shard, when queries are
+//! answered and `/readyz` tells how many shards are built.
+//!
+//! Once a [`Shutdown`] is requested, queries are refused with 503 and
+//! `/readyz` fails, while those in flight are answered.
+//!
+//! `POST /reload` reads the dictionary again, as SIGHUP does. Given a token,
+//! the server only takes it with an `Authorization: Bearer <token>` header;
+//! queries stay open to everyone.
+//!
+//! A server can answer from several dictionaries, each a [`Namespace`] with a
+//! limit and token of its own: the one named `""` at the paths above, any
+//! other under `/t/<name>/`, e.g. `GET /t/shop/complete?q=ap`, so that
+//! neither sees the other's words, queries or reloads: SIGHUP reloads them
+//! all, `POST /t/<name>/reload` that one alone.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Read},
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    thread,
+};
+
+use juniper::{
+    graphql_object, http::GraphQLRequest, EmptyMutation, EmptySubscription, GraphQLObject, RootNode,
+};
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::{auth::token_matches, complete::Completer, response, shutdown::Shutdown};
+
+/// What the endpoint answers from: completions, plus whole-word lookups and
+/// the size of the dictionary.
+pub trait Dictionary: Completer + Send + Sync {
+    fn contains(&self, word: &str) -> bool;
+
+    /// The number of words stored.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The version of the words answered from, 1 as loaded and one more
+    /// with every reload.
+    fn version(&self) -> u64 {
+        1
+    }
+
+    /// Why the last reload failed, if it did, leaving the words of the
+    /// version before.
+    fn reload_error(&self) -> Option<String> {
+        None
+    }
+
+    /// Starts reading the words again in the background, returning whether
+    /// that is supported.
+    fn reload(&self) -> bool {
+        false
+    }
+
+    /// How many shards of the words are built, of how many, while the
+    /// others still are and queries are only answered as theirs is.
+    fn loading(&self) -> Option<(usize, usize)> {
+        None
+    }
+}
+
+/// A dictionary served, with what its clients may do.
+pub struct Namespace<D> {
+    pub dictionary: D,
+    /// Completions allowed per query, whatever the query asks for.
+    pub max_limit: usize,
+    /// The token reloads need, if any.
+    pub token: Option<String>,
+}
+
+struct Context {
+    dictionary: Box<dyn Dictionary>,
+    max_limit: usize,
+    token: Option<String>,
+    queries: AtomicU64,
+}
+
+impl juniper::Context for Context {}
+
+/// A completion, heaviest first.
+#[derive(GraphQLObject)]
+struct Completion {
+    word: String,
+    // GraphQL integers are 32 bits, too few for corpus frequencies.
+    weight: f64,
+}
+
+/// Figures about the dictionary and the server.
+#[derive(GraphQLObject)]
+struct Stats {
+    /// The number of words stored.
+    words: f64,
+    /// The number of GraphQL queries served so far, this one included.
+    queries: f64,
+}
+
+struct Query;
+
+#[graphql_object]
+#[graphql(context = Context)]
+impl Query {
+    /// Completes `prefix`, heaviest words first.
+    fn complete(
+        context: &Context,
+        prefix: String,
+        #[graphql(default = 10)] limit: i32,
+    ) -> Vec<Completion> {
+        let limit = usize::try_from(limit).unwrap_or(0).min(context.max_limit);
+        context
+            .dictionary
+            .complete(&prefix, limit)
+            .into_iter()
+            .map(|suggestion| Completion {
+                word: suggestion.word,
+                weight: suggestion.weight as f64,
+            })
+            .collect()
+    }
+
+    /// Whether `word` itself is stored.
+    fn contains(context: &Context, word: String) -> bool {
+        context.dictionary.contains(&word)
+    }
+
+    fn stats(context: &Context) -> Stats {
+        Stats {
+            words: context.dictionary.len() as f64,
+            queries: context.queries.load(Ordering::Relaxed) as f64,
+        }
+    }
+}
+
+type Schema = RootNode<Query, EmptyMutation<Context>, EmptySubscription<Context>>;
+
+/// Serves GraphQL queries over HTTP on `listener` until the process ends,
+/// from the namespaces `load` builds by name. The server listens while it
+/// does, and stops with its error if it fails.
+pub fn serve<D, E>(
+    listener: TcpListener,
+    load: impl FnOnce() -> Result<BTreeMap<String, Namespace<D>>, E>,
+    shutdown: &Shutdown,
+) -> Result<(), E>
+where
+    D: Dictionary + 'static,
+    E: From<io::Error>,
+{
+    let server = Server::from_listener(listener, None).map_err(io::Error::other)?;
+    let contexts = OnceLock::new();
+    thread::scope(|scope| {
+        let answering = scope.spawn(|| answer(&server, &contexts, shutdown));
+        match load() {
+            Ok(namespaces) => {
+                let namespaces = namespaces.into_iter().map(|(name, namespace)| {
+                    let context = Context {
+                        dictionary: Box::new(namespace.dictionary) as Box<dyn Dictionary>,
+                        max_limit: namespace.max_limit,
+                        token: namespace.token,
+                        queries: AtomicU64::new(0),
+                    };
+                    (name, context)
+                });
+                let _ = contexts.set(namespaces.collect());
+            }
+            Err(err) => {
+                server.unblock();
+                let _ = answering.join();
+                return Err(err);
+            }
+        }
+        match answering.join() {
+            Ok(result) => Ok(result?),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    })
+}
+
+// Answers requests until the server is unblocked, from the namespace each
+// names once they are loaded.
+fn answer(
+    server: &Server,
+    contexts: &OnceLock<BTreeMap<String, Context>>,
+    shutdown: &Shutdown,
+) -> io::Result<()> {
+    let schema = Schema::new(Query, EmptyMutation::new(), EmptySubscription::new());
+    let json = Header::from_bytes("Content-Type", "application/json").expect("a valid header");
+    for mut request in server.incoming_requests() {
+        let (name, url) = route(request.url());
+        let (name, url) = (name.to_string(), url.to_string());
+        let context = match contexts.get().map(|contexts| contexts.get(&name)) {
+            Some(None) => {
+                let reply = serde_json::json!({ "error": format!("no namespace {}", name) });
+                request.respond(
+                    Response::from_string(reply.to_string())
+                        .with_status_code(404)
+                        .with_header(json.clone()),
+                )?;
+                continue;
+            }
+            loaded => loaded.flatten(),
+        };
+        // Probes are answered to the end, the rest only until a shutdown.
+        let path = url.split('?').next().unwrap_or_default();
+        let probe = *request.method() == Method::Get && ["/healthz", "/readyz"].contains(&path);
+        let in_flight = shutdown.begin();
+        if in_flight.is_none() && !probe {
+            let reply = serde_json::json!({ "error": "the server is shutting down" });
+            request.respond(
+                Response::from_string(reply.to_string())
+                    .with_status_code(503)
+                    .with_header(json.clone()),
+            )?;
+            continue;
+        }
+        if *request.method() == Method::Get {
+            let (status, reply) = get(context, &url, in_flight.is_none());
+            request.respond(
+                Response::from_string(reply.to_string())
+                    .with_status_code(status)
+                    .with_header(json.clone()),
+            )?;
+            continue;
+        }
+        if *request.method() != Method::Post {
+            request
+                .respond(Response::from_string("POST a GraphQL query\n").with_status_code(405))?;
+            continue;
+        }
+        if url == "/reload" {
+            let bearer = request
+                .headers()
+                .iter()
+                .find(|header| header.field.equiv("Authorization"))
+                .and_then(|header| header.value.as_str().strip_prefix("Bearer "));
+            // The token comes with the namespace, so none is taken while
+            // loading.
+            let token = context.and_then(|context| context.token.as_deref());
+            let (status, reply) = match (context, token, bearer) {
+                (None, _, _) => (503, serde_json::json!({ "error": LOADING })),
+                (Some(context), _, _) if context.dictionary.loading().is_some() => {
+                    (503, serde_json::json!({ "error": LOADING }))
+                }
+                (_, Some(token), Some(bearer)) if !token_matches(bearer.trim(), token) => {
+                    (401, serde_json::json!({ "error": "wrong token" }))
+                }
+                (_, Some(_), None) => (401, serde_json::json!({ "error": "missing bearer token" })),
+                (Some(context), _, _) if context.dictionary.reload() => {
+                    (202, serde_json::json!({ "reloading": true }))
+                }
+                (Some(_), _, _) => (
+                    501,
+                    serde_json::json!({ "error": "reloads aren't supported" }),
+                ),
+            };
+            let mut response = Response::from_string(reply.to_string())
+                .with_status_code(status)
+                .with_header(json.clone());
+            if status == 401 {
+                response.add_header(
+                    Header::from_bytes("WWW-Authenticate", "Bearer").expect("a valid header"),
+                );
+            }
+            request.respond(response)?;
+            continue;
+        }
+        let mut body = vec![];
+        request
+            .as_reader()
+            .take(MAX_BODY as u64 + 1)
+            .read_to_end(&mut body)?;
+        let body = match body.len() {
+            len if len > MAX_BODY => Err((413, format!("the body is over {} bytes", MAX_BODY))),
+            _ => String::from_utf8(body).map_err(|_| (400, "the body isn't UTF-8".to_string())),
+        };
+        let body = match body {
+            Ok(body) => body,
+            Err((status, message)) => {
+                request.respond(
+                    Response::from_string(serde_json::json!({ "error": message }).to_string())
+                        .with_status_code(status)
+                        .with_header(json.clone()),
+                )?;
+                continue;
+            }
+        };
+        if path == "/complete/batch" {
+            let query = url.split_once('?').map_or("", |(_, query)| query);
+            let (status, reply) = match context.map(|context| batch(context, query, &body)) {
+                Some(Ok(envelopes)) => (200, envelopes),
+                Some(Err((status, message))) => (status, serde_json::json!({ "error": message })),
+                None => (503, serde_json::json!({ "error": LOADING })),
+            };
+            request.respond(
+                Response::from_string(reply.to_string())
+                    .with_status_code(status)
+                    .with_header(json.clone()),
+            )?;
+            continue;
+        }
+        let (status, reply) = match serde_json::from_str::<GraphQLRequest>(&body) {
+            Ok(query) => match context {
+                Some(context) => {
+                    context.queries.fetch_add(1, Ordering::Relaxed);
+                    let response = query.execute_sync(&schema, context);
+                    let status = if response.is_ok() { 200 } else { 400 };
+                    (status, serde_json::to_string(&response)?)
+                }
+                None => (
+                    503,
+                    serde_json::json!({ "errors": [{ "message": LOADING }] }).to_string(),
+                ),
+            },
+            Err(err) => (
+                400,
+                serde_json::json!({ "errors": [{ "message": err.to_string() }] }).to_string(),
+            ),
+        };
+        request.respond(
+            Response::from_string(reply)
+                .with_status_code(status)
+                .with_header(json.clone()),
+        )?;
+    }
+    Ok(())
+}
+
+const LOADING: &str = "the dictionary is still loading";
+
+// Splits `url` into the name of the namespace it asks and the rest of it,
+// `/t/shop/complete?q=ap` into `shop` and `/complete?q=ap`.
+fn route(url: &str) -> (&str, &str) {
+    match url.strip_prefix("/t/") {
+        Some(rest) => rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len())),
+        None => ("", url),
+    }
+}
+
+// Answers a GET of `url` with a status code and JSON, from `context` once the
+// dictionary is loaded.
+fn get(context: Option<&Context>, url: &str, shutting_down: bool) -> (u16, serde_json::Value) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let error = |status, message: String| (status, serde_json::json!({ "error": message }));
+    match (path, context) {
+        ("/healthz", _) => (200, serde_json::json!({ "status": "ok" })),
+        ("/readyz", _) if shutting_down => (
+            503,
+            serde_json::json!({ "ready": false, "shutting_down": true }),
+        ),
+        ("/readyz", None) => (503, serde_json::json!({ "ready": false })),
+        ("/readyz", Some(context)) => {
+            let dictionary = &context.dictionary;
+            let mut status = serde_json::json!({
+                "ready": true,
+                "version": dictionary.version(),
+                "words": dictionary.len(),
+            });
+            if let Some((built, shards)) = dictionary.loading() {
+                status["ready"] = false.into();
+                status["shards_built"] = built.into();
+                status["shards"] = shards.into();
+                return (503, status);
+            }
+            match dictionary.reload_error() {
+                Some(err) => {
+                    status["ready"] = false.into();
+                    status["reload_error"] = err.into();
+                    (503, status)
+                }
+                None => (200, status),
+            }
+        }
+        ("/complete", None) => error(503, LOADING.to_string()),
+        ("/complete", Some(context)) => match complete(context, query) {
+            Ok(envelope) => (200, envelope),
+            Err((status, message)) => error(status, message),
+        },
+        _ => error(
+            404,
+            format!("no {}, GET /complete?q=<prefix>, /healthz or /readyz", path),
+        ),
+    }
+}
+
+/// The most bytes a POST body may have.
+pub const MAX_BODY: usize = 1 << 20;
+
+/// The most prefixes `POST /complete/batch` takes at once.
+pub const MAX_BATCH: usize = 1000;
+
+// Answers `/complete` with the parameters of `query`, or fails with a status
+// code and why.
+fn complete(context: &Context, query: &str) -> Result<serde_json::Value, (u16, String)> {
+    let (prefix, limit, version) = parameters(query)?;
+    let prefix = prefix.ok_or((400, "missing q, the prefix to complete".to_string()))?;
+    Ok(envelope(context, &prefix, limit, version))
+}
+
+// Answers `/complete/batch` with the parameters of `query` for each prefix of
+// the JSON array `body`.
+fn batch(context: &Context, query: &str, body: &str) -> Result<serde_json::Value, (u16, String)> {
+    let (_, limit, version) = parameters(query)?;
+    let prefixes: Vec<String> = serde_json::from_str(body)
+        .map_err(|err| (400, format!("expected a JSON array of prefixes: {}", err)))?;
+    if prefixes.len() > MAX_BATCH {
+        return Err((
+            413,
+            format!("{} prefixes, at most {} at once", prefixes.len(), MAX_BATCH),
+        ));
+    }
+    let envelopes = prefixes
+        .iter()
+        .map(|prefix| envelope(context, prefix, limit, version))
+        .collect();
+    Ok(serde_json::Value::Array(envelopes))
+}
+
+// The prefix, limit and version a `/complete` query string asks for.
+fn parameters(query: &str) -> Result<(Option<String>, usize, u32), (u16, String)> {
+    let mut prefix = None;
+    let mut limit = 10;
+    let mut version = response::VERSIONS[0];
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = decode(value).ok_or((400, format!("invalid escape in {}", name)))?;
+        match name {
+            "q" => prefix = Some(value),
+            "limit" => {
+                limit = value
+                    .parse()
+                    .map_err(|_| (400, format!("invalid limit: {}", value)))?
+            }
+            "version" => version = response::negotiate(&value).map_err(|err| (400, err))?,
+            _ => {}
+        }
+    }
+    Ok((prefix, limit, version))
+}
+
+fn envelope(context: &Context, prefix: &str, limit: usize, version: u32) -> serde_json::Value {
+    let limit = usize::min(limit, context.max_limit);
+    // One more than the limit tells whether there are more.
+    let mut suggestions = context.dictionary.complete(prefix, limit.saturating_add(1));
+    let truncated = suggestions.len() > limit;
+    suggestions.truncate(limit);
+    let suggestions = suggestions.iter().map(response::suggestion).collect();
+    response::envelope(version, prefix, suggestions, truncated)
+}
+
+// Undoes the percent-encoding of a query string value, `+` for a space.
+fn decode(value: &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut rest = value.bytes();
+    while let Some(byte) = rest.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [rest.next()?, rest.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
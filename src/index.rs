@@ -0,0 +1,384 @@
+//! A compact, read-only on-disk form of a dictionary trie.
+//!
+//! Words are sorted and front-coded: each one stores only the length of the
+//! prefix it shares with the previous word and the remaining bytes. Every
+//! [`RESTART_INTERVAL`] words the prefix is reset so a reader can binary
+//! search the restart points instead of decoding the whole file. All numbers
+//! are LEB128 varints except the fixed-width restart table at the end:
+//!
+//! ```text
+//! magic "PTI1" | word count | entries... | restart offsets (u32 LE)... |
+//! restart count (u32 LE) | restart table offset (u64 LE)
+//! entry: shared len | suffix len | suffix | weight | tag count | (len | tag)...
+//! ```
+//!
+//! For distribution, [`write_compressed_index`] wraps the same bytes in a
+//! zstd frame after the magic "PTZ1", often a fraction of the size; opening
+//! it decompresses the whole index once, up front.
+
+use std::{
+    borrow::Cow,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{
+    complete::{Completer, Suggestion},
+    key::TrieKey,
+    trie::Trie,
+};
+
+pub const MAGIC: &[u8; 4] = b"PTI1";
+
+/// The magic of a zstd-compressed index.
+pub const COMPRESSED_MAGIC: &[u8; 4] = b"PTZ1";
+
+/// Number of words between two restart points.
+pub const RESTART_INTERVAL: usize = 16;
+
+/// Writes the trie's words, weights and tags as a front-coded index.
+pub fn write_index<W: Write, K: TrieKey>(out: &mut W, trie: &Trie<K>) -> io::Result<()> {
+    let mut words: Vec<_> = trie
+        .iter()
+        .map(|(word, info)| IndexEntry {
+            word,
+            weight: info.weight,
+            tags: info.tags.iter().map(String::as_str).collect(),
+        })
+        .collect();
+    // Byte order, which is what front coding and the reader's binary search
+    // rely on; token keys don't sort that way on their own.
+    words.sort_by(|a, b| a.word.cmp(&b.word));
+    write_entries(out, &words)
+}
+
+/// Writes the index [`write_index`] would, compressed with zstd at `level`,
+/// from 1 to 22; 3 is zstd's usual trade-off, 19 and up are slow to write but
+/// no slower to open.
+///
+/// ```
+/// use prefix_tree::{index::{self, Index}, Completer, Trie};
+///
+/// let trie: Trie = ["apple", "apricot", "banana"].into_iter().collect();
+/// let mut data = vec![];
+/// index::write_compressed_index(&mut data, &trie, 19).unwrap();
+/// let index = Index::from_bytes(data).unwrap();
+/// assert_eq!(index.complete("ap", 10).len(), 2);
+/// ```
+#[cfg(feature = "zstd")]
+pub fn write_compressed_index<W: Write, K: TrieKey>(
+    out: &mut W,
+    trie: &Trie<K>,
+    level: i32,
+) -> io::Result<()> {
+    let mut index = vec![];
+    write_index(&mut index, trie)?;
+    out.write_all(COMPRESSED_MAGIC)?;
+    out.write_all(&zstd::bulk::compress(&index, level)?)
+}
+
+// Writes entries already sorted by their words' bytes.
+pub(crate) fn write_entries<W: Write>(out: &mut W, words: &[IndexEntry]) -> io::Result<()> {
+    let mut buf = Vec::from(&MAGIC[..]);
+    write_varint(&mut buf, words.len() as u64);
+    let mut restarts = Vec::new();
+    let mut previous = "";
+    for (i, entry) in words.iter().enumerate() {
+        let word = entry.word.as_str();
+        let shared = if i % RESTART_INTERVAL == 0 {
+            restarts.push(buf.len() as u32);
+            0
+        } else {
+            shared_prefix_len(previous, word)
+        };
+        let suffix = &word.as_bytes()[shared..];
+        write_varint(&mut buf, shared as u64);
+        write_bytes(&mut buf, suffix);
+        write_varint(&mut buf, entry.weight);
+        write_varint(&mut buf, entry.tags.len() as u64);
+        for tag in &entry.tags {
+            write_bytes(&mut buf, tag.as_bytes());
+        }
+        previous = word;
+    }
+    let table = buf.len() as u64;
+    for offset in &restarts {
+        buf.extend_from_slice(&offset.to_le_bytes());
+    }
+    buf.extend_from_slice(&(restarts.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&table.to_le_bytes());
+    out.write_all(&buf)
+}
+
+/// A front-coded index loaded back into memory, answering completions
+/// without rebuilding the trie.
+#[derive(Debug)]
+pub struct Index {
+    data: Cow<'static, [u8]>,
+    restarts: Vec<usize>,
+    // Where the restart table starts, i.e. where the entries end.
+    end: usize,
+}
+
+/// One decoded index entry, borrowing its tags from the index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IndexEntry<'a> {
+    pub(crate) word: String,
+    pub(crate) weight: u64,
+    pub(crate) tags: Vec<&'a str>,
+}
+
+impl Index {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> io::Result<Self> {
+        Self::from_data(Cow::Owned(data))
+    }
+
+    /// An index baked into the binary, e.g. with [`prefix_tree_embed!`]. The
+    /// words are read in place, so this only goes over the restart table.
+    ///
+    /// ```
+    /// use prefix_tree::{index::{self, Index}, Completer, Trie};
+    ///
+    /// let trie: Trie = ["apple", "apricot", "banana"].into_iter().collect();
+    /// let mut data = vec![];
+    /// index::write_index(&mut data, &trie).unwrap();
+    /// let index = Index::from_static(data.leak()).unwrap();
+    /// assert_eq!(index.complete("ap", 10).len(), 2);
+    /// ```
+    ///
+    /// [`prefix_tree_embed!`]: crate::prefix_tree_embed
+    pub fn from_static(data: &'static [u8]) -> io::Result<Self> {
+        Self::from_data(Cow::Borrowed(data))
+    }
+
+    fn from_data(data: Cow<'static, [u8]>) -> io::Result<Self> {
+        if let Some(compressed) = data.strip_prefix(COMPRESSED_MAGIC) {
+            return Self::decompress(compressed);
+        }
+        if data.len() < MAGIC.len() + 12 || !data.starts_with(MAGIC) {
+            return Err(invalid("not a prefix-tree index"));
+        }
+        let trailer = data.len() - 12;
+        let count = u32::from_le_bytes(data[trailer..trailer + 4].try_into().unwrap()) as usize;
+        let end = u64::from_le_bytes(data[trailer + 4..].try_into().unwrap()) as usize;
+        if end.checked_add(count * 4) != Some(trailer) {
+            return Err(invalid("corrupt restart table"));
+        }
+        let restarts = data[end..trailer]
+            .chunks_exact(4)
+            .map(|offset| u32::from_le_bytes(offset.try_into().unwrap()) as usize)
+            .collect();
+        Ok(Self {
+            data,
+            restarts,
+            end,
+        })
+    }
+
+    #[cfg(feature = "zstd")]
+    fn decompress(compressed: &[u8]) -> io::Result<Self> {
+        let data = zstd::stream::decode_all(compressed)
+            .map_err(|_| invalid("corrupt compressed index"))?;
+        if data.starts_with(COMPRESSED_MAGIC) {
+            return Err(invalid("index compressed twice"));
+        }
+        Self::from_data(Cow::Owned(data))
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    fn decompress(_: &[u8]) -> io::Result<Self> {
+        Err(invalid(
+            "compressed index, which needs a build with the zstd feature",
+        ))
+    }
+
+    /// Every entry, in order.
+    pub(crate) fn entries(&self) -> io::Result<Vec<IndexEntry<'_>>> {
+        let mut entries = vec![];
+        let Some(&start) = self.restarts.first() else {
+            return Ok(entries);
+        };
+        let mut reader = Reader::new(&self.data, start);
+        let mut previous = String::new();
+        while reader.pos < self.end {
+            let entry = reader.entry(&previous)?;
+            previous.clone_from(&entry.word);
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Restricts completions to words carrying `tag`.
+    pub fn tagged<'a>(&'a self, tag: &'a str) -> IndexTagged<'a> {
+        IndexTagged { index: self, tag }
+    }
+
+    fn complete_where(
+        &self,
+        prefix: &str,
+        limit: usize,
+        filter: impl Fn(&[&str]) -> bool,
+    ) -> io::Result<Vec<Suggestion>> {
+        // Find the first restart point whose word doesn't sort before the
+        // prefix; matches may begin in the block just before it.
+        let mut lo = 0;
+        let mut hi = self.restarts.len();
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let mut reader = Reader::new(&self.data, self.restarts[mid]);
+            if reader.entry("")?.word.as_str() < prefix {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        let mut suggestions = vec![];
+        let Some(&start) = self.restarts.get(lo.saturating_sub(1)) else {
+            return Ok(suggestions);
+        };
+        let mut reader = Reader::new(&self.data, start);
+        let mut previous = String::new();
+        while reader.pos < self.end {
+            let entry = reader.entry(&previous)?;
+            if entry.word.starts_with(prefix) {
+                if filter(&entry.tags) {
+                    suggestions.push(Suggestion {
+                        word: entry.word.clone(),
+                        weight: entry.weight,
+                    });
+                }
+            } else if entry.word.as_str() > prefix {
+                break;
+            }
+            previous = entry.word;
+        }
+        suggestions.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.word.cmp(&b.word)));
+        suggestions.truncate(limit);
+        Ok(suggestions)
+    }
+}
+
+// Completer has no room for errors; a corrupt index completes nothing.
+impl Completer for Index {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        self.complete_where(prefix, limit, |_| true)
+            .unwrap_or_default()
+    }
+}
+
+/// Completions of an index limited to the words carrying a tag, see
+/// [`Index::tagged`].
+pub struct IndexTagged<'a> {
+    index: &'a Index,
+    tag: &'a str,
+}
+
+impl Completer for IndexTagged<'_> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        self.index
+            .complete_where(prefix, limit, |tags| tags.contains(&self.tag))
+            .unwrap_or_default()
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    pub(crate) pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    pub(crate) fn entry(&mut self, previous: &str) -> io::Result<IndexEntry<'a>> {
+        let shared = self.varint()? as usize;
+        let suffix = self.str()?;
+        let mut word = previous
+            .get(..shared)
+            .ok_or_else(|| invalid("corrupt entry"))?
+            .to_string();
+        word.push_str(suffix);
+        let weight = self.varint()?;
+        let tags = (0..self.varint()?)
+            .map(|_| self.str())
+            .collect::<io::Result<_>>()?;
+        Ok(IndexEntry { word, weight, tags })
+    }
+
+    pub(crate) fn varint(&mut self) -> io::Result<u64> {
+        let mut n = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| invalid("truncated index"))?;
+            self.pos += 1;
+            n |= u64::from(byte & 0x7f) << shift;
+            if byte < 0x80 {
+                return Ok(n);
+            }
+        }
+        Err(invalid("corrupt varint"))
+    }
+
+    pub(crate) fn str(&mut self) -> io::Result<&'a str> {
+        let len = self.varint()? as usize;
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| invalid("truncated index"))?;
+        self.pos += len;
+        std::str::from_utf8(bytes).map_err(|_| invalid("invalid UTF-8 in index"))
+    }
+}
+
+pub(crate) fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// In bytes, backed off to a character boundary so suffixes stay valid UTF-8.
+fn shared_prefix_len(a: &str, b: &str) -> usize {
+    let mut len = a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count();
+    while !b.is_char_boundary(len) {
+        len -= 1;
+    }
+    len
+}
+
+pub(crate) fn write_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+pub(crate) fn write_varint(buf: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        buf.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+/// Bakes an index written by `prefix-tree compact` into the binary as an
+/// [`Index`], for tools shipping a fixed vocabulary: nothing is loaded or
+/// parsed at startup and no data file is needed at run time.
+///
+/// The path is resolved like [`include_bytes!`]'s. To build the index from a
+/// dictionary at compile time, write it from a build script into `OUT_DIR`:
+///
+/// ```ignore
+/// let completions = prefix_tree::prefix_tree_embed!(concat!(env!("OUT_DIR"), "/trie.idx"));
+/// ```
+#[macro_export]
+macro_rules! prefix_tree_embed {
+    ($path:expr) => {
+        $crate::index::Index::from_static(include_bytes!($path))
+            .expect("embedded file is a prefix-tree index")
+    };
+}
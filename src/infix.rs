@@ -0,0 +1,321 @@
+//! Infix search: the words containing a piece of text anywhere, answered from
+//! a suffix automaton over the whole dictionary instead of a scan of every
+//! word.
+//!
+//! The automaton has a state per class of substrings that end at the same
+//! places, so walking the query's characters from the start state takes
+//! `O(query)` steps, after which the words containing it are listed straight
+//! from the state. It is built once, e.g. with `prefix-tree infix-index`, and
+//! saved in the index format's varints:
+//!
+//! ```text
+//! magic "PTS1" | word count | (len | word)... | state count | states... |
+//! occurrence count | word id...
+//! state: transition count | (char | target)... | first | end
+//! ```
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{
+    index::{invalid, write_bytes, write_varint, Reader},
+    key::TrieKey,
+    trie::Trie,
+};
+
+pub const MAGIC: &[u8; 4] = b"PTS1";
+
+const NONE: u32 = u32::MAX;
+
+/// A suffix automaton over every word of a dictionary, for
+/// [`SuffixAutomaton::find`].
+///
+/// ```
+/// use prefix_tree::{infix::SuffixAutomaton, Trie};
+///
+/// let trie: Trie = ["banana", "bandana", "cabana"].into_iter().collect();
+/// let automaton = SuffixAutomaton::from_trie(&trie);
+/// assert_eq!(automaton.find("ana"), ["banana", "bandana", "cabana"]);
+/// assert_eq!(automaton.find("nda"), ["bandana"]);
+///
+/// let mut data = vec![];
+/// automaton.write(&mut data).unwrap();
+/// let automaton = SuffixAutomaton::from_bytes(&data).unwrap();
+/// assert_eq!(automaton.find("cab"), ["cabana"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SuffixAutomaton {
+    // Sorted, so ids order words alphabetically.
+    words: Vec<String>,
+    states: Vec<State>,
+    // Ids of the words ending a substring at each state, grouped so that those
+    // of a state and of every state whose suffix link leads to it are one
+    // range.
+    occurrences: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct State {
+    // Sorted by character.
+    next: Vec<(char, u32)>,
+    // The range of `occurrences` of the words containing the state's
+    // substrings.
+    first: u32,
+    end: u32,
+}
+
+impl State {
+    fn next(&self, c: char) -> Option<u32> {
+        self.next
+            .binary_search_by_key(&c, |&(c, _)| c)
+            .ok()
+            .map(|i| self.next[i].1)
+    }
+
+    fn set_next(&mut self, c: char, target: u32) {
+        match self.next.binary_search_by_key(&c, |&(c, _)| c) {
+            Ok(i) => self.next[i].1 = target,
+            Err(i) => self.next.insert(i, (c, target)),
+        }
+    }
+}
+
+// The automaton while it is built, with what the finished one doesn't need.
+struct Builder {
+    states: Vec<State>,
+    // The length of the longest substring of each state.
+    len: Vec<u32>,
+    // The state of the longest suffix of a state's substrings not in it.
+    link: Vec<u32>,
+}
+
+impl Builder {
+    fn add_state(&mut self, len: u32, link: u32, next: Vec<(char, u32)>) -> u32 {
+        self.states.push(State {
+            next,
+            ..State::default()
+        });
+        self.len.push(len);
+        self.link.push(link);
+        (self.states.len() - 1) as u32
+    }
+
+    // Splits off the part of `q` reachable from `p` by `c` as a state of its
+    // own, redirecting the transitions into it.
+    fn split(&mut self, mut p: u32, c: char, q: u32) -> u32 {
+        let next = self.states[q as usize].next.clone();
+        let clone = self.add_state(self.len[p as usize] + 1, self.link[q as usize], next);
+        self.link[q as usize] = clone;
+        while p != NONE && self.states[p as usize].next(c) == Some(q) {
+            self.states[p as usize].set_next(c, clone);
+            p = self.link[p as usize];
+        }
+        clone
+    }
+
+    // Extends the word ending at `last` by `c`, returning the state of the
+    // longer word. Words are added one after the other, each from the start
+    // state, so `c` may already lead somewhere.
+    fn extend(&mut self, last: u32, c: char) -> u32 {
+        if let Some(q) = self.states[last as usize].next(c) {
+            if self.len[last as usize] + 1 == self.len[q as usize] {
+                return q;
+            }
+            return self.split(last, c, q);
+        }
+        let cur = self.add_state(self.len[last as usize] + 1, 0, vec![]);
+        let mut p = last;
+        while p != NONE && self.states[p as usize].next(c).is_none() {
+            self.states[p as usize].set_next(c, cur);
+            p = self.link[p as usize];
+        }
+        if p != NONE {
+            let q = self.states[p as usize].next(c).expect("p has moved by c");
+            self.link[cur as usize] = if self.len[p as usize] + 1 == self.len[q as usize] {
+                q
+            } else {
+                self.split(p, c, q)
+            };
+        }
+        cur
+    }
+}
+
+impl SuffixAutomaton {
+    /// The automaton of a trie's words.
+    pub fn from_trie<K: TrieKey, V>(trie: &Trie<K, V>) -> Self {
+        let mut words = vec![];
+        trie.for_each_word(|word, _| words.push(word.to_string()));
+        Self::new(words)
+    }
+
+    pub fn new(mut words: Vec<String>) -> Self {
+        words.sort();
+        words.dedup();
+        let mut builder = Builder {
+            states: vec![],
+            len: vec![],
+            link: vec![],
+        };
+        builder.add_state(0, NONE, vec![]);
+        // The state every prefix of every word ends at.
+        let mut ends = vec![];
+        for (id, word) in words.iter().enumerate() {
+            let mut last = 0;
+            for c in word.chars() {
+                last = builder.extend(last, c);
+                ends.push((last, id as u32));
+            }
+        }
+
+        // Number the states in preorder of the suffix link tree, where a
+        // state's substrings are suffixes of those of every state below it.
+        let count = builder.states.len();
+        let mut children = vec![vec![]; count];
+        for state in 1..count {
+            children[builder.link[state] as usize].push(state as u32);
+        }
+        let (mut order, mut below) = (vec![0; count], vec![0; count]);
+        let mut next = 0;
+        let mut stack = vec![(0, false)];
+        while let Some((state, done)) = stack.pop() {
+            if done {
+                below[state as usize] = next;
+                continue;
+            }
+            order[state as usize] = next;
+            next += 1;
+            stack.push((state, true));
+            stack.extend(children[state as usize].iter().map(|&child| (child, false)));
+        }
+
+        let mut ends: Vec<(u32, u32)> = ends
+            .into_iter()
+            .map(|(state, id)| (order[state as usize], id))
+            .collect();
+        ends.sort_unstable();
+        let position = |order: u32| ends.partition_point(|&(o, _)| o < order) as u32;
+        for (state, s) in builder.states.iter_mut().enumerate() {
+            s.first = position(order[state]);
+            s.end = position(below[state]);
+        }
+        Self {
+            words,
+            states: builder.states,
+            occurrences: ends.into_iter().map(|(_, id)| id).collect(),
+        }
+    }
+
+    /// The words containing `infix`, in alphabetical order.
+    pub fn find(&self, infix: &str) -> Vec<&str> {
+        let mut state = 0;
+        for c in infix.chars() {
+            match self.states[state as usize].next(c) {
+                Some(next) => state = next,
+                None => return vec![],
+            }
+        }
+        let state = &self.states[state as usize];
+        let mut ids = self.occurrences[state.first as usize..state.end as usize].to_vec();
+        ids.sort_unstable();
+        ids.dedup();
+        ids.into_iter()
+            .map(|id| self.words[id as usize].as_str())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut buf = Vec::from(&MAGIC[..]);
+        write_varint(&mut buf, self.words.len() as u64);
+        for word in &self.words {
+            write_bytes(&mut buf, word.as_bytes());
+        }
+        write_varint(&mut buf, self.states.len() as u64);
+        for state in &self.states {
+            write_varint(&mut buf, state.next.len() as u64);
+            for &(c, target) in &state.next {
+                write_varint(&mut buf, u64::from(c));
+                write_varint(&mut buf, u64::from(target));
+            }
+            write_varint(&mut buf, u64::from(state.first));
+            write_varint(&mut buf, u64::from(state.end));
+        }
+        write_varint(&mut buf, self.occurrences.len() as u64);
+        for &id in &self.occurrences {
+            write_varint(&mut buf, u64::from(id));
+        }
+        out.write_all(&buf)
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Reads an automaton saved by [`SuffixAutomaton::write`].
+    pub fn from_bytes(data: &[u8]) -> io::Result<Self> {
+        if !data.starts_with(MAGIC) {
+            return Err(invalid("not a prefix-tree infix index"));
+        }
+        let mut reader = Reader::new(data, MAGIC.len());
+        let words = (0..reader.varint()?)
+            .map(|_| reader.str().map(str::to_string))
+            .collect::<io::Result<Vec<_>>>()?;
+        let count = reader.varint()?;
+        let mut states = vec![];
+        for _ in 0..count {
+            let next = (0..reader.varint()?)
+                .map(|_| {
+                    let c = u32::try_from(reader.varint()?)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| invalid("invalid character in infix index"))?;
+                    let target = reader.varint()?;
+                    if target >= count {
+                        return Err(invalid("corrupt transition"));
+                    }
+                    Ok((c, target as u32))
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+            let (first, end) = (reader.varint()?, reader.varint()?);
+            states.push((next, first, end));
+        }
+        let occurrences = (0..reader.varint()?)
+            .map(|_| match reader.varint()? {
+                id if (id as usize) < words.len() => Ok(id as u32),
+                _ => Err(invalid("corrupt occurrence")),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let states = states
+            .into_iter()
+            .map(|(next, first, end)| {
+                if first > end || end > occurrences.len() as u64 {
+                    return Err(invalid("corrupt state"));
+                }
+                Ok(State {
+                    next,
+                    first: first as u32,
+                    end: end as u32,
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        if states.is_empty() {
+            return Err(invalid("infix index without states"));
+        }
+        Ok(Self {
+            words,
+            states,
+            occurrences,
+        })
+    }
+}
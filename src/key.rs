@@ -0,0 +1,173 @@
+use std::{
+    collections::hash_map::{DefaultHasher, RandomState},
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+    sync::OnceLock,
+};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The alphabet of the trie's edge labels. Words are split into keys when
+/// they're inserted or looked up, and keys are joined back together to spell
+/// out completions, so the same trie code works over characters, bytes,
+/// grapheme clusters or whole tokens. Keys are ordered so words can be listed
+/// in sorted order.
+pub trait TrieKey: Clone + Ord + Hash + fmt::Debug + fmt::Display {
+    fn segment(text: &str) -> Vec<Self>;
+
+    fn join(keys: &[Self]) -> String;
+
+    /// The key as a single character, if it is one; keyboard-aware typo costs
+    /// only apply to those.
+    fn as_char(&self) -> Option<char>;
+}
+
+impl TrieKey for char {
+    fn segment(text: &str) -> Vec<Self> {
+        text.chars().collect()
+    }
+
+    fn join(keys: &[Self]) -> String {
+        keys.iter().collect()
+    }
+
+    fn as_char(&self) -> Option<char> {
+        Some(*self)
+    }
+}
+
+/// UTF-8 bytes, for tries over ASCII-heavy data (identifiers, paths, IP
+/// addresses) where a byte per edge is all that's needed.
+impl TrieKey for u8 {
+    fn segment(text: &str) -> Vec<Self> {
+        text.as_bytes().to_vec()
+    }
+
+    fn join(keys: &[Self]) -> String {
+        String::from_utf8_lossy(keys).into_owned()
+    }
+
+    fn as_char(&self) -> Option<char> {
+        self.is_ascii().then_some(char::from(*self))
+    }
+}
+
+/// An extended grapheme cluster, so that emoji ZWJ sequences like "👩‍💻" or
+/// Indic conjuncts are single edges instead of being split mid-character.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Grapheme(Box<str>);
+
+impl fmt::Display for Grapheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TrieKey for Grapheme {
+    fn segment(text: &str) -> Vec<Self> {
+        text.graphemes(true).map(|g| Grapheme(g.into())).collect()
+    }
+
+    fn join(keys: &[Self]) -> String {
+        keys.iter().map(|g| &*g.0).collect()
+    }
+
+    fn as_char(&self) -> Option<char> {
+        let mut chars = self.0.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => Some(ch),
+            _ => None,
+        }
+    }
+}
+
+/// A whitespace-separated word, for tries over phrases: "new york" and
+/// "new delhi" share the "new" edge and complete token by token.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Token(Box<str>);
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TrieKey for Token {
+    fn segment(text: &str) -> Vec<Self> {
+        text.split_whitespace().map(|t| Token(t.into())).collect()
+    }
+
+    fn join(keys: &[Self]) -> String {
+        keys.iter().map(|t| &*t.0).collect::<Vec<_>>().join(" ")
+    }
+
+    fn as_char(&self) -> Option<char> {
+        None
+    }
+}
+
+// The seeds of every `KeyHasher`, drawn once per process so that which keys
+// collide can't be worked out ahead of time.
+static SEEDS: OnceLock<(u64, RandomState)> = OnceLock::new();
+
+fn seeds() -> &'static (u64, RandomState) {
+    SEEDS.get_or_init(|| {
+        let random = RandomState::new();
+        (random.hash_one(0u8), random)
+    })
+}
+
+/// Hashes the keys of a node's children. A `u8` or `char` key is a single
+/// seeded multiply rather than the SipHash of the standard `HashMap`, while
+/// string keys such as [`Grapheme`] and [`Token`] still go through a
+/// randomly keyed SipHash.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct KeyHasher;
+
+impl BuildHasher for KeyHasher {
+    type Hasher = KeyHash;
+
+    fn build_hasher(&self) -> KeyHash {
+        KeyHash {
+            hash: seeds().0,
+            sip: None,
+        }
+    }
+}
+
+pub(crate) struct KeyHash {
+    hash: u64,
+    sip: Option<DefaultHasher>,
+}
+
+impl KeyHash {
+    fn mix(&mut self, n: u64) {
+        // A folded multiply: every bit of the key reaches the low bits the
+        // table picks buckets with.
+        let product = u128::from(self.hash ^ n) * 0x9e37_79b9_7f4a_7c15;
+        self.hash = (product as u64) ^ (product >> 64) as u64;
+    }
+}
+
+impl Hasher for KeyHash {
+    fn write(&mut self, bytes: &[u8]) {
+        self.sip
+            .get_or_insert_with(|| seeds().1.build_hasher())
+            .write(bytes);
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.mix(u64::from(n));
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.mix(u64::from(n));
+    }
+
+    fn finish(&self) -> u64 {
+        match &self.sip {
+            Some(sip) => sip.finish() ^ self.hash,
+            None => self.hash,
+        }
+    }
+}
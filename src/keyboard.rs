@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+/// Letter rows of the supported layouts, top to bottom. Each row sits roughly
+/// half a key to the right of the one above it, so key `c` of a row touches
+/// keys `c` and `c + 1` of the row above and `c - 1` and `c` of the row below.
+const LAYOUTS: &[(&str, [&str; 3])] = &[
+    ("qwerty", ["qwertyuiop", "asdfghjkl", "zxcvbnm"]),
+    ("qwertz", ["qwertzuiop", "asdfghjkl", "yxcvbnm"]),
+    ("azerty", ["azertyuiop", "qsdfghjklm", "wxcvbn"]),
+    ("dvorak", ["',.pyfgcrl", "aoeuidhtns", ";qjkxbmwvz"]),
+];
+
+pub fn layout_names() -> impl Iterator<Item = &'static str> {
+    LAYOUTS.iter().map(|(name, _)| *name)
+}
+
+#[derive(Debug)]
+pub struct Keyboard {
+    positions: HashMap<char, (usize, usize)>,
+}
+
+impl Keyboard {
+    pub fn layout(name: &str) -> Option<Self> {
+        let (_, rows) = LAYOUTS.iter().find(|(n, _)| *n == name)?;
+        let mut positions = HashMap::new();
+        for (r, row) in rows.iter().enumerate() {
+            for (c, key) in row.chars().enumerate() {
+                positions.insert(key, (r, c));
+            }
+        }
+        Some(Self { positions })
+    }
+
+    /// Whether `a` and `b` are next to each other on the layout, whatever
+    /// their case.
+    ///
+    /// ```
+    /// use prefix_tree::keyboard::Keyboard;
+    ///
+    /// let dvorak = Keyboard::layout("dvorak").unwrap();
+    /// assert!(dvorak.adjacent('u', 'P'));
+    /// // The punctuation left of "p" puts "e" under "." and "p".
+    /// assert!(!dvorak.adjacent('e', 'f'));
+    /// ```
+    pub fn adjacent(&self, a: char, b: char) -> bool {
+        let (Some(&(ra, ca)), Some(&(rb, cb))) = (
+            self.positions.get(&a.to_ascii_lowercase()),
+            self.positions.get(&b.to_ascii_lowercase()),
+        ) else {
+            return false;
+        };
+        if ra == rb {
+            ca.abs_diff(cb) == 1
+        } else if ra + 1 == rb {
+            cb + 1 == ca || cb == ca
+        } else if rb + 1 == ra {
+            ca + 1 == cb || ca == cb
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! A prefix-tree (Trie) over dictionary words, with the completion, fuzzy
+//! and phonetic lookups used by the `prefix-tree` CLI.
+
+mod alias;
+pub mod alloc_stats;
+pub mod anagram;
+mod auth;
+mod bloom;
+mod builder;
+#[cfg(feature = "client")]
+pub mod client;
+mod complete;
+pub mod dot;
+mod double_array;
+mod frozen;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+pub mod fuzzy;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod index;
+pub mod infix;
+mod key;
+pub mod keyboard;
+pub mod lsp;
+pub mod nvim;
+mod order;
+pub mod patch;
+pub mod pattern;
+mod persistent;
+pub mod phonetic;
+pub mod plan;
+mod progressive;
+pub mod readline;
+pub mod resp;
+pub mod response;
+mod sample;
+mod score;
+#[cfg(feature = "scripting")]
+mod script;
+#[cfg(feature = "serde")]
+mod serialize;
+mod shard;
+pub mod shutdown;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stem;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod top;
+pub mod tree;
+mod trie;
+mod walk;
+pub mod wordle;
+
+pub use alias::{Aliased, Aliases};
+pub use bloom::Bloom;
+pub use builder::{DisplayCase, TrieBuilder};
+pub use complete::{
+    Cached, Completer, CompletionSession, Explanation, Merged, MinLen, Suggestion, Tagged,
+};
+pub use double_array::DoubleArray;
+pub use frozen::Frozen;
+pub use key::{Grapheme, Token, TrieKey};
+pub use order::ChildOrder;
+pub use persistent::PersistentTrie;
+pub use progressive::Progressive;
+pub use sample::Sampler;
+pub use score::{Candidate, Frequency, History, Length, Recency, Scored, Scorer};
+#[cfg(feature = "scripting")]
+pub use script::ScriptScorer;
+pub use shard::Sharded;
+pub use trie::{Entry, Node, Trie, WordEntry, WordInfo, DEFAULT_WEIGHT};
+pub use walk::{Iter, Walk};
@@ -0,0 +1,197 @@
+//! A minimal Language Server Protocol server offering the completions of a
+//! [`Completer`] for the word under the cursor, in any kind of document.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use serde_json::{json, Value};
+
+use crate::complete::Completer;
+
+// JSON-RPC error code for requests the server doesn't implement.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Serves LSP messages read from `input` until the client sends `exit`,
+/// completing the word before the cursor with up to `limit` suggestions.
+pub fn serve(
+    input: impl BufRead,
+    mut output: impl Write,
+    completer: &dyn Completer,
+    limit: usize,
+) -> io::Result<()> {
+    let mut server = Server {
+        completer,
+        limit,
+        documents: HashMap::new(),
+    };
+    let mut input = input;
+    while let Some(message) = read_message(&mut input)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        if method == "exit" {
+            break;
+        }
+        let response = server.handle(method, &message["params"]);
+        // Notifications carry no id and get no response.
+        if let Some(id) = message.get("id") {
+            let mut reply = json!({ "jsonrpc": "2.0", "id": id });
+            match response {
+                Ok(result) => reply["result"] = result,
+                Err(error) => reply["error"] = error,
+            }
+            write_message(&mut output, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+struct Server<'a> {
+    completer: &'a dyn Completer,
+    limit: usize,
+    // Full text of the open documents, by URI.
+    documents: HashMap<String, String>,
+}
+
+impl Server<'_> {
+    fn handle(&mut self, method: &str, params: &Value) -> Result<Value, Value> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    // Full document sync.
+                    "textDocumentSync": 1,
+                    "completionProvider": {},
+                },
+                "serverInfo": { "name": "prefix-tree" },
+            })),
+            "shutdown" => Ok(Value::Null),
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                self.update(&document["uri"], &document["text"]);
+                Ok(Value::Null)
+            }
+            "textDocument/didChange" => {
+                if let Some(change) = params["contentChanges"].as_array().and_then(|c| c.last()) {
+                    self.update(&params["textDocument"]["uri"], &change["text"]);
+                }
+                Ok(Value::Null)
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = params["textDocument"]["uri"].as_str() {
+                    self.documents.remove(uri);
+                }
+                Ok(Value::Null)
+            }
+            "textDocument/completion" => Ok(self.complete(params)),
+            _ if method.starts_with("$/") || method == "initialized" => Ok(Value::Null),
+            _ => Err(json!({
+                "code": METHOD_NOT_FOUND,
+                "message": format!("unsupported method: {}", method),
+            })),
+        }
+    }
+
+    fn update(&mut self, uri: &Value, text: &Value) {
+        if let (Some(uri), Some(text)) = (uri.as_str(), text.as_str()) {
+            self.documents.insert(uri.to_string(), text.to_string());
+        }
+    }
+
+    fn complete(&self, params: &Value) -> Value {
+        let prefix = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| self.documents.get(uri))
+            .map(|text| {
+                let position = &params["position"];
+                word_before(
+                    text,
+                    position["line"].as_u64().unwrap_or_default() as usize,
+                    position["character"].as_u64().unwrap_or_default() as usize,
+                )
+            })
+            .unwrap_or_default();
+        if prefix.is_empty() {
+            return json!({ "isIncomplete": false, "items": [] });
+        }
+        // One more than asked tells whether the list was cut short.
+        let mut suggestions = self
+            .completer
+            .complete(prefix, self.limit.saturating_add(1));
+        let incomplete = suggestions.len() > self.limit;
+        suggestions.truncate(self.limit);
+        let items: Vec<_> = suggestions
+            .iter()
+            .enumerate()
+            .map(|(rank, suggestion)| {
+                json!({
+                    "label": suggestion.word,
+                    // Text
+                    "kind": 1,
+                    // Keeps the completer's order rather than the editor's.
+                    "sortText": format!("{:08}", rank),
+                })
+            })
+            .collect();
+        json!({ "isIncomplete": incomplete, "items": items })
+    }
+}
+
+// The run of word characters ending at the cursor. LSP positions count UTF-16
+// code units.
+fn word_before(text: &str, line: usize, character: usize) -> &str {
+    let Some(line) = text.lines().nth(line) else {
+        return "";
+    };
+    let mut end = line.len();
+    let mut units = 0;
+    for (i, ch) in line.char_indices() {
+        if units >= character {
+            end = i;
+            break;
+        }
+        units += ch.len_utf16();
+    }
+    let before = &line[..end];
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, ch)| ch.is_alphanumeric() || *ch == '_' || *ch == '\'')
+        .last()
+        .map_or(end, |(i, _)| i);
+    &before[start..]
+}
+
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| invalid("missing Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| invalid(&err.to_string()))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -1,150 +1,5796 @@
 use std::{
-    collections::HashMap,
-    env,
-    fs::File,
-    io::{self, BufRead, Write},
-    process::{exit, Command, Stdio},
+    any::TypeId,
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet},
+    env, fmt,
+    fs::{self, File},
+    io::{self, BufRead, Read, Write},
+    mem,
+    net::TcpListener,
+    panic,
+    path::{Path, PathBuf},
+    process::{exit, Child, Command, Stdio},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex, RwLock,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
 };
 
+use arc_swap::ArcSwap;
+use rand::{rngs::StdRng, RngExt, SeedableRng};
+use rustyline::{error::ReadlineError, history::DefaultHistory, Editor};
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[cfg(feature = "client")]
+use prefix_tree::client::PrefixTreeClient;
+#[cfg(feature = "sqlite")]
+use prefix_tree::sqlite;
+#[cfg(feature = "tls")]
+use prefix_tree::tls;
+use prefix_tree::{
+    alloc_stats::{self, Usage},
+    anagram::AnagramIndex,
+    dot,
+    fuzzy::{self, Costs, Ranking},
+    index::{self, Index},
+    infix::SuffixAutomaton,
+    keyboard::{self, Keyboard},
+    lsp, nvim, patch, pattern,
+    phonetic::{Algorithm, PhoneticIndex},
+    plan::Plan,
+    readline::ReadlineHelper,
+    resp::{self, Store},
+    response,
+    shutdown::{InFlight, Shutdown},
+    stem,
+    top::TopIndex,
+    tree,
+    wordle::{self, Constraints},
+    Aliases, Bloom, Cached, Completer, DisplayCase, Entry, Frozen, Grapheme, History, Merged,
+    Sampler, Sharded, Suggestion, Token, Trie, TrieBuilder, TrieKey, Walk, WordInfo,
+    DEFAULT_WEIGHT,
+};
+#[cfg(feature = "graphql")]
+use prefix_tree::{graphql, Progressive};
+#[cfg(feature = "scripting")]
+use prefix_tree::{Candidate, Scorer, ScriptScorer};
+#[cfg(feature = "graphql")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static ALLOCATOR: alloc_stats::Counting = alloc_stats::Counting;
+
+// Why a run failed. Every kind exits with its own code, listed in the usage;
+// 1 is left to answers like `contains --quiet` finding nothing.
 #[derive(Debug)]
-struct Node {
-    end: bool,
-    children: HashMap<char, Node>,
+enum Error {
+    // Bad arguments, reported with the usage.
+    Usage(String),
+    // A --score-script that doesn't compile or fails while scoring.
+    #[cfg(feature = "scripting")]
+    Script(String),
+    DictionaryMissing(String),
+    BadUtf8 {
+        source: String,
+        line: usize,
+    },
+    // With the dot file written instead.
+    GraphvizMissing(String),
+    // A file that isn't an index or patch this version can read.
+    IndexFormat {
+        path: String,
+        message: String,
+    },
+    Io(io::Error),
+}
+
+impl Error {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) => 2,
+            #[cfg(feature = "scripting")]
+            Error::Script(_) => 2,
+            Error::DictionaryMissing(_) => 3,
+            Error::BadUtf8 { .. } => 4,
+            Error::GraphvizMissing(_) => 5,
+            Error::IndexFormat { .. } => 6,
+            Error::Io(_) => 7,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Usage(message) | Error::DictionaryMissing(message) => write!(f, "{}", message),
+            #[cfg(feature = "scripting")]
+            Error::Script(message) => write!(f, "{}", message),
+            Error::BadUtf8 { source, line } => {
+                write!(f, "{}: line {} is not valid UTF-8", source, line)
+            }
+            Error::GraphvizMissing(path) => write!(
+                f,
+                "{path} was written, but rendering it needs Graphviz, which wasn't found. \
+                 Install it (apt install graphviz, brew install graphviz or winget install graphviz) \
+                 and run `dot -Tsvg {path} -o trie.svg`, or pass --no-render to only write {path}"
+            ),
+            Error::IndexFormat { path, message } => write!(f, "{}: {}", path, message),
+            Error::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+// Finds Graphviz's dot program on the PATH, or where its installers put it when
+// they leave the PATH alone.
+fn find_graphviz() -> Option<PathBuf> {
+    let program = if cfg!(windows) { "dot.exe" } else { "dot" };
+    let mut dirs: Vec<PathBuf> = env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect())
+        .unwrap_or_default();
+    if cfg!(windows) {
+        for var in ["ProgramFiles", "ProgramFiles(x86)"] {
+            if let Some(dir) = env::var_os(var) {
+                dirs.push(Path::new(&dir).join("Graphviz").join("bin"));
+            }
+        }
+    } else if cfg!(target_os = "macos") {
+        for dir in ["/opt/homebrew/bin", "/usr/local/bin", "/opt/local/bin"] {
+            dirs.push(PathBuf::from(dir));
+        }
+    }
+    dirs.into_iter()
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+// The picks recorded in a history file, none if it doesn't exist yet,
+// decaying with the --half-life if one is given.
+fn read_history(path: &str, options: &LoadOptions) -> Result<History, Error> {
+    let history = match File::open(path) {
+        Ok(file) => History::read(io::BufReader::new(file))?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => History::new(),
+        Err(err) => return Err(err.into()),
+    };
+    Ok(match options.half_life {
+        Some(half_life) => history.half_life(half_life),
+        None => history,
+    })
+}
+
+// Tells a file of the wrong format from other failures to read it.
+fn format_error(path: &str, err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::InvalidData => Error::IndexFormat {
+            path: path.to_string(),
+            message: err.to_string(),
+        },
+        _ => Error::Io(err),
+    }
+}
+
+fn open_index(path: &str) -> Result<Index, Error> {
+    Index::open(path).map_err(|err| format_error(path, err))
+}
+
+// The table given by the --sqlite, --table, --column, --weight-column and
+// --tags-column options of import and export, with the database's path.
+#[cfg(feature = "sqlite")]
+fn sqlite_table(
+    command: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<(String, sqlite::Table), Error> {
+    let (mut path, mut name, mut column) = (None, None, None);
+    let (mut weight, mut tags) = (None, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sqlite" => path = Some(flag_value(args, "--sqlite")?),
+            "--table" => name = Some(flag_value(args, "--table")?),
+            "--column" => column = Some(flag_value(args, "--column")?),
+            "--weight-column" => weight = Some(flag_value(args, "--weight-column")?),
+            "--tags-column" => tags = Some(flag_value(args, "--tags-column")?),
+            _ => return Err(Error::Usage(format!("unknown {} option: {}", command, arg))),
+        }
+    }
+    let (Some(path), Some(name), Some(column)) = (path, name, column) else {
+        return Err(Error::Usage(
+            "import and export need --sqlite, --table and --column.".to_string(),
+        ));
+    };
+    let mut table = sqlite::Table::new(&name, &column);
+    table.weight = weight;
+    table.tags = tags;
+    Ok((path, table))
+}
+
+// Database errors are reported as I/O errors of the database file.
+#[cfg(feature = "sqlite")]
+fn sqlite_error(path: &str, err: rusqlite::Error) -> Error {
+    Error::Io(io::Error::other(format!("{}: {}", path, err)))
+}
+
+// Normalizes prefixes the way the dictionary was when it was loaded, for
+// completers that receive raw user input, completing nothing for those of
+// fewer than `min_prefix` characters.
+struct Normalized<'a> {
+    completer: &'a dyn Completer,
+    options: &'a LoadOptions,
+    min_prefix: usize,
+}
+
+impl Completer for Normalized<'_> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        // Short prefixes would enumerate most of the dictionary, which a
+        // server answering every keystroke shouldn't.
+        if prefix.chars().count() < self.min_prefix {
+            return vec![];
+        }
+        let prefix = self.options.query_prefix(prefix);
+        if self.options.aliases.is_empty() {
+            return self.completer.complete(&prefix, limit);
+        }
+        self.options
+            .aliases
+            .over(self.completer)
+            .show_alias(self.options.show_alias)
+            .complete(&prefix, limit)
+    }
+}
+
+// Completion settings shared by every prefix of a run.
+struct Batch<'a> {
+    completer: Normalized<'a>,
+    limit: usize,
+    min_prefix: usize,
+    // Ends every line of text output; NUL for `xargs -0` and the like.
+    terminator: char,
+    // Complete the last word of free text, see `last_word`, unless the whole
+    // text starts a word.
+    free_text: bool,
+    // Complete what follows the last whitespace instead, see `last_token`.
+    last_token: bool,
+    // Print each word's score after it, see `write_json` for JSON.
+    with_scores: bool,
+    // The version of the envelope JSON output comes in, see `response`;
+    // without one, the records of `write_json`.
+    schema: Option<u32>,
+}
+
+impl Batch<'_> {
+    // The text before the part completed, and that part.
+    fn split<'t>(&self, text: &'t str) -> (&'t str, &'t str) {
+        if self.last_token {
+            last_token(text)
+        } else if self.free_text {
+            last_word(text)
+        } else {
+            ("", text)
+        }
+    }
+}
+
+impl Completer for Batch<'_> {
+    fn complete(&self, text: &str, limit: usize) -> Vec<Suggestion> {
+        // Multi-word entries such as "Star Wars" complete whole while the
+        // text is a prefix of one, and only otherwise does its last word
+        // complete alone.
+        if (self.free_text || self.last_token) && text.chars().count() >= self.min_prefix {
+            let whole = self.completer.complete(text, limit);
+            if !whole.is_empty() {
+                return whole;
+            }
+        }
+        let (head, prefix) = self.split(text);
+        // Short prefixes would enumerate most of the dictionary, which
+        // interactive callers completing on every keystroke don't want.
+        if prefix.chars().count() < self.min_prefix {
+            return vec![];
+        }
+        let mut suggestions = self.completer.complete(prefix, limit);
+        for suggestion in &mut suggestions {
+            suggestion.word.insert_str(0, head);
+        }
+        suggestions
+    }
+}
+
+// Splits text typed into a field at the start of its last word, by the
+// Unicode word boundaries of UAX #29: the head is echoed untouched in front
+// of the completions of the word. Text ending between words has an empty
+// last word.
+fn last_word(text: &str) -> (&str, &str) {
+    match text.split_word_bound_indices().next_back() {
+        Some((at, word)) if word.chars().any(char::is_alphanumeric) => text.split_at(at),
+        _ => (text, ""),
+    }
+}
+
+// Splits text after its last whitespace, for shells and chat inputs that
+// complete the fragment being typed whatever it holds, "git checkout
+// feat/lo" completing "feat/lo" where `last_word` would complete "lo".
+fn last_token(text: &str) -> (&str, &str) {
+    match text.char_indices().rfind(|(_, c)| c.is_whitespace()) {
+        Some((at, c)) => text.split_at(at + c.len_utf8()),
+        None => ("", text),
+    }
+}
+
+// Whether queries are free text whose last word is completed. Token keys
+// complete whole phrases, a word per key, already.
+fn is_free_text<K: TrieKey + 'static>() -> bool {
+    TypeId::of::<K>() != TypeId::of::<Token>()
+}
+
+// How the results of complete and suggest are printed.
+#[derive(Clone, Copy, PartialEq)]
+enum Output {
+    Text,
+    // One JSON record per query, with all of its results.
+    Json,
+    // JSON Lines: one record per result, naming the query it answers, so
+    // results can be filtered with jq or shipped to a log as they come.
+    Jsonl,
+}
+
+impl Output {
+    fn parse(name: &str) -> Result<Self, Error> {
+        match name {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "jsonl" => Ok(Self::Jsonl),
+            _ => Err(Error::Usage(format!("unknown output format: {}", name))),
+        }
+    }
+}
+
+impl Batch<'_> {
+    // Completes one prefix per line, printing each prefix followed by its
+    // indented completions, or JSON records. Streaming flushes the results
+    // of every prefix as soon as they are written, for callers waiting on
+    // them before sending the next one.
+    fn print_batch(
+        &self,
+        prefixes: impl BufRead,
+        output: Output,
+        streaming: bool,
+    ) -> io::Result<()> {
+        let mut out = io::BufWriter::new(io::stdout().lock());
+        for prefix in prefixes.lines() {
+            let prefix = prefix?;
+            if output == Output::Text {
+                write!(out, "{}{}", prefix, self.terminator)?;
+                for suggestion in &self.complete(&prefix, self.limit) {
+                    write!(out, "    ")?;
+                    self.write_line(&mut out, suggestion)?;
+                }
+            } else {
+                self.write_json(&mut out, &prefix, output)?;
+            }
+            if streaming {
+                out.flush()?;
+            }
+        }
+        out.flush()
+    }
+
+    // A completion as a line of text output, followed by its score if asked.
+    fn write_line(&self, out: &mut impl Write, suggestion: &Suggestion) -> io::Result<()> {
+        if self.with_scores {
+            write!(
+                out,
+                "{}\t{}{}",
+                suggestion.word, suggestion.weight, self.terminator
+            )
+        } else {
+            write!(out, "{}{}", suggestion.word, self.terminator)
+        }
+    }
+
+    // Writes the completions of a prefix as a single JSON record, or as one
+    // JSON Lines record each. With scores, the suggestions of a single record
+    // are objects with the word and its score rather than bare words; JSON
+    // Lines records carry the score as their weight anyway. With a schema
+    // version, either is one envelope per prefix.
+    fn write_json(&self, out: &mut impl Write, prefix: &str, output: Output) -> io::Result<()> {
+        let Some(version) = self.schema else {
+            let suggestions = self.complete(prefix, self.limit);
+            return write_json(out, prefix, &suggestions, output, self.with_scores);
+        };
+        // One more than the limit tells whether there are more.
+        let mut suggestions = self.complete(prefix, self.limit.saturating_add(1));
+        let truncated = suggestions.len() > self.limit;
+        suggestions.truncate(self.limit);
+        let suggestions = suggestions.iter().map(response::suggestion).collect();
+        let envelope = response::envelope(version, prefix, suggestions, truncated);
+        writeln!(out, "{}", envelope)
+    }
+}
+
+fn write_json(
+    out: &mut impl Write,
+    prefix: &str,
+    suggestions: &[Suggestion],
+    output: Output,
+    with_scores: bool,
+) -> io::Result<()> {
+    if output == Output::Json {
+        let words: Vec<_> = if with_scores {
+            suggestions
+                .iter()
+                .map(|s| serde_json::json!({ "word": s.word, "score": s.weight }))
+                .collect()
+        } else {
+            suggestions
+                .iter()
+                .map(|s| serde_json::json!(s.word))
+                .collect()
+        };
+        let record = serde_json::json!({ "prefix": prefix, "suggestions": words });
+        return writeln!(out, "{}", record);
+    }
+    for (rank, suggestion) in suggestions.iter().enumerate() {
+        let record = serde_json::json!({
+            "prefix": prefix,
+            "rank": rank,
+            "word": suggestion.word,
+            "weight": suggestion.weight,
+        });
+        writeln!(out, "{}", record)?;
+    }
+    Ok(())
+}
+
+// A line of output with placeholders for what is known about a word, for
+// integrations wanting a format of their own.
+struct Template {
+    parts: Vec<Part>,
+}
+
+enum Part {
+    Text(String),
+    Word,
+    Score,
+    Weight,
+    Tags,
+    // How many leading characters the word shares with the query.
+    Matched,
+}
+
+// The values a template line is filled in with.
+struct Fields<'a> {
+    word: &'a str,
+    score: String,
+    weight: u64,
+    tags: &'a [String],
+    query: &'a str,
+}
+
+impl Template {
+    // Placeholders are {word}, {score}, {weight}, {tags} and {matched}; \t,
+    // \n and \\ are unescaped, since shells pass them on as typed.
+    fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some('\\') => text.push('\\'),
+                    Some(c) => {
+                        text.push('\\');
+                        text.push(c);
+                    }
+                    None => text.push('\\'),
+                },
+                '{' => {
+                    let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    let part = match name.as_str() {
+                        "word" => Part::Word,
+                        "score" => Part::Score,
+                        "weight" => Part::Weight,
+                        "tags" => Part::Tags,
+                        "matched" => Part::Matched,
+                        _ => return Err(format!("unknown placeholder {{{}}}", name)),
+                    };
+                    if !text.is_empty() {
+                        parts.push(Part::Text(mem::take(&mut text)));
+                    }
+                    parts.push(part);
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Self { parts })
+    }
+
+    fn write(&self, out: &mut impl Write, fields: &Fields) -> io::Result<()> {
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => write!(out, "{}", text)?,
+                Part::Word => write!(out, "{}", fields.word)?,
+                Part::Score => write!(out, "{}", fields.score)?,
+                Part::Weight => write!(out, "{}", fields.weight)?,
+                Part::Tags => write!(out, "{}", fields.tags.join(","))?,
+                Part::Matched => {
+                    let matched = fields
+                        .word
+                        .chars()
+                        .zip(fields.query.chars())
+                        .take_while(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+                        .count();
+                    write!(out, "{}", matched)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_template(template: Option<String>) -> Result<Option<Template>, Error> {
+    let Some(template) = template else {
+        return Ok(None);
+    };
+    match Template::parse(&template) {
+        Ok(template) => Ok(Some(template)),
+        Err(err) => Err(Error::Usage(format!("invalid --template: {}", err))),
+    }
+}
+
+fn print_completions(
+    completer: &dyn Completer,
+    prefix: &str,
+    limit: usize,
+    terminator: char,
+) -> io::Result<()> {
+    for suggestion in completer.complete(prefix, limit) {
+        write!(io::stdout(), "{}{}", suggestion.word, terminator)?;
+    }
+    Ok(())
+}
+
+const DICTIONARY: &str = "dictionary.txt";
+
+// Word lists shipped by most Linux distributions and macOS, probed in order
+// when there is no local dictionary. Override with PREFIX_TREE_WORDLISTS,
+// which takes a PATH-style list of files.
+const SYSTEM_WORDLISTS: &[&str] = &["/usr/share/dict/words", "/usr/dict/words"];
+
+// The built-in list for `--stopwords english`: words too common to be worth
+// completing.
+const ENGLISH_STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been",
+    "but", "by", "can", "could", "did", "do", "does", "for", "from", "had", "has", "have", "he",
+    "her", "him", "his", "how", "i", "if", "in", "into", "is", "it", "its", "me", "my", "no",
+    "not", "of", "on", "or", "our", "she", "so", "than", "that", "the", "their", "them", "then",
+    "there", "these", "they", "this", "those", "to", "up", "us", "was", "we", "were", "what",
+    "when", "which", "who", "will", "with", "would", "you", "your",
+];
+
+// The dictionary of a language, next to the default one.
+fn language_dictionary(lang: &str) -> String {
+    format!("dictionary.{}.txt", lang)
+}
+
+// The languages with a dictionary in the current directory, sorted.
+fn languages() -> io::Result<Vec<String>> {
+    let mut languages = vec![];
+    for entry in fs::read_dir(".")? {
+        let name = entry?.file_name();
+        let lang = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("dictionary."))
+            .and_then(|name| name.strip_suffix(".txt"));
+        if let Some(lang) = lang.filter(|lang| !lang.is_empty()) {
+            languages.push(lang.to_string());
+        }
+    }
+    languages.sort();
+    Ok(languages)
+}
+
+// A small English word list, the last resort when there is neither a local
+// dictionary nor a system word list.
+#[cfg(feature = "embedded-dictionary")]
+const EMBEDDED_DICTIONARY: &str = include_str!("../data/english.txt");
+
+fn is_url(dictionary: &str) -> bool {
+    dictionary.starts_with("http://") || dictionary.starts_with("https://")
+}
+
+// The body of the dictionary at `url`. With a cache directory, the last copy
+// fetched is kept there with its ETag, so an unchanged dictionary isn't sent
+// again and one that can't be reached is loaded from the copy.
+#[cfg(feature = "http")]
+fn fetch_dictionary(url: &str, cache: Option<&Path>) -> Result<Vec<u8>, Error> {
+    let name: String = url
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let cached = cache
+        .map(|dir| dir.join(&name))
+        .filter(|path| path.is_file());
+    let etag = cached
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path.with_extension("etag")).ok());
+    let mut request = ureq::get(url);
+    if let Some(etag) = &etag {
+        request = request.header("If-None-Match", etag.trim());
+    }
+    let response = match (request.call(), &cached) {
+        (Ok(response), Some(path)) if response.status() == 304 => return Ok(fs::read(path)?),
+        (Ok(response), _) => response,
+        (Err(ureq::Error::StatusCode(404)), None) => {
+            return Err(Error::DictionaryMissing(format!("{} not found.", url)));
+        }
+        (Err(err), Some(path)) => {
+            writeln!(
+                io::stderr(),
+                "warning: {}: {}; loading the copy cached at {}",
+                url,
+                err,
+                path.display()
+            )?;
+            return Ok(fs::read(path)?);
+        }
+        (Err(err), None) => return Err(io::Error::other(format!("{}: {}", url, err)).into()),
+    };
+    let etag = response
+        .headers()
+        .get("etag")
+        .and_then(|etag| etag.to_str().ok())
+        .map(str::to_string);
+    let mut body = vec![];
+    response.into_body().into_reader().read_to_end(&mut body)?;
+    if let Some(dir) = cache {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(&name);
+        let partial = path.with_extension("partial");
+        fs::write(&partial, &body)?;
+        fs::rename(&partial, &path)?;
+        match etag {
+            Some(etag) => fs::write(path.with_extension("etag"), etag)?,
+            None => match fs::remove_file(path.with_extension("etag")) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            },
+        }
+    }
+    Ok(body)
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch_dictionary(_: &str, _: Option<&Path>) -> Result<Vec<u8>, Error> {
+    Err(Error::Usage(
+        "--dict <url> needs a build with the http feature.".to_string(),
+    ))
+}
+
+#[derive(Clone, Copy)]
+enum Archive {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Archive {
+    // The kind of archive at `path`, by its extension.
+    fn of(path: &str) -> Option<Archive> {
+        let path = path.to_ascii_lowercase();
+        if path.ends_with(".zip") {
+            Some(Archive::Zip)
+        } else if path.ends_with(".tar") {
+            Some(Archive::Tar)
+        } else if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+            Some(Archive::TarGz)
+        } else {
+            None
+        }
+    }
+
+    // The member of the archive in `data` at `member`, or all its files one
+    // after the other, each ending in a newline so their lines stay apart.
+    #[cfg(feature = "archives")]
+    fn extract(self, data: &[u8], member: Option<&str>, name: &str) -> Result<Vec<u8>, Error> {
+        let invalid = |err: &dyn fmt::Display| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", name, err))
+        };
+        let mut out = vec![];
+        let mut found = false;
+        let mut take = |path: &str, file: &mut dyn Read| -> io::Result<()> {
+            if member.is_some_and(|member| member != path) {
+                return Ok(());
+            }
+            found = true;
+            file.read_to_end(&mut out)?;
+            if !out.is_empty() && !out.ends_with(b"\n") {
+                out.push(b'\n');
+            }
+            Ok(())
+        };
+        match self {
+            Archive::Zip => {
+                let mut archive =
+                    zip::ZipArchive::new(io::Cursor::new(data)).map_err(|err| invalid(&err))?;
+                for i in 0..archive.len() {
+                    let mut file = archive.by_index(i).map_err(|err| invalid(&err))?;
+                    if file.is_file() {
+                        let path = file.name().map_err(|err| invalid(&err))?.into_owned();
+                        take(&path, &mut file)?;
+                    }
+                }
+            }
+            Archive::Tar | Archive::TarGz => {
+                let data: Box<dyn Read> = match self {
+                    Archive::TarGz => Box::new(flate2::read::GzDecoder::new(data)),
+                    _ => Box::new(data),
+                };
+                let mut archive = tar::Archive::new(data);
+                for entry in archive.entries()? {
+                    let mut entry = entry?;
+                    if entry.header().entry_type().is_file() {
+                        // Archives made of "." name their members "./en.txt".
+                        let path = entry.path()?.to_string_lossy().into_owned();
+                        take(path.strip_prefix("./").unwrap_or(&path), &mut entry)?;
+                    }
+                }
+            }
+        }
+        if !found {
+            return Err(Error::DictionaryMissing(format!("{} not found.", name)));
+        }
+        Ok(out)
+    }
+
+    #[cfg(not(feature = "archives"))]
+    fn extract(self, _: &[u8], _: Option<&str>, _: &str) -> Result<Vec<u8>, Error> {
+        Err(Error::Usage(
+            "--dict <archive> needs a build with the archives feature.".to_string(),
+        ))
+    }
+}
+
+// The dictionary to load, with the name errors report it by.
+fn open_dictionary(options: &LoadOptions) -> Result<(Box<dyn Read + Send>, String), Error> {
+    if let Some((data, name)) = options.read.lock().unwrap().take() {
+        return Ok((Box::new(io::Cursor::new(data)), name));
+    }
+    if let Some(dictionary) = &options.dict {
+        // A member of an archive is named after it, following "::".
+        let (location, member) = match dictionary.split_once("::") {
+            Some((location, member)) => (location, Some(member)),
+            None => (dictionary.as_str(), None),
+        };
+        if let Some(archive) = Archive::of(location) {
+            let data = match is_url(location) {
+                true => fetch_dictionary(location, options.dict_cache.as_deref())?,
+                false => match fs::read(location) {
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                        return Err(Error::DictionaryMissing(format!("{} not found.", location)));
+                    }
+                    result => result?,
+                },
+            };
+            let body = archive.extract(&data, member, dictionary)?;
+            return Ok((Box::new(io::Cursor::new(body)), dictionary.clone()));
+        }
+        if member.is_some() {
+            return Err(Error::Usage(format!(
+                "{} isn't a zip or tar archive.",
+                location
+            )));
+        }
+        if is_url(dictionary) {
+            let body = fetch_dictionary(dictionary, options.dict_cache.as_deref())?;
+            return Ok((Box::new(io::Cursor::new(body)), dictionary.clone()));
+        }
+        return match File::open(dictionary) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Err(Error::DictionaryMissing(
+                format!("{} not found.", dictionary),
+            )),
+            result => Ok((Box::new(result?), dictionary.clone())),
+        };
+    }
+    if let Some(lang) = options.lang.as_deref() {
+        // A language never falls back to the system word list, which would
+        // quietly be the wrong language.
+        let path = language_dictionary(lang);
+        return match File::open(&path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Err(Error::DictionaryMissing(
+                format!("{} not found for --lang {}.", path, lang),
+            )),
+            result => Ok((Box::new(result?), path)),
+        };
+    }
+    match File::open(DICTIONARY) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        result => return Ok((Box::new(result?), DICTIONARY.to_string())),
+    }
+    let candidates: Vec<PathBuf> = match env::var_os("PREFIX_TREE_WORDLISTS") {
+        Some(paths) => env::split_paths(&paths).collect(),
+        None => SYSTEM_WORDLISTS.iter().map(PathBuf::from).collect(),
+    };
+    for path in candidates {
+        if let Ok(file) = File::open(&path) {
+            return Ok((Box::new(file), path.display().to_string()));
+        }
+    }
+    #[cfg(feature = "embedded-dictionary")]
+    {
+        Ok((
+            Box::new(EMBEDDED_DICTIONARY.as_bytes()),
+            "the built-in word list".to_string(),
+        ))
+    }
+    #[cfg(not(feature = "embedded-dictionary"))]
+    {
+        Err(Error::DictionaryMissing(format!(
+            "{} not found and no system word list is available.\n\
+             Set PREFIX_TREE_WORDLISTS to a list of word list files to probe.",
+            DICTIONARY
+        )))
+    }
+}
+
+fn flag_value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, Error> {
+    if let Some(value) = args.next() {
+        return Ok(value);
+    }
+    Err(Error::Usage(format!("{} expects a value.", flag)))
+}
+
+fn parse_flag<T: FromStr>(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<T, Error> {
+    let value = flag_value(args, flag)?;
+    if let Ok(value) = value.parse() {
+        return Ok(value);
+    }
+    Err(Error::Usage(format!(
+        "invalid value for {}: {}",
+        flag, value
+    )))
+}
+
+// The token the servers take writes with: --auth-token, or else, out of
+// sight of ps, PREFIX_TREE_AUTH_TOKEN. Without one, writes are open.
+fn auth_token(flag: Option<String>) -> Option<String> {
+    flag.or_else(|| env::var("PREFIX_TREE_AUTH_TOKEN").ok())
+        .filter(|token| !token.is_empty())
+}
+
+// Says on stderr whether `writes` need the token, so a server that lets
+// anyone write never starts silently.
+fn report_auth(writes: &str, token: Option<&str>) -> io::Result<()> {
+    match token {
+        Some(_) => writeln!(io::stderr(), "{} need the auth token", writes),
+        None => writeln!(
+            io::stderr(),
+            "WARNING: {} are open to anyone; set --auth-token or PREFIX_TREE_AUTH_TOKEN",
+            writes
+        ),
+    }
+}
+
+// Where `query` looks for a running resp server by default.
+const DAEMON: &str = "127.0.0.1:6380";
+
+// How long `query` waits on the server before answering by itself.
+#[cfg(feature = "client")]
+const DAEMON_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Answers `args`, a complete or contains the resp server at `address` can
+// answer as well, from it, returning false without printing anything when
+// there is no server there or it fails to.
+#[cfg(feature = "client")]
+fn query_daemon(address: &str, args: &[String]) -> Result<bool, Error> {
+    use std::net::ToSocketAddrs;
+
+    let (subcommand, rest) = args.split_first().expect("a subcommand");
+    // Its own limit is the only option the server knows of.
+    let complete = match (subcommand.as_str(), rest) {
+        ("complete", [prefix]) => Some((prefix, usize::MAX)),
+        ("complete", [prefix, flag, limit] | [flag, limit, prefix]) if flag == "--limit" => {
+            match limit.parse() {
+                Ok(limit) => Some((prefix, limit)),
+                Err(_) => return Ok(false),
+            }
+        }
+        ("contains", words) if !words.is_empty() && !words.iter().any(|w| w.starts_with("--")) => {
+            None
+        }
+        _ => return Ok(false),
+    };
+    let Some(address) = address
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut all| all.next())
+    else {
+        return Ok(false);
+    };
+    let Ok(mut client) = PrefixTreeClient::connect_timeout(&address, DAEMON_TIMEOUT) else {
+        return Ok(false);
+    };
+    let mut lines = vec![];
+    let mut all_found = true;
+    match complete {
+        Some((prefix, limit)) => match client.complete(prefix, limit) {
+            Ok(suggestions) => lines.extend(suggestions.into_iter().map(|s| s.word)),
+            Err(_) => return Ok(false),
+        },
+        None => {
+            for word in rest {
+                let Ok(found) = client.contains(word) else {
+                    return Ok(false);
+                };
+                all_found &= found;
+                lines.push(match rest.len() {
+                    1 => found.to_string(),
+                    _ => format!("{}\t{}", word, found),
+                });
+            }
+        }
+    }
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for line in lines {
+        writeln!(out, "{}", line)?;
+    }
+    out.flush()?;
+    // Like contains, telling whether every word was found.
+    if !all_found {
+        exit(1);
+    }
+    Ok(true)
 }
 
-impl Node {
-    fn new() -> Self {
-        Self {
-            children: HashMap::new(),
-            end: false,
+// The value of --schema-version, a version of the response envelope this
+// build writes.
+fn schema_version(args: &mut impl Iterator<Item = String>) -> Result<u32, Error> {
+    response::negotiate(&flag_value(args, "--schema-version")?).map_err(Error::Usage)
+}
+
+// Removes every occurrence of a global switch from the arguments, reporting
+// whether it was given.
+fn take_switch(args: &mut Vec<String>, switch: &str) -> bool {
+    let len = args.len();
+    args.retain(|arg| arg != switch);
+    args.len() != len
+}
+
+// Removes a global option and its value from the arguments.
+fn take_option(args: &mut Vec<String>, option: &str) -> Result<Option<String>, Error> {
+    let Some(i) = args.iter().position(|arg| arg == option) else {
+        return Ok(None);
+    };
+    args.remove(i);
+    if i < args.len() {
+        return Ok(Some(args.remove(i)));
+    }
+    Err(Error::Usage(format!("{} expects a value.", option)))
+}
+
+fn usage(mut sink: impl Write) -> io::Result<()> {
+    writeln!(sink, "Usage: ./prefix-tree [OPTIONS] <SUBCOMMAND>")?;
+    writeln!(sink, "OPTIONS")?;
+    writeln!(
+        sink,
+        "    --stem            Stem words (\"running\" -> \"run\") when loading and querying"
+    )?;
+    writeln!(
+        sink,
+        "    --keys <unit>     Split words into char, byte, grapheme or token keys (default: byte for an ASCII dictionary, char otherwise)"
+    )?;
+    writeln!(sink, "    --graphemes       Shorthand for --keys grapheme")?;
+    writeln!(
+        sink,
+        "    --fold-case       Lowercase words when loading and querying"
+    )?;
+    writeln!(
+        sink,
+        "    --dedup           Keep only the first dictionary line of a repeated word"
+    )?;
+    writeln!(
+        sink,
+        "    --display-case <c>  Casing completions show for folded words: frequent, first, folded (default: frequent)"
+    )?;
+    writeln!(
+        sink,
+        "    --lang <code>     Load the dictionary of a language, dictionary.<code>.txt"
+    )?;
+    writeln!(
+        sink,
+        "    --dict <file|url> Load this dictionary instead; http(s) URLs need the http feature, archive.zip[::member] and .tar[.gz] the archives feature"
+    )?;
+    writeln!(
+        sink,
+        "    --dict-cache <dir>  Keep the copy of a --dict URL there, fetched again only when its ETag changes"
+    )?;
+    writeln!(
+        sink,
+        "    --stopwords <file>  Leave the words listed in the file, one per line, out of the Trie; english for a built-in list"
+    )?;
+    writeln!(
+        sink,
+        "    --exclude <file>  Never insert the words listed in the file, one per line, e.g. a blocklist"
+    )?;
+    writeln!(
+        sink,
+        "    --max-mem <n>     Sort the dictionary on disk in runs of n bytes (k, M, G suffixes) before building"
+    )?;
+    writeln!(
+        sink,
+        "    --history-log <file>  Log of feedback picks folded into the weights on load (default: history.log)"
+    )?;
+    writeln!(
+        sink,
+        "    --half-life <days>  Let a pick, logged or in a --history file, count half as much every that many days"
+    )?;
+    writeln!(
+        sink,
+        "    --aliases <file>  Store and look up the alias=canonical spellings of the file as their canonical forms"
+    )?;
+    writeln!(
+        sink,
+        "    --show-alias      Complete aliases as typed, not as their canonical forms"
+    )?;
+    writeln!(
+        sink,
+        "    --profile <name>  Add the options of the [name] section of prefix-tree.conf (or $PREFIX_TREE_CONFIG)"
+    )?;
+    writeln!(sink, "SUBCOMMANDS")?;
+    writeln!(
+        sink,
+        "    dot               Dump the Trie into a Graphviz dot file."
+    )?;
+    writeln!(
+        sink,
+        "        --no-render   Only write trie.dot, without rendering trie.svg with Graphviz"
+    )?;
+    writeln!(
+        sink,
+        "        --out <file>  Also write the dot text to the file, or only there with --no-render"
+    )?;
+    writeln!(
+        sink,
+        "        --cluster     Draw the subtree of every first letter as a labelled, colored cluster"
+    )?;
+    writeln!(
+        sink,
+        "        --word-shape <shape>  Graphviz shape of the nodes where a word ends (default: doublecircle)"
+    )?;
+    writeln!(
+        sink,
+        "        --plain-words Draw the nodes where a word ends like the others"
+    )?;
+    writeln!(
+        sink,
+        "        --word-labels Label the nodes where a word ends with the whole word"
+    )?;
+    writeln!(
+        sink,
+        "        --compact     Number the nodes and leave the labels off the edges, for smaller files"
+    )?;
+    writeln!(
+        sink,
+        "        --merge-chains  Draw a chain of single children ending a word as one node"
+    )?;
+    writeln!(
+        sink,
+        "        --max-nodes <n>  Draw only the levels nearest the root that fit in n nodes, warning when that cuts any"
+    )?;
+    writeln!(
+        sink,
+        "    compact           Write the dictionary as a compact front-coded index"
+    )?;
+    writeln!(
+        sink,
+        "        --output <file>  Index file to write (default: trie.idx)"
+    )?;
+    writeln!(
+        sink,
+        "        --compress    Compress the index with zstd, for distribution (zstd builds only)"
+    )?;
+    writeln!(
+        sink,
+        "        --level <n>   zstd level of --compress, 1 to 22 (default: 19)"
+    )?;
+    writeln!(
+        sink,
+        "    abbrev [word...]  Print every word, or the given ones, with its shortest prefix no other word has, tab-separated"
+    )?;
+    writeln!(
+        sink,
+        "    infix-index       Write a suffix automaton of the dictionary for fast infix queries"
+    )?;
+    writeln!(
+        sink,
+        "        --output <file>  Index file to write (default: infix.idx)"
+    )?;
+    writeln!(
+        sink,
+        "    infix <text>      Print the words containing the text anywhere, in sorted order"
+    )?;
+    writeln!(
+        sink,
+        "        --index <file>  Answer from an index written by infix-index instead of the dictionary"
+    )?;
+    writeln!(sink, "        --limit <n>   Maximum number of words")?;
+    writeln!(
+        sink,
+        "        -0, --null    End each word with NUL instead of a newline"
+    )?;
+    writeln!(
+        sink,
+        "    glob <pattern>    Print the words matching the pattern, ? for any one key and * for any run, in sorted order"
+    )?;
+    writeln!(sink, "        --limit <n>   Maximum number of words")?;
+    writeln!(
+        sink,
+        "        --plan        Print the strategy, node visits and prunes of the query to stderr first"
+    )?;
+    writeln!(
+        sink,
+        "        -0, --null    End each word with NUL instead of a newline"
+    )?;
+    writeln!(
+        sink,
+        "    fill <pattern>    Print the words of the pattern's length with its letters in place, _ for any one letter"
+    )?;
+    writeln!(sink, "        --limit <n>   Maximum number of words")?;
+    writeln!(
+        sink,
+        "        -0, --null    End each word with NUL instead of a newline"
+    )?;
+    writeln!(
+        sink,
+        "    diff-index <old> <new>  Write the patch turning one index into another to stdout"
+    )?;
+    writeln!(
+        sink,
+        "    apply-patch <index> <patch>  Update an index with a patch written by diff-index"
+    )?;
+    writeln!(
+        sink,
+        "        --output <file>  Index file to write (default: the index being patched)"
+    )?;
+    writeln!(
+        sink,
+        "    tree [prefix]     Print the Trie below the prefix as a tree, marking word ends with *"
+    )?;
+    writeln!(
+        sink,
+        "        --depth <n>   Maximum number of levels to print"
+    )?;
+    writeln!(
+        sink,
+        "    list              Print every word in the Trie in sorted order"
+    )?;
+    writeln!(
+        sink,
+        "        --info        Also print weights and tags, as dictionary lines"
+    )?;
+    writeln!(
+        sink,
+        "        --min-len <n>  Only words of at least n characters"
+    )?;
+    writeln!(
+        sink,
+        "        --max-len <n>  Only words of at most n characters"
+    )?;
+    writeln!(
+        sink,
+        "        --charset <a>  Only words made of these characters and ranges, e.g. a-z"
+    )?;
+    writeln!(sink, "        --prefix <p>  Only words starting with p")?;
+    writeln!(
+        sink,
+        "    top               Print the heaviest words, as ranking will promote them"
+    )?;
+    writeln!(sink, "        --n <n>       Number of words (default: 50)")?;
+    writeln!(sink, "        --prefix <p>  Only words starting with p")?;
+    writeln!(
+        sink,
+        "        --with-scores  Follow each word with a tab and its weight"
+    )?;
+    writeln!(
+        sink,
+        "        -0, --null    End each word with NUL instead of a newline"
+    )?;
+    writeln!(
+        sink,
+        "    extremes [prefix] Print the shortest and longest words starting with the prefix"
+    )?;
+    writeln!(
+        sink,
+        "    count [prefix]    Print how many words start with the prefix"
+    )?;
+    writeln!(
+        sink,
+        "    select <k> [prefix]  Print the k-th word starting with the prefix in sorted order, from 0"
+    )?;
+    writeln!(
+        sink,
+        "    rank <word>       Print how many words sort before the word"
+    )?;
+    writeln!(
+        sink,
+        "    range <low> <high>  Print the words from low to high, both included, in sorted order"
+    )?;
+    writeln!(
+        sink,
+        "    next <word>       Print the first word sorting after the word, stored or not"
+    )?;
+    writeln!(
+        sink,
+        "    prev <word>       Print the last word sorting before the word, stored or not"
+    )?;
+    writeln!(
+        sink,
+        "    prefixes --len <n>  Count the distinct n-key prefixes and list those covering the most words"
+    )?;
+    writeln!(
+        sink,
+        "        --min-count <m>  Only list prefixes of at least m words"
+    )?;
+    writeln!(
+        sink,
+        "        --limit <n>   Maximum number of prefixes to list (default: 10)"
+    )?;
+    writeln!(
+        sink,
+        "    rename-prefix <from> <to>  Print the dictionary with words starting with from moved under to"
+    )?;
+    writeln!(
+        sink,
+        "    import --sqlite <db> --table <t> --column <c>  Print the words of a SQLite table as dictionary lines (sqlite feature)"
+    )?;
+    writeln!(sink, "        --weight-column <c>  Column of the weights")?;
+    writeln!(
+        sink,
+        "        --tags-column <c>    Column of the comma-separated tags"
+    )?;
+    writeln!(
+        sink,
+        "    export --sqlite <db> --table <t> --column <c>  Write the words of the Trie to a SQLite table, creating it if needed"
+    )?;
+    writeln!(
+        sink,
+        "                      Takes --weight-column and --tags-column as import does"
+    )?;
+    writeln!(
+        sink,
+        "    complete <prefix> Suggest prefix autocompletion based on the Trie"
+    )?;
+    writeln!(
+        sink,
+        "                      Of several words, only the last is completed, behind the others as typed"
+    )?;
+    writeln!(
+        sink,
+        "        --tag <tag>   Only suggest words carrying the given tag"
+    )?;
+    writeln!(
+        sink,
+        "        --index <file>  Complete from an index written by compact instead of the dictionary"
+    )?;
+    writeln!(
+        sink,
+        "        --source <file>[:n]  Mix in completions from another word list, at most n of them"
+    )?;
+    writeln!(
+        sink,
+        "        --limit <n>   Maximum number of completions, heaviest first"
+    )?;
+    writeln!(
+        sink,
+        "        --min-prefix <n>  Complete nothing for prefixes shorter than n characters"
+    )?;
+    writeln!(
+        sink,
+        "        --last-token  Complete only what follows the last whitespace, keeping the words before it"
+    )?;
+    writeln!(
+        sink,
+        "        --batch <file>  Complete every prefix of the file, one per line"
+    )?;
+    writeln!(
+        sink,
+        "        --stdin       Complete prefixes read line by line from stdin, answering each at once"
+    )?;
+    writeln!(
+        sink,
+        "        --json        Print results as one JSON record per prefix"
+    )?;
+    writeln!(
+        sink,
+        "        --output <format>  text, json (as --json) or jsonl: one JSON record per completion"
+    )?;
+    writeln!(
+        sink,
+        "        -0, --null    End each completion with NUL instead of a newline"
+    )?;
+    writeln!(
+        sink,
+        "        --template <t>  Print each completion as t, filling in {{word}}, {{score}}, {{weight}}, {{tags}} and {{matched}}"
+    )?;
+    writeln!(
+        sink,
+        "        --with-scores  Follow each completion with a tab and its score; JSON suggestions become {{word, score}} objects"
+    )?;
+    writeln!(
+        sink,
+        "        --schema-version <n>  Print JSON output as the versioned response envelope, one per prefix (versions: 1)"
+    )?;
+    writeln!(
+        sink,
+        "        --exec <cmd>  Pipe the completions into the shell command cmd, e.g. fzf, which prints the pick (alias: --pipe)"
+    )?;
+    writeln!(
+        sink,
+        "        --history <file>  Rank the completions picked most often, as recorded by pick, higher"
+    )?;
+    writeln!(
+        sink,
+        "        --fuzzy-prefix  Also complete prefixes within --max-dist edits of the one typed, closest first"
+    )?;
+    writeln!(
+        sink,
+        "        --max-dist <n>  Maximum edit distance of a --fuzzy-prefix prefix (default: 1)"
+    )?;
+    writeln!(
+        sink,
+        "        --quiet       Print nothing, only exit with 1 if nothing completes the prefix"
+    )?;
+    writeln!(
+        sink,
+        "        --count-only  Print the number of completions instead of the completions"
+    )?;
+    writeln!(
+        sink,
+        "        --explain     Report nodes visited and time per phase to stderr, and heap use in alloc-stats builds"
+    )?;
+    writeln!(
+        sink,
+        "        --shards <n>  Split the dictionary across n tries, built and searched in parallel"
+    )?;
+    writeln!(
+        sink,
+        "        --min-len <n> Never suggest words of fewer than n keys"
+    )?;
+    writeln!(
+        sink,
+        "        --score-script <file>  Rank with a Rhai script of word, weight, prefix and tags (scripting feature)"
+    )?;
+    writeln!(
+        sink,
+        "    contains <word>...  Check whether the words are in the Trie, one word<TAB>true|false line each for several"
+    )?;
+    writeln!(
+        sink,
+        "        --stdin       Also check the words read line by line from stdin"
+    )?;
+    writeln!(
+        sink,
+        "        --json        Print one JSON record per word, as {{\"word\": ..., \"found\": ...}}"
+    )?;
+    writeln!(
+        sink,
+        "        --quiet       Print nothing, only exit with 1 if any word is missing"
+    )?;
+    writeln!(
+        sink,
+        "    query complete|contains ...  Ask a running resp server, falling back to loading the dictionary when there is none"
+    )?;
+    writeln!(
+        sink,
+        "        --daemon <addr>  Address of the server (default: $PREFIX_TREE_DAEMON, or else 127.0.0.1:6380)"
+    )?;
+    writeln!(
+        sink,
+        "    verify <file>     Print the words of the file, one per line, missing from the Trie, with how often"
+    )?;
+    writeln!(
+        sink,
+        "        --json        Print a JSON summary with the counts and the missing words instead"
+    )?;
+    writeln!(
+        sink,
+        "    starts-with <prefix>  Check whether any word in the Trie starts with the prefix"
+    )?;
+    writeln!(
+        sink,
+        "        --quiet       Print nothing, only exit with 1 if no word starts with it"
+    )?;
+    writeln!(
+        sink,
+        "    suggest <word>    Suggest dictionary words close to a misspelled word"
+    )?;
+    writeln!(
+        sink,
+        "                      Of several words, only the last is corrected, behind the others as typed"
+    )?;
+    writeln!(
+        sink,
+        "        --max-dist <n>          Maximum edit distance (default: 2)"
+    )?;
+    writeln!(
+        sink,
+        "        -0, --null              End each suggestion with NUL instead of a newline"
+    )?;
+    writeln!(
+        sink,
+        "        --output <format>       text, json or jsonl: one JSON record per suggestion"
+    )?;
+    writeln!(
+        sink,
+        "        --timeout-ms <n>        Stop searching after n milliseconds and print what was found"
+    )?;
+    writeln!(
+        sink,
+        "        --explain               Report nodes visited, branches pruned and time per phase to stderr"
+    )?;
+    writeln!(
+        sink,
+        "        --plan                  Print the strategy, estimated node visits and prunes to stderr before searching"
+    )?;
+    writeln!(
+        sink,
+        "        --shards <n>            Split the dictionary across n tries, built and searched in parallel"
+    )?;
+    writeln!(
+        sink,
+        "        --min-len <n>           Never suggest words of fewer than n keys"
+    )?;
+    writeln!(
+        sink,
+        "        --template <t>          Print each suggestion as t, with the placeholders of complete --template"
+    )?;
+    writeln!(
+        sink,
+        "        --with-scores           Follow each suggestion with a tab and its ranking score, as complete --with-scores"
+    )?;
+    writeln!(
+        sink,
+        "        --schema-version <n>    Print JSON output as the versioned response envelope (versions: 1)"
+    )?;
+    writeln!(
+        sink,
+        "        --limit <n>             Maximum number of suggestions (default: 10)"
+    )?;
+    writeln!(
+        sink,
+        "        --distance-weight <x>   Score penalty per edit (default: 1.0)"
+    )?;
+    writeln!(
+        sink,
+        "        --frequency-weight <x>  Score bonus per log-weight (default: 0.1)"
+    )?;
+    writeln!(
+        sink,
+        "        --min-score <x>         Drop suggestions scoring below x"
+    )?;
+    writeln!(
+        sink,
+        "        --layout <name>         Keyboard layout for typo costs: {}, none (default: qwerty)",
+        keyboard::layout_names().collect::<Vec<_>>().join(", ")
+    )?;
+    writeln!(
+        sink,
+        "        --adjacent-cost <x>     Cost of substituting a neighbouring key (default: 0.5)"
+    )?;
+    writeln!(
+        sink,
+        "    lsp               Serve completions over the Language Server Protocol on stdio"
+    )?;
+    writeln!(
+        sink,
+        "        --limit <n>   Maximum number of completions per request (default: 50)"
+    )?;
+    writeln!(
+        sink,
+        "        --min-prefix <n>  Complete nothing for prefixes shorter than n characters"
+    )?;
+    writeln!(
+        sink,
+        "        --cache <n>   Cache the completions of the n most recent queries (default: 0)"
+    )?;
+    writeln!(
+        sink,
+        "    nvim              Serve completions over Neovim's MessagePack-RPC on stdio"
+    )?;
+    writeln!(
+        sink,
+        "        --limit <n>   Default maximum number of completions per request (default: 50)"
+    )?;
+    writeln!(
+        sink,
+        "        --min-prefix <n>  Complete nothing for prefixes shorter than n characters"
+    )?;
+    writeln!(
+        sink,
+        "        --cache <n>   Cache the completions of the n most recent queries (default: 0)"
+    )?;
+    writeln!(
+        sink,
+        "    graphql           Serve complete, contains and stats GraphQL queries over HTTP (graphql feature)"
+    )?;
+    writeln!(
+        sink,
+        "                      GET /complete?q=<prefix>&limit=<n>&version=<v> answers with the versioned response envelope"
+    )?;
+    writeln!(
+        sink,
+        "                      GET /healthz answers while the server is up, /readyz once the dictionary is loaded"
+    )?;
+    writeln!(
+        sink,
+        "                      POST /reload reads the dictionary again, as SIGHUP does"
+    )?;
+    writeln!(
+        sink,
+        "        --listen <addr>  Address to listen on (default: 127.0.0.1:8080)"
+    )?;
+    writeln!(
+        sink,
+        "        --auth-token <t>  Take POST /reload only with Authorization: Bearer t (default: $PREFIX_TREE_AUTH_TOKEN)"
+    )?;
+    writeln!(
+        sink,
+        "        --tls-cert <file>  Serve HTTPS with the PEM certificate chain of the file (tls feature)"
+    )?;
+    writeln!(
+        sink,
+        "        --tls-key <file>   The PEM private key of --tls-cert"
+    )?;
+    writeln!(
+        sink,
+        "        --limit <n>   Maximum number of completions per query (default: 50)"
+    )?;
+    writeln!(
+        sink,
+        "        --min-prefix <n>  Complete nothing for prefixes shorter than n characters"
+    )?;
+    writeln!(
+        sink,
+        "        --cache <n>   Cache the completions of the n most recent queries (default: 0)"
+    )?;
+    writeln!(
+        sink,
+        "        --namespace <name>=<dict>  Also serve dict under /t/<name>/, with the --limit, --min-prefix, --cache and --auth-token following"
+    )?;
+    writeln!(
+        sink,
+        "        --progressive  Answer queries as soon as the words with their first character are built, while the rest load"
+    )?;
+    writeln!(
+        sink,
+        "    The servers reload the dictionary on SIGHUP without interrupting requests, rebuilding only changed words."
+    )?;
+    writeln!(
+        sink,
+        "    Socket-activated by systemd, graphql and resp listen on the socket LISTEN_FDS passes, and notify NOTIFY_SOCKET once loaded."
+    )?;
+    writeln!(
+        sink,
+        "    On SIGTERM or SIGINT they refuse new requests and exit once those in flight are answered, or --grace <seconds> later (default: 10)."
+    )?;
+    writeln!(
+        sink,
+        "    resp              Serve PT.COMPLETE, PT.ADD, PT.DEL and PT.EXISTS over the Redis protocol, for Redis clients"
+    )?;
+    writeln!(
+        sink,
+        "                      Words added are kept in memory, until the server exits or PT.SNAPSHOT writes them to --snapshot"
+    )?;
+    writeln!(
+        sink,
+        "        --listen <addr>  Address to listen on (default: 127.0.0.1:6380)"
+    )?;
+    writeln!(
+        sink,
+        "        --auth-token <t>  Take PT.ADD, PT.DEL, PT.SHRINK, PT.SNAPSHOT and PT.RESTORE only after AUTH t (default: $PREFIX_TREE_AUTH_TOKEN)"
+    )?;
+    writeln!(
+        sink,
+        "        --snapshot <file>  Write the words to file on PT.SNAPSHOT, read them back on PT.RESTORE, and start from it if it exists"
+    )?;
+    writeln!(
+        sink,
+        "        --limit <n>   Default maximum number of completions per PT.COMPLETE (default: 50)"
+    )?;
+    writeln!(
+        sink,
+        "        --min-prefix <n>  Complete nothing for prefixes shorter than n characters"
+    )?;
+    writeln!(
+        sink,
+        "    repl              Query the Trie interactively, with Tab completion"
+    )?;
+    writeln!(
+        sink,
+        "        --copy        Copy the first completion of complete to the clipboard; Alt-W copies the word before the cursor (clipboard feature)"
+    )?;
+    writeln!(
+        sink,
+        "    random            Print words drawn uniformly at random from the Trie"
+    )?;
+    writeln!(
+        sink,
+        "        --count <n>   Number of words to draw (default: 1)"
+    )?;
+    writeln!(
+        sink,
+        "        --prefix <p>  Only draw words starting with the prefix"
+    )?;
+    writeln!(
+        sink,
+        "        --weighted    Draw words in proportion to their weights"
+    )?;
+    writeln!(
+        sink,
+        "        --min-len <n> Only draw words of at least n characters"
+    )?;
+    writeln!(
+        sink,
+        "        --max-len <n> Only draw words of at most n characters"
+    )?;
+    writeln!(
+        sink,
+        "    passphrase        Print a diceware-style passphrase of random words, its entropy to stderr"
+    )?;
+    writeln!(sink, "        --words <n>   Number of words (default: 6)")?;
+    writeln!(
+        sink,
+        "        --separator <s>  Text between the words (default: a space)"
+    )?;
+    writeln!(
+        sink,
+        "    bench             Time lookups and prefix counts on the Trie and on HashSet, BTreeSet and sorted Vec baselines"
+    )?;
+    writeln!(
+        sink,
+        "        --lookups <n> Number of word lookups, half of them missing (default: 100000)"
+    )?;
+    writeln!(
+        sink,
+        "        --prefixes <n>  Number of prefix counts (default: 1000)"
+    )?;
+    writeln!(
+        sink,
+        "        --seed <n>    Seed of the random queries (default: 0)"
+    )?;
+    writeln!(
+        sink,
+        "    gen               Print a synthetic dictionary for stress tests"
+    )?;
+    writeln!(
+        sink,
+        "        --words <n>   Number of words, with an optional k, M or G suffix (default: 1000)"
+    )?;
+    writeln!(
+        sink,
+        "        --alphabet <a>  Characters and ranges to draw from, e.g. a-z0-9 (default: a-z)"
+    )?;
+    writeln!(
+        sink,
+        "        --zipf <s>    Weight the words by a Zipf distribution of exponent s"
+    )?;
+    writeln!(
+        sink,
+        "        --min-len <n> Minimum word length (default: 1)"
+    )?;
+    writeln!(
+        sink,
+        "        --max-len <n> Maximum word length (default: 12)"
+    )?;
+    writeln!(
+        sink,
+        "        --seed <n>    Seed of the generator, for reproducible output"
+    )?;
+    writeln!(
+        sink,
+        "    add <word> [weight]  Append the word to dictionary.txt, or the --lang dictionary"
+    )?;
+    writeln!(
+        sink,
+        "        --ttl <seconds>  Let the word expire that many seconds from now"
+    )?;
+    writeln!(
+        sink,
+        "    clean [file]      Rewrite dictionary.txt, or the --lang dictionary, trimmed, in NFC, deduplicated and sorted"
+    )?;
+    writeln!(
+        sink,
+        "        --output <file>  Write the result there instead, - for stdout"
+    )?;
+    writeln!(
+        sink,
+        "        --check       Only report, and exit with 1 if anything would change"
+    )?;
+    writeln!(
+        sink,
+        "    feedback <prefix> <word>  Log that word was picked when completing prefix, raising its weight from then on"
+    )?;
+    writeln!(
+        sink,
+        "    pick <word> --history <file>  Record that the completion word was picked, to boost it in complete --history"
+    )?;
+    writeln!(
+        sink,
+        "    check-file <path> Print the words of a file missing from the dictionary, as path:line:column: word"
+    )?;
+    writeln!(
+        sink,
+        "                      Without --lang, the language dictionary the file matches best is used"
+    )?;
+    writeln!(
+        sink,
+        "        --bloom       Reject most unknown words with a Bloom filter before looking them up"
+    )?;
+    writeln!(
+        sink,
+        "    autocorrect <file>  Print the file with unknown words replaced by their one closest match, ambiguous ones as word[?a|b]"
+    )?;
+    writeln!(
+        sink,
+        "        --max-dist <n>  Maximum edit distance of a correction (default: 1)"
+    )?;
+    writeln!(
+        sink,
+        "    sounds-like <word> List dictionary words pronounced like the given word"
+    )?;
+    writeln!(
+        sink,
+        "        --algorithm <name>      Phonetic code: metaphone, soundex (default: metaphone)"
+    )?;
+    writeln!(
+        sink,
+        "        -0, --null              End each word with NUL instead of a newline"
+    )?;
+    writeln!(
+        sink,
+        "    anagrams <letters> List dictionary words made of exactly the given letters"
+    )?;
+    writeln!(
+        sink,
+        "        --sub                   Also list words made of some of them, longest first"
+    )?;
+    writeln!(
+        sink,
+        "        -0, --null              End each word with NUL instead of a newline"
+    )?;
+    writeln!(
+        sink,
+        "    solve             List the words fitting Wordle-style clues, most frequent first"
+    )?;
+    writeln!(
+        sink,
+        "        --green <pattern>       Letters known in place, _ for unknown ones (default: _____)"
+    )?;
+    writeln!(
+        sink,
+        "        --yellow <l:p,...>      Letters in the word but not at position p, counting from 1"
+    )?;
+    writeln!(
+        sink,
+        "        --gray <letters>        Letters not in the word"
+    )?;
+    writeln!(
+        sink,
+        "        --limit <n>             Maximum number of words"
+    )?;
+    writeln!(
+        sink,
+        "        -0, --null              End each word with NUL instead of a newline"
+    )?;
+    writeln!(sink, "EXIT CODES")?;
+    writeln!(sink, "    0  Success")?;
+    writeln!(
+        sink,
+        "    1  No match, e.g. contains --quiet on an unknown word"
+    )?;
+    writeln!(sink, "    2  Invalid arguments or score script")?;
+    writeln!(sink, "    3  Dictionary missing")?;
+    writeln!(sink, "    4  Dictionary line that isn't valid UTF-8")?;
+    writeln!(sink, "    5  Graphviz missing")?;
+    writeln!(sink, "    6  Index or patch of the wrong format")?;
+    writeln!(sink, "    7  Any other I/O error")?;
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = try_main() {
+        let _ = writeln!(io::stderr(), "ERROR: {}", err);
+        if let Error::Usage(_) = err {
+            let _ = writeln!(io::stderr());
+            let _ = usage(io::stderr());
+        }
+        exit(err.exit_code());
+    }
+}
+
+// A named section of the config file, `prefix-tree.conf` or the file
+// `PREFIX_TREE_CONFIG` names, bundling options for one use case:
+//
+//     [code]
+//     --keys token --dict identifiers.txt
+//     complete --limit 50
+//
+// Lines are split at whitespace. Options starting a line apply to every
+// subcommand, those after a subcommand's name to that subcommand only, and
+// options given on the command line win over both.
+struct Profile {
+    options: Vec<Vec<String>>,
+    subcommands: Vec<(String, Vec<Vec<String>>)>,
+}
+
+impl Profile {
+    fn read(name: &str) -> Result<Profile, Error> {
+        let path =
+            env::var("PREFIX_TREE_CONFIG").unwrap_or_else(|_| "prefix-tree.conf".to_string());
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(Error::Usage(format!(
+                    "--profile needs a config file, {} is missing.",
+                    path
+                )));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mut profile = None;
+        let mut section = None;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let Some(header) = header.strip_suffix(']') else {
+                    return Err(Error::Usage(format!(
+                        "{}:{}: unclosed section header",
+                        path,
+                        number + 1
+                    )));
+                };
+                section = Some(header.trim().to_string());
+                if header.trim() == name && profile.is_none() {
+                    profile = Some(Profile {
+                        options: vec![],
+                        subcommands: vec![],
+                    });
+                }
+                continue;
+            }
+            let Some(current) = section.as_deref() else {
+                return Err(Error::Usage(format!(
+                    "{}:{}: options outside of a [profile] section",
+                    path,
+                    number + 1
+                )));
+            };
+            if current != name {
+                continue;
+            }
+            let Some(profile) = profile.as_mut() else {
+                continue;
+            };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words[0].starts_with("--") {
+                profile.options.extend(option_groups(&words));
+            } else {
+                profile
+                    .subcommands
+                    .push((words[0].to_string(), option_groups(&words[1..])));
+            }
+        }
+        profile.ok_or_else(|| Error::Usage(format!("no profile {} in {}.", name, path)))
+    }
+
+    // Puts the profile's options for every subcommand before `args`.
+    fn apply(&self, args: &mut Vec<String>) {
+        let mut given = vec![];
+        for group in &self.options {
+            if !args.contains(&group[0]) {
+                given.extend(group.iter().cloned());
+            }
+        }
+        args.splice(0..0, given);
+    }
+
+    // Adds the options for the subcommand `args` start with at their end.
+    fn apply_subcommand(&self, args: &mut Vec<String>) {
+        let Some(subcommand) = args.first() else {
+            return;
+        };
+        let groups: Vec<&Vec<String>> = self
+            .subcommands
+            .iter()
+            .filter(|(name, _)| name == subcommand)
+            .flat_map(|(_, groups)| groups)
+            .filter(|group| !args.contains(&group[0]))
+            .collect();
+        for group in groups {
+            args.extend(group.iter().cloned());
+        }
+    }
+}
+
+// Splits the words of a config line into options, each with the values
+// following it.
+fn option_groups(words: &[&str]) -> Vec<Vec<String>> {
+    let mut groups: Vec<Vec<String>> = vec![];
+    for word in words {
+        match groups.last_mut() {
+            Some(group) if !word.starts_with("--") => group.push(word.to_string()),
+            _ => groups.push(vec![word.to_string()]),
+        }
+    }
+    groups
+}
+
+fn try_main() -> Result<(), Error> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let profile = match take_option(&mut args, "--profile")? {
+        Some(name) => Some(Profile::read(&name)?),
+        None => None,
+    };
+    if let Some(profile) = &profile {
+        profile.apply(&mut args);
+    }
+    let display_case = match take_option(&mut args, "--display-case")? {
+        Some(name) => match DisplayCase::from_name(&name) {
+            Some(display_case) => display_case,
+            None => {
+                return Err(Error::Usage(format!("unknown display case: {}", name)));
+            }
+        },
+        None => DisplayCase::default(),
+    };
+    let max_mem = match take_option(&mut args, "--max-mem")? {
+        Some(value) => match parse_count(&value) {
+            Some(max_mem) => Some(max_mem),
+            None => {
+                return Err(Error::Usage(format!(
+                    "invalid value for --max-mem: {}",
+                    value
+                )));
+            }
+        },
+        None => None,
+    };
+    let half_life = match take_option(&mut args, "--half-life")? {
+        Some(value) => match value.parse::<f64>() {
+            Ok(days) if days > 0.0 && days.is_finite() => {
+                Some(Duration::from_secs_f64(days * 24.0 * 60.0 * 60.0))
+            }
+            _ => {
+                return Err(Error::Usage(format!(
+                    "invalid value for --half-life: {}",
+                    value
+                )));
+            }
+        },
+        None => None,
+    };
+    let stopwords = match take_option(&mut args, "--stopwords")?.as_deref() {
+        Some("english") => ENGLISH_STOPWORDS
+            .iter()
+            .map(|word| word.to_string())
+            .collect(),
+        Some(path) => fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect(),
+        None => HashSet::new(),
+    };
+    let mut options = LoadOptions {
+        stem: take_switch(&mut args, "--stem"),
+        fold_case: take_switch(&mut args, "--fold-case"),
+        display_case,
+        dedup: take_switch(&mut args, "--dedup"),
+        max_mem,
+        lang: take_option(&mut args, "--lang")?,
+        dict: take_option(&mut args, "--dict")?,
+        dict_cache: take_option(&mut args, "--dict-cache")?.map(PathBuf::from),
+        stopwords,
+        history_log: take_option(&mut args, "--history-log")?
+            .unwrap_or_else(|| "history.log".to_string()),
+        half_life,
+        aliases: match take_option(&mut args, "--aliases")? {
+            Some(path) => Aliases::read(io::BufReader::new(File::open(path)?))?,
+            None => Aliases::new(),
+        },
+        show_alias: take_switch(&mut args, "--show-alias"),
+        exclude: HashSet::new(),
+        read: Arc::default(),
+    };
+    if let Some(path) = take_option(&mut args, "--exclude")? {
+        // Stored the way the words they remove are.
+        options.exclude = fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .map(|word| options.stored_word(word))
+            .collect();
+    }
+    let mut keys = take_option(&mut args, "--keys")?;
+    if take_switch(&mut args, "--graphemes") {
+        keys = Some("grapheme".to_string());
+    }
+    if let Some(profile) = &profile {
+        profile.apply_subcommand(&mut args);
+    }
+    if args.first().is_some_and(|arg| arg == "query") {
+        args.remove(0);
+        if !matches!(
+            args.first().map(String::as_str),
+            Some("complete" | "contains")
+        ) {
+            return Err(Error::Usage("query runs complete or contains.".to_string()));
+        }
+        let daemon = take_option(&mut args, "--daemon")?
+            .or_else(|| env::var("PREFIX_TREE_DAEMON").ok())
+            .unwrap_or_else(|| DAEMON.to_string());
+        // What the server can't answer, or none is there to, is answered
+        // here, as if asked without query.
+        #[cfg(feature = "client")]
+        if query_daemon(&daemon, &args)? {
+            return Ok(());
+        }
+        #[cfg(not(feature = "client"))]
+        let _ = daemon;
+    }
+    match keys.as_deref() {
+        None if ascii_dictionary(&args, &options) => run::<u8>(args, options),
+        None | Some("char") => run::<char>(args, options),
+        Some("byte") => run::<u8>(args, options),
+        Some("grapheme") => run::<Grapheme>(args, options),
+        Some("token") => run::<Token>(args, options),
+        Some(keys) => Err(Error::Usage(format!("unknown key type: {}", keys))),
+    }
+}
+
+// Subcommands that answer the same with byte keys as with char keys when
+// every word is ASCII. The fuzzy ones count edits in keys, which for a
+// non-ASCII query would be bytes, and `tree` and `dot` print keys one by one,
+// which bytes display as numbers.
+const BYTE_KEYED: &[&str] = &[
+    "complete",
+    "contains",
+    "verify",
+    "starts-with",
+    "count",
+    "select",
+    "rank",
+    "range",
+    "next",
+    "prev",
+    "list",
+    "extremes",
+    "prefixes",
+    "abbrev",
+    "compact",
+    "infix-index",
+    "infix",
+    "glob",
+    "fill",
+    "anagrams",
+    "solve",
+    "top",
+    "export",
+    "random",
+    "passphrase",
+    "lsp",
+    "nvim",
+    "graphql",
+    "resp",
+    "bench",
+];
+
+// Whether `args` run a subcommand that can take the byte-keyed path and the
+// dictionary is pure ASCII. The dictionary is read whole to tell, and kept
+// for the load to take rather than read again.
+fn ascii_dictionary(args: &[String], options: &LoadOptions) -> bool {
+    let byte_keyed = args
+        .first()
+        .is_some_and(|subcommand| BYTE_KEYED.contains(&subcommand.as_str()));
+    // Namespaces bring dictionaries of their own and an index stands in for
+    // the dictionary, so neither reads it here.
+    if !byte_keyed
+        || args
+            .iter()
+            .any(|arg| matches!(arg.as_str(), "--fuzzy-prefix" | "--namespace" | "--index"))
+    {
+        return false;
+    }
+    // Not worth fetching a remote dictionary twice for, nor holding one
+    // whole that --max-mem is to sort in runs.
+    if options.dict.as_deref().is_some_and(is_url) || options.max_mem.is_some() {
+        return false;
+    }
+    let Ok((mut dictionary, name)) = open_dictionary(options) else {
+        return false;
+    };
+    let mut data = Vec::new();
+    if dictionary.read_to_end(&mut data).is_err() {
+        return false;
+    }
+    let ascii = data.is_ascii();
+    *options.read.lock().unwrap() = Some((data, name));
+    ascii
+}
+
+// A frozen trie for the long-running modes: on SIGHUP the dictionary is
+// read again in the background, the words whose lines changed are rebuilt,
+// and the result is published as a new version. Each query pins
+// the version current when it starts, so neither side ever waits on a lock.
+// Every version comes with its own cache, so a reload never serves stale
+// completions.
+struct Reloading<K> {
+    current: Arc<ArcSwap<Cached<Frozen<K>>>>,
+    // Read by the readiness check of the GraphQL server.
+    #[cfg_attr(not(feature = "graphql"), allow(dead_code))]
+    status: Arc<ReloadStatus>,
+    // Asks for a reload of this trie alone, as a POST /reload does.
+    #[cfg(unix)]
+    #[cfg_attr(not(feature = "graphql"), allow(dead_code))]
+    reload: mpsc::Sender<()>,
+}
+
+// How the reloads so far went.
+struct ReloadStatus {
+    // The version published, 1 as loaded.
+    version: AtomicU64,
+    // Why the last reload failed, if it did.
+    error: Mutex<Option<String>>,
+}
+
+impl<K: TrieKey + Send + Sync + 'static> Reloading<K> {
+    fn new(trie: Trie<K>, options: &LoadOptions, cache: usize) -> io::Result<Self> {
+        let current = Arc::new(ArcSwap::from_pointee(Cached::new(trie.freeze(), cache)));
+        let status = Arc::new(ReloadStatus {
+            version: AtomicU64::new(1),
+            error: Mutex::new(None),
+        });
+        #[cfg(unix)]
+        let reload = {
+            let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGHUP])?;
+            let (reload, reloads) = mpsc::channel();
+            let signaled = reload.clone();
+            thread::spawn(move || {
+                for _ in signals.forever() {
+                    if signaled.send(()).is_err() {
+                        break;
+                    }
+                }
+            });
+            let current = Arc::clone(&current);
+            let status = Arc::clone(&status);
+            let options = options.clone();
+            thread::spawn(move || {
+                // Known after the first reload, which has to read every line
+                // anyway; from then on only changed words are rebuilt.
+                let mut loaded: Option<Loaded<K>> = None;
+                for () in reloads {
+                    let result = match &mut loaded {
+                        // Whatever a delta saves, it costs the whole
+                        // dictionary in memory, which --max-mem rules out.
+                        _ if options.max_mem.is_some() => {
+                            load::<K>(&options).map(|trie| (trie.freeze(), None))
+                        }
+                        Some(loaded) => loaded
+                            .reload(&options)
+                            .map(|delta| (loaded.trie.clone().freeze(), Some(delta))),
+                        None => Loaded::load(&options).map(|fresh| {
+                            let frozen = fresh.trie.clone().freeze();
+                            loaded = Some(fresh);
+                            (frozen, None)
+                        }),
+                    };
+                    let _ = match result {
+                        Ok((trie, delta)) => {
+                            let len = trie.len();
+                            current.store(Arc::new(Cached::new(trie, cache)));
+                            status.version.fetch_add(1, Ordering::Relaxed);
+                            *status.error.lock().unwrap() = None;
+                            match delta {
+                                Some((changed, removed)) => writeln!(
+                                    io::stderr(),
+                                    "reloaded {} words, {} changed, {} removed",
+                                    len,
+                                    changed,
+                                    removed
+                                ),
+                                None => writeln!(io::stderr(), "reloaded {} words", len),
+                            }
+                        }
+                        Err(err) => {
+                            *status.error.lock().unwrap() = Some(err.to_string());
+                            writeln!(io::stderr(), "ERROR: reload failed: {}", err)
+                        }
+                    };
+                }
+            });
+            reload
+        };
+        #[cfg(not(unix))]
+        let _ = options;
+        Ok(Self {
+            current,
+            status,
+            #[cfg(unix)]
+            reload,
+        })
+    }
+}
+
+// A dictionary as last loaded, with a hash of every stored word's lines and
+// picks, so that a reload rebuilds only the words whose hash changed and
+// removes those no line stores any more.
+#[cfg(unix)]
+struct Loaded<K> {
+    trie: Trie<K>,
+    hashes: HashMap<String, u64>,
+}
+
+#[cfg(unix)]
+impl<K: TrieKey> Loaded<K> {
+    fn load(options: &LoadOptions) -> Result<Self, Error> {
+        let mut loaded = Self {
+            trie: Trie::new(),
+            hashes: HashMap::new(),
+        };
+        loaded.reload(options)?;
+        Ok(loaded)
+    }
+
+    // Brings the trie up to date with the dictionary, returning how many
+    // words were rebuilt and how many removed.
+    fn reload(&mut self, options: &LoadOptions) -> Result<(usize, usize), Error> {
+        use std::hash::{DefaultHasher, Hash, Hasher};
+
+        let (file, name) = open_dictionary(options)?;
+        let history = options.history()?;
+        // Every line of a word counts, in order: repeated lines merge.
+        let mut lines: HashMap<String, Vec<String>> = HashMap::new();
+        for line in dictionary_lines(io::BufReader::new(file), &name) {
+            let line = line?;
+            lines
+                .entry(options.stored_word(&line))
+                .or_default()
+                .push(line);
+        }
+        let mut hashes = HashMap::with_capacity(lines.len());
+        let mut changed = 0;
+        let mut rebuilt = Vec::new();
+        for (word, lines) in lines {
+            let mut hasher = DefaultHasher::new();
+            (&lines, history.picks(&word).to_bits()).hash(&mut hasher);
+            let hash = hasher.finish();
+            if self.hashes.get(&word) != Some(&hash) {
+                let mut builder = options.builder::<K>();
+                for line in &lines {
+                    push_line(&mut builder, line, options, &history);
+                }
+                // The lines may store nothing, e.g. for a stopword.
+                self.trie.remove(&word);
+                rebuilt.extend(builder.build().drain_prefix(""));
+                changed += 1;
+            }
+            hashes.insert(word, hash);
+        }
+        // Put back in one sorted pass, sharing the walk of common prefixes.
+        rebuilt.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        self.trie.extend_sorted(rebuilt);
+        let mut removed = 0;
+        for word in self.hashes.keys() {
+            if !hashes.contains_key(word) {
+                removed += usize::from(self.trie.remove(word).is_some());
+            }
+        }
+        self.hashes = hashes;
+        Ok((changed, removed))
+    }
+}
+
+impl<K: TrieKey> Completer for Reloading<K> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        let trie = self.current.load_full();
+        trie.complete(prefix, limit)
+    }
+}
+
+// The dictionary behind the GraphQL endpoint, looking words up the way they
+// were loaded.
+#[cfg(feature = "graphql")]
+struct Served<K> {
+    // Set once the dictionary is loaded whole.
+    trie: Arc<OnceLock<Reloading<K>>>,
+    // With --progressive, what answers until the first reload sets `trie`,
+    // shard by shard, with how that reload went and what asks for it.
+    partial: Arc<Mutex<Option<Progressive<K>>>>,
+    first_reload: Option<(Arc<ReloadStatus>, mpsc::Sender<()>)>,
+    options: LoadOptions,
+    min_prefix: usize,
+}
+
+// What a query of `Served` is answered from.
+#[cfg(feature = "graphql")]
+enum View<'a, K> {
+    Whole(&'a Reloading<K>),
+    Partial(Progressive<K>, &'a ReloadStatus),
+}
+
+#[cfg(feature = "graphql")]
+impl<K: TrieKey + Send + Sync + 'static> Served<K> {
+    fn new(trie: Reloading<K>, options: LoadOptions, min_prefix: usize) -> Self {
+        Self {
+            trie: Arc::new(OnceLock::from(trie)),
+            partial: Arc::new(Mutex::new(None)),
+            first_reload: None,
+            options,
+            min_prefix,
+        }
+    }
+
+    // Answers from the shards as they are built, until a reload, by SIGHUP
+    // or POST /reload, loads the dictionary whole, which only then takes the
+    // memory of a second copy, as any reload does.
+    fn progressive(
+        partial: Progressive<K>,
+        options: LoadOptions,
+        cache: usize,
+        min_prefix: usize,
+    ) -> io::Result<Self> {
+        let status = Arc::new(ReloadStatus {
+            version: AtomicU64::new(1),
+            error: Mutex::new(None),
+        });
+        let (reload, reloads) = mpsc::channel();
+        #[cfg(unix)]
+        {
+            let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGHUP])?;
+            let signaled = reload.clone();
+            thread::spawn(move || {
+                for _ in signals.forever() {
+                    // The whole trie takes its own SIGHUPs.
+                    if signaled.send(()).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        let served = Self {
+            trie: Arc::new(OnceLock::new()),
+            partial: Arc::new(Mutex::new(Some(partial))),
+            first_reload: Some((Arc::clone(&status), reload)),
+            options,
+            min_prefix,
+        };
+        let (trie, partial, options) = (
+            Arc::clone(&served.trie),
+            Arc::clone(&served.partial),
+            served.options.clone(),
+        );
+        thread::spawn(move || {
+            for () in reloads {
+                let loaded = load::<K>(&options)
+                    .and_then(|whole| Ok(Reloading::new(whole, &options, cache)?));
+                match loaded {
+                    Ok(reloading) => {
+                        let len = reloading.current.load().get_ref().len();
+                        reloading.status.version.store(2, Ordering::Relaxed);
+                        let _ = trie.set(reloading);
+                        *partial.lock().unwrap() = None;
+                        let _ = writeln!(io::stderr(), "reloaded {} words", len);
+                        return;
+                    }
+                    Err(err) => {
+                        *status.error.lock().unwrap() = Some(err.to_string());
+                        let _ = writeln!(io::stderr(), "ERROR: reload failed: {}", err);
+                    }
+                }
+            }
+        });
+        Ok(served)
+    }
+
+    fn view(&self) -> View<'_, K> {
+        if let Some(trie) = self.trie.get() {
+            return View::Whole(trie);
+        }
+        let partial = self.partial.lock().unwrap().clone();
+        match (partial, &self.first_reload) {
+            (Some(partial), Some((status, _))) => View::Partial(partial, status),
+            // Let go of just after the trie was set.
+            _ => View::Whole(self.trie.get().expect("the reloaded trie")),
+        }
+    }
+}
+
+#[cfg(feature = "graphql")]
+impl<K: TrieKey + Send + Sync + 'static> Completer for Served<K> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        let view = self.view();
+        let completer: &dyn Completer = match &view {
+            View::Whole(trie) => *trie,
+            View::Partial(partial, _) => partial,
+        };
+        let completer = Normalized {
+            completer,
+            options: &self.options,
+            min_prefix: self.min_prefix,
+        };
+        completer.complete(prefix, limit)
+    }
+}
+
+#[cfg(feature = "graphql")]
+impl<K: TrieKey + Send + Sync + 'static> graphql::Dictionary for Served<K> {
+    fn contains(&self, word: &str) -> bool {
+        let word = self.options.query(word);
+        match self.view() {
+            View::Whole(trie) => {
+                let trie = trie.current.load();
+                let info = trie.get_ref().get(&word);
+                info.is_some_and(|info| !info.expired())
+            }
+            View::Partial(partial, _) => partial.get(&word).is_some_and(|info| !info.expired()),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self.view() {
+            View::Whole(trie) => trie.current.load().get_ref().len(),
+            View::Partial(partial, _) => partial.len(),
+        }
+    }
+
+    fn version(&self) -> u64 {
+        let status = match self.view() {
+            View::Whole(trie) => &trie.status,
+            View::Partial(_, status) => status,
+        };
+        status.version.load(Ordering::Relaxed)
+    }
+
+    fn reload_error(&self) -> Option<String> {
+        let status = match self.view() {
+            View::Whole(trie) => &trie.status,
+            View::Partial(_, status) => status,
+        };
+        status.error.lock().unwrap().clone()
+    }
+
+    fn loading(&self) -> Option<(usize, usize)> {
+        match self.view() {
+            View::Partial(partial, _) => {
+                Some(partial.built()).filter(|(built, shards)| built < shards)
+            }
+            View::Whole(_) => None,
+        }
+    }
+
+    #[cfg(unix)]
+    fn reload(&self) -> bool {
+        match (self.view(), &self.first_reload) {
+            (View::Partial(..), Some((_, reload))) => reload.send(()).is_ok(),
+            _ => self
+                .trie
+                .get()
+                .is_some_and(|trie| trie.reload.send(()).is_ok()),
+        }
+    }
+}
+
+// A dictionary of the GraphQL server, with the options given after its
+// --namespace, or before any for the one named "".
+#[cfg(feature = "graphql")]
+struct Tenant {
+    name: String,
+    options: LoadOptions,
+    limit: usize,
+    min_prefix: usize,
+    cache: usize,
+    token: Option<String>,
+}
+
+#[cfg(feature = "graphql")]
+impl Tenant {
+    fn new(name: &str, options: LoadOptions) -> Self {
+        Self {
+            name: name.to_string(),
+            options,
+            limit: 50,
+            min_prefix: 0,
+            cache: 0,
+            token: None,
+        }
+    }
+}
+
+// The socket a server accepts connections on: the one systemd hands a
+// socket-activated service, started on the first connection to it, or else
+// a new one bound to `address`.
+fn server_socket(address: &str) -> Result<TcpListener, Error> {
+    #[cfg(unix)]
+    if let Some(listener) = inherited_listener()? {
+        return Ok(listener);
+    }
+    Ok(TcpListener::bind(address)?)
+}
+
+// The first socket passed by systemd's LISTEN_FDS protocol, if any was
+// passed to this process: descriptors from 3 on, as many as LISTEN_FDS says,
+// meant for the process LISTEN_PID names.
+#[cfg(unix)]
+fn inherited_listener() -> Result<Option<TcpListener>, Error> {
+    use std::os::fd::FromRawFd;
+
+    let (Ok(pid), Ok(fds)) = (env::var("LISTEN_PID"), env::var("LISTEN_FDS")) else {
+        return Ok(None);
+    };
+    // Not meant for the processes this one starts.
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+    if pid.parse() != Ok(std::process::id()) {
+        return Ok(None);
+    }
+    match fds.parse::<u32>() {
+        Ok(0) | Err(_) => return Ok(None),
+        Ok(1) => {}
+        Ok(fds) => writeln!(
+            io::stderr(),
+            "warning: systemd passed {} sockets, only the first is listened on",
+            fds
+        )?,
+    }
+    // SAFETY: descriptor 3 is the first of those systemd passed, for this
+    // process alone to own. Left alone until it proves to be a TCP socket.
+    let listener = mem::ManuallyDrop::new(unsafe { TcpListener::from_raw_fd(3) });
+    if listener.local_addr().is_err() {
+        return Err(Error::Usage(
+            "descriptor 3, passed by systemd, isn't a TCP socket; use ListenStream=<port>."
+                .to_string(),
+        ));
+    }
+    Ok(Some(mem::ManuallyDrop::into_inner(listener)))
+}
+
+// Tells systemd a Type=notify service is ready, sending `state` to
+// NOTIFY_SOCKET, if it is set.
+fn notify(state: &str) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Some(path) = env::var_os("NOTIFY_SOCKET") {
+        use std::os::unix::{ffi::OsStrExt, net::UnixDatagram};
+
+        let socket = UnixDatagram::unbound()?;
+        // A leading @ names a socket in the abstract namespace.
+        #[cfg(target_os = "linux")]
+        if let Some(name) = path.as_bytes().strip_prefix(b"@") {
+            use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+
+            socket.send_to_addr(state.as_bytes(), &SocketAddr::from_abstract_name(name)?)?;
+            return Ok(());
+        }
+        socket.send_to(state.as_bytes(), path)?;
+    }
+    #[cfg(not(unix))]
+    let _ = state;
+    Ok(())
+}
+
+// How long a server stopped by SIGTERM or SIGINT waits for the requests in
+// flight by default, see `drain_on_terminate`.
+const GRACE: Duration = Duration::from_secs(10);
+
+// Makes SIGTERM and SIGINT stop a server gracefully: it refuses new
+// requests, while those in flight get up to `grace` to be answered before
+// the process exits, with 1 if any is still running.
+fn drain_on_terminate(grace: Duration) -> io::Result<Shutdown> {
+    let shutdown = Shutdown::new();
+    #[cfg(unix)]
+    {
+        use signal_hook::consts::{SIGINT, SIGTERM};
+
+        let mut signals = signal_hook::iterator::Signals::new([SIGTERM, SIGINT])?;
+        let shutdown = shutdown.clone();
+        thread::spawn(move || {
+            if signals.forever().next().is_none() {
+                return;
+            }
+            let _ = writeln!(
+                io::stderr(),
+                "shutting down, answering the requests in flight"
+            );
+            shutdown.request();
+            if shutdown.wait_idle(grace) {
+                exit(0);
+            }
+            let _ = writeln!(
+                io::stderr(),
+                "warning: requests still in flight after {:?}, exiting anyway",
+                grace
+            );
+            exit(1);
+        });
+    }
+    #[cfg(not(unix))]
+    let _ = grace;
+    Ok(shutdown)
+}
+
+// The output of the stdio servers, counting a response in flight from its
+// first byte to the flush ending it, so a shutdown never cuts one short. A
+// response not started before the shutdown fails to, ending the server.
+struct Draining<W> {
+    output: W,
+    shutdown: Shutdown,
+    in_flight: Option<InFlight>,
+}
+
+impl<W: Write> Write for Draining<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.in_flight.is_none() {
+            self.in_flight = self.shutdown.begin();
+            if self.in_flight.is_none() {
+                return Err(io::Error::other("shutting down"));
+            }
+        }
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let result = self.output.flush();
+        self.in_flight = None;
+        result
+    }
+}
+
+// Writes a dictionary line, as Entry::parse reads it.
+fn write_entry(out: &mut impl Write, entry: &Entry) -> io::Result<()> {
+    write!(out, "{}", entry.word)?;
+    if let Some(weight) = entry.weight {
+        write!(out, "\t{}", weight)?;
+    }
+    if !entry.tags.is_empty() {
+        write!(out, "\t{}", entry.tags.join(","))?;
+    }
+    if let Some(expires) = entry.expires {
+        write!(out, "\texpires={}", expires)?;
+    }
+    writeln!(out)
+}
+
+// Writes everything to both writers.
+struct Tee<A, B>(A, B);
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write_all(buf)?;
+        self.1.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()?;
+        self.1.flush()
+    }
+}
+
+// Passes writes on until the reader goes away, as a picker does once
+// something is picked, and drops them from then on.
+struct UntilClosed<W>(W);
+
+impl<W: Write> Write for UntilClosed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.0.write(buf) {
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(buf.len()),
+            written => written,
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.0.flush() {
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            flushed => flushed,
+        }
+    }
+}
+
+// Starts `command` with the shell, as typed on the command line, to read
+// from its stdin; its stdout is ours.
+fn spawn_shell(command: &str) -> io::Result<Child> {
+    let (shell, flag) = match cfg!(windows) {
+        true => ("cmd", "/C"),
+        false => ("sh", "-c"),
+    };
+    Command::new(shell)
+        .args([flag, command])
+        .stdin(Stdio::piped())
+        .spawn()
+}
+
+// What `clean` did to a dictionary, by line.
+#[derive(Default)]
+struct Cleaned {
+    lines: usize,
+    blank: usize,
+    trimmed: usize,
+    normalized: usize,
+    folded: usize,
+    merged: usize,
+    reordered: bool,
+}
+
+impl fmt::Display for Cleaned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} lines: {} blank dropped, {} trimmed, {} normalized to NFC, {} case-folded, {} merged into another line",
+            self.lines, self.blank, self.trimmed, self.normalized, self.folded, self.merged
+        )?;
+        if self.reordered {
+            write!(f, ", sorted")?;
+        }
+        Ok(())
+    }
+}
+
+// The columns of a word being cleaned, merged across its lines.
+#[derive(Default)]
+struct CleanWord {
+    weight: Option<u64>,
+    tags: Vec<String>,
+    expires: Option<u64>,
+}
+
+// Writes dictionary text in canonical form: lines trimmed, words in Unicode
+// NFC and lowercased with `fold_case`, blank lines dropped, the lines of a
+// word merged into one the way loading merges them (a later weight or expiry
+// wins, tags add up) and words sorted.
+fn clean_dictionary(
+    lines: &[String],
+    fold_case: bool,
+    out: &mut impl Write,
+) -> io::Result<Cleaned> {
+    let mut cleaned = Cleaned::default();
+    let mut words: BTreeMap<String, CleanWord> = BTreeMap::new();
+    let mut previous: Option<String> = None;
+    for line in lines {
+        cleaned.lines += 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            cleaned.blank += 1;
+            continue;
+        }
+        let entry = Entry::parse(trimmed);
+        let word = entry.word.trim();
+        if trimmed != line || word != entry.word {
+            cleaned.trimmed += 1;
+        }
+        let mut clean: String = word.nfc().collect();
+        if clean != word {
+            cleaned.normalized += 1;
+        }
+        if fold_case {
+            let folded = clean.to_lowercase();
+            if folded != clean {
+                cleaned.folded += 1;
+                clean = folded;
+            }
+        }
+        if previous.as_ref().is_some_and(|previous| *previous > clean) {
+            cleaned.reordered = true;
+        }
+        previous = Some(clean.clone());
+        let merged = words.contains_key(&clean);
+        cleaned.merged += usize::from(merged);
+        let columns = words.entry(clean).or_default();
+        columns.weight = entry.weight.or(columns.weight);
+        columns.expires = entry.expires.or(columns.expires);
+        for tag in entry.tags {
+            if !columns.tags.iter().any(|t| t == tag) {
+                columns.tags.push(tag.to_string());
+            }
+        }
+    }
+    for (word, columns) in &words {
+        let entry = Entry {
+            word,
+            weight: columns.weight,
+            tags: columns.tags.iter().map(String::as_str).collect(),
+            expires: columns.expires,
+        };
+        write_entry(out, &entry)?;
+    }
+    Ok(cleaned)
+}
+
+// Seconds since the Unix epoch.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+// The trie behind the RESP server, which takes new words as well, looking
+// words up and storing them the way the dictionary was loaded, and keeping
+// them across restarts in the --snapshot file, if given.
+struct Live<K> {
+    trie: RwLock<Trie<K>>,
+    options: LoadOptions,
+    min_prefix: usize,
+    snapshot: Option<PathBuf>,
+}
+
+impl<K: TrieKey> Live<K> {
+    fn snapshot_path(&self) -> io::Result<&Path> {
+        self.snapshot.as_deref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the server has no --snapshot file",
+            )
+        })
+    }
+}
+
+impl<K: TrieKey + Send + Sync> Store for Live<K> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        let trie = self.trie.read().unwrap();
+        let completer = Normalized {
+            completer: &*trie,
+            options: &self.options,
+            min_prefix: self.min_prefix,
+        };
+        completer.complete(prefix, limit)
+    }
+
+    fn add(&self, word: &str, weight: Option<u64>, expires: Option<u64>) -> bool {
+        self.trie.add(&self.options.query(word), weight, expires)
+    }
+
+    fn remove(&self, word: &str) -> bool {
+        self.trie.remove(&self.options.query(word))
+    }
+
+    fn exists(&self, word: &str) -> bool {
+        self.trie.exists(&self.options.query(word))
+    }
+
+    fn shrink(&self) -> usize {
+        self.trie.shrink()
+    }
+
+    // The words as stored, already looked up the way they were loaded, as
+    // dictionary lines, expired ones left out.
+    fn snapshot(&self) -> io::Result<usize> {
+        let path = self.snapshot_path()?;
+        let trie = self.trie.read().unwrap();
+        let mut count = 0;
+        let words = trie
+            .iter()
+            .filter(|(_, info)| !info.expired())
+            .inspect(|_| count += 1);
+        // Written beside the file and renamed, so a crash mid-way leaves the
+        // last snapshot whole.
+        let partial = path.with_extension("partial");
+        let mut file = File::create(&partial)?;
+        write_words(&mut file, words, true)?;
+        file.sync_all()?;
+        fs::rename(partial, path)?;
+        Ok(count)
+    }
+
+    fn restore(&self) -> io::Result<usize> {
+        let text = fs::read_to_string(self.snapshot_path()?)?;
+        let mut trie = Trie::new();
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            trie.insert_entry(&Entry::parse(line));
+        }
+        let words = trie.len();
+        *self.trie.write().unwrap() = trie;
+        Ok(words)
+    }
+}
+
+// Writes every word to `out` in sorted order; with `info`, as dictionary
+// lines that load back into the same trie.
+fn write_words<'t>(
+    out: impl Write,
+    words: impl IntoIterator<Item = (String, &'t WordInfo)>,
+    info: bool,
+) -> io::Result<()> {
+    let mut out = io::BufWriter::new(out);
+    for (word, word_info) in words {
+        if info {
+            write!(out, "{}\t{}", word, word_info.weight)?;
+            if !word_info.tags.is_empty() {
+                write!(out, "\t{}", word_info.tags.join(","))?;
+            }
+            if let Some(expires) = word_info.expires {
+                write!(out, "\texpires={}", expires)?;
+            }
+        } else {
+            write!(out, "{}", word)?;
+        }
+        writeln!(out)?;
+    }
+    out.flush()
+}
+
+// The words `list` prints: `min_len..=max_len` characters long, starting
+// with `prefix` and, given a charset, made of its characters only.
+struct ListFilter {
+    min_len: usize,
+    max_len: usize,
+    charset: Option<Vec<char>>,
+    prefix: String,
+}
+
+impl ListFilter {
+    // The words kept, in sorted order. Branches that only hold words left
+    // out, too long, off the prefix or with a character outside the charset,
+    // are never entered.
+    fn words<K: TrieKey>(&self, trie: &Trie<K>) -> Vec<String> {
+        let mut words = vec![];
+        trie.walk(|spelled, node| {
+            let on_prefix = if spelled.len() < self.prefix.len() {
+                self.prefix.starts_with(spelled)
+            } else {
+                spelled.starts_with(&self.prefix)
+            };
+            let len = spelled.chars().count();
+            let in_charset = self
+                .charset
+                .as_ref()
+                .is_none_or(|charset| spelled.chars().all(|c| charset.contains(&c)));
+            if !on_prefix || len > self.max_len || !in_charset {
+                return Walk::Skip;
+            }
+            if node.value().is_some() && len >= self.min_len && spelled.len() >= self.prefix.len() {
+                words.push(spelled.to_string());
+            }
+            Walk::Continue
+        });
+        words.sort_by_cached_key(|word| K::segment(word));
+        words
+    }
+}
+
+// A synthetic dictionary for stress tests: `words` random words of
+// `min_len..=max_len` characters from `alphabet`, weighted by a Zipf
+// distribution of exponent `zipf` over their position if one is given.
+struct Synthetic {
+    words: u64,
+    alphabet: Vec<char>,
+    zipf: Option<f64>,
+    min_len: usize,
+    max_len: usize,
+}
+
+impl Synthetic {
+    fn write(&self, out: &mut impl Write, rng: &mut impl rand::Rng) -> io::Result<()> {
+        let mut word = String::new();
+        for rank in 1..=self.words {
+            word.clear();
+            let len = rng.random_range(self.min_len..=self.max_len);
+            for _ in 0..len {
+                word.push(self.alphabet[rng.random_range(0..self.alphabet.len())]);
+            }
+            match self.zipf {
+                // The first word is as heavy as there are words, and the
+                // weights fall off as rank^-s down to 1.
+                Some(s) => {
+                    let weight = (self.words as f64 / (rank as f64).powf(s)).ceil();
+                    writeln!(out, "{}\t{}", word, weight as u64)?;
+                }
+                None => writeln!(out, "{}", word)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Lookups and prefix counts over the dictionary's words, timed on the trie
+// and on the standard collections one might use instead. Each kind of query
+// runs the same random workload on every structure.
+struct Bench {
+    lookups: usize,
+    prefixes: usize,
+    seed: u64,
+}
+
+// What a structure took to build, per query and in memory.
+struct Measured {
+    name: &'static str,
+    build: Phase,
+    lookup: Phase,
+    prefix: Phase,
+    // Estimated from capacities and element sizes, without allocator
+    // overhead; B-tree nodes are left out.
+    memory: usize,
+}
+
+// The time a phase took, per query for queries, and what it allocated, all
+// zeros without the alloc-stats feature.
+struct Phase {
+    time: Duration,
+    usage: Usage,
+}
+
+impl Phase {
+    fn measure<T>(f: impl FnOnce() -> T) -> (T, Phase) {
+        let start = Instant::now();
+        let (result, usage) = alloc_stats::measure(f);
+        let time = start.elapsed();
+        (result, Phase { time, usage })
+    }
+}
+
+// Bytes in the largest unit that keeps them above 1.
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+impl Bench {
+    fn run<K: TrieKey>(&self, mut words: Vec<String>) -> io::Result<()> {
+        words.sort_unstable();
+        if words.is_empty() {
+            return writeln!(io::stderr(), "no words to benchmark");
+        }
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        // Every other lookup misses.
+        let lookups: Vec<String> = (0..self.lookups)
+            .map(|i| {
+                let word = &words[rng.random_range(0..words.len())];
+                if i % 2 == 0 {
+                    word.clone()
+                } else {
+                    format!("{}\u{1}", word)
+                }
+            })
+            .collect();
+        let prefixes: Vec<String> = (0..self.prefixes)
+            .map(|i| {
+                let word = &words[rng.random_range(0..words.len())];
+                word.chars().take(1 + i % 3).collect()
+            })
+            .collect();
+        let text_size: usize = words.iter().map(String::capacity).sum();
+
+        let mut results = vec![];
+        let (trie, build) =
+            Phase::measure(|| -> Trie<K, ()> { words.iter().map(|word| (word, ())).collect() });
+        results.push(Measured {
+            name: "trie",
+            build,
+            lookup: self.time(&lookups, |word| trie.contains(word)),
+            prefix: self.time(&prefixes, |prefix| trie.count_prefix(prefix)),
+            memory: trie.heap_size(),
+        });
+        drop(trie);
+
+        let (set, build) =
+            Phase::measure(|| -> HashSet<&str> { words.iter().map(String::as_str).collect() });
+        results.push(Measured {
+            name: "HashSet",
+            build,
+            lookup: self.time(&lookups, |word| set.contains(word)),
+            // Nothing but a scan finds the words starting with a prefix.
+            prefix: self.time(&prefixes, |prefix| {
+                set.iter().filter(|word| word.starts_with(prefix)).count()
+            }),
+            memory: set.capacity() * (mem::size_of::<String>() + 1) + text_size,
+        });
+        drop(set);
+
+        let (set, build) =
+            Phase::measure(|| -> BTreeSet<&str> { words.iter().map(String::as_str).collect() });
+        results.push(Measured {
+            name: "BTreeSet",
+            build,
+            lookup: self.time(&lookups, |word| set.contains(word)),
+            prefix: self.time(&prefixes, |prefix| {
+                set.range(prefix..)
+                    .take_while(|word| word.starts_with(prefix))
+                    .count()
+            }),
+            memory: set.len() * mem::size_of::<String>() + text_size,
+        });
+        drop(set);
+
+        let (sorted, build) = Phase::measure(|| {
+            let mut sorted: Vec<&str> = words.iter().map(String::as_str).collect();
+            sorted.sort_unstable();
+            sorted
+        });
+        results.push(Measured {
+            name: "sorted Vec",
+            build,
+            lookup: self.time(&lookups, |word| sorted.binary_search(&word).is_ok()),
+            prefix: self.time(&prefixes, |prefix| {
+                let first = sorted.partition_point(|word| *word < prefix);
+                sorted[first..]
+                    .iter()
+                    .take_while(|word| word.starts_with(prefix))
+                    .count()
+            }),
+            memory: sorted.capacity() * mem::size_of::<String>() + text_size,
+        });
+
+        let mut out = io::stdout().lock();
+        writeln!(
+            out,
+            "{} words, {} lookups (half missing), {} prefix counts",
+            words.len(),
+            self.lookups,
+            self.prefixes
+        )?;
+        writeln!(
+            out,
+            "{:<12}{:>12}{:>20}{:>20}{:>22}",
+            "structure", "build", "lookup", "prefix count", "memory (est.)"
+        )?;
+        let trie = &results[0];
+        for measured in &results {
+            let relative = |a: f64, b: f64| if b > 0.0 { a / b } else { 1.0 };
+            writeln!(
+                out,
+                "{:<12}{:>12}{:>20}{:>20}{:>22}",
+                measured.name,
+                format!("{:.1?}", measured.build.time),
+                format!(
+                    "{:.1?} ({:.2}x)",
+                    measured.lookup.time,
+                    relative(
+                        measured.lookup.time.as_secs_f64(),
+                        trie.lookup.time.as_secs_f64()
+                    )
+                ),
+                format!(
+                    "{:.1?} ({:.2}x)",
+                    measured.prefix.time,
+                    relative(
+                        measured.prefix.time.as_secs_f64(),
+                        trie.prefix.time.as_secs_f64()
+                    )
+                ),
+                format!(
+                    "{:.1} MiB ({:.2}x)",
+                    measured.memory as f64 / (1 << 20) as f64,
+                    relative(measured.memory as f64, trie.memory as f64)
+                ),
+            )?;
+        }
+        writeln!(
+            out,
+            "Times are per query; the factors compare with the trie, lower is better."
+        )?;
+        if cfg!(feature = "alloc-stats") {
+            self.print_allocations(&mut out, &results)?;
+        }
+        Ok(())
+    }
+
+    fn print_allocations(&self, out: &mut impl Write, results: &[Measured]) -> io::Result<()> {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{:<12}{:>24}{:>14}{:>20}{:>20}",
+            "structure", "build allocations", "peak heap", "allocs per lookup", "per prefix count"
+        )?;
+        let per_query = |phase: &Phase, queries: usize| match queries {
+            0 => 0.0,
+            n => phase.usage.allocations as f64 / n as f64,
+        };
+        for measured in results {
+            let build = &measured.build.usage;
+            writeln!(
+                out,
+                "{:<12}{:>24}{:>14}{:>20.2}{:>20.2}",
+                measured.name,
+                format!("{} ({})", build.allocations, format_bytes(build.bytes)),
+                format_bytes(build.peak as u64),
+                per_query(&measured.lookup, self.lookups),
+                per_query(&measured.prefix, self.prefixes),
+            )?;
         }
+        writeln!(
+            out,
+            "Peak heap is what building took at most above what was allocated before."
+        )
+    }
+
+    // The mean time `f` takes over the queries, and what they allocated in
+    // all.
+    fn time<T>(&self, queries: &[String], f: impl Fn(&str) -> T) -> Phase {
+        let (_, mut phase) = Phase::measure(|| {
+            for query in queries {
+                std::hint::black_box(f(std::hint::black_box(query)));
+            }
+        });
+        phase.time = phase
+            .time
+            .checked_div(queries.len() as u32)
+            .unwrap_or_default();
+        phase
     }
 }
 
-fn insert_text(root: &mut Node, text: &str) {
-    let mut node = root;
-    for ch in text.chars() {
-        node = node.children.entry(ch).or_insert(Node::new());
+// A count with an optional k, M or G suffix, e.g. "10M".
+fn parse_count(value: &str) -> Option<u64> {
+    let (digits, scale) = match value.char_indices().last()? {
+        (i, 'k' | 'K') => (&value[..i], 1_000),
+        (i, 'm' | 'M') => (&value[..i], 1_000_000),
+        (i, 'g' | 'G') => (&value[..i], 1_000_000_000),
+        _ => (value, 1),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(scale)
+}
+
+// Characters and ranges of them, e.g. "a-z0-9_".
+fn parse_alphabet(spec: &str) -> Vec<char> {
+    let chars: Vec<char> = spec.chars().collect();
+    let mut alphabet = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        if chars.get(i + 1) == Some(&'-') && i + 2 < chars.len() {
+            alphabet.extend(chars[i]..=chars[i + 2]);
+            i += 3;
+        } else {
+            alphabet.push(chars[i]);
+            i += 1;
+        }
     }
-    node.end = true;
+    alphabet.sort();
+    alphabet.dedup();
+    alphabet
 }
 
-#[allow(dead_code)]
-fn check(root: &Node, text: &str) -> bool {
-    let mut node = root;
-    for ch in text.chars() {
-        if let Some(child) = node.children.get(&ch) {
-            node = child;
+// Global options controlling how the dictionary is loaded. Queries go
+// through the same normalization as the words they are matched against.
+#[derive(Clone)]
+struct LoadOptions {
+    stem: bool,
+    fold_case: bool,
+    display_case: DisplayCase,
+    dedup: bool,
+    // Sort the dictionary in runs of at most this many bytes before
+    // building, spilling them to disk.
+    max_mem: Option<u64>,
+    // Load dictionary.<lang>.txt instead of the default dictionary.
+    lang: Option<String>,
+    // The dictionary file or URL to load instead of either, with where to
+    // keep the copy of a URL's.
+    dict: Option<String>,
+    dict_cache: Option<PathBuf>,
+    // Lowercase words left out of the trie.
+    stopwords: HashSet<String>,
+    // The log `feedback` appends picks to, folded into the weights on load.
+    history_log: String,
+    // How long until a pick counts half as much, in the log and in history
+    // files alike.
+    half_life: Option<Duration>,
+    // Spellings stored, and looked up, as their canonical forms.
+    aliases: Aliases,
+    // Complete aliases as typed rather than as their canonical forms.
+    show_alias: bool,
+    // Words never inserted, as stored.
+    exclude: HashSet<String>,
+    // The dictionary as read to tell whether it is ASCII, taken by the
+    // first load so that the file is read once.
+    read: Arc<Mutex<Option<ReadDictionary>>>,
+}
+
+// The bytes of a dictionary with its name.
+type ReadDictionary = (Vec<u8>, String);
+
+impl LoadOptions {
+    // The dictionary file `add` and `clean` write: the --dict one, the --lang
+    // one or the default.
+    fn dictionary_file(&self) -> Result<String, Error> {
+        match (&self.dict, &self.lang) {
+            (Some(dict), _) if is_url(dict) => Err(Error::Usage(
+                "a dictionary loaded from a URL can't be written to.".to_string(),
+            )),
+            (Some(dict), _) if Archive::of(dict.split("::").next().unwrap_or(dict)).is_some() => {
+                Err(Error::Usage(
+                    "a dictionary inside an archive can't be written to.".to_string(),
+                ))
+            }
+            (Some(dict), _) => Ok(dict.clone()),
+            (None, Some(lang)) => Ok(language_dictionary(lang)),
+            (None, None) => Ok(DICTIONARY.to_string()),
+        }
+    }
+
+    fn query<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        let word = if self.stem {
+            Cow::Owned(stem::stem(word))
+        } else {
+            self.query_prefix(word)
+        };
+        match self.aliases.canonical(&word) {
+            canonical if canonical != word => Cow::Owned(canonical.to_string()),
+            _ => word,
+        }
+    }
+
+    fn query_prefix<'a>(&self, prefix: &'a str) -> Cow<'a, str> {
+        if self.fold_case {
+            Cow::Owned(prefix.to_lowercase())
+        } else {
+            Cow::Borrowed(prefix)
+        }
+    }
+
+    fn builder<K: TrieKey>(&self) -> TrieBuilder<K> {
+        TrieBuilder::new()
+            .fold_case(self.fold_case)
+            .display_case(self.display_case)
+            .dedup(self.dedup)
+    }
+
+    // The picks logged by `feedback`, by stored word. Read on every load, so
+    // a server reloading on SIGHUP picks up the feedback since it started.
+    fn history(&self) -> Result<History, Error> {
+        let mut history = History::new();
+        if let Some(half_life) = self.half_life {
+            history = history.half_life(half_life);
+        }
+        let file = match File::open(&self.history_log) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(history),
+            Err(err) => return Err(err.into()),
+        };
+        for line in io::BufReader::new(file).lines() {
+            let line = line?;
+            // The prefix, the word and, since half-lives, when it was picked.
+            let mut fields = line.split('\t').skip(1);
+            let Some(word) = fields.next() else {
+                continue;
+            };
+            let word = self.stored_word(word);
+            match fields.next().and_then(|at| at.parse().ok()) {
+                Some(at) => history.pick_at(&word, at),
+                None => history.pick(&word),
+            }
+        }
+        Ok(history)
+    }
+
+    // The word the builder stores for a dictionary line.
+    fn stored_word(&self, line: &str) -> String {
+        let word = stemmed(Entry::parse(line).word, self.stem);
+        let word = self.aliases.canonical(&word);
+        if self.fold_case {
+            word.to_lowercase()
         } else {
-            return false;
+            word.to_string()
         }
     }
-    return true;
 }
 
-fn dump_dot<T: Write>(file: &mut T, root: &Node, index: &mut u16) -> io::Result<()> {
-    let root_index = *index;
-    for (item, child) in &root.children {
-        *index += 1;
-        writeln!(file, "  Node_{} [label=\"{}\"]", index, item)?;
-        writeln!(
-            file,
-            "  Node_{} -> Node_{} [label=\"{}\"]",
-            root_index, index, item
-        )?;
-        dump_dot(file, child, index)?
+fn load<K: TrieKey>(options: &LoadOptions) -> Result<Trie<K>, Error> {
+    let (file, name) = open_dictionary(options)?;
+    load_from(file, &name, options)
+}
+
+// The lines of a dictionary, with lines that aren't UTF-8 reported by
+// `name` and number.
+fn dictionary_lines<'a>(
+    reader: impl BufRead + 'a,
+    name: &'a str,
+) -> impl Iterator<Item = Result<String, Error>> + 'a {
+    reader.lines().enumerate().map(move |(i, line)| {
+        line.map_err(|err| match err.kind() {
+            io::ErrorKind::InvalidData => Error::BadUtf8 {
+                source: name.to_string(),
+                line: i + 1,
+            },
+            _ => Error::Io(err),
+        })
+    })
+}
+
+fn load_from<K: TrieKey>(
+    file: impl Read,
+    name: &str,
+    options: &LoadOptions,
+) -> Result<Trie<K>, Error> {
+    let mut builder = options.builder();
+    let history = options.history()?;
+    let mut push = |line: &str| {
+        push_line(&mut builder, line, options, &history);
+        Ok(())
+    };
+    let lines = dictionary_lines(io::BufReader::new(file), name);
+    match options.max_mem {
+        Some(max_mem) => {
+            // Sorted by the word the builder stores, so the first casing of a
+            // folded word and the first of repeated lines stay first.
+            external_sort(lines, max_mem, |line| options.stored_word(line), push)?;
+        }
+        None => {
+            for line in lines {
+                push(&line?)?;
+            }
+        }
     }
-    Ok(())
+    Ok(builder.build())
+}
+
+fn push_line<K: TrieKey>(
+    builder: &mut TrieBuilder<K>,
+    line: &str,
+    options: &LoadOptions,
+    history: &History,
+) {
+    let mut entry = Entry::parse(line);
+    if options.stopwords.contains(&entry.word.to_lowercase()) {
+        return;
+    }
+    if entry.expires.is_some_and(|expires| expires <= unix_now()) {
+        return;
+    }
+    // Case is folded by the builder, which keeps track of the original
+    // casings for display.
+    let word = stemmed(entry.word, options.stem);
+    if options.exclude.contains(&*options.query_prefix(&word)) {
+        return;
+    }
+    entry.word = options.aliases.canonical(&word);
+    let stored = options.query_prefix(entry.word);
+    if history.picks(&stored) > 0.0 {
+        let weight = entry.weight.unwrap_or(DEFAULT_WEIGHT);
+        entry.weight = Some(history.boosted(&stored, weight));
+    }
+    builder.push(&entry);
 }
 
-fn find_prefix<'a>(root: &'a Node, prefix: &str) -> &'a Node {
-    let mut node = root;
-    for ch in prefix.chars() {
-        if let Some(child) = node.children.get(&ch) {
-            node = child;
+// Lines handed to a shard's builder at a time.
+const SHARD_BATCH: usize = 1024;
+
+// Loads the dictionary into `count` shards, each built on its own thread
+// from the lines this one routes to it.
+fn load_sharded<K: TrieKey + Send + Sync>(
+    options: &LoadOptions,
+    count: usize,
+) -> Result<Sharded<K>, Error> {
+    if count == 1 {
+        return Ok(Sharded::from_shards(vec![load(options)?]));
+    }
+    let (file, name) = open_dictionary(options)?;
+    let lines = dictionary_lines(io::BufReader::new(file), &name);
+    let history = &options.history()?;
+    thread::scope(|scope| {
+        let (senders, handles): (Vec<_>, Vec<_>) = (0..count)
+            .map(|_| {
+                let (sender, receiver) = mpsc::sync_channel::<Vec<String>>(4);
+                let handle = scope.spawn(move || {
+                    let mut builder = options.builder();
+                    for line in receiver.into_iter().flatten() {
+                        push_line(&mut builder, &line, options, history);
+                    }
+                    builder.build()
+                });
+                (sender, handle)
+            })
+            .unzip();
+        let mut batches = vec![Vec::new(); count];
+        for line in lines {
+            let line = line?;
+            let shard = Sharded::<K>::shard_of(&options.stored_word(&line), count);
+            batches[shard].push(line);
+            if batches[shard].len() == SHARD_BATCH {
+                // Fails only if the builder panicked, which joining it reports.
+                let _ = senders[shard].send(mem::take(&mut batches[shard]));
+            }
+        }
+        for (sender, batch) in senders.into_iter().zip(batches) {
+            let _ = sender.send(batch);
         }
+        let shards = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect();
+        Ok(Sharded::from_shards(shards))
+    })
+}
+
+// Loads the dictionary into a shard per first character, built in the
+// background, wanted ones first, while the returned dictionary answers from
+// those built, see `Progressive`.
+#[cfg(feature = "graphql")]
+fn load_progressive<K: TrieKey + Send + Sync + 'static>(
+    options: &LoadOptions,
+) -> Result<Progressive<K>, Error> {
+    let (file, name) = open_dictionary(options)?;
+    let mut shards: HashMap<char, Vec<String>> = HashMap::new();
+    for line in dictionary_lines(io::BufReader::new(file), &name) {
+        let line = line?;
+        let first = options.stored_word(&line).chars().next().unwrap_or('\0');
+        shards.entry(first).or_default().push(line);
     }
-    return node;
+    let history = options.history()?;
+    let options = options.clone();
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    let build = move |lines: Vec<String>| {
+        let mut builder = options.builder();
+        for line in &lines {
+            push_line(&mut builder, line, &options, &history);
+        }
+        builder.build()
+    };
+    Ok(Progressive::spawn(shards, build, threads))
 }
 
-fn print_autocompletion(root: &Node, buffer: &mut Vec<char>, prefix: &str) -> io::Result<()> {
-    if root.end {
-        writeln!(
-            io::stdout(),
-            "{}{}",
-            prefix,
-            buffer.iter().collect::<String>()
-        )?;
+// The words of `text` with their 1-based line and column: runs of letters,
+// with apostrophes inside a word ("don't") kept.
+fn words_of(text: &str) -> Vec<(usize, usize, &str)> {
+    let mut words = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let mut start = None;
+        let mut column = 0;
+        let mut chars = line.char_indices().peekable();
+        while let Some((at, c)) = chars.next() {
+            let inside = c == '\''
+                && start.is_some()
+                && chars.peek().is_some_and(|(_, next)| next.is_alphabetic());
+            if c.is_alphabetic() || inside {
+                start.get_or_insert((at, column + 1));
+            } else if let Some((from, col)) = start.take() {
+                words.push((i + 1, col, &line[from..at]));
+            }
+            column += 1;
+        }
+        if let Some((from, col)) = start {
+            words.push((i + 1, col, &line[from..]));
+        }
+    }
+    words
+}
+
+// Whether the dictionary has `word`, or its lowercase form for words
+// capitalized at the start of a sentence. Words `bloom` rejects are never
+// looked up in the trie.
+fn is_known<K: TrieKey>(
+    trie: &Trie<K>,
+    bloom: Option<&Bloom>,
+    options: &LoadOptions,
+    word: &str,
+) -> bool {
+    let contains = |word: &str| {
+        let word = options.query(word);
+        bloom.is_none_or(|bloom| bloom.may_contain(&word)) && trie.contains(&word)
+    };
+    contains(word) || contains(&word.to_lowercase())
+}
+
+// `word` capitalized like `original`, so a correction at the start of a
+// sentence keeps its capital.
+fn cased_like(word: &str, original: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) if original.starts_with(char::is_uppercase) => {
+            first.to_uppercase().chain(chars).collect()
+        }
+        _ => word.to_string(),
+    }
+}
+
+fn stemmed(word: &str, enabled: bool) -> Cow<'_, str> {
+    if enabled {
+        Cow::Owned(stem::stem(word))
+    } else {
+        Cow::Borrowed(word)
+    }
+}
+
+// Temporary files, removed when dropped.
+struct Runs(Vec<PathBuf>);
+
+impl Drop for Runs {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// Calls `each` with the lines sorted by `key`, lines with equal keys in
+// their original order. The lines are sorted in runs of about `max_mem`
+// bytes, written to temporary files when there is more than one, and then
+// merged, so they never all sit in memory at once.
+fn external_sort(
+    mut lines: impl Iterator<Item = Result<String, Error>>,
+    max_mem: u64,
+    key: impl Fn(&str) -> String,
+    mut each: impl FnMut(&str) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut runs = Runs(vec![]);
+    let mut run: Vec<(String, String)> = vec![];
+    let mut size = 0;
+    loop {
+        let line = lines.next().transpose()?;
+        let full = size >= max_mem;
+        if full || (line.is_none() && !runs.0.is_empty() && !run.is_empty()) {
+            // A stable sort keeps equal keys in order within the run.
+            run.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let path = env::temp_dir().join(format!(
+                "prefix-tree-{}-{}.run",
+                std::process::id(),
+                runs.0.len()
+            ));
+            let mut file = io::BufWriter::new(File::create(&path)?);
+            runs.0.push(path);
+            for (_, line) in run.drain(..) {
+                writeln!(file, "{}", line)?;
+            }
+            file.flush()?;
+            size = 0;
+        }
+        let Some(line) = line else {
+            break;
+        };
+        let line_key = key(&line);
+        // Roughly what the pair costs, allocations included.
+        size += (line.len() + line_key.len() + 2 * mem::size_of::<String>()) as u64;
+        run.push((line_key, line));
+    }
+    if runs.0.is_empty() {
+        run.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (_, line) in run {
+            each(&line)?;
+        }
         return Ok(());
     }
 
-    for (item, child) in &root.children {
-        buffer.push(*item);
-        print_autocompletion(child, buffer, prefix)?;
-        buffer.pop();
+    // Between equal keys the earlier run wins, keeping the original order.
+    let mut readers = runs
+        .0
+        .iter()
+        .map(|path| Ok(io::BufReader::new(File::open(path)?).lines()))
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut heap = BinaryHeap::new();
+    for (i, reader) in readers.iter_mut().enumerate() {
+        if let Some(line) = reader.next().transpose()? {
+            heap.push(Reverse((key(&line), i, line)));
+        }
+    }
+    while let Some(Reverse((_, i, line))) = heap.pop() {
+        each(&line)?;
+        if let Some(line) = readers[i].next().transpose()? {
+            heap.push(Reverse((key(&line), i, line)));
+        }
     }
     Ok(())
 }
 
-fn usage(mut sink: impl Write) -> io::Result<()> {
-    writeln!(sink, "Usage: ./prefix-tree <SUBCOMMAND>")?;
-    writeln!(sink, "SUBCOMMANDS")?;
-    writeln!(
-        sink,
-        "    dot               Dump the Trie into a Graphviz dot file."
-    )?;
-    writeln!(
-        sink,
-        "    complete <prefix> Suggest prefix autocompletion based on the Trie"
-    )?;
-    Ok(())
+const REPL_COMMANDS: &[&str] = &[
+    "complete",
+    "contains",
+    "starts-with",
+    "suggest",
+    "help",
+    "quit",
+];
+
+// The system clipboard, held open for the whole session: on X11 and Wayland
+// what is copied only lasts as long as the program that copied it, unless a
+// clipboard manager takes it over.
+#[cfg(feature = "clipboard")]
+struct Clipboard(std::sync::Mutex<arboard::Clipboard>);
+
+#[cfg(feature = "clipboard")]
+impl Clipboard {
+    fn open() -> io::Result<Self> {
+        let clipboard = arboard::Clipboard::new().map_err(io::Error::other)?;
+        Ok(Self(std::sync::Mutex::new(clipboard)))
+    }
+
+    fn copy(&self, text: &str) -> io::Result<()> {
+        let mut clipboard = self.0.lock().unwrap();
+        clipboard.set_text(text).map_err(io::Error::other)
+    }
 }
 
-fn main() -> io::Result<()> {
-    let mut root = Node::new();
-    let file = File::open("dictionary.txt")?;
-    for line in io::BufReader::new(file).lines() {
-        let line = line?;
-        insert_text(&mut root, &line);
+// Copies the word before the cursor, a completion once Tab has filled it in,
+// when bound to a key.
+#[cfg(feature = "clipboard")]
+struct CopyWord(Arc<Clipboard>);
+
+#[cfg(feature = "clipboard")]
+impl rustyline::ConditionalEventHandler for CopyWord {
+    fn handle(
+        &self,
+        _: &rustyline::Event,
+        _: rustyline::RepeatCount,
+        _: bool,
+        ctx: &rustyline::EventContext,
+    ) -> Option<rustyline::Cmd> {
+        if let Some(word) = ctx.line()[..ctx.pos()].split_whitespace().next_back() {
+            // Nothing can be reported in the middle of the line, and the
+            // word is still there to copy by hand.
+            let _ = self.0.copy(word);
+        }
+        Some(rustyline::Cmd::Noop)
+    }
+}
+
+// Interactive loop over an already loaded trie; Tab completes command names
+// and then words from the trie itself. With `copy`, the first completion of
+// `complete` goes to the clipboard.
+fn repl<K: TrieKey>(
+    trie: &Trie<K>,
+    options: &LoadOptions,
+    #[cfg_attr(not(feature = "clipboard"), allow(unused_variables))] copy: bool,
+) -> io::Result<()> {
+    let completer = Normalized {
+        completer: trie,
+        options,
+        min_prefix: 0,
+    };
+    let helper = ReadlineHelper::new(&completer).commands(REPL_COMMANDS);
+    let mut editor = Editor::<_, DefaultHistory>::new().map_err(io::Error::other)?;
+    editor.set_helper(Some(helper));
+    // Without a display there is no clipboard, which only matters if
+    // copying was asked for.
+    #[cfg(feature = "clipboard")]
+    let clipboard = match Clipboard::open() {
+        Ok(clipboard) => {
+            let clipboard = Arc::new(clipboard);
+            editor.bind_sequence(
+                rustyline::KeyEvent::alt('w'),
+                rustyline::EventHandler::Conditional(Box::new(CopyWord(clipboard.clone()))),
+            );
+            Some(clipboard).filter(|_| copy)
+        }
+        Err(err) if copy => return Err(err),
+        Err(_) => None,
+    };
+    loop {
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Eof | ReadlineError::Interrupted) => return Ok(()),
+            Err(err) => return Err(io::Error::other(err)),
+        };
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            continue;
+        };
+        let _ = editor.add_history_entry(line.as_str());
+        let argument = words.next().unwrap_or_default();
+        match command {
+            "complete" => {
+                print_completions(&completer, argument, 20, '\n')?;
+                #[cfg(feature = "clipboard")]
+                if let Some(clipboard) = &clipboard {
+                    if let Some(first) = completer.complete(argument, 1).first() {
+                        clipboard.copy(&first.word)?;
+                        writeln!(io::stdout(), "copied {}", first.word)?;
+                    }
+                }
+            }
+            "contains" => writeln!(io::stdout(), "{}", trie.contains(&options.query(argument)))?,
+            "starts-with" => writeln!(
+                io::stdout(),
+                "{}",
+                trie.starts_with(&options.query_prefix(argument))
+            )?,
+            "suggest" => {
+                let ranking = Ranking::default();
+                let mut matches =
+                    fuzzy::search(trie, &options.query(argument), 2.0, &Costs::default());
+                ranking.rank(&mut matches);
+                for m in matches.iter().take(10) {
+                    writeln!(io::stdout(), "{}", m.word)?;
+                }
+            }
+            "quit" | "exit" => return Ok(()),
+            _ => writeln!(io::stdout(), "commands: {}", REPL_COMMANDS.join(", "))?,
+        }
     }
+}
 
-    if let Some(subcommand) = env::args().nth(1) {
+fn run<K: TrieKey + Send + Sync + 'static>(
+    args: Vec<String>,
+    options: LoadOptions,
+) -> Result<(), Error> {
+    let mut args = args.into_iter();
+    if let Some(subcommand) = args.next() {
         match subcommand.as_str() {
             "dot" => {
-                let mut dot_file = File::create("trie.dot")?;
-                writeln!(&dot_file, "digraph Trie {{")?;
-                writeln!(&dot_file, "  Node_{} [label=\"{}\"]", 0, "root")?;
-                dump_dot(&mut dot_file, &root, &mut 0)?;
-                writeln!(&dot_file, "}}")?;
-                let child = Command::new("dot")
-                    .arg("-Tsvg")
-                    .arg("trie.dot")
-                    .stdout(Stdio::piped())
+                let mut render = true;
+                let mut style = dot::DotStyle::default();
+                let mut out = None;
+                let mut max_nodes = None;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--no-render" => render = false,
+                        "--max-nodes" => max_nodes = Some(parse_flag(&mut args, "--max-nodes")?),
+                        "--out" => out = Some(flag_value(&mut args, "--out")?),
+                        "--cluster" => style.clusters = true,
+                        "--word-shape" => {
+                            style.word_shape = Some(flag_value(&mut args, "--word-shape")?)
+                        }
+                        "--plain-words" => style.word_shape = None,
+                        "--word-labels" => style.word_labels = true,
+                        "--compact" => style.compact = true,
+                        "--merge-chains" => style.merge_chains = true,
+                        _ => return Err(Error::Usage(format!("unknown dot option: {}", arg))),
+                    }
+                }
+                let trie = load::<K>(&options)?;
+                // A drawing too large for Graphviz is cut to the levels that
+                // fit, rather than written whole.
+                let cut = max_nodes.and_then(|max| Some((max, dot::depth_within(&trie, max)?)));
+                if let Some((max_nodes, (depth, nodes))) = cut {
+                    writeln!(
+                        io::stderr(),
+                        "warning: the trie has more than {} nodes; drawing the {} within {} keys of the root, the cut ones dashed",
+                        max_nodes, nodes, depth
+                    )?;
+                    style.max_depth = Some(depth);
+                }
+                let graphviz = find_graphviz().filter(|_| render);
+                let Some(graphviz) = graphviz else {
+                    // Without Graphviz the dot file is all there is to show.
+                    let path = out.unwrap_or_else(|| "trie.dot".to_string());
+                    let mut file = io::BufWriter::new(File::create(&path)?);
+                    dot::write_dot_with(&mut file, &trie, &style)?;
+                    file.flush()?;
+                    return match render {
+                        true => Err(Error::GraphvizMissing(path)),
+                        false => Ok(()),
+                    };
+                };
+                // The dot text goes straight into Graphviz, and into the
+                // --out file only if one is asked for.
+                let mut child = Command::new(graphviz)
+                    .args(["-Tsvg", "-o", "trie.svg"])
+                    .stdin(Stdio::piped())
                     .spawn()?;
-                let output = child.wait_with_output()?;
-                if output.status.success() {
-                    let raw_output = String::from_utf8_lossy(output.stdout.as_slice());
-                    let mut graph_svg = File::create("trie.svg")?;
-                    writeln!(graph_svg, "{}", raw_output)?;
+                let stdin = io::BufWriter::new(child.stdin.take().expect("stdin is piped"));
+                let written = match &out {
+                    Some(path) => {
+                        let file = io::BufWriter::new(File::create(path)?);
+                        let mut tee = Tee(stdin, file);
+                        dot::write_dot_with(&mut tee, &trie, &style).and_then(|()| tee.flush())
+                    }
+                    None => {
+                        let mut stdin = stdin;
+                        dot::write_dot_with(&mut stdin, &trie, &style).and_then(|()| stdin.flush())
+                    }
+                };
+                let status = child.wait()?;
+                written?;
+                if !status.success() {
+                    return Err(Error::Io(io::Error::other(format!(
+                        "Graphviz failed to render trie.svg: {}",
+                        status
+                    ))));
+                }
+            }
+            "compact" => {
+                let mut output = String::from("trie.idx");
+                let mut level = None;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--output" => output = flag_value(&mut args, "--output")?,
+                        "--compress" => level = level.or(Some(19)),
+                        "--level" => level = Some(parse_flag::<i32>(&mut args, "--level")?),
+                        _ => return Err(Error::Usage(format!("unknown compact option: {}", arg))),
+                    }
+                }
+                if level.is_some() && cfg!(not(feature = "zstd")) {
+                    return Err(Error::Usage(
+                        "--compress needs a build with the zstd feature.".to_string(),
+                    ));
+                }
+                let trie = load::<K>(&options)?;
+                let mut file = io::BufWriter::new(File::create(output)?);
+                match level {
+                    #[cfg(feature = "zstd")]
+                    Some(level) => index::write_compressed_index(&mut file, &trie, level)?,
+                    _ => index::write_index(&mut file, &trie)?,
+                }
+                file.flush()?;
+            }
+            "abbrev" => {
+                let trie = load::<K>(&options)?;
+                let words: Vec<String> = args.by_ref().collect();
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                if words.is_empty() {
+                    for (word, _) in trie.iter() {
+                        let abbrev = trie.shortest_unique_prefix(&word).expect("word is stored");
+                        writeln!(out, "{}\t{}", word, abbrev)?;
+                    }
+                }
+                let mut missing = false;
+                for word in &words {
+                    let word = options.query(word);
+                    match trie.shortest_unique_prefix(&word) {
+                        Some(abbrev) => writeln!(out, "{}\t{}", word, abbrev)?,
+                        None => {
+                            missing = true;
+                            writeln!(io::stderr(), "not in the dictionary: {}", word)?;
+                        }
+                    }
+                }
+                out.flush()?;
+                if missing {
+                    exit(1);
                 }
             }
+            "infix-index" => {
+                let trie = load::<K>(&options)?;
+                let mut output = String::from("infix.idx");
+                while let Some(arg) = args.next() {
+                    if arg == "--output" {
+                        output = flag_value(&mut args, "--output")?;
+                    }
+                }
+                let mut file = io::BufWriter::new(File::create(output)?);
+                SuffixAutomaton::from_trie(&trie).write(&mut file)?;
+                file.flush()?;
+            }
+            "infix" => {
+                let mut infix = None;
+                let mut index_path = None;
+                let mut limit = usize::MAX;
+                let mut terminator = '\n';
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--index" => index_path = Some(flag_value(&mut args, "--index")?),
+                        "--limit" => limit = parse_flag(&mut args, "--limit")?,
+                        "-0" | "--null" => terminator = '\0',
+                        _ => infix = Some(arg),
+                    }
+                }
+                let Some(infix) = infix else {
+                    return Err(Error::Usage(
+                        "infix expects the text to look for.".to_string(),
+                    ));
+                };
+                let automaton = match &index_path {
+                    Some(path) => {
+                        SuffixAutomaton::open(path).map_err(|err| format_error(path, err))?
+                    }
+                    None => SuffixAutomaton::from_trie(&load::<K>(&options)?),
+                };
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                for word in automaton
+                    .find(&options.query_prefix(&infix))
+                    .into_iter()
+                    .take(limit)
+                {
+                    write!(out, "{}{}", word, terminator)?;
+                }
+                out.flush()?;
+            }
+            "glob" => {
+                let mut pattern = None;
+                let mut limit = usize::MAX;
+                let mut terminator = '\n';
+                let mut show_plan = false;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--limit" => limit = parse_flag(&mut args, "--limit")?,
+                        "--plan" => show_plan = true,
+                        "-0" | "--null" => terminator = '\0',
+                        _ => pattern = Some(arg),
+                    }
+                }
+                let Some(pattern) = pattern else {
+                    return Err(Error::Usage("glob expects a pattern.".to_string()));
+                };
+                let pattern = options.query_prefix(&pattern);
+                let trie = load::<K>(&options)?;
+                if show_plan {
+                    write!(io::stderr(), "{}", pattern::plan(&trie, &pattern))?;
+                }
+                let words = pattern::matches(&trie, &pattern);
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                for word in words.iter().take(limit) {
+                    write!(out, "{}{}", word, terminator)?;
+                }
+                out.flush()?;
+            }
+            "fill" => {
+                let mut pattern = None;
+                let mut limit = usize::MAX;
+                let mut terminator = '\n';
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--limit" => limit = parse_flag(&mut args, "--limit")?,
+                        "-0" | "--null" => terminator = '\0',
+                        _ => pattern = Some(arg),
+                    }
+                }
+                let Some(pattern) = pattern else {
+                    return Err(Error::Usage("fill expects a pattern.".to_string()));
+                };
+                let pattern = options.query_prefix(&pattern);
+                let trie = load::<K>(&options)?;
+                let words = pattern::fill(&trie, &pattern);
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                for word in words.iter().take(limit) {
+                    write!(out, "{}{}", word, terminator)?;
+                }
+                out.flush()?;
+            }
+            "diff-index" => {
+                let (Some(old), Some(new)) = (args.next(), args.next()) else {
+                    return Err(Error::Usage(
+                        "diff-index expects two index files.".to_string(),
+                    ));
+                };
+                let mut out = io::stdout().lock();
+                patch::write_patch(&mut out, &open_index(&old)?, &open_index(&new)?)?;
+                out.flush()?;
+            }
+            "apply-patch" => {
+                let mut files = vec![];
+                let mut output = None;
+                while let Some(arg) = args.next() {
+                    if arg == "--output" {
+                        output = Some(flag_value(&mut args, "--output")?);
+                    } else {
+                        files.push(arg);
+                    }
+                }
+                let [index, patch_file] = &files[..] else {
+                    return Err(Error::Usage(
+                        "apply-patch expects an index and a patch file.".to_string(),
+                    ));
+                };
+                let output = PathBuf::from(output.unwrap_or_else(|| index.clone()));
+                let mut patched = vec![];
+                patch::apply_patch(&mut patched, &open_index(index)?, &fs::read(patch_file)?)
+                    .map_err(|err| format_error(patch_file, err))?;
+                // Write beside the output and rename, so a reader of the
+                // index never sees it half written.
+                let partial = output.with_extension("partial");
+                fs::write(&partial, patched)?;
+                fs::rename(partial, output)?;
+            }
+            "tree" => {
+                let trie = load::<K>(&options)?;
+                let mut prefix = String::new();
+                let mut depth = usize::MAX;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--depth" => depth = parse_flag(&mut args, "--depth")?,
+                        _ => prefix = arg,
+                    }
+                }
+                let prefix = options.query_prefix(&prefix);
+                tree::write_tree(&mut io::stdout().lock(), &trie, &prefix, depth)?;
+            }
+            "list" => {
+                let trie = load::<K>(&options)?;
+                let mut args: Vec<String> = args.by_ref().collect();
+                let info = take_switch(&mut args, "--info");
+                let mut filter = ListFilter {
+                    min_len: 0,
+                    max_len: usize::MAX,
+                    charset: None,
+                    prefix: String::new(),
+                };
+                let mut args = args.into_iter();
+                let mut filtered = false;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--min-len" => filter.min_len = parse_flag(&mut args, "--min-len")?,
+                        "--max-len" => filter.max_len = parse_flag(&mut args, "--max-len")?,
+                        "--charset" => {
+                            filter.charset =
+                                Some(parse_alphabet(&flag_value(&mut args, "--charset")?))
+                        }
+                        "--prefix" => {
+                            filter.prefix = options
+                                .query_prefix(&flag_value(&mut args, "--prefix")?)
+                                .into_owned()
+                        }
+                        _ => return Err(Error::Usage(format!("unknown list option: {}", arg))),
+                    }
+                    filtered = true;
+                }
+                if filtered {
+                    let words = filter.words(&trie);
+                    let words = words.into_iter().filter_map(|word| {
+                        let info = trie.get(&word)?;
+                        Some((word, info))
+                    });
+                    write_words(io::stdout().lock(), words, info)?;
+                } else {
+                    write_words(io::stdout().lock(), trie.iter(), info)?;
+                }
+            }
+            "top" => {
+                let mut n = 50;
+                let mut prefix = String::new();
+                let mut with_scores = false;
+                let mut terminator = '\n';
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--n" => n = parse_flag(&mut args, "--n")?,
+                        "--prefix" => prefix = flag_value(&mut args, "--prefix")?,
+                        "--with-scores" => with_scores = true,
+                        "-0" | "--null" => terminator = '\0',
+                        _ => return Err(Error::Usage(format!("unknown top option: {}", arg))),
+                    }
+                }
+                let trie = load::<K>(&options)?;
+                let index = TopIndex::new(&trie);
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                for suggestion in index.top(&options.query_prefix(&prefix), n) {
+                    if with_scores {
+                        write!(
+                            out,
+                            "{}\t{}{}",
+                            suggestion.word, suggestion.weight, terminator
+                        )?;
+                    } else {
+                        write!(out, "{}{}", suggestion.word, terminator)?;
+                    }
+                }
+                out.flush()?;
+            }
+            "extremes" => {
+                let trie = load::<K>(&options)?;
+                let prefix = args.next().unwrap_or_default();
+                let prefix = options.query_prefix(&prefix);
+                let extremes = [
+                    ("shortest", trie.shortest_word(&prefix)),
+                    ("longest", trie.longest_word(&prefix)),
+                ];
+                for (name, word) in extremes {
+                    if let Some(word) = word {
+                        writeln!(
+                            io::stdout(),
+                            "{}: {} ({})",
+                            name,
+                            word,
+                            K::segment(&word).len()
+                        )?;
+                    }
+                }
+            }
+            "count" => {
+                let trie = load::<K>(&options)?;
+                let prefix = args.next().unwrap_or_default();
+                writeln!(
+                    io::stdout(),
+                    "{}",
+                    trie.count_prefix(&options.query_prefix(&prefix))
+                )?;
+            }
+            "select" => {
+                let trie = load::<K>(&options)?;
+                let k = parse_flag(&mut args, "select")?;
+                let prefix = args.next().unwrap_or_default();
+                match trie.select(k, &options.query_prefix(&prefix)) {
+                    Some(word) => writeln!(io::stdout(), "{}", word)?,
+                    None => exit(1),
+                }
+            }
+            "rank" => {
+                let trie = load::<K>(&options)?;
+                let word = flag_value(&mut args, "rank")?;
+                writeln!(io::stdout(), "{}", trie.rank(&options.query(&word)))?;
+            }
+            "range" => {
+                let trie = load::<K>(&options)?;
+                let (Some(low), Some(high)) = (args.next(), args.next()) else {
+                    return Err(Error::Usage("range expects two bounds.".to_string()));
+                };
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                for (word, _) in trie.range(&options.query(&low), &options.query(&high)) {
+                    writeln!(out, "{}", word)?;
+                }
+                out.flush()?;
+            }
+            "next" | "prev" => {
+                let trie = load::<K>(&options)?;
+                let word = flag_value(&mut args, &subcommand)?;
+                let word = options.query(&word);
+                let neighbour = if subcommand == "next" {
+                    trie.next_after(&word)
+                } else {
+                    trie.prev_before(&word)
+                };
+                match neighbour {
+                    Some(neighbour) => writeln!(io::stdout(), "{}", neighbour)?,
+                    None => exit(1),
+                }
+            }
+            "prefixes" => {
+                let mut len = None;
+                let mut min_count = 1;
+                let mut limit = 10;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--len" => len = Some(parse_flag(&mut args, "--len")?),
+                        "--min-count" => min_count = parse_flag(&mut args, "--min-count")?,
+                        "--limit" => limit = parse_flag(&mut args, "--limit")?,
+                        _ => return Err(Error::Usage(format!("unknown prefixes option: {}", arg))),
+                    }
+                }
+                let Some(len) = len else {
+                    return Err(Error::Usage("prefixes expects --len.".to_string()));
+                };
+                let trie = load::<K>(&options)?;
+                let mut counts = trie.prefix_counts(len);
+                writeln!(io::stdout(), "{} distinct prefixes", counts.len())?;
+                counts.retain(|&(_, count)| count >= min_count);
+                counts.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+                for (prefix, count) in counts.into_iter().take(limit) {
+                    writeln!(io::stdout(), "{}\t{}", prefix, count)?;
+                }
+            }
+            "rename-prefix" => {
+                let mut trie = load::<K>(&options)?;
+                let (Some(from), Some(to)) = (args.next(), args.next()) else {
+                    return Err(Error::Usage(
+                        "rename-prefix expects two prefixes.".to_string(),
+                    ));
+                };
+                let moved =
+                    trie.rename_prefix(&options.query_prefix(&from), &options.query_prefix(&to));
+                writeln!(io::stderr(), "moved {} words", moved)?;
+                write_words(io::stdout().lock(), trie.iter(), true)?;
+            }
+            #[cfg(feature = "sqlite")]
+            "import" => {
+                let (path, table) = sqlite_table("import", &mut args)?;
+                let db =
+                    rusqlite::Connection::open(&path).map_err(|err| sqlite_error(&path, err))?;
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                let (mut words, mut skipped) = (0, 0);
+                let mut result = Ok(());
+                table
+                    .read(&db, |entry| {
+                        // A line per word, so a word can't hold a line break
+                        // or the tab separating the columns.
+                        if entry.word.contains(['\t', '\n', '\r']) {
+                            skipped += 1;
+                            return;
+                        }
+                        words += 1;
+                        if result.is_ok() {
+                            result = write_entry(&mut out, entry);
+                        }
+                    })
+                    .map_err(|err| sqlite_error(&path, err))?;
+                result?;
+                out.flush()?;
+                writeln!(io::stderr(), "imported {} words", words)?;
+                if skipped > 0 {
+                    writeln!(
+                        io::stderr(),
+                        "WARNING: skipped {} words with tabs or line breaks",
+                        skipped
+                    )?;
+                }
+            }
+            #[cfg(feature = "sqlite")]
+            "export" => {
+                let (path, table) = sqlite_table("export", &mut args)?;
+                let trie = load::<K>(&options)?;
+                let mut db =
+                    rusqlite::Connection::open(&path).map_err(|err| sqlite_error(&path, err))?;
+                let words = table
+                    .write(&mut db, &trie)
+                    .map_err(|err| sqlite_error(&path, err))?;
+                writeln!(io::stderr(), "exported {} words to {}", words, path)?;
+            }
+            #[cfg(not(feature = "sqlite"))]
+            "import" | "export" => {
+                return Err(Error::Usage(
+                    "import and export need a build with the sqlite feature.".to_string(),
+                ));
+            }
             "complete" => {
-                if let Some(prefix) = env::args().nth(2) {
-                    let node = find_prefix(&mut root, prefix.as_str());
-                    let mut buffer = vec![];
-                    print_autocompletion(&node, &mut buffer, &prefix)?;
+                let mut prefix = None;
+                let mut tag = None;
+                let mut limit = usize::MAX;
+                let mut min_prefix = 0;
+                let mut index_path = None;
+                let mut batch = None;
+                let mut output = Output::Text;
+                let mut stdin = false;
+                let mut terminator = '\n';
+                let mut quiet = false;
+                let mut count_only = false;
+                let mut explain = false;
+                let mut shards = 1;
+                let mut min_len = 0;
+                let mut score_script = None;
+                let mut template = None;
+                let mut history = None;
+                let mut fuzzy_prefix = false;
+                let mut max_dist = 1.0;
+                let mut sources = vec![];
+                let mut last_token = false;
+                let mut with_scores = false;
+                let mut exec = None;
+                let mut schema = None;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--exec" | "--pipe" => exec = Some(flag_value(&mut args, &arg)?),
+                        "--schema-version" => schema = Some(schema_version(&mut args)?),
+                        "--fuzzy-prefix" => fuzzy_prefix = true,
+                        "--last-token" => last_token = true,
+                        "--with-scores" => with_scores = true,
+                        "--max-dist" => max_dist = parse_flag(&mut args, "--max-dist")?,
+                        "--history" => history = Some(flag_value(&mut args, "--history")?),
+                        "--template" => template = Some(flag_value(&mut args, "--template")?),
+                        "--score-script" => {
+                            score_script = Some(flag_value(&mut args, "--score-script")?)
+                        }
+                        "--min-len" => min_len = parse_flag(&mut args, "--min-len")?,
+                        "--quiet" => quiet = true,
+                        "--shards" => shards = parse_flag(&mut args, "--shards")?,
+                        "--explain" => explain = true,
+                        "--count-only" => count_only = true,
+                        "--source" => {
+                            let source = flag_value(&mut args, "--source")?;
+                            // An optional ":<cap>" suffix limits the completions
+                            // the source contributes.
+                            sources.push(match source.rsplit_once(':') {
+                                Some((path, cap)) if cap.parse::<usize>().is_ok() => {
+                                    (path.to_string(), cap.parse().unwrap())
+                                }
+                                _ => (source, usize::MAX),
+                            });
+                        }
+                        "--index" => index_path = Some(flag_value(&mut args, "--index")?),
+                        "--tag" => tag = Some(flag_value(&mut args, "--tag")?),
+                        "--limit" => limit = parse_flag(&mut args, "--limit")?,
+                        "--min-prefix" => min_prefix = parse_flag(&mut args, "--min-prefix")?,
+                        "--batch" => batch = Some(flag_value(&mut args, "--batch")?),
+                        "--json" => output = Output::Json,
+                        "--output" => output = Output::parse(&flag_value(&mut args, "--output")?)?,
+                        "--stdin" => stdin = true,
+                        "-0" | "--null" => terminator = '\0',
+                        _ if arg.starts_with("--") => {
+                            return Err(Error::Usage(format!("unknown complete option: {}", arg)))
+                        }
+                        _ => prefix = Some(arg),
+                    }
+                }
+                if prefix.is_none() && batch.is_none() && !stdin {
+                    return Ok(());
+                }
+                if explain {
+                    let Some(prefix) = prefix.filter(|_| {
+                        index_path.is_none()
+                            && sources.is_empty()
+                            && batch.is_none()
+                            && !stdin
+                            && score_script.is_none()
+                            && history.is_none()
+                            && !fuzzy_prefix
+                    }) else {
+                        return Err(Error::Usage("--explain only explains a single prefix completed from the dictionary.".to_string()));
+                    };
+                    let (trie, loaded) = alloc_stats::measure(|| load::<K>(&options));
+                    let trie = trie?;
+                    let prefix = options.query_prefix(&prefix);
+                    let ((suggestions, explanation), queried) =
+                        alloc_stats::measure(|| match &tag {
+                            Some(tag) => trie
+                                .tagged(tag)
+                                .min_len(min_len)
+                                .complete_explained(&prefix, limit),
+                            None => trie.min_len(min_len).complete_explained(&prefix, limit),
+                        });
+                    for suggestion in &suggestions {
+                        write!(io::stdout(), "{}{}", suggestion.word, terminator)?;
+                    }
+                    let mut err = io::stderr().lock();
+                    writeln!(
+                        err,
+                        "prefix walk: {} nodes in {:?}",
+                        explanation.prefix_nodes, explanation.prefix_walk
+                    )?;
+                    writeln!(
+                        err,
+                        "enumeration: {} nodes, {} words in {:?}",
+                        explanation.nodes_enumerated, explanation.words, explanation.enumeration
+                    )?;
+                    writeln!(
+                        err,
+                        "ranking: {} of {} words kept in {:?}",
+                        suggestions.len(),
+                        explanation.words,
+                        explanation.ranking
+                    )?;
+                    if cfg!(feature = "alloc-stats") {
+                        for (phase, usage) in [("load", loaded), ("query", queried)] {
+                            writeln!(
+                                err,
+                                "{} heap: {} allocations, {}, peak {}",
+                                phase,
+                                usage.allocations,
+                                format_bytes(usage.bytes),
+                                format_bytes(usage.peak as u64)
+                            )?;
+                        }
+                    }
+                    return Ok(());
+                }
+                if score_script.is_some() && cfg!(not(feature = "scripting")) {
+                    return Err(Error::Usage(
+                        "--score-script needs a build with the scripting feature.".to_string(),
+                    ));
+                }
+                if score_script.is_some()
+                    && (index_path.is_some() || tag.is_some() || shards > 1 || min_len > 0)
+                {
+                    return Err(Error::Usage("--score-script can't be combined with --index, --tag, --shards or --min-len.".to_string()));
+                }
+                #[cfg(feature = "scripting")]
+                let script = match &score_script {
+                    Some(path) => match ScriptScorer::new(&fs::read_to_string(path)?) {
+                        Ok(script) => Some(script),
+                        Err(err) => return Err(Error::Script(format!("{}: {}", path, err))),
+                    },
+                    None => None,
+                };
+                #[cfg(feature = "scripting")]
+                let scored;
+                if history.is_some()
+                    && (index_path.is_some()
+                        || tag.is_some()
+                        || shards > 1
+                        || min_len > 0
+                        || score_script.is_some())
+                {
+                    return Err(Error::Usage("--history can't be combined with --index, --tag, --shards, --min-len or --score-script.".to_string()));
+                }
+                if fuzzy_prefix
+                    && (index_path.is_some()
+                        || tag.is_some()
+                        || shards > 1
+                        || score_script.is_some()
+                        || history.is_some())
+                {
+                    return Err(Error::Usage("--fuzzy-prefix can't be combined with --index, --tag, --shards, --score-script or --history.".to_string()));
+                }
+                let mut history = history
+                    .as_deref()
+                    .map(|path| read_history(path, &options))
+                    .transpose()?;
+                let boosted;
+                if min_len > 0 && (index_path.is_some() || shards > 1) {
+                    return Err(Error::Usage(
+                        "--min-len can't be combined with --index or --shards.".to_string(),
+                    ));
+                }
+                if shards == 0 || (shards > 1 && (index_path.is_some() || tag.is_some())) {
+                    return Err(Error::Usage("--shards needs a positive count, and can't be combined with --index or --tag.".to_string()));
+                }
+                if template.is_some() && output != Output::Text {
+                    return Err(Error::Usage(
+                        "--template can't be combined with JSON output.".to_string(),
+                    ));
+                }
+                if template.is_some() && with_scores {
+                    return Err(Error::Usage(
+                        "--with-scores can't be combined with --template, which has {score}."
+                            .to_string(),
+                    ));
+                }
+                if schema.is_some() && (output == Output::Text || with_scores) {
+                    return Err(Error::Usage("--schema-version needs --output json or jsonl, and can't be combined with --with-scores.".to_string()));
+                }
+                if exec.is_some() && (batch.is_some() || stdin || quiet || count_only) {
+                    return Err(Error::Usage("--exec pipes the completions of one prefix, and can't be combined with --batch, --stdin, --quiet or --count-only.".to_string()));
+                }
+                let template = parse_template(template)?;
+                // Loaded up front, so templates can look up tags in them.
+                let sharded = match shards {
+                    1 => None,
+                    _ => Some(load_sharded::<K>(&options, shards)?),
+                };
+                let dictionary = match (&index_path, shards) {
+                    (None, 1) => Some(load::<K>(&options)?),
+                    _ => None,
+                };
+                let (index, index_tagged, trie_tagged, trie_long, trie_fuzzy);
+                let source: &dyn Completer = match (&index_path, &tag) {
+                    // Answered straight from the prebuilt index, without
+                    // reading the dictionary at all.
+                    (Some(path), None) => {
+                        index = open_index(path)?;
+                        &index
+                    }
+                    (Some(path), Some(tag)) => {
+                        index = open_index(path)?;
+                        index_tagged = index.tagged(tag);
+                        &index_tagged
+                    }
+                    (None, None) if sharded.is_some() => sharded.as_ref().unwrap(),
+                    #[cfg(feature = "scripting")]
+                    (None, None) if script.is_some() => {
+                        let trie = dictionary.as_ref().unwrap();
+                        let script = script.as_ref().unwrap();
+                        scored = trie.scored(|candidate: &Candidate| script.score(candidate));
+                        &scored
+                    }
+                    (None, None) if fuzzy_prefix => {
+                        let costs = Costs {
+                            min_len,
+                            ..Costs::default()
+                        };
+                        trie_fuzzy = dictionary
+                            .as_ref()
+                            .unwrap()
+                            .fuzzy_prefix(max_dist)
+                            .costs(costs);
+                        &trie_fuzzy
+                    }
+                    (None, None) if history.is_some() => {
+                        boosted = dictionary.as_ref().unwrap().scored(history.take().unwrap());
+                        &boosted
+                    }
+                    (None, None) if min_len > 0 => {
+                        trie_long = dictionary.as_ref().unwrap().min_len(min_len);
+                        &trie_long
+                    }
+                    (None, None) => dictionary.as_ref().unwrap(),
+                    (None, Some(tag)) => {
+                        trie_tagged = dictionary.as_ref().unwrap().tagged(tag).min_len(min_len);
+                        &trie_tagged
+                    }
+                };
+                let extra = sources
+                    .iter()
+                    .map(|(path, _)| load_from::<K>(File::open(path)?, path, &options))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let extra_tagged: Vec<_> = match &tag {
+                    Some(tag) => extra.iter().map(|trie| trie.tagged(tag)).collect(),
+                    None => vec![],
+                };
+                let mut merged = Merged::new().source(source, usize::MAX);
+                for (i, (_, cap)) in sources.iter().enumerate() {
+                    merged = match extra_tagged.get(i) {
+                        Some(tagged) => merged.source(tagged, *cap),
+                        None => merged.source(&extra[i], *cap),
+                    };
+                }
+                let source: &dyn Completer = if sources.is_empty() { source } else { &merged };
+                let completer = Batch {
+                    completer: Normalized {
+                        completer: source,
+                        options: &options,
+                        min_prefix: 0,
+                    },
+                    limit,
+                    min_prefix,
+                    terminator,
+                    free_text: is_free_text::<K>(),
+                    last_token,
+                    with_scores,
+                    schema,
+                };
+                if let Some(path) = batch {
+                    let prefixes = io::BufReader::new(File::open(path)?);
+                    completer.print_batch(prefixes, output, false)?;
+                } else if stdin {
+                    completer.print_batch(io::stdin().lock(), output, true)?;
+                } else if let Some(prefix) = prefix {
+                    // With --exec the completions go to the command instead,
+                    // and it prints what it makes of them.
+                    let mut picker = exec.as_deref().map(spawn_shell).transpose()?;
+                    let target: Box<dyn Write> = match &mut picker {
+                        Some(picker) => {
+                            Box::new(UntilClosed(picker.stdin.take().expect("stdin is piped")))
+                        }
+                        None => Box::new(io::stdout().lock()),
+                    };
+                    if quiet || count_only {
+                        // Like grep -q and grep -c, the exit code tells
+                        // whether anything matched.
+                        let count = completer.complete(&prefix, limit).len();
+                        if count_only {
+                            writeln!(io::stdout(), "{}", count)?;
+                        }
+                        if count == 0 {
+                            exit(1);
+                        }
+                    } else if let Some(template) = template {
+                        let mut out = io::BufWriter::new(target);
+                        let head = completer.split(&prefix).0;
+                        for suggestion in completer.complete(&prefix, limit) {
+                            let word = options.query(&suggestion.word[head.len()..]);
+                            let info = match (&dictionary, &sharded) {
+                                (Some(trie), _) => trie.get(&word),
+                                (_, Some(sharded)) => sharded.get(&word),
+                                _ => None,
+                            };
+                            let fields = Fields {
+                                word: &suggestion.word,
+                                score: suggestion.weight.to_string(),
+                                weight: suggestion.weight,
+                                tags: info.map_or(&[], |info| &info.tags),
+                                query: &prefix,
+                            };
+                            template.write(&mut out, &fields)?;
+                            write!(out, "{}", terminator)?;
+                        }
+                        out.flush()?;
+                    } else if output == Output::Text {
+                        let mut out = io::BufWriter::new(target);
+                        for suggestion in completer.complete(&prefix, limit) {
+                            completer.write_line(&mut out, &suggestion)?;
+                        }
+                        out.flush()?;
+                    } else {
+                        let mut out = target;
+                        completer.write_json(&mut out, &prefix, output)?;
+                    }
+                    // Its stdin is closed by now, telling it the list is
+                    // whole. A picker left without a pick fails, and so do we,
+                    // with its exit code.
+                    if let Some(mut picker) = picker {
+                        let status = picker.wait()?;
+                        if !status.success() {
+                            exit(status.code().unwrap_or(1));
+                        }
+                    }
+                }
+                #[cfg(feature = "scripting")]
+                if let Some(err) = script.as_ref().and_then(ScriptScorer::error) {
+                    return Err(Error::Script(format!("{}: {}", score_script.unwrap(), err)));
+                }
+            }
+            "contains" => {
+                let trie = load::<K>(&options)?;
+                let mut args: Vec<String> = args.by_ref().collect();
+                let quiet = take_switch(&mut args, "--quiet");
+                let json = take_switch(&mut args, "--json");
+                let stdin = take_switch(&mut args, "--stdin");
+                if let ([word], false, false) = (&args[..], json, stdin) {
+                    let found = trie.contains(&options.query(word));
+                    if !quiet {
+                        writeln!(io::stdout(), "{}", found)?;
+                    }
+                    if !found {
+                        exit(1);
+                    }
+                    return Ok(());
+                }
+                // Several words get a line each, telling which is which, and
+                // the exit code tells whether all of them were found.
+                let mut words: Box<dyn Iterator<Item = io::Result<String>>> =
+                    Box::new(args.into_iter().map(Ok));
+                if stdin {
+                    words = Box::new(words.chain(io::stdin().lock().lines()));
+                }
+                let mut all_found = true;
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                for word in words {
+                    let word = word?;
+                    let found = trie.contains(&options.query(&word));
+                    all_found &= found;
+                    if quiet {
+                        continue;
+                    }
+                    if json {
+                        let record = serde_json::json!({ "word": word, "found": found });
+                        writeln!(out, "{}", record)?;
+                    } else {
+                        writeln!(out, "{}\t{}", word, found)?;
+                    }
+                }
+                out.flush()?;
+                if !all_found {
+                    exit(1);
+                }
+            }
+            "verify" => {
+                let mut path = None;
+                let mut json = false;
+                for arg in args.by_ref() {
+                    match arg.as_str() {
+                        "--json" => json = true,
+                        _ => path = Some(arg),
+                    }
+                }
+                let Some(path) = path else {
+                    return Err(Error::Usage("verify expects a word file.".to_string()));
+                };
+                let text = fs::read_to_string(&path)?;
+                let trie = load::<K>(&options)?;
+                let lines: Vec<Cow<str>> = text
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(|line| options.query(line))
+                    .collect();
+                let words: Vec<&str> = lines.iter().map(|line| &**line).collect();
+                let found = trie.contains_many(&words);
+                // Every word missed, with how often, in order of first
+                // appearance.
+                let mut misses: Vec<(&str, usize)> = vec![];
+                let mut seen: HashMap<&str, usize> = HashMap::new();
+                for (&word, _) in words.iter().zip(&found).filter(|(_, found)| !**found) {
+                    match seen.get(word) {
+                        Some(&i) => misses[i].1 += 1,
+                        None => {
+                            seen.insert(word, misses.len());
+                            misses.push((word, 1));
+                        }
+                    }
                 }
+                let hits = found.iter().filter(|&&found| found).count();
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                if json {
+                    let missing: Vec<_> = misses
+                        .iter()
+                        .map(|(word, count)| serde_json::json!({ "word": word, "count": count }))
+                        .collect();
+                    let summary = serde_json::json!({
+                        "path": path,
+                        "words": words.len(),
+                        "found": hits,
+                        "missing": missing,
+                    });
+                    writeln!(out, "{}", summary)?;
+                } else {
+                    for (word, count) in &misses {
+                        writeln!(out, "{}\t{}", word, count)?;
+                    }
+                }
+                out.flush()?;
+                if !json {
+                    writeln!(
+                        io::stderr(),
+                        "{}: {} of {} words found, {} distinct missing",
+                        path,
+                        hits,
+                        words.len(),
+                        misses.len()
+                    )?;
+                }
+                if !misses.is_empty() {
+                    exit(1);
+                }
+            }
+            "starts-with" => {
+                let trie = load::<K>(&options)?;
+                let mut args: Vec<String> = args.by_ref().collect();
+                let quiet = take_switch(&mut args, "--quiet");
+                if let Some(prefix) = args.first() {
+                    let found = trie.starts_with(&options.query_prefix(prefix));
+                    if !quiet {
+                        writeln!(io::stdout(), "{}", found)?;
+                    }
+                    if !found {
+                        exit(1);
+                    }
+                }
+            }
+            "suggest" => {
+                let mut word = None;
+                let mut shards = 1;
+                let mut template = None;
+                let mut max_dist = 2.0;
+                let mut limit = 10;
+                let mut ranking = Ranking::default();
+                let mut costs = Costs::default();
+                let mut terminator = '\n';
+                let mut deadline = None;
+                let mut explain = false;
+                let mut show_plan = false;
+                let mut output = Output::Text;
+                let mut with_scores = false;
+                let mut schema = None;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--output" => output = Output::parse(&flag_value(&mut args, "--output")?)?,
+                        "--with-scores" => with_scores = true,
+                        "--schema-version" => schema = Some(schema_version(&mut args)?),
+                        "--max-dist" => max_dist = parse_flag(&mut args, "--max-dist")?,
+                        "--explain" => explain = true,
+                        "--plan" => show_plan = true,
+                        "--shards" => shards = parse_flag(&mut args, "--shards")?,
+                        "--min-len" => costs.min_len = parse_flag(&mut args, "--min-len")?,
+                        "--template" => template = Some(flag_value(&mut args, "--template")?),
+                        "--limit" => limit = parse_flag(&mut args, "--limit")?,
+                        "-0" | "--null" => terminator = '\0',
+                        "--timeout-ms" => {
+                            let ms = parse_flag(&mut args, "--timeout-ms")?;
+                            deadline = Some(Instant::now() + Duration::from_millis(ms));
+                        }
+                        "--distance-weight" => {
+                            ranking.distance_weight = parse_flag(&mut args, "--distance-weight")?
+                        }
+                        "--frequency-weight" => {
+                            ranking.frequency_weight = parse_flag(&mut args, "--frequency-weight")?
+                        }
+                        "--min-score" => ranking.min_score = parse_flag(&mut args, "--min-score")?,
+                        "--layout" => {
+                            let name = flag_value(&mut args, "--layout")?;
+                            if name == "none" {
+                                costs.keyboard = None;
+                            } else if let Some(keyboard) = Keyboard::layout(&name) {
+                                costs.keyboard = Some(keyboard);
+                            } else {
+                                return Err(Error::Usage(format!(
+                                    "unknown keyboard layout: {}",
+                                    name
+                                )));
+                            }
+                        }
+                        "--adjacent-cost" => {
+                            costs.adjacent_cost = parse_flag(&mut args, "--adjacent-cost")?
+                        }
+                        _ => word = Some(arg),
+                    }
+                }
+                if shards == 0 {
+                    return Err(Error::Usage("--shards needs a positive count.".to_string()));
+                }
+                if schema.is_some() && (output == Output::Text || with_scores) {
+                    return Err(Error::Usage("--schema-version needs --output json or jsonl, and can't be combined with --with-scores.".to_string()));
+                }
+                if template.is_some() && output != Output::Text {
+                    return Err(Error::Usage(
+                        "--template can't be combined with JSON output.".to_string(),
+                    ));
+                }
+                if template.is_some() && with_scores {
+                    return Err(Error::Usage(
+                        "--with-scores can't be combined with --template, which has {score}."
+                            .to_string(),
+                    ));
+                }
+                let template = parse_template(template)?;
+                let sharded = load_sharded::<K>(&options, shards)?;
+                if let Some(text) = word {
+                    let (head, word) = if is_free_text::<K>() {
+                        last_word(&text)
+                    } else {
+                        ("", &text[..])
+                    };
+                    let word = options.query(word);
+                    let query = format!("{}{}", head, word);
+                    if show_plan {
+                        let plan = sharded
+                            .shards()
+                            .iter()
+                            .map(|shard| fuzzy::plan(shard, &word, max_dist, &costs))
+                            .reduce(Plan::merge)
+                            .expect("at least one shard");
+                        let mut err = io::stderr().lock();
+                        write!(err, "{}", plan)?;
+                        if deadline.is_some() {
+                            writeln!(err, "prune: whatever is left at the --timeout-ms deadline")?;
+                        }
+                    }
+                    let start = Instant::now();
+                    let (mut matches, truncated, stats) =
+                        sharded.search_explained(&word, max_dist, &costs, deadline);
+                    if truncated {
+                        writeln!(
+                            io::stderr(),
+                            "WARNING: search timed out, suggestions may be incomplete"
+                        )?;
+                    }
+                    let searched = Instant::now();
+                    let found = matches.len();
+                    ranking.rank(&mut matches);
+                    if explain {
+                        let mut err = io::stderr().lock();
+                        writeln!(
+                            err,
+                            "search: {} nodes visited, {} branches pruned, {} matches in {:?}",
+                            stats.nodes_visited,
+                            stats.branches_pruned,
+                            found,
+                            searched - start
+                        )?;
+                        writeln!(
+                            err,
+                            "ranking: {} of {} matches kept in {:?}",
+                            matches.len().min(limit),
+                            found,
+                            searched.elapsed()
+                        )?;
+                    }
+                    // A search cut short by the deadline may have missed
+                    // some, too.
+                    let truncated = truncated || matches.len() > limit;
+                    matches.truncate(limit);
+                    let mut out = io::BufWriter::new(io::stdout().lock());
+                    match output {
+                        _ if schema.is_some() => {
+                            let suggestions = matches
+                                .iter()
+                                .map(|m| {
+                                    let mut suggestion = response::suggestion(&Suggestion {
+                                        word: format!("{}{}", head, m.word),
+                                        weight: m.weight,
+                                    });
+                                    suggestion["distance"] = serde_json::json!(m.distance);
+                                    suggestion["score"] = serde_json::json!(ranking.score(m));
+                                    suggestion
+                                })
+                                .collect();
+                            let envelope =
+                                response::envelope(schema.unwrap(), &query, suggestions, truncated);
+                            writeln!(out, "{}", envelope)?;
+                        }
+                        Output::Json => {
+                            let words: Vec<_> = matches
+                                .iter()
+                                .map(|m| {
+                                    let word = format!("{}{}", head, m.word);
+                                    if with_scores {
+                                        serde_json::json!({
+                                            "word": word,
+                                            "score": ranking.score(m),
+                                        })
+                                    } else {
+                                        serde_json::json!(word)
+                                    }
+                                })
+                                .collect();
+                            let record =
+                                serde_json::json!({ "query": query, "suggestions": words });
+                            writeln!(out, "{}", record)?;
+                        }
+                        Output::Jsonl => {
+                            for (rank, m) in matches.iter().enumerate() {
+                                let record = serde_json::json!({
+                                    "query": query,
+                                    "rank": rank,
+                                    "word": format!("{}{}", head, m.word),
+                                    "distance": m.distance,
+                                    "score": ranking.score(m),
+                                    "weight": m.weight,
+                                });
+                                writeln!(out, "{}", record)?;
+                            }
+                        }
+                        Output::Text => {
+                            for m in &matches {
+                                match &template {
+                                    Some(template) => {
+                                        let info = sharded.get(&m.word);
+                                        let fields = Fields {
+                                            word: &format!("{}{}", head, m.word),
+                                            score: format!("{:.3}", ranking.score(m)),
+                                            weight: m.weight,
+                                            tags: info.map_or(&[], |info| &info.tags),
+                                            query: &query,
+                                        };
+                                        template.write(&mut out, &fields)?;
+                                    }
+                                    None if with_scores => {
+                                        write!(out, "{}{}\t{:.3}", head, m.word, ranking.score(m))?
+                                    }
+                                    None => write!(out, "{}{}", head, m.word)?,
+                                }
+                                write!(out, "{}", terminator)?;
+                            }
+                        }
+                    }
+                    out.flush()?;
+                }
+            }
+            "lsp" => {
+                let mut limit = 50;
+                let mut min_prefix = 0;
+                let mut cache = 0;
+                let mut grace = GRACE;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--grace" => grace = Duration::from_secs(parse_flag(&mut args, "--grace")?),
+                        "--limit" => limit = parse_flag(&mut args, "--limit")?,
+                        "--min-prefix" => min_prefix = parse_flag(&mut args, "--min-prefix")?,
+                        "--cache" => cache = parse_flag(&mut args, "--cache")?,
+                        _ => {
+                            return Err(Error::Usage(format!(
+                                "unknown {} option: {}",
+                                subcommand, arg
+                            )))
+                        }
+                    }
+                }
+                let trie = Reloading::new(load::<K>(&options)?, &options, cache)?;
+                let completer = Normalized {
+                    completer: &trie,
+                    options: &options,
+                    min_prefix,
+                };
+                let output = Draining {
+                    output: io::stdout().lock(),
+                    shutdown: drain_on_terminate(grace)?,
+                    in_flight: None,
+                };
+                let shutdown = output.shutdown.clone();
+                let served = lsp::serve(io::stdin().lock(), output, &completer, limit);
+                // Ended by the shutdown rather than failing.
+                if !shutdown.requested() {
+                    served?;
+                }
+            }
+            "nvim" => {
+                let mut limit = 50;
+                let mut min_prefix = 0;
+                let mut cache = 0;
+                let mut grace = GRACE;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--grace" => grace = Duration::from_secs(parse_flag(&mut args, "--grace")?),
+                        "--limit" => limit = parse_flag(&mut args, "--limit")?,
+                        "--min-prefix" => min_prefix = parse_flag(&mut args, "--min-prefix")?,
+                        "--cache" => cache = parse_flag(&mut args, "--cache")?,
+                        _ => {
+                            return Err(Error::Usage(format!(
+                                "unknown {} option: {}",
+                                subcommand, arg
+                            )))
+                        }
+                    }
+                }
+                let trie = Reloading::new(load::<K>(&options)?, &options, cache)?;
+                let completer = Normalized {
+                    completer: &trie,
+                    options: &options,
+                    min_prefix,
+                };
+                let output = Draining {
+                    output: io::stdout().lock(),
+                    shutdown: drain_on_terminate(grace)?,
+                    in_flight: None,
+                };
+                let shutdown = output.shutdown.clone();
+                let served = nvim::serve(io::stdin().lock(), output, &completer, limit);
+                if !shutdown.requested() {
+                    served?;
+                }
+            }
+            #[cfg(feature = "graphql")]
+            "graphql" => {
+                let mut listen = "127.0.0.1:8080".to_string();
+                let mut tenants = vec![Tenant::new("", options.clone())];
+                let (mut cert, mut key) = (None, None);
+                let mut grace = GRACE;
+                let mut progressive = false;
+                while let Some(arg) = args.next() {
+                    let tenant = tenants.last_mut().expect("the top namespace");
+                    match arg.as_str() {
+                        "--progressive" => progressive = true,
+                        "--grace" => grace = Duration::from_secs(parse_flag(&mut args, "--grace")?),
+                        "--auth-token" => {
+                            tenant.token = Some(flag_value(&mut args, "--auth-token")?)
+                        }
+                        "--listen" => listen = flag_value(&mut args, "--listen")?,
+                        "--limit" => tenant.limit = parse_flag(&mut args, "--limit")?,
+                        "--min-prefix" => {
+                            tenant.min_prefix = parse_flag(&mut args, "--min-prefix")?
+                        }
+                        "--cache" => tenant.cache = parse_flag(&mut args, "--cache")?,
+                        "--namespace" => {
+                            let value = flag_value(&mut args, "--namespace")?;
+                            let Some((name, dictionary)) = value.split_once('=') else {
+                                return Err(Error::Usage(format!(
+                                    "--namespace takes <name>=<dictionary>, not {}",
+                                    value
+                                )));
+                            };
+                            if name.is_empty() || name.contains(['/', '?']) {
+                                return Err(Error::Usage(format!(
+                                    "invalid namespace name: {:?}",
+                                    name
+                                )));
+                            }
+                            if tenants.iter().any(|tenant| tenant.name == name) {
+                                return Err(Error::Usage(format!(
+                                    "namespace {} given twice",
+                                    name
+                                )));
+                            }
+                            // Picks only ever weigh the words they were made in.
+                            let mut options = options.clone();
+                            options.dict = Some(dictionary.to_string());
+                            options.history_log = format!("{}.history.log", name);
+                            tenants.push(Tenant::new(name, options));
+                        }
+                        "--tls-cert" => cert = Some(flag_value(&mut args, "--tls-cert")?),
+                        "--tls-key" => key = Some(flag_value(&mut args, "--tls-key")?),
+                        _ => return Err(Error::Usage(format!("unknown graphql option: {}", arg))),
+                    }
+                }
+                let listener = server_socket(&listen)?;
+                let address = listener.local_addr()?;
+                let (listener, scheme) = match (cert, key) {
+                    (None, None) => (listener, "http"),
+                    #[cfg(feature = "tls")]
+                    (Some(cert), Some(key)) => {
+                        let config = tls::config(Path::new(&cert), Path::new(&key))?;
+                        // The HTTP server takes the connections decrypted.
+                        let backend = TcpListener::bind("127.0.0.1:0")?;
+                        let backend_address = backend.local_addr()?;
+                        thread::spawn(move || {
+                            if let Err(err) = tls::terminate(listener, config, backend_address) {
+                                let _ = writeln!(io::stderr(), "ERROR: {}", err);
+                                exit(7);
+                            }
+                        });
+                        (backend, "https")
+                    }
+                    #[cfg(not(feature = "tls"))]
+                    (Some(_), Some(_)) => {
+                        return Err(Error::Usage(
+                            "--tls-cert needs a build with the tls feature.".to_string(),
+                        ));
+                    }
+                    _ => {
+                        return Err(Error::Usage(
+                            "--tls-cert and --tls-key go together.".to_string(),
+                        ));
+                    }
+                };
+                writeln!(io::stderr(), "serving GraphQL on {}://{}", scheme, address)?;
+                // A namespace without a token of its own takes the top one's.
+                let token = auth_token(tenants[0].token.take());
+                for tenant in &mut tenants {
+                    tenant.token = tenant.token.take().or_else(|| token.clone());
+                    let reloads = match tenant.name.as_str() {
+                        "" => "reloads at /reload".to_string(),
+                        name => format!("reloads at /t/{}/reload", name),
+                    };
+                    report_auth(&reloads, tenant.token.as_deref())?;
+                }
+                // Listening already, so /healthz answers while it loads.
+                let load = || -> Result<_, Error> {
+                    let mut namespaces = BTreeMap::new();
+                    let mut words = 0;
+                    for tenant in &tenants {
+                        let options = tenant.options.clone();
+                        let served = if progressive {
+                            let partial = load_progressive::<K>(&options)?;
+                            Served::progressive(partial, options, tenant.cache, tenant.min_prefix)?
+                        } else {
+                            let trie = load::<K>(&options)?;
+                            words += trie.len();
+                            let trie = Reloading::new(trie, &options, tenant.cache)?;
+                            Served::new(trie, options, tenant.min_prefix)
+                        };
+                        let namespace = graphql::Namespace {
+                            dictionary: served,
+                            max_limit: tenant.limit,
+                            token: tenant.token.clone(),
+                        };
+                        namespaces.insert(tenant.name.clone(), namespace);
+                    }
+                    let status = match progressive {
+                        true => "serving while the shards are built".to_string(),
+                        false => format!("serving {} words", words),
+                    };
+                    notify(&format!("READY=1\nSTATUS={}", status))?;
+                    Ok(namespaces)
+                };
+                let shutdown = drain_on_terminate(grace)?;
+                graphql::serve(listener, load, &shutdown)?;
+            }
+            #[cfg(not(feature = "graphql"))]
+            "graphql" => {
+                return Err(Error::Usage(
+                    "graphql needs a build with the graphql feature.".to_string(),
+                ));
+            }
+            "resp" => {
+                let mut listen = "127.0.0.1:6380".to_string();
+                let mut limit = 50;
+                let mut min_prefix = 0;
+                let mut snapshot = None;
+                let mut token = None;
+                let mut grace = GRACE;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--grace" => grace = Duration::from_secs(parse_flag(&mut args, "--grace")?),
+                        "--auth-token" => token = Some(flag_value(&mut args, "--auth-token")?),
+                        "--listen" => listen = flag_value(&mut args, "--listen")?,
+                        "--limit" => limit = parse_flag(&mut args, "--limit")?,
+                        "--min-prefix" => min_prefix = parse_flag(&mut args, "--min-prefix")?,
+                        "--snapshot" => {
+                            snapshot = Some(PathBuf::from(flag_value(&mut args, "--snapshot")?))
+                        }
+                        _ => return Err(Error::Usage(format!("unknown resp option: {}", arg))),
+                    }
+                }
+                // A snapshot taken before a restart holds the words added
+                // since the dictionary was loaded, so it stands in for it.
+                let restore = snapshot.as_ref().is_some_and(|path| path.exists());
+                let live = Live {
+                    trie: RwLock::new(match restore {
+                        true => Trie::new(),
+                        false => load::<K>(&options)?,
+                    }),
+                    options: options.clone(),
+                    min_prefix,
+                    snapshot,
+                };
+                if restore {
+                    let words = live.restore()?;
+                    let path = live.snapshot_path()?.display();
+                    writeln!(io::stderr(), "restored {} words from {}", words, path)?;
+                }
+                let listener = server_socket(&listen)?;
+                writeln!(io::stderr(), "serving RESP on {}", listener.local_addr()?)?;
+                let words = live.trie.read().unwrap().len();
+                notify(&format!("READY=1\nSTATUS=serving {} words", words))?;
+                let token = auth_token(token);
+                report_auth(
+                    "PT.ADD, PT.DEL, PT.SHRINK, PT.SNAPSHOT and PT.RESTORE",
+                    token.as_deref(),
+                )?;
+                let shutdown = drain_on_terminate(grace)?;
+                resp::serve(listener, &live, limit, token.as_deref(), &shutdown)?;
+            }
+            "repl" => {
+                let copy = args.any(|arg| arg == "--copy");
+                if copy && cfg!(not(feature = "clipboard")) {
+                    return Err(Error::Usage(
+                        "--copy needs a build with the clipboard feature.".to_string(),
+                    ));
+                }
+                let trie = load::<K>(&options)?;
+                repl(&trie, &options, copy)?;
+            }
+            "random" => {
+                let mut count = 1;
+                let mut prefix = String::new();
+                let mut weighted = false;
+                let mut min_len = 0;
+                let mut max_len = usize::MAX;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--count" => count = parse_flag(&mut args, "--count")?,
+                        "--prefix" => prefix = flag_value(&mut args, "--prefix")?,
+                        "--weighted" => weighted = true,
+                        "--min-len" => min_len = parse_flag(&mut args, "--min-len")?,
+                        "--max-len" => max_len = parse_flag(&mut args, "--max-len")?,
+                        _ => return Err(Error::Usage(format!("unknown random option: {}", arg))),
+                    }
+                }
+                let trie = load::<K>(&options)?;
+                let prefix = options.query_prefix(&prefix);
+                let mut rng = rand::rng();
+                if !weighted && min_len == 0 && max_len == usize::MAX {
+                    // Uniform draws need no weights, only the subtree counts.
+                    let words = trie.count_prefix(&prefix);
+                    let draws = if words == 0 { 0 } else { count };
+                    for _ in 0..draws {
+                        let word = trie
+                            .select(rng.random_range(0..words), &prefix)
+                            .expect("drawn below the count");
+                        writeln!(io::stdout(), "{}", word)?;
+                    }
+                } else {
+                    let sampler = Sampler::with_weights(&trie, &prefix, |word, info| {
+                        if !(min_len..=max_len).contains(&word.chars().count()) {
+                            0.0
+                        } else if weighted {
+                            info.weight as f64
+                        } else {
+                            1.0
+                        }
+                    });
+                    for _ in 0..count {
+                        let Some((word, _)) = sampler.sample(&mut rng) else {
+                            break;
+                        };
+                        writeln!(io::stdout(), "{}", word)?;
+                    }
+                }
+            }
+            "bench" => {
+                let mut bench = Bench {
+                    lookups: 100_000,
+                    prefixes: 1_000,
+                    seed: 0,
+                };
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--lookups" => bench.lookups = parse_flag(&mut args, "--lookups")?,
+                        "--prefixes" => bench.prefixes = parse_flag(&mut args, "--prefixes")?,
+                        "--seed" => bench.seed = parse_flag(&mut args, "--seed")?,
+                        _ => return Err(Error::Usage(format!("unknown bench option: {}", arg))),
+                    }
+                }
+                let trie = load::<K>(&options)?;
+                let words = trie.iter().map(|(word, _)| word).collect();
+                drop(trie);
+                bench.run::<K>(words)?;
+            }
+            "gen" => {
+                let mut synthetic = Synthetic {
+                    words: 1000,
+                    alphabet: parse_alphabet("a-z"),
+                    zipf: None,
+                    min_len: 1,
+                    max_len: 12,
+                };
+                let mut seed = None;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--words" => {
+                            let value = flag_value(&mut args, "--words")?;
+                            let Some(words) = parse_count(&value) else {
+                                return Err(Error::Usage(format!(
+                                    "invalid value for --words: {}",
+                                    value
+                                )));
+                            };
+                            synthetic.words = words;
+                        }
+                        "--alphabet" => {
+                            synthetic.alphabet =
+                                parse_alphabet(&flag_value(&mut args, "--alphabet")?)
+                        }
+                        "--zipf" => synthetic.zipf = Some(parse_flag(&mut args, "--zipf")?),
+                        "--min-len" => synthetic.min_len = parse_flag(&mut args, "--min-len")?,
+                        "--max-len" => synthetic.max_len = parse_flag(&mut args, "--max-len")?,
+                        "--seed" => seed = Some(parse_flag(&mut args, "--seed")?),
+                        _ => return Err(Error::Usage(format!("unknown gen option: {}", arg))),
+                    }
+                }
+                if synthetic.alphabet.is_empty()
+                    || synthetic.min_len == 0
+                    || synthetic.min_len > synthetic.max_len
+                {
+                    return Err(Error::Usage(
+                        "gen needs a non-empty alphabet and 0 < --min-len <= --max-len."
+                            .to_string(),
+                    ));
+                }
+                // The same seed always generates the same dictionary.
+                let seed = seed.unwrap_or_else(|| rand::rng().random());
+                let mut rng = StdRng::seed_from_u64(seed);
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                synthetic.write(&mut out, &mut rng)?;
+                out.flush()?;
+            }
+            "passphrase" => {
+                let trie = load::<K>(&options)?;
+                let mut words = 6;
+                let mut separator = String::from(" ");
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--words" => words = parse_flag(&mut args, "--words")?,
+                        "--separator" => separator = flag_value(&mut args, "--separator")?,
+                        _ => {
+                            return Err(Error::Usage(format!("unknown passphrase option: {}", arg)))
+                        }
+                    }
+                }
+                // Uniform draws from the thread-local generator, which is
+                // cryptographically secure, so every word adds log2(n) bits.
+                // Blank words would add nothing to see to the passphrase.
+                let sampler = Sampler::with_weights(&trie, "", |word, _| {
+                    if word.trim().is_empty() {
+                        0.0
+                    } else {
+                        1.0
+                    }
+                });
+                if sampler.is_empty() {
+                    return Err(Error::DictionaryMissing(
+                        "the dictionary has no words to draw a passphrase from.".to_string(),
+                    ));
+                }
+                let mut rng = rand::rng();
+                let passphrase: Vec<_> = (0..words)
+                    .filter_map(|_| sampler.sample(&mut rng).map(|(word, _)| word))
+                    .collect();
+                writeln!(io::stdout(), "{}", passphrase.join(&separator))?;
+                writeln!(
+                    io::stderr(),
+                    "entropy: {:.1} bits ({} words from {} dictionary words)",
+                    passphrase.len() as f64 * (sampler.len() as f64).log2(),
+                    passphrase.len(),
+                    sampler.len()
+                )?;
+            }
+            "add" => {
+                let mut word = None;
+                let mut weight = None;
+                let mut ttl = None;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--ttl" => ttl = Some(parse_flag::<u64>(&mut args, "--ttl")?),
+                        _ if word.is_none() => word = Some(arg),
+                        _ => match arg.parse() {
+                            Ok(parsed) => weight = Some(parsed),
+                            Err(_) => {
+                                return Err(Error::Usage(format!("invalid weight: {}", arg)));
+                            }
+                        },
+                    }
+                }
+                let Some(word) = word.filter(|word| !word.is_empty()) else {
+                    return Err(Error::Usage("add expects a word.".to_string()));
+                };
+                if word.contains(['\t', '\n']) {
+                    return Err(Error::Usage(
+                        "a word can't contain tabs or newlines.".to_string(),
+                    ));
+                }
+                let path = options.dictionary_file()?;
+                let entry = Entry {
+                    word: &word,
+                    weight,
+                    expires: ttl.map(|ttl| unix_now().saturating_add(ttl)),
+                    ..Entry::default()
+                };
+                let mut line = vec![];
+                write_entry(&mut line, &entry)?;
+                let mut file = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                file.write_all(&line)?;
+            }
+            "clean" => {
+                let mut path = None;
+                let mut output = None;
+                let mut check = false;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--output" => output = Some(flag_value(&mut args, "--output")?),
+                        "--check" => check = true,
+                        _ => path = Some(arg),
+                    }
+                }
+                let path = match path {
+                    Some(path) => path,
+                    None => options.dictionary_file()?,
+                };
+                let text = match fs::read(&path) {
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                        return Err(Error::DictionaryMissing(format!("{} not found.", path)));
+                    }
+                    result => result?,
+                };
+                let lines = dictionary_lines(&text[..], &path).collect::<Result<Vec<_>, _>>()?;
+                let mut canonical = vec![];
+                let cleaned = clean_dictionary(&lines, options.fold_case, &mut canonical)?;
+                let changed = canonical != text;
+                writeln!(io::stderr(), "{}: {}", path, cleaned)?;
+                if check {
+                    if changed {
+                        exit(1);
+                    }
+                    return Ok(());
+                }
+                match output.as_deref() {
+                    Some("-") => io::stdout().write_all(&canonical)?,
+                    Some(output) => fs::write(output, canonical)?,
+                    None if changed => {
+                        // Written beside the dictionary and renamed, so a
+                        // load running meanwhile never reads it half written.
+                        let path = PathBuf::from(path);
+                        let partial = path.with_extension("partial");
+                        fs::write(&partial, canonical)?;
+                        fs::rename(partial, path)?;
+                    }
+                    None => {}
+                }
+            }
+            "feedback" => {
+                let (Some(prefix), Some(word)) = (args.next(), args.next()) else {
+                    return Err(Error::Usage(
+                        "feedback expects a prefix and the word picked for it.".to_string(),
+                    ));
+                };
+                let mut log = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&options.history_log)?;
+                // One write per pick, so concurrent frontends don't interleave
+                // their lines.
+                let line = format!("{}\t{}\t{}\n", prefix, word, unix_now());
+                log.write_all(line.as_bytes())?;
+            }
+            "pick" => {
+                let mut word = None;
+                let mut path = None;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--history" => path = Some(flag_value(&mut args, "--history")?),
+                        _ => word = Some(arg),
+                    }
+                }
+                let (Some(word), Some(path)) = (word, path) else {
+                    return Err(Error::Usage(
+                        "pick expects a word and --history <file>.".to_string(),
+                    ));
+                };
+                let mut history = read_history(&path, &options)?;
+                history.pick(&options.query(&word));
+                // Written beside the history and renamed, so a complete
+                // running meanwhile never reads it half written.
+                let path = PathBuf::from(path);
+                let partial = path.with_extension("partial");
+                let mut file = io::BufWriter::new(File::create(&partial)?);
+                history.write(&mut file)?;
+                file.flush()?;
+                drop(file);
+                fs::rename(partial, path)?;
+            }
+            "check-file" => {
+                let mut args: Vec<String> = args.by_ref().collect();
+                let use_bloom = take_switch(&mut args, "--bloom");
+                let Some(path) = args.first() else {
+                    return Ok(());
+                };
+                let text = fs::read_to_string(path)?;
+                let words = words_of(&text);
+                let candidates = match (&options.lang, &options.dict) {
+                    (None, None) => languages()?,
+                    _ => vec![],
+                };
+                let trie = if candidates.is_empty() {
+                    load::<K>(&options)?
+                } else {
+                    // The language whose dictionary knows most of the words.
+                    let mut best: Option<(usize, String, Trie<K>)> = None;
+                    for lang in candidates {
+                        let options = LoadOptions {
+                            lang: Some(lang.clone()),
+                            read: Arc::default(),
+                            ..options.clone()
+                        };
+                        let trie = load::<K>(&options)?;
+                        let known = words
+                            .iter()
+                            .filter(|(_, _, word)| is_known(&trie, None, &options, word))
+                            .count();
+                        if best.as_ref().is_none_or(|(most, _, _)| known > *most) {
+                            best = Some((known, lang, trie));
+                        }
+                    }
+                    let (_, lang, trie) = best.expect("there is a candidate language");
+                    writeln!(io::stderr(), "detected language: {}", lang)?;
+                    trie
+                };
+                let bloom = use_bloom.then(|| trie.bloom(0.01));
+                let mut misspelled = false;
+                let mut out = io::stdout().lock();
+                for (line, column, word) in words {
+                    if !is_known(&trie, bloom.as_ref(), &options, word) {
+                        misspelled = true;
+                        writeln!(out, "{}:{}:{}: {}", path, line, column, word)?;
+                    }
+                }
+                out.flush()?;
+                if misspelled {
+                    exit(1);
+                }
+            }
+            "autocorrect" => {
+                let mut path = None;
+                let mut max_dist = 1.0;
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--max-dist" => max_dist = parse_flag(&mut args, "--max-dist")?,
+                        _ => path = Some(arg),
+                    }
+                }
+                let Some(path) = path else {
+                    return Err(Error::Usage("autocorrect expects a file.".to_string()));
+                };
+                let text = fs::read_to_string(&path)?;
+                let trie = load::<K>(&options)?;
+                // OCR and log noise has nothing to do with keyboards, so every
+                // edit costs the same.
+                let costs = Costs {
+                    keyboard: None,
+                    ..Costs::default()
+                };
+                let (mut corrected, mut ambiguous) = (0, 0);
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                let mut copied = 0;
+                for (_, _, word) in words_of(&text) {
+                    if is_known(&trie, None, &options, word) {
+                        continue;
+                    }
+                    let mut matches = fuzzy::search(
+                        &trie,
+                        &options.query(&word.to_lowercase()),
+                        max_dist,
+                        &costs,
+                    );
+                    let Some(closest) = matches.iter().map(|m| m.distance).reduce(f64::min) else {
+                        continue;
+                    };
+                    matches.retain(|m| m.distance == closest);
+                    let at = word.as_ptr() as usize - text.as_ptr() as usize;
+                    write!(out, "{}", &text[copied..at])?;
+                    copied = at + word.len();
+                    if let [only] = &matches[..] {
+                        corrected += 1;
+                        write!(out, "{}", cased_like(&only.word, word))?;
+                    } else {
+                        // Left for a person to decide, with the candidates.
+                        ambiguous += 1;
+                        matches.sort_by(|a, b| {
+                            b.weight.cmp(&a.weight).then_with(|| a.word.cmp(&b.word))
+                        });
+                        let words: Vec<_> =
+                            matches.iter().map(|m| cased_like(&m.word, word)).collect();
+                        write!(out, "{}[?{}]", word, words.join("|"))?;
+                    }
+                }
+                write!(out, "{}", &text[copied..])?;
+                out.flush()?;
+                writeln!(
+                    io::stderr(),
+                    "{}: {} corrected, {} ambiguous",
+                    path,
+                    corrected,
+                    ambiguous
+                )?;
+            }
+            "sounds-like" => {
+                let mut word = None;
+                let mut algorithm = Algorithm::Metaphone;
+                let mut terminator = '\n';
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "-0" | "--null" => terminator = '\0',
+                        "--algorithm" => {
+                            let name = flag_value(&mut args, "--algorithm")?;
+                            if let Some(a) = Algorithm::from_name(&name) {
+                                algorithm = a;
+                            } else {
+                                return Err(Error::Usage(format!(
+                                    "unknown phonetic algorithm: {}",
+                                    name
+                                )));
+                            }
+                        }
+                        _ if arg.starts_with("--") => {
+                            return Err(Error::Usage(format!(
+                                "unknown sounds-like option: {}",
+                                arg
+                            )))
+                        }
+                        _ => word = Some(arg),
+                    }
+                }
+                let Some(word) = word else {
+                    return Err(Error::Usage("sounds-like expects a word.".to_string()));
+                };
+                let trie = load::<K>(&options)?;
+                let mut index = PhoneticIndex::new(algorithm);
+                trie.for_each_word(|w, _| index.insert(w.to_string()));
+                let mut matches = index.lookup(&word).to_vec();
+                matches.sort();
+                for m in matches {
+                    write!(io::stdout(), "{}{}", m, terminator)?;
+                }
+            }
+            "anagrams" => {
+                let mut letters = None;
+                let mut sub = false;
+                let mut terminator = '\n';
+                for arg in args.by_ref() {
+                    match arg.as_str() {
+                        "--sub" => sub = true,
+                        "-0" | "--null" => terminator = '\0',
+                        _ => letters = Some(arg),
+                    }
+                }
+                let Some(letters) = letters else {
+                    return Err(Error::Usage("anagrams expects letters.".to_string()));
+                };
+                let trie = load::<K>(&options)?;
+                let mut index = AnagramIndex::new();
+                trie.for_each_word(|word, _| index.insert(word.to_string()));
+                let words = if sub {
+                    index.sub_anagrams(&letters)
+                } else {
+                    let mut words = index.anagrams(&letters).to_vec();
+                    words.sort_unstable();
+                    words
+                };
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                for word in &words {
+                    write!(out, "{}{}", word, terminator)?;
+                }
+                out.flush()?;
+            }
+            "solve" => {
+                let mut constraints = Constraints {
+                    green: vec![None; 5],
+                    ..Constraints::default()
+                };
+                let mut limit = usize::MAX;
+                let mut terminator = '\n';
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--green" => {
+                            constraints.green = flag_value(&mut args, "--green")?
+                                .chars()
+                                .map(|letter| (letter != '_').then_some(letter))
+                                .collect();
+                        }
+                        "--yellow" => {
+                            let value = flag_value(&mut args, "--yellow")?;
+                            for clue in value.split(',') {
+                                let mut letters = clue.chars();
+                                let position = match (letters.next(), letters.next()) {
+                                    (Some(letter), Some(':')) => letters
+                                        .as_str()
+                                        .parse::<usize>()
+                                        .ok()
+                                        .filter(|&position| position > 0)
+                                        .map(|position| (letter, position - 1)),
+                                    _ => None,
+                                };
+                                let Some(position) = position else {
+                                    return Err(Error::Usage(format!(
+                                        "invalid value for --yellow: {}",
+                                        clue
+                                    )));
+                                };
+                                constraints.yellow.push(position);
+                            }
+                        }
+                        "--gray" | "--grey" => {
+                            constraints.gray = flag_value(&mut args, &arg)?.chars().collect();
+                        }
+                        "--limit" => limit = parse_flag(&mut args, "--limit")?,
+                        "-0" | "--null" => terminator = '\0',
+                        _ => return Err(Error::Usage(format!("unknown solve option: {}", arg))),
+                    }
+                }
+                let trie = load::<K>(&options)?;
+                let mut out = io::BufWriter::new(io::stdout().lock());
+                for suggestion in wordle::solve(&trie, &constraints).iter().take(limit) {
+                    write!(out, "{}{}", suggestion.word, terminator)?;
+                }
+                out.flush()?;
             }
             _ => {
-                writeln!(io::stderr(), "ERROR: no subcommand found.\n")?;
-                usage(io::stderr())?;
-                exit(1);
+                return Err(Error::Usage("no subcommand found.".to_string()));
             }
         }
     } else {
-        usage(io::stderr())?;
-        writeln!(io::stderr(), "ERROR: no subcommand is provided")?;
-        exit(1);
+        return Err(Error::Usage("no subcommand is provided".to_string()));
     }
 
     Ok(())
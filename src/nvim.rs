@@ -0,0 +1,80 @@
+//! A MessagePack-RPC server for Neovim, so the trie can stay resident in one
+//! process per editor session and feed an omnifunc or completion plugin:
+//!
+//! ```lua
+//! local job = vim.fn.jobstart({ "prefix-tree", "nvim" }, { rpc = true })
+//! local words = vim.rpcrequest(job, "complete", "ap")
+//! ```
+
+use std::io::{self, BufRead, ErrorKind, Write};
+
+use rmpv::{decode, encode, Value};
+
+use crate::complete::Completer;
+
+// MessagePack-RPC message types.
+const REQUEST: u64 = 0;
+const RESPONSE: u64 = 1;
+
+/// Serves requests read from `input` until it is closed. The `complete`
+/// method takes a prefix and an optional limit (`limit` by default) and
+/// returns the completed words, best first.
+pub fn serve(
+    mut input: impl BufRead,
+    mut output: impl Write,
+    completer: &dyn Completer,
+    limit: usize,
+) -> io::Result<()> {
+    loop {
+        let message = match decode::read_value(&mut input) {
+            Ok(message) => message,
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        let Some(message) = message.as_array() else {
+            continue;
+        };
+        // Notifications (type 2) expect no answer and none are supported.
+        let [kind, id, method, params] = message.as_slice() else {
+            continue;
+        };
+        if kind.as_u64() != Some(REQUEST) {
+            continue;
+        }
+        let (error, result) = match handle(method.as_str(), params, completer, limit) {
+            Ok(result) => (Value::Nil, result),
+            Err(error) => (Value::from(error), Value::Nil),
+        };
+        let response = Value::Array(vec![RESPONSE.into(), id.clone(), error, result]);
+        encode::write_value(&mut output, &response)?;
+        output.flush()?;
+    }
+}
+
+fn handle(
+    method: Option<&str>,
+    params: &Value,
+    completer: &dyn Completer,
+    limit: usize,
+) -> Result<Value, String> {
+    let params = params.as_array().map(Vec::as_slice).unwrap_or_default();
+    match method {
+        Some("complete") => {
+            let prefix = params
+                .first()
+                .and_then(Value::as_str)
+                .ok_or("complete expects a prefix")?;
+            let limit = params
+                .get(1)
+                .and_then(Value::as_u64)
+                .map_or(limit, |n| n as usize);
+            let words = completer
+                .complete(prefix, limit)
+                .into_iter()
+                .map(|suggestion| Value::from(suggestion.word))
+                .collect();
+            Ok(Value::Array(words))
+        }
+        _ => Err(format!("unsupported method: {}", method.unwrap_or("?"))),
+    }
+}
@@ -0,0 +1,125 @@
+use std::{cmp::Ordering, collections::hash_map, fmt, sync::Arc};
+
+use crate::{
+    key::TrieKey,
+    trie::{Node, WordInfo},
+};
+
+type Compare<K, V> = dyn Fn((&K, &Node<K, V>), (&K, &Node<K, V>)) -> Ordering + Send + Sync;
+
+/// The order a [`Trie`](crate::Trie) visits the children of a node in, set
+/// with [`Trie::with_child_order`](crate::Trie::with_child_order): key order
+/// by default, or any other a comparator of two children gives, e.g. the
+/// most words first for best-first enumeration, or a locale's order for
+/// display.
+///
+/// ```
+/// use prefix_tree::{ChildOrder, Completer, Trie};
+///
+/// let trie: Trie = ["apple", "banana", "blueberry", "cherry"].into_iter().collect();
+/// let trie = trie.with_child_order(ChildOrder::most_words());
+/// assert_eq!(trie.keys().collect::<Vec<_>>(), ["banana", "blueberry", "apple", "cherry"]);
+/// // Words of equal weight complete in the same order.
+/// assert_eq!(trie.complete("", 1)[0].word, "banana");
+///
+/// let reversed = ChildOrder::by_key(|key: &char, _| std::cmp::Reverse(*key));
+/// let trie = trie.with_child_order(reversed);
+/// assert_eq!(trie.keys().next().as_deref(), Some("cherry"));
+/// ```
+pub struct ChildOrder<K = char, V = WordInfo> {
+    // `None` for key order.
+    compare: Option<Arc<Compare<K, V>>>,
+}
+
+impl<K, V> Clone for ChildOrder<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            compare: self.compare.clone(),
+        }
+    }
+}
+
+impl<K, V> Default for ChildOrder<K, V> {
+    fn default() -> Self {
+        Self::keys()
+    }
+}
+
+impl<K, V> fmt::Debug for ChildOrder<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.compare {
+            None => f.write_str("ChildOrder::keys"),
+            Some(_) => f.write_str("ChildOrder::by(..)"),
+        }
+    }
+}
+
+impl<K, V> ChildOrder<K, V> {
+    /// Children in key order, the default.
+    pub fn keys() -> Self {
+        Self { compare: None }
+    }
+
+    /// Children in the order `compare` gives, between their keys and nodes.
+    pub fn by(
+        compare: impl Fn((&K, &Node<K, V>), (&K, &Node<K, V>)) -> Ordering + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            compare: Some(Arc::new(compare)),
+        }
+    }
+
+    /// Children in the order of what `f` maps them to, e.g. a collation key.
+    pub fn by_key<T: Ord>(f: impl Fn(&K, &Node<K, V>) -> T + Send + Sync + 'static) -> Self {
+        Self::by(move |(a, a_node), (b, b_node)| f(a, a_node).cmp(&f(b, b_node)))
+    }
+
+    /// Whether this is key order.
+    pub fn is_keys(&self) -> bool {
+        self.compare.is_none()
+    }
+}
+
+impl<K: TrieKey, V> ChildOrder<K, V> {
+    /// Children with the most words below them first, in key order among
+    /// equals.
+    pub fn most_words() -> Self {
+        Self::by(|(a, a_node), (b, b_node)| b_node.words.cmp(&a_node.words).then_with(|| a.cmp(b)))
+    }
+
+    /// The children of `node` in this order.
+    pub(crate) fn sorted<'a>(&self, node: &'a Node<K, V>) -> Vec<(&'a K, &'a Node<K, V>)> {
+        let mut children: Vec<_> = node.children.iter().collect();
+        match &self.compare {
+            None => children.sort_by_key(|(key, _)| *key),
+            Some(compare) => children.sort_by(|a, b| compare(*a, *b)),
+        }
+        children
+    }
+
+    /// The children of `node` in this order, or in no particular one for
+    /// key order, which callers that sort what they collect don't need.
+    pub(crate) fn visit<'a>(&self, node: &'a Node<K, V>) -> Children<'a, K, V> {
+        match self.compare {
+            None => Children::Any(node.children.iter()),
+            Some(_) => Children::Sorted(self.sorted(node).into_iter()),
+        }
+    }
+}
+
+/// See [`ChildOrder::visit`].
+pub(crate) enum Children<'a, K, V> {
+    Any(hash_map::Iter<'a, K, Node<K, V>>),
+    Sorted(std::vec::IntoIter<(&'a K, &'a Node<K, V>)>),
+}
+
+impl<'a, K, V> Iterator for Children<'a, K, V> {
+    type Item = (&'a K, &'a Node<K, V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Any(children) => children.next(),
+            Self::Sorted(children) => children.next(),
+        }
+    }
+}
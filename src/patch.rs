@@ -0,0 +1,111 @@
+//! Deltas between two indexes, so an index that is already deployed can be
+//! brought up to date by shipping only the words that changed.
+//!
+//! A patch lists its operations sorted by word, in the index's number and
+//! string encoding:
+//!
+//! ```text
+//! magic "PTD1" | word count of the old index | op count | ops...
+//! op: 0 | word                                   (removed)
+//!     1 | word | weight | tag count | tags...    (added or changed)
+//! ```
+
+use std::io::{self, Write};
+
+use crate::index::{invalid, write_bytes, write_entries, write_varint, Index, IndexEntry, Reader};
+
+pub const MAGIC: &[u8; 4] = b"PTD1";
+
+const REMOVE: u64 = 0;
+const UPSERT: u64 = 1;
+
+/// Writes the patch turning `old` into `new`.
+pub fn write_patch<W: Write>(out: &mut W, old: &Index, new: &Index) -> io::Result<()> {
+    let old = old.entries()?;
+    let new = new.entries()?;
+    let mut ops = Vec::new();
+    let mut count = 0;
+    let (mut i, mut j) = (0, 0);
+    loop {
+        let (op, entry) = match (old.get(i), new.get(j)) {
+            (Some(a), Some(b)) if a.word == b.word => {
+                i += 1;
+                j += 1;
+                if a == b {
+                    continue;
+                }
+                (UPSERT, b)
+            }
+            (Some(a), Some(b)) if a.word < b.word => {
+                i += 1;
+                (REMOVE, a)
+            }
+            (Some(a), None) => {
+                i += 1;
+                (REMOVE, a)
+            }
+            (_, Some(b)) => {
+                j += 1;
+                (UPSERT, b)
+            }
+            (None, None) => break,
+        };
+        count += 1;
+        write_varint(&mut ops, op);
+        write_bytes(&mut ops, entry.word.as_bytes());
+        if op == UPSERT {
+            write_varint(&mut ops, entry.weight);
+            write_varint(&mut ops, entry.tags.len() as u64);
+            for tag in &entry.tags {
+                write_bytes(&mut ops, tag.as_bytes());
+            }
+        }
+    }
+
+    let mut buf = Vec::from(&MAGIC[..]);
+    write_varint(&mut buf, old.len() as u64);
+    write_varint(&mut buf, count);
+    buf.extend_from_slice(&ops);
+    out.write_all(&buf)
+}
+
+/// Applies a patch written by [`write_patch`] to `old`, writing the updated
+/// index.
+pub fn apply_patch<W: Write>(out: &mut W, old: &Index, patch: &[u8]) -> io::Result<()> {
+    if !patch.starts_with(MAGIC) {
+        return Err(invalid("not a prefix-tree patch"));
+    }
+    let mut reader = Reader::new(patch, MAGIC.len());
+    let entries = old.entries()?;
+    if reader.varint()? != entries.len() as u64 {
+        return Err(invalid("patch was made for a different index"));
+    }
+    let mut words = Vec::with_capacity(entries.len());
+    let mut entries = entries.into_iter().peekable();
+    for _ in 0..reader.varint()? {
+        let op = reader.varint()?;
+        let word = reader.str()?;
+        while let Some(entry) = entries.next_if(|entry| entry.word.as_str() < word) {
+            words.push(entry);
+        }
+        // The entry being replaced or removed.
+        entries.next_if(|entry| entry.word == word);
+        match op {
+            REMOVE => {}
+            UPSERT => {
+                let weight = reader.varint()?;
+                let tags = (0..reader.varint()?)
+                    .map(|_| reader.str())
+                    .collect::<io::Result<_>>()?;
+                words.push(IndexEntry {
+                    word: word.to_string(),
+                    weight,
+                    tags,
+                });
+            }
+            _ => return Err(invalid("corrupt patch")),
+        }
+    }
+    words.extend(entries);
+    write_entries(out, &words)
+}
@@ -0,0 +1,239 @@
+//! Wildcard queries: the words matching a pattern where `?` stands for any
+//! one key and `*` for any run of keys, possibly none, so `ap?l*` finds
+//! "apple" and "applesauce" but not "aplomb".
+//!
+//! The pattern is run as an automaton alongside a walk of the trie: a node
+//! is entered only while some position of the pattern can still be reached,
+//! and the keys before the first wildcard are looked up directly.
+//!
+//! [`fill`] solves crossword-style patterns instead, where `_` stands for an
+//! unknown letter and the length of the word is fixed.
+
+use crate::{
+    key::TrieKey,
+    plan::{self, Plan, Strategy},
+    trie::{Node, Trie},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<K> {
+    Key(K),
+    One,
+    Any,
+}
+
+fn parse<K: TrieKey>(pattern: &str) -> Vec<Token<K>> {
+    K::segment(pattern)
+        .into_iter()
+        .map(|key| match key.as_char() {
+            Some('?') => Token::One,
+            Some('*') => Token::Any,
+            _ => Token::Key(key),
+        })
+        .collect()
+}
+
+/// The words of `trie` matching `pattern`, in sorted order.
+///
+/// ```
+/// use prefix_tree::{pattern, Trie};
+///
+/// let trie: Trie = ["aplomb", "apple", "applesauce", "apply"].into_iter().collect();
+/// assert_eq!(pattern::matches(&trie, "ap?l*"), ["apple", "applesauce", "apply"]);
+/// assert_eq!(pattern::matches(&trie, "*e"), ["apple", "applesauce"]);
+/// ```
+pub fn matches<K: TrieKey>(trie: &Trie<K>, pattern: &str) -> Vec<String> {
+    let tokens = parse::<K>(pattern);
+    let mut states = vec![0];
+    closure(&tokens, &mut states);
+    let mut path = vec![];
+    let mut found = vec![];
+    walk(&trie.root, &tokens, &states, &mut path, &mut found);
+    found.sort_unstable();
+    found
+}
+
+/// What [`matches`] for `pattern` would do, without matching.
+///
+/// ```
+/// use prefix_tree::{pattern, plan::Strategy, Trie};
+///
+/// let trie: Trie = ["apple", "apply", "banana"].into_iter().collect();
+/// assert_eq!(pattern::plan(&trie, "ap?l?").strategy, Strategy::Automaton);
+/// assert_eq!(pattern::plan(&trie, "*a").strategy, Strategy::FullScan);
+/// ```
+pub fn plan<K: TrieKey>(trie: &Trie<K>, pattern: &str) -> Plan {
+    let tokens = parse::<K>(pattern);
+    let literal: Vec<K> = tokens
+        .iter()
+        .map_while(|token| match token {
+            Token::Key(key) => Some(key.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut prunes = vec![];
+    if !literal.is_empty() {
+        prunes.push(format!(
+            "descends straight to the {:?} the pattern starts with",
+            K::join(&literal)
+        ));
+    }
+    let Some(node) = trie.get_node(&K::join(&literal)) else {
+        prunes.push("no word starts that way: nothing else is visited".to_string());
+        return Plan {
+            strategy: Strategy::Automaton,
+            visits: literal.len(),
+            capped: false,
+            prunes,
+        };
+    };
+    let rest = &tokens[literal.len()..];
+    let depth = if rest.contains(&Token::Any) {
+        usize::MAX
+    } else {
+        rest.len()
+    };
+    // After a leading `*`, every branch can still match.
+    let keys = rest.iter().any(|token| matches!(token, Token::Key(_)));
+    if keys && rest.first() != Some(&Token::Any) {
+        prunes.push("branches whose keys no position of the pattern accepts".to_string());
+    }
+    let reach = plan::reach(node, depth);
+    if reach.cut {
+        prunes.push(format!("nothing deeper than {} keys", tokens.len()));
+    }
+    Plan {
+        strategy: if literal.is_empty() && depth == usize::MAX {
+            Strategy::FullScan
+        } else {
+            Strategy::Automaton
+        },
+        visits: literal.len() + reach.nodes,
+        capped: reach.capped,
+        prunes,
+    }
+}
+
+/// The words of `trie` spelling out `pattern`, in sorted order, where each
+/// `_` is an unknown letter and every other key is fixed. Only the branches
+/// spelling the fixed keys at their positions are walked, and none deeper
+/// than the pattern.
+///
+/// ```
+/// use prefix_tree::{pattern, Trie};
+///
+/// let trie: Trie = ["crossword", "cross", "crossroad", "b-side"].into_iter().collect();
+/// assert_eq!(pattern::fill(&trie, "c__ss_o_d"), ["crossroad", "crossword"]);
+/// assert!(pattern::fill(&trie, "b_side").is_empty());
+/// ```
+pub fn fill<K: TrieKey>(trie: &Trie<K>, pattern: &str) -> Vec<String> {
+    let slots: Vec<Option<K>> = K::segment(pattern)
+        .into_iter()
+        .map(|key| (key.as_char() != Some('_')).then_some(key))
+        .collect();
+    let mut path = vec![];
+    let mut found = vec![];
+    fill_from(&trie.root, &slots, &mut path, &mut found);
+    found.sort_unstable();
+    found
+}
+
+fn fill_from<'a, K: TrieKey>(
+    root: &'a Node<K>,
+    slots: &[Option<K>],
+    path: &mut Vec<K>,
+    found: &mut Vec<String>,
+) {
+    // The children of `node` that can fill `slot`.
+    let candidates = |node: &'a Node<K>, slot: &Option<K>| -> Vec<(&'a K, &'a Node<K>)> {
+        match slot {
+            Some(key) => node.children.get_key_value(key).into_iter().collect(),
+            None => node
+                .children
+                .iter()
+                .filter(|(key, _)| key.as_char().is_some_and(char::is_alphabetic))
+                .collect(),
+        }
+    };
+    let Some(first) = slots.first() else {
+        if root.value.is_some() {
+            found.push(K::join(path));
+        }
+        return;
+    };
+    let base = path.len();
+    // One level per slot filled, with the candidates still to try for it.
+    let mut stack = vec![candidates(root, first).into_iter()];
+    while let Some(level) = stack.last_mut() {
+        let Some((key, child)) = level.next() else {
+            stack.pop();
+            continue;
+        };
+        path.truncate(base + stack.len() - 1);
+        path.push(key.clone());
+        match slots.get(stack.len()) {
+            Some(slot) => stack.push(candidates(child, slot).into_iter()),
+            None if child.value.is_some() => found.push(K::join(path)),
+            None => {}
+        }
+    }
+    path.truncate(base);
+}
+
+// Adds the positions reachable from `states` by skipping `*`s, which match
+// nothing as well.
+fn closure<K: TrieKey>(tokens: &[Token<K>], states: &mut Vec<usize>) {
+    let mut i = 0;
+    while i < states.len() {
+        let state = states[i];
+        if tokens.get(state) == Some(&Token::Any) && !states.contains(&(state + 1)) {
+            states.push(state + 1);
+        }
+        i += 1;
+    }
+}
+
+fn walk<K: TrieKey>(
+    root: &Node<K>,
+    tokens: &[Token<K>],
+    states: &[usize],
+    path: &mut Vec<K>,
+    found: &mut Vec<String>,
+) {
+    if root.value.is_some() && states.contains(&tokens.len()) {
+        found.push(K::join(path));
+    }
+    let base = path.len();
+    // The children of each node on the path still to try, with the
+    // pattern positions the node is at.
+    let mut stack = vec![(root.children.iter(), states.to_vec())];
+    while let Some((children, states)) = stack.last_mut() {
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        let mut next = vec![];
+        for &state in states.iter() {
+            let to = match tokens.get(state) {
+                Some(Token::Key(expected)) if expected == key => state + 1,
+                Some(Token::One) => state + 1,
+                Some(Token::Any) => state,
+                _ => continue,
+            };
+            if !next.contains(&to) {
+                next.push(to);
+            }
+        }
+        if next.is_empty() {
+            continue;
+        }
+        closure(tokens, &mut next);
+        path.truncate(base + stack.len() - 1);
+        path.push(key.clone());
+        if child.value.is_some() && next.contains(&tokens.len()) {
+            found.push(K::join(path));
+        }
+        stack.push((child.children.iter(), next));
+    }
+    path.truncate(base);
+}
@@ -0,0 +1,301 @@
+use std::{collections::hash_map, sync::Arc};
+
+use crate::{
+    complete::{Completer, Suggestion},
+    key::TrieKey,
+    trie::{Node, Trie, WordInfo},
+};
+
+/// An immutable trie whose updates return a new version, leaving the old one
+/// untouched.
+///
+/// A new version copies only the nodes on the path to the changed word and
+/// shares every other subtree with the version it came from, so keeping old
+/// versions around for undo or speculative edits is cheap, and so is
+/// cloning.
+///
+/// ```
+/// use prefix_tree::{Completer, PersistentTrie, WordInfo};
+///
+/// let empty: PersistentTrie = PersistentTrie::new();
+/// let before = empty.insert("apple", WordInfo::default());
+/// let after = before.insert("apricot", WordInfo::default());
+/// assert!(!before.contains("apricot"));
+/// assert_eq!(after.complete("ap", 10).len(), 2);
+/// assert_eq!(after.remove("apricot").len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct PersistentTrie<K = char, V = WordInfo> {
+    root: Arc<PersistentNode<K, V>>,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct PersistentNode<K, V> {
+    value: Option<V>,
+    // Sorted by key.
+    children: Vec<(K, Arc<PersistentNode<K, V>>)>,
+}
+
+// Dropping the last version holding a node would drop its children before
+// it, one stack frame per key of the longest word. The children no other
+// version shares are taken apart on a stack of our own instead.
+impl<K, V> Drop for PersistentNode<K, V> {
+    fn drop(&mut self) {
+        let mut pending = std::mem::take(&mut self.children);
+        while let Some((_, child)) = pending.pop() {
+            if let Some(mut child) = Arc::into_inner(child) {
+                pending.append(&mut child.children);
+            }
+        }
+    }
+}
+
+impl<K, V> Default for PersistentNode<K, V> {
+    fn default() -> Self {
+        Self {
+            value: None,
+            children: Vec::new(),
+        }
+    }
+}
+
+// Cloning a version only shares its root, so no bounds on `K` or `V`.
+impl<K, V> Clone for PersistentTrie<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            root: Arc::clone(&self.root),
+            len: self.len,
+        }
+    }
+}
+
+impl<K: TrieKey, V> Default for PersistentTrie<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: TrieKey, V> PersistentTrie<K, V> {
+    pub fn new() -> Self {
+        Self {
+            root: Arc::default(),
+            len: 0,
+        }
+    }
+
+    /// The number of words stored.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, word: &str) -> Option<&V> {
+        self.find(word)?.value.as_ref()
+    }
+
+    /// Whether `word` itself is stored, see [`Trie::contains`].
+    pub fn contains(&self, word: &str) -> bool {
+        self.get(word).is_some()
+    }
+
+    /// Whether any stored word starts with `prefix`.
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        // Removals prune emptied branches, so every node leads to a word.
+        self.find(prefix).is_some()
+    }
+
+    /// Calls `f` with every word and its value, in key order.
+    pub fn for_each_word(&self, mut f: impl FnMut(&str, &V)) {
+        for_each_word(&self.root, &mut Vec::new(), &mut f);
+    }
+
+    fn find(&self, text: &str) -> Option<&PersistentNode<K, V>> {
+        let mut node = &*self.root;
+        for key in K::segment(text) {
+            let i = node.children.binary_search_by(|(k, _)| k.cmp(&key)).ok()?;
+            node = &node.children[i].1;
+        }
+        Some(node)
+    }
+}
+
+impl<K: TrieKey, V: Clone> PersistentTrie<K, V> {
+    /// A version with `value` stored under `word`.
+    pub fn insert(&self, word: &str, value: V) -> Self {
+        let (root, added) = inserted(&self.root, &K::segment(word), value);
+        Self {
+            root: Arc::new(root),
+            len: self.len + usize::from(added),
+        }
+    }
+
+    /// A version without `word`, pruning the branches it leaves empty.
+    pub fn remove(&self, word: &str) -> Self {
+        match removed(&self.root, &K::segment(word)) {
+            Some(root) => Self {
+                root: Arc::new(root),
+                len: self.len - 1,
+            },
+            None => self.clone(),
+        }
+    }
+}
+
+impl<K: TrieKey, V> From<Trie<K, V>> for PersistentTrie<K, V> {
+    fn from(trie: Trie<K, V>) -> Self {
+        Self {
+            len: trie.len(),
+            root: Arc::new(convert(trie.root)),
+        }
+    }
+}
+
+impl<K: TrieKey> Completer for PersistentTrie<K> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        let Some(node) = self.find(prefix) else {
+            return vec![];
+        };
+        let mut suggestions = vec![];
+        for_each_word(node, &mut K::segment(prefix), &mut |word, info| {
+            if info.expired() {
+                return;
+            }
+            suggestions.push(Suggestion {
+                word: info.display.as_deref().unwrap_or(word).to_string(),
+                weight: info.weight,
+            });
+        });
+        suggestions.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.word.cmp(&b.word)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+fn for_each_word<K: TrieKey, V>(
+    node: &PersistentNode<K, V>,
+    buffer: &mut Vec<K>,
+    f: &mut impl FnMut(&str, &V),
+) {
+    if let Some(value) = &node.value {
+        f(&K::join(buffer), value);
+    }
+    let base = buffer.len();
+    let mut stack = vec![node.children.iter()];
+    while let Some(children) = stack.last_mut() {
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        buffer.truncate(base + stack.len() - 1);
+        buffer.push(key.clone());
+        if let Some(value) = &child.value {
+            f(&K::join(buffer), value);
+        }
+        stack.push(child.children.iter());
+    }
+    buffer.truncate(base);
+}
+
+// A copy of `root` with `value` stored at `keys` below it, and whether the
+// word is new.
+fn inserted<K: TrieKey, V: Clone>(
+    root: &PersistentNode<K, V>,
+    keys: &[K],
+    value: V,
+) -> (PersistentNode<K, V>, bool) {
+    // Copies of the nodes on the path, each with where the next one goes.
+    let mut path = Vec::with_capacity(keys.len());
+    let mut node = Some(root);
+    for key in keys {
+        let copy = node.map_or_else(PersistentNode::default, PersistentNode::clone);
+        let slot = copy.children.binary_search_by(|(k, _)| k.cmp(key));
+        node = node.zip(slot.ok()).map(|(node, i)| &*node.children[i].1);
+        path.push((copy, slot));
+    }
+    let mut node = node.map_or_else(PersistentNode::default, PersistentNode::clone);
+    let added = node.value.is_none();
+    node.value = Some(value);
+    for ((mut parent, slot), key) in path.into_iter().zip(keys).rev() {
+        let child = Arc::new(node);
+        match slot {
+            Ok(i) => parent.children[i].1 = child,
+            Err(i) => parent.children.insert(i, (key.clone(), child)),
+        }
+        node = parent;
+    }
+    (node, added)
+}
+
+// A copy of `root` without the word at `keys` below it, or `None` if no such
+// word is stored.
+fn removed<K: TrieKey, V: Clone>(
+    root: &PersistentNode<K, V>,
+    keys: &[K],
+) -> Option<PersistentNode<K, V>> {
+    let mut path = Vec::with_capacity(keys.len());
+    let mut node = root;
+    for key in keys {
+        let i = node.children.binary_search_by(|(k, _)| k.cmp(key)).ok()?;
+        path.push((node, i));
+        node = &node.children[i].1;
+    }
+    node.value.as_ref()?;
+    let mut node = PersistentNode {
+        value: None,
+        children: node.children.clone(),
+    };
+    for (parent, i) in path.into_iter().rev() {
+        let mut copy = parent.clone();
+        if node.value.is_none() && node.children.is_empty() {
+            copy.children.remove(i);
+        } else {
+            copy.children[i].1 = Arc::new(node);
+        }
+        node = copy;
+    }
+    Some(node)
+}
+
+fn convert<K: TrieKey, V>(root: Node<K, V>) -> PersistentNode<K, V> {
+    // A node being converted: its key, its value, the children still to
+    // convert and the ones converted.
+    struct Frame<K, V> {
+        key: Option<K>,
+        value: Option<V>,
+        pending: hash_map::IntoIter<K, Node<K, V>>,
+        children: Vec<(K, Arc<PersistentNode<K, V>>)>,
+    }
+    let frame = |key, node: Node<K, V>| {
+        let (value, children) = node.into_parts();
+        Frame {
+            key,
+            value,
+            children: Vec::with_capacity(children.len()),
+            pending: children.into_iter(),
+        }
+    };
+    let mut stack = vec![frame(None, root)];
+    loop {
+        let top = stack.last_mut().expect("the root is converted last");
+        if let Some((key, child)) = top.pending.next() {
+            stack.push(frame(Some(key), child));
+            continue;
+        }
+        let Frame {
+            key,
+            value,
+            mut children,
+            ..
+        } = stack.pop().expect("the root is converted last");
+        children.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let node = PersistentNode { value, children };
+        match (key, stack.last_mut()) {
+            (Some(key), Some(parent)) => parent.children.push((key, Arc::new(node))),
+            _ => return node,
+        }
+    }
+}
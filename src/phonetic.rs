@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Algorithm {
+    Soundex,
+    Metaphone,
+}
+
+impl Algorithm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "soundex" => Some(Self::Soundex),
+            "metaphone" => Some(Self::Metaphone),
+            _ => None,
+        }
+    }
+
+    pub fn encode(self, word: &str) -> String {
+        match self {
+            Self::Soundex => soundex(word),
+            Self::Metaphone => metaphone(word),
+        }
+    }
+}
+
+/// Maps phonetic codes to the dictionary words sharing them.
+pub struct PhoneticIndex {
+    algorithm: Algorithm,
+    codes: HashMap<String, Vec<String>>,
+}
+
+impl PhoneticIndex {
+    pub fn new(algorithm: Algorithm) -> Self {
+        Self {
+            algorithm,
+            codes: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, word: String) {
+        let code = self.algorithm.encode(&word);
+        if !code.is_empty() {
+            self.codes.entry(code).or_default().push(word);
+        }
+    }
+
+    pub fn lookup(&self, word: &str) -> &[String] {
+        self.codes
+            .get(&self.algorithm.encode(word))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+fn letters(word: &str) -> Vec<char> {
+    word.chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'A' | 'E' | 'I' | 'O' | 'U')
+}
+
+pub fn soundex(word: &str) -> String {
+    fn digit(c: char) -> Option<char> {
+        match c {
+            'B' | 'F' | 'P' | 'V' => Some('1'),
+            'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+            'D' | 'T' => Some('3'),
+            'L' => Some('4'),
+            'M' | 'N' => Some('5'),
+            'R' => Some('6'),
+            _ => None,
+        }
+    }
+
+    let letters = letters(word);
+    let Some(&first) = letters.first() else {
+        return String::new();
+    };
+    let mut code = String::from(first);
+    let mut last = digit(first);
+    for &c in &letters[1..] {
+        let d = digit(c);
+        if d.is_some() && d != last {
+            code.extend(d);
+            if code.len() == 4 {
+                break;
+            }
+        }
+        // H and W don't separate letters with the same code, vowels do.
+        if c != 'H' && c != 'W' {
+            last = d;
+        }
+    }
+    while code.len() < 4 {
+        code.push('0');
+    }
+    code
+}
+
+/// Lawrence Philips' original Metaphone, which, unlike Soundex, knows about
+/// silent letters: "nite" and "night" both encode to "NT".
+pub fn metaphone(word: &str) -> String {
+    let mut w = letters(word);
+    match w.as_slice() {
+        ['A', 'E', ..] | ['G', 'N', ..] | ['K', 'N', ..] | ['P', 'N', ..] | ['W', 'R', ..] => {
+            w.remove(0);
+        }
+        ['X', ..] => w[0] = 'S',
+        ['W', 'H', ..] => {
+            w.remove(1);
+        }
+        _ => {}
+    }
+
+    let at = |i: usize| w.get(i).copied().unwrap_or('\0');
+    let mut code = String::new();
+    for i in 0..w.len() {
+        let c = w[i];
+        let prev = if i > 0 { at(i - 1) } else { '\0' };
+        let next = at(i + 1);
+        let after = at(i + 2);
+        if c == prev && c != 'C' {
+            continue;
+        }
+        match c {
+            'A' | 'E' | 'I' | 'O' | 'U' => {
+                if i == 0 {
+                    code.push(c);
+                }
+            }
+            'B' => {
+                if !(prev == 'M' && i + 1 == w.len()) {
+                    code.push('B');
+                }
+            }
+            'C' => {
+                if next == 'I' && after == 'A' {
+                    code.push('X');
+                } else if next == 'H' {
+                    code.push(if prev == 'S' { 'K' } else { 'X' });
+                } else if matches!(next, 'I' | 'E' | 'Y') {
+                    if prev != 'S' {
+                        code.push('S');
+                    }
+                } else {
+                    code.push('K');
+                }
+            }
+            'D' => {
+                if next == 'G' && matches!(after, 'E' | 'Y' | 'I') {
+                    code.push('J');
+                } else {
+                    code.push('T');
+                }
+            }
+            'G' => {
+                let silent_gh = next == 'H' && i + 2 < w.len() && !is_vowel(after);
+                let silent_gn = next == 'N'
+                    && (i + 2 == w.len() || (after == 'E' && at(i + 3) == 'D' && i + 4 == w.len()));
+                if silent_gh || silent_gn {
+                    continue;
+                }
+                if matches!(next, 'I' | 'E' | 'Y') && prev != 'G' {
+                    code.push('J');
+                } else {
+                    code.push('K');
+                }
+            }
+            'H' => {
+                let after_digraph = matches!(prev, 'C' | 'S' | 'P' | 'T' | 'G');
+                let silent_after_vowel = is_vowel(prev) && !is_vowel(next);
+                if !after_digraph && !silent_after_vowel {
+                    code.push('H');
+                }
+            }
+            'K' => {
+                if prev != 'C' {
+                    code.push('K');
+                }
+            }
+            'P' => code.push(if next == 'H' { 'F' } else { 'P' }),
+            'Q' => code.push('K'),
+            'S' => {
+                if next == 'H' || (next == 'I' && matches!(after, 'O' | 'A')) {
+                    code.push('X');
+                } else {
+                    code.push('S');
+                }
+            }
+            'T' => {
+                if next == 'I' && matches!(after, 'O' | 'A') {
+                    code.push('X');
+                } else if next == 'H' {
+                    code.push('0');
+                } else if !(next == 'C' && after == 'H') {
+                    code.push('T');
+                }
+            }
+            'V' => code.push('F'),
+            'W' | 'Y' => {
+                if is_vowel(next) {
+                    code.push(c);
+                }
+            }
+            'X' => code.push_str("KS"),
+            'Z' => code.push('S'),
+            _ => code.push(c),
+        }
+    }
+    code
+}
@@ -0,0 +1,112 @@
+//! What a fuzzy or wildcard query is about to do, worked out from the
+//! trie's shape without running it, so `--max-dist` and patterns can be tuned
+//! before a slow query is paid for.
+
+use std::fmt;
+
+use crate::{key::TrieKey, trie::Node};
+
+// Nodes counted at most when estimating visits, so that planning stays cheap
+// however large the query.
+const BUDGET: usize = 100_000;
+
+/// How a query walks the trie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Steps an automaton of the query along the trie's edges, leaving every
+    /// branch it can't follow.
+    Automaton,
+    /// Computes a row of the edit-distance matrix per node, leaving every
+    /// subtree whose row is out of reach.
+    Traversal,
+    /// Has to look at every node: nothing about the query narrows the walk.
+    FullScan,
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Strategy::Automaton => "automaton intersection",
+            Strategy::Traversal => "edit-distance traversal",
+            Strategy::FullScan => "full scan",
+        })
+    }
+}
+
+/// The plan of a query, see [`crate::fuzzy::plan`] and
+/// [`crate::pattern::plan`]. Displayed, it is a line per fact:
+///
+/// ```text
+/// strategy: edit-distance traversal
+/// visits: at most 5210 nodes
+/// prune: subtrees with no prefix within 2 edits of "aple"
+/// ```
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub strategy: Strategy,
+    /// The most nodes the query can visit.
+    pub visits: usize,
+    /// The estimate stopped counting at `visits`, and the query may visit
+    /// more.
+    pub capped: bool,
+    /// The ways the walk is cut short, in words.
+    pub prunes: Vec<String>,
+}
+
+impl Plan {
+    /// The plan of the same query on two tries, as over the shards of a
+    /// [`crate::Sharded`] dictionary.
+    pub fn merge(mut self, other: Plan) -> Plan {
+        self.visits += other.visits;
+        self.capped |= other.capped;
+        if other.strategy == Strategy::FullScan {
+            self.strategy = Strategy::FullScan;
+        }
+        self
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "strategy: {}", self.strategy)?;
+        if self.capped {
+            writeln!(f, "visits: more than {} nodes", self.visits)?;
+        } else {
+            writeln!(f, "visits: at most {} nodes", self.visits)?;
+        }
+        for prune in &self.prunes {
+            writeln!(f, "prune: {}", prune)?;
+        }
+        Ok(())
+    }
+}
+
+/// Nodes at most `depth` keys below `node`, `node` included, with whether
+/// the depth left any out and whether the count ran out of budget.
+pub(crate) struct Reach {
+    pub nodes: usize,
+    pub cut: bool,
+    pub capped: bool,
+}
+
+pub(crate) fn reach<K: TrieKey, V>(node: &Node<K, V>, depth: usize) -> Reach {
+    let mut reach = Reach {
+        nodes: 0,
+        cut: false,
+        capped: false,
+    };
+    let mut stack = vec![(node, 0)];
+    while let Some((node, level)) = stack.pop() {
+        if reach.nodes == BUDGET {
+            reach.capped = true;
+            break;
+        }
+        reach.nodes += 1;
+        if level == depth {
+            reach.cut |= !node.children.is_empty();
+            continue;
+        }
+        stack.extend(node.children.values().map(|child| (child, level + 1)));
+    }
+    reach
+}
@@ -0,0 +1,217 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+};
+
+use crate::{
+    complete::{Completer, Suggestion},
+    key::TrieKey,
+    trie::{Trie, WordInfo},
+};
+
+/// A dictionary split by first character across tries built in the
+/// background, which answers a query as soon as the shard it needs is built
+/// rather than once they all are.
+///
+/// A query waiting on a shard has it built next, ahead of the others, so the
+/// first characters clients type are the first available. An empty prefix
+/// needs every shard. A build that panics leaves its shard empty.
+///
+/// ```
+/// use prefix_tree::{Completer, Progressive, Trie};
+///
+/// let shards = [('a', vec!["apple", "apricot"]), ('b', vec!["banana"])];
+/// let progressive: Progressive = Progressive::spawn(shards, |words| words.into_iter().collect(), 1);
+/// assert_eq!(progressive.complete("ap", 10).len(), 2);
+/// assert!(progressive.contains("banana"));
+/// assert!(progressive.complete("c", 10).is_empty());
+///
+/// let trie: Trie = progressive.into_trie();
+/// assert_eq!(trie.len(), 3);
+/// ```
+pub struct Progressive<K = char> {
+    shared: Arc<Shared<K>>,
+}
+
+impl<K> Clone for Progressive<K> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+struct Shared<K> {
+    // The shard of the words starting with each character.
+    shards: HashMap<char, usize>,
+    state: Mutex<State<K>>,
+    built: Condvar,
+}
+
+type Build<K> = Box<dyn FnOnce() -> Trie<K> + Send>;
+
+struct State<K> {
+    tries: Vec<Option<Arc<Trie<K>>>>,
+    // What builds each shard not started on yet.
+    pending: Vec<Option<Build<K>>>,
+    // Shards queries wait on, in the order they started to, built before the
+    // others.
+    wanted: VecDeque<usize>,
+}
+
+impl<K: TrieKey + Send + Sync + 'static> Progressive<K> {
+    /// Starts building a shard for each character of `shards`, from the
+    /// words starting with it, with `build`, on `threads` threads. The empty
+    /// word, if stored, is in the shard of `'\0'`.
+    pub fn spawn<T: Send + 'static>(
+        shards: impl IntoIterator<Item = (char, T)>,
+        build: impl Fn(T) -> Trie<K> + Send + Sync + 'static,
+        threads: usize,
+    ) -> Self {
+        let build = Arc::new(build);
+        let mut index = HashMap::new();
+        let mut pending: Vec<Option<Build<K>>> = vec![];
+        for (first, words) in shards {
+            let build = Arc::clone(&build);
+            index.insert(first, pending.len());
+            pending.push(Some(Box::new(move || build(words))));
+        }
+        let shared = Arc::new(Shared {
+            shards: index,
+            state: Mutex::new(State {
+                tries: (0..pending.len()).map(|_| None).collect(),
+                pending,
+                wanted: VecDeque::new(),
+            }),
+            built: Condvar::new(),
+        });
+        for _ in 0..threads.max(1) {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || shared.work());
+        }
+        Self { shared }
+    }
+}
+
+impl<K: TrieKey> Shared<K> {
+    // Builds shards, wanted ones first, until none is left to start on.
+    fn work(&self) {
+        loop {
+            let (shard, build) = {
+                let mut state = self.state.lock().unwrap();
+                let wanted = state.wanted.pop_front();
+                let next = wanted.or_else(|| state.pending.iter().position(Option::is_some));
+                let Some(shard) = next else {
+                    return;
+                };
+                match state.pending[shard].take() {
+                    Some(build) => (shard, build),
+                    // Started already, on behalf of another query.
+                    None => continue,
+                }
+            };
+            let trie = panic::catch_unwind(AssertUnwindSafe(build)).unwrap_or_default();
+            self.state.lock().unwrap().tries[shard] = Some(Arc::new(trie));
+            self.built.notify_all();
+        }
+    }
+
+    // The trie of `shard`, once built, which is `urgent` to build next if
+    // not started on yet.
+    fn wait(&self, shard: usize, urgent: bool) -> Arc<Trie<K>> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(trie) = &state.tries[shard] {
+                return Arc::clone(trie);
+            }
+            if urgent && state.pending[shard].is_some() && !state.wanted.contains(&shard) {
+                state.wanted.push_back(shard);
+            }
+            state = self.built.wait(state).unwrap();
+        }
+    }
+}
+
+impl<K: TrieKey> Progressive<K> {
+    // The trie of the shard storing the words starting with `prefix`,
+    // waiting for it to be built, or `None` if no word does.
+    fn shard_of(&self, prefix: &str) -> Option<Arc<Trie<K>>> {
+        let first = prefix.chars().next().unwrap_or('\0');
+        let shard = *self.shared.shards.get(&first)?;
+        Some(self.shared.wait(shard, true))
+    }
+
+    // Every shard's trie, waiting for those not built yet, without getting
+    // ahead of the queries of a single one.
+    fn all(&self) -> Vec<Arc<Trie<K>>> {
+        (0..self.shared.shards.len())
+            .map(|shard| self.shared.wait(shard, false))
+            .collect()
+    }
+
+    /// How many shards are built, of how many.
+    pub fn built(&self) -> (usize, usize) {
+        let state = self.shared.state.lock().unwrap();
+        let built = state.tries.iter().filter(|trie| trie.is_some()).count();
+        (built, state.tries.len())
+    }
+
+    /// The number of words stored in the shards built so far.
+    pub fn len(&self) -> usize {
+        let state = self.shared.state.lock().unwrap();
+        state.tries.iter().flatten().map(|trie| trie.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The info of `word`, waiting for its shard.
+    pub fn get(&self, word: &str) -> Option<WordInfo> {
+        self.shard_of(word)?.get(word).cloned()
+    }
+
+    /// Whether `word` itself is stored, waiting for its shard.
+    pub fn contains(&self, word: &str) -> bool {
+        self.shard_of(word).is_some_and(|trie| trie.contains(word))
+    }
+
+    /// Waits for every shard and merges them into a single trie. Shards
+    /// other clones still share are copied.
+    pub fn into_trie(self) -> Trie<K> {
+        let mut shards = self.all();
+        // The last clone holds the only other reference to each, which the
+        // state lets go of.
+        if let Ok(shared) = Arc::try_unwrap(self.shared) {
+            drop(shards);
+            let state = shared.state.into_inner().unwrap();
+            shards = state.tries.into_iter().flatten().collect();
+        }
+        let mut trie = Trie::new();
+        for shard in shards {
+            trie.merge(Arc::unwrap_or_clone(shard));
+        }
+        trie
+    }
+}
+
+impl<K: TrieKey> Completer for Progressive<K> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        if !prefix.is_empty() {
+            return self
+                .shard_of(prefix)
+                .map_or_else(Vec::new, |trie| trie.complete(prefix, limit));
+        }
+        // No word is in two shards, as in `Sharded`.
+        let mut suggestions: Vec<Suggestion> = self
+            .all()
+            .iter()
+            .flat_map(|trie| trie.complete(prefix, limit))
+            .collect();
+        suggestions.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.word.cmp(&b.word)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
@@ -0,0 +1,84 @@
+//! Tab completion for line editors built on `rustyline`, backed by any
+//! [`Completer`]: the prefix-tree REPL uses it, and so can other CLIs
+//! embedding the library.
+
+use rustyline::{
+    completion, highlight::Highlighter, hint::Hinter, validate::Validator, Context, Helper,
+};
+
+use crate::complete::Completer;
+
+/// A `rustyline` helper completing the word before the cursor.
+pub struct ReadlineHelper<'a> {
+    completer: &'a dyn Completer,
+    commands: &'a [&'a str],
+    limit: usize,
+}
+
+impl<'a> ReadlineHelper<'a> {
+    pub fn new(completer: &'a dyn Completer) -> Self {
+        Self {
+            completer,
+            commands: &[],
+            limit: 100,
+        }
+    }
+
+    /// Completes the first word of a line from `commands` rather than from
+    /// the completer, for command-style prompts.
+    pub fn commands(mut self, commands: &'a [&'a str]) -> Self {
+        self.commands = commands;
+        self
+    }
+
+    /// Maximum number of candidates offered at once.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl completion::Completer for ReadlineHelper<'_> {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        let word_len: usize = before
+            .chars()
+            .rev()
+            .take_while(|ch| !ch.is_whitespace())
+            .map(char::len_utf8)
+            .sum();
+        let start = pos - word_len;
+        let word = &before[start..];
+        let candidates = if before[..start].trim().is_empty() && !self.commands.is_empty() {
+            self.commands
+                .iter()
+                .filter(|command| command.starts_with(word))
+                .map(|command| format!("{} ", command))
+                .collect()
+        } else {
+            self.completer
+                .complete(word, self.limit)
+                .into_iter()
+                .map(|suggestion| suggestion.word)
+                .collect()
+        };
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReadlineHelper<'_> {
+    type Hint = String;
+}
+
+impl Highlighter for ReadlineHelper<'_> {}
+
+impl Validator for ReadlineHelper<'_> {}
+
+impl Helper for ReadlineHelper<'_> {}
@@ -0,0 +1,442 @@
+//! A server speaking a minimal subset of the Redis protocol (RESP), so any
+//! Redis client library can query a resident trie:
+//!
+//! ```text
+//! $ redis-cli -p 6380 PT.ADD apricot 3
+//! (integer) 1
+//! $ redis-cli -p 6380 PT.COMPLETE ap 2
+//! 1) "apple"
+//! 2) "apricot"
+//! ```
+//!
+//! `PT.COMPLETE prefix [limit] [WITHWEIGHTS]` answers the completed words,
+//! best first, each followed by its weight with `WITHWEIGHTS`,
+//! `PT.ADD word [weight] [EX seconds]` stores a word, forgotten after
+//! `seconds` if given, and answers 1 if it is new, `PT.DEL word` removes a
+//! word and answers 1 if it was stored,
+//! `PT.EXISTS word` answers whether a word is stored, and `PT.SHRINK` drops
+//! expired words, hands back memory the trie no longer uses and answers about
+//! how many bytes. `PING`, `QUIT`, and the `COMMAND` and `CLIENT` calls
+//! clients make on connecting, are understood as well.
+//!
+//! Words added are kept in memory, so a store that can, see
+//! [`Store::snapshot`], writes them all to its snapshot file on
+//! `PT.SNAPSHOT` and reads them back on `PT.RESTORE`, answering how many.
+//! The file is the store's to choose, never the client's.
+//!
+//! Given a token, the server only takes `PT.ADD`, `PT.DEL`, `PT.SHRINK`,
+//! `PT.SNAPSHOT` and `PT.RESTORE` from connections that sent it with
+//! `AUTH token` first, as Redis clients do when configured with a password;
+//! reads stay open to everyone.
+//!
+//! Once a [`Shutdown`] is requested, new connections are dropped, and each
+//! command read is refused with an error ending its connection, while those
+//! in flight are answered.
+
+use std::{
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::TcpListener,
+    sync::RwLock,
+    thread,
+};
+
+use crate::{
+    auth::token_matches,
+    complete::{Completer, Suggestion},
+    key::TrieKey,
+    shutdown::Shutdown,
+    trie::{now, Entry, Trie, WordInfo},
+};
+
+// Longest bulk string accepted in a command, and longest line: an inline
+// command, or the header of an array or a bulk string.
+const MAX_BULK: usize = 1 << 20;
+
+// Most strings in a command, as Redis takes, and most bytes in them all,
+// so no client has the server buffer more than that of one command.
+const MAX_MULTIBULK: usize = 1024 * 1024;
+const MAX_COMMAND: usize = 4 * MAX_BULK;
+
+/// What the server answers from. A trie behind a lock is one, taking writes
+/// from `PT.ADD`.
+pub trait Store: Sync {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion>;
+
+    /// Stores `word`, with `weight` if one is given and expiring at `expires`
+    /// (see [`WordInfo::expires`]), and returns whether it wasn't stored
+    /// already.
+    fn add(&self, word: &str, weight: Option<u64>, expires: Option<u64>) -> bool;
+
+    /// Removes `word`, and returns whether it was stored.
+    fn remove(&self, word: &str) -> bool;
+
+    fn exists(&self, word: &str) -> bool;
+
+    /// Frees what the store holds but doesn't use, expired words included,
+    /// see [`Trie::shrink_to_fit`], and returns about how many bytes that was.
+    fn shrink(&self) -> usize;
+
+    /// Writes every word to the store's snapshot file, and returns how many.
+    /// Unsupported unless the store has one.
+    fn snapshot(&self) -> io::Result<usize> {
+        Err(io::Error::new(
+            ErrorKind::Unsupported,
+            "snapshots aren't supported",
+        ))
+    }
+
+    /// Replaces the words with those of the store's snapshot file, and
+    /// returns how many. Unsupported unless the store has one.
+    fn restore(&self) -> io::Result<usize> {
+        Err(io::Error::new(
+            ErrorKind::Unsupported,
+            "snapshots aren't supported",
+        ))
+    }
+}
+
+impl<K: TrieKey + Send + Sync> Store for RwLock<Trie<K>> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        self.read().unwrap().complete(prefix, limit)
+    }
+
+    fn add(&self, word: &str, weight: Option<u64>, expires: Option<u64>) -> bool {
+        let mut trie = self.write().unwrap();
+        let entry = trie.entry(word);
+        let added = entry.get().is_none_or(WordInfo::expired);
+        let info = entry.or_default();
+        if added {
+            // Nothing of an expired word carries over.
+            *info = WordInfo::default();
+        }
+        info.expires = expires;
+        info.apply(&Entry {
+            word,
+            weight,
+            ..Entry::default()
+        });
+        added
+    }
+
+    fn remove(&self, word: &str) -> bool {
+        let removed = self.write().unwrap().remove(word);
+        removed.is_some_and(|info| !info.expired())
+    }
+
+    fn exists(&self, word: &str) -> bool {
+        let trie = self.read().unwrap();
+        trie.get(word).is_some_and(|info| !info.expired())
+    }
+
+    fn shrink(&self) -> usize {
+        let mut trie = self.write().unwrap();
+        let before = trie.heap_size();
+        trie.remove_expired();
+        trie.shrink_to_fit();
+        before - trie.heap_size()
+    }
+}
+
+/// Accepts connections on `listener` until it fails, serving each on a
+/// thread of its own with [`serve_connection`].
+pub fn serve(
+    listener: TcpListener,
+    store: &dyn Store,
+    limit: usize,
+    token: Option<&str>,
+    shutdown: &Shutdown,
+) -> io::Result<()> {
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            if shutdown.requested() {
+                continue;
+            }
+            let input = BufReader::new(stream.try_clone()?);
+            scope.spawn(move || {
+                // A client going away mid-command is its own business.
+                let _ = serve_connection(input, stream, store, limit, token, shutdown);
+            });
+        }
+        Ok(())
+    })
+}
+
+/// Answers the commands read from `input` until it ends or the client
+/// quits. `PT.COMPLETE` without a limit answers up to `limit` words. With a
+/// `token`, writes wait for `AUTH` with it.
+///
+/// ```
+/// use std::sync::RwLock;
+///
+/// use prefix_tree::{resp, shutdown::Shutdown, Trie};
+///
+/// let trie: RwLock<Trie> = RwLock::new(["apple"].into_iter().collect());
+/// let shutdown = Shutdown::new();
+/// let mut output = vec![];
+/// let commands = "PT.ADD apply 5\r\nPT.COMPLETE ap\r\n";
+/// resp::serve_connection(commands.as_bytes(), &mut output, &trie, 10, None, &shutdown).unwrap();
+/// assert_eq!(output, b":1\r\n*2\r\n$5\r\napply\r\n$5\r\napple\r\n");
+///
+/// let mut output = vec![];
+/// let commands = "PT.ADD apricot\r\nAUTH s3cret\r\nPT.ADD apricot\r\n";
+/// let token = Some("s3cret");
+/// resp::serve_connection(commands.as_bytes(), &mut output, &trie, 10, token, &shutdown).unwrap();
+/// assert_eq!(output, b"-NOAUTH Authentication required.\r\n+OK\r\n:1\r\n");
+///
+/// // A trie behind a lock has no snapshot file to write.
+/// let mut output = vec![];
+/// resp::serve_connection(&b"PT.SNAPSHOT\r\n"[..], &mut output, &trie, 10, None, &shutdown).unwrap();
+/// assert_eq!(output, b"-ERR snapshots aren't supported\r\n");
+///
+/// // A line too long to be a command ends the connection unread.
+/// let mut output = vec![];
+/// let commands = format!("PT.ADD {}\r\n", "a".repeat(2 << 20));
+/// resp::serve_connection(commands.as_bytes(), &mut output, &trie, 10, None, &shutdown).unwrap();
+/// assert_eq!(output, b"-ERR Protocol error: too big inline request\r\n");
+///
+/// // So does a command of too many strings, or too many bytes in them.
+/// let bulk = format!("${}\r\n{}\r\n", 1 << 20, "a".repeat(1 << 20));
+/// for commands in ["*2000000\r\n".to_string(), format!("*5\r\n{}", bulk.repeat(5))] {
+///     let mut output = vec![];
+///     resp::serve_connection(commands.as_bytes(), &mut output, &trie, 10, None, &shutdown).unwrap();
+///     assert_eq!(output, b"-ERR Protocol error: invalid multibulk length\r\n");
+/// }
+/// ```
+pub fn serve_connection(
+    mut input: impl BufRead,
+    mut output: impl Write,
+    store: &dyn Store,
+    limit: usize,
+    token: Option<&str>,
+    shutdown: &Shutdown,
+) -> io::Result<()> {
+    let mut authenticated = token.is_none();
+    loop {
+        let command = match read_command(&mut input) {
+            Ok(Some(command)) => command,
+            Ok(None) => return Ok(()),
+            Err(err) if err.kind() == ErrorKind::InvalidData => {
+                // Like Redis, give up on a client that can't be understood.
+                Reply::Error(format!("ERR Protocol error: {}", err)).write(&mut output)?;
+                return output.flush();
+            }
+            Err(err) => return Err(err),
+        };
+        let Some(name) = command.first() else {
+            continue;
+        };
+        let Some(_in_flight) = shutdown.begin() else {
+            Reply::Error("ERR the server is shutting down".to_string()).write(&mut output)?;
+            return output.flush();
+        };
+        let name = name.to_ascii_uppercase();
+        let reply = match name.as_str() {
+            "AUTH" => auth(&command[1..], token, &mut authenticated),
+            "PT.ADD" | "PT.DEL" | "PT.SHRINK" | "PT.SNAPSHOT" | "PT.RESTORE" if !authenticated => {
+                Reply::Error("NOAUTH Authentication required.".to_string())
+            }
+            _ => handle(&name, &command[1..], store, limit),
+        };
+        reply.write(&mut output)?;
+        output.flush()?;
+        if name == "QUIT" {
+            return Ok(());
+        }
+    }
+}
+
+enum Reply {
+    Status(&'static str),
+    Error(String),
+    Integer(i64),
+    Bulk(String),
+    Array(Vec<String>),
+}
+
+impl Reply {
+    fn write(&self, output: &mut impl Write) -> io::Result<()> {
+        match self {
+            Reply::Status(status) => write!(output, "+{}\r\n", status),
+            Reply::Error(message) => write!(output, "-{}\r\n", message),
+            Reply::Integer(n) => write!(output, ":{}\r\n", n),
+            Reply::Bulk(text) => write!(output, "${}\r\n{}\r\n", text.len(), text),
+            Reply::Array(items) => {
+                write!(output, "*{}\r\n", items.len())?;
+                for item in items {
+                    write!(output, "${}\r\n{}\r\n", item.len(), item)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn handle(name: &str, args: &[String], store: &dyn Store, limit: usize) -> Reply {
+    let arity = |min: usize, max: usize| (min..=max).contains(&args.len());
+    let wrong_arity = || {
+        Reply::Error(format!(
+            "ERR wrong number of arguments for '{}' command",
+            name.to_ascii_lowercase()
+        ))
+    };
+    let not_a_number = || Reply::Error("ERR value is not an integer or out of range".to_string());
+    match name {
+        "PING" => match args {
+            [] => Reply::Status("PONG"),
+            [message] => Reply::Bulk(message.clone()),
+            _ => wrong_arity(),
+        },
+        "QUIT" => Reply::Status("OK"),
+        // Asked by clients on connecting; there is nothing to tell.
+        "COMMAND" => Reply::Array(vec![]),
+        "CLIENT" => Reply::Status("OK"),
+        "PT.COMPLETE" if arity(1, 3) => {
+            let (prefix, mut rest) = (&args[0], &args[1..]);
+            let with_weights = rest
+                .last()
+                .is_some_and(|last| last.eq_ignore_ascii_case("WITHWEIGHTS"));
+            if with_weights {
+                rest = &rest[..rest.len() - 1];
+            }
+            let limit = match rest {
+                [] => limit,
+                [count] => match count.parse() {
+                    Ok(limit) => limit,
+                    Err(_) => return not_a_number(),
+                },
+                _ => return Reply::Error("ERR syntax error".to_string()),
+            };
+            let words = store.complete(prefix, limit);
+            Reply::Array(match with_weights {
+                true => words
+                    .into_iter()
+                    .flat_map(|s| [s.word, s.weight.to_string()])
+                    .collect(),
+                false => words.into_iter().map(|s| s.word).collect(),
+            })
+        }
+        "PT.ADD" if arity(1, 4) => {
+            let (word, mut rest) = (&args[0], &args[1..]);
+            let mut weight = None;
+            if let Some((first, after)) = rest.split_first() {
+                if !first.eq_ignore_ascii_case("EX") {
+                    match first.parse() {
+                        Ok(parsed) => weight = Some(parsed),
+                        Err(_) => return not_a_number(),
+                    }
+                    rest = after;
+                }
+            }
+            let expires = match rest {
+                [] => None,
+                [ex, seconds] if ex.eq_ignore_ascii_case("EX") => match seconds.parse::<u64>() {
+                    Ok(seconds) => Some(now().saturating_add(seconds)),
+                    Err(_) => return not_a_number(),
+                },
+                _ => return Reply::Error("ERR syntax error".to_string()),
+            };
+            Reply::Integer(i64::from(store.add(word, weight, expires)))
+        }
+        "PT.DEL" if arity(1, 1) => Reply::Integer(i64::from(store.remove(&args[0]))),
+        "PT.EXISTS" if arity(1, 1) => Reply::Integer(i64::from(store.exists(&args[0]))),
+        "PT.SHRINK" if arity(0, 0) => Reply::Integer(store.shrink() as i64),
+        "PT.SNAPSHOT" | "PT.RESTORE" if arity(0, 0) => {
+            let words = match name {
+                "PT.SNAPSHOT" => store.snapshot(),
+                _ => store.restore(),
+            };
+            match words {
+                Ok(words) => Reply::Integer(words as i64),
+                Err(err) => Reply::Error(format!("ERR {}", err)),
+            }
+        }
+        "PT.COMPLETE" | "PT.ADD" | "PT.DEL" | "PT.EXISTS" | "PT.SHRINK" | "PT.SNAPSHOT"
+        | "PT.RESTORE" => wrong_arity(),
+        _ => Reply::Error(format!(
+            "ERR unknown command '{}'",
+            name.to_ascii_lowercase()
+        )),
+    }
+}
+
+// Answers `AUTH [username] password`, the username being ignored, as Redis
+// does for its default user.
+fn auth(args: &[String], token: Option<&str>, authenticated: &mut bool) -> Reply {
+    let password = match args {
+        [password] | [_, password] => password,
+        [] => return Reply::Error("ERR wrong number of arguments for 'auth' command".to_string()),
+        _ => return Reply::Error("ERR syntax error".to_string()),
+    };
+    match token {
+        None => Reply::Error("ERR AUTH called without any password configured".to_string()),
+        Some(token) if token_matches(password, token) => {
+            *authenticated = true;
+            Reply::Status("OK")
+        }
+        Some(_) => Reply::Error(
+            "WRONGPASS invalid username-password pair or user is disabled.".to_string(),
+        ),
+    }
+}
+
+// Reads a command: an array of bulk strings, as client libraries send, or a
+// line of space-separated words, as typed into telnet. `None` once the input
+// ends.
+fn read_command(input: &mut impl BufRead) -> io::Result<Option<Vec<String>>> {
+    let Some(line) = read_line(input)? else {
+        return Ok(None);
+    };
+    let Some(count) = line.strip_prefix('*') else {
+        return Ok(Some(line.split_whitespace().map(str::to_string).collect()));
+    };
+    let count: usize = count
+        .parse()
+        .ok()
+        .filter(|&count| count <= MAX_MULTIBULK)
+        .ok_or_else(|| invalid("invalid multibulk length"))?;
+    let mut command = vec![];
+    let mut size = 0;
+    for _ in 0..count {
+        let header = read_line(input)?.ok_or_else(|| invalid("unexpected end of command"))?;
+        let len = header
+            .strip_prefix('$')
+            .and_then(|len| len.parse::<usize>().ok())
+            .filter(|&len| len <= MAX_BULK)
+            .ok_or_else(|| invalid("invalid bulk length"))?;
+        size += len;
+        if size > MAX_COMMAND {
+            return Err(invalid("invalid multibulk length"));
+        }
+        // The string and the CRLF ending it.
+        let mut data = vec![0; len + 2];
+        input.read_exact(&mut data)?;
+        data.truncate(len);
+        command.push(String::from_utf8(data).map_err(|_| invalid("command isn't UTF-8"))?);
+    }
+    Ok(Some(command))
+}
+
+// A line without its line ending, `None` at the end of the input. Reading
+// stops past `MAX_BULK` bytes, however long the line goes on.
+fn read_line(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    // Room for the longest line and its CRLF.
+    let limit = MAX_BULK + 2;
+    let read = input
+        .by_ref()
+        .take(limit as u64)
+        .read_until(b'\n', &mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if read == limit && !line.ends_with(b"\n") {
+        return Err(invalid("too big inline request"));
+    }
+    let line = String::from_utf8(line).map_err(|_| invalid("command isn't UTF-8"))?;
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,71 @@
+//! The versioned envelope JSON answers come in, so client integrations can
+//! pin the shape they were written against: `complete` and `suggest` print it
+//! with `--schema-version`, and the HTTP server answers `GET /complete` with
+//! it.
+//!
+//! ```json
+//! {"version": 1, "query": "ap", "suggestions": [{"word": "apple", "weight": 3}], "truncated": false}
+//! ```
+//!
+//! Within a version, fields are only ever added, to the envelope or to its
+//! suggestions, so a client ignoring the fields it doesn't know keeps
+//! working; a field renamed or removed makes a new version, and the old ones
+//! stay on offer.
+
+use serde_json::{json, Value};
+
+use crate::complete::Suggestion;
+
+/// The versions this build answers in, oldest first.
+pub const VERSIONS: &[u32] = &[1];
+
+/// The version `requested` names, if this build answers in it.
+///
+/// ```
+/// use prefix_tree::response;
+///
+/// assert_eq!(response::negotiate("1"), Ok(1));
+/// assert!(response::negotiate("2").is_err());
+/// ```
+pub fn negotiate(requested: &str) -> Result<u32, String> {
+    match requested.trim().parse() {
+        Ok(version) if VERSIONS.contains(&version) => Ok(version),
+        _ => {
+            let known: Vec<_> = VERSIONS.iter().map(u32::to_string).collect();
+            Err(format!(
+                "unsupported response version {}, expected one of {}",
+                requested,
+                known.join(", ")
+            ))
+        }
+    }
+}
+
+/// A completion as an element of the envelope's `suggestions`, to which
+/// callers may add fields of their own, e.g. a `distance`.
+pub fn suggestion(suggestion: &Suggestion) -> Value {
+    json!({ "word": suggestion.word, "weight": suggestion.weight })
+}
+
+/// The envelope of `version` answering `query`, `truncated` telling whether
+/// there was more to suggest than `suggestions`.
+///
+/// ```
+/// use prefix_tree::{response, Suggestion};
+///
+/// let apple = Suggestion { word: "apple".to_string(), weight: 3 };
+/// let envelope = response::envelope(1, "ap", vec![response::suggestion(&apple)], true);
+/// assert_eq!(
+///     envelope.to_string(),
+///     r#"{"query":"ap","suggestions":[{"weight":3,"word":"apple"}],"truncated":true,"version":1}"#
+/// );
+/// ```
+pub fn envelope(version: u32, query: &str, suggestions: Vec<Value>, truncated: bool) -> Value {
+    debug_assert!(VERSIONS.contains(&version));
+    json!({
+        "version": version,
+        "query": query,
+        "suggestions": suggestions,
+        "truncated": truncated,
+    })
+}
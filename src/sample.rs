@@ -0,0 +1,139 @@
+use rand::{Rng, RngExt};
+
+use crate::{
+    key::TrieKey,
+    trie::{Node, Trie},
+};
+
+/// Draws words at random from a trie, or from the words starting with a
+/// prefix, either uniformly or in proportion to a weight. The weight below
+/// every node is summed once up front, so each draw is a single walk from the
+/// root down to the word.
+pub struct Sampler<'a, K, V> {
+    prefix: Vec<K>,
+    // The node of the prefix first, empty if no word starts with it.
+    counts: Vec<Counted<'a, K, V>>,
+}
+
+// A trie node together with the words and the total weight of its subtree.
+struct Counted<'a, K, V> {
+    node: &'a Node<K, V>,
+    // The weight of the node's own word, 0 if it has none.
+    own: f64,
+    total: f64,
+    words: u64,
+    // With the index of each child in `counts`.
+    children: Vec<(&'a K, usize)>,
+}
+
+impl<'a, K: TrieKey, V> Sampler<'a, K, V> {
+    /// Draws every word with the same probability.
+    pub fn new(trie: &'a Trie<K, V>, prefix: &str) -> Self {
+        Self::with_weights(trie, prefix, |_, _| 1.0)
+    }
+
+    /// Draws words with a probability proportional to `weight(word, value)`.
+    /// Words weighing 0 or less are never drawn, which also makes the weight
+    /// a filter.
+    pub fn with_weights(
+        trie: &'a Trie<K, V>,
+        prefix: &str,
+        weight: impl Fn(&str, &V) -> f64,
+    ) -> Self {
+        let prefix = K::segment(prefix);
+        let counts = trie
+            .get_node(&K::join(&prefix))
+            .map_or_else(Vec::new, |node| count(node, &mut prefix.clone(), &weight));
+        Self { prefix, counts }
+    }
+
+    /// The number of words that can be drawn.
+    pub fn len(&self) -> u64 {
+        self.counts.first().map_or(0, |counts| counts.words)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A random word and its value, or `None` if there are no words to draw.
+    pub fn sample(&self, rng: &mut impl Rng) -> Option<(String, &'a V)> {
+        let mut counted = self.counts.first().filter(|counts| counts.words > 0)?;
+        let mut keys = self.prefix.clone();
+        let mut position = rng.random_range(0.0..counted.total);
+        loop {
+            if let Some(value) = &counted.node.value {
+                if position < counted.own {
+                    return Some((K::join(&keys), value));
+                }
+            }
+            position -= counted.own;
+            let mut drawable = counted
+                .children
+                .iter()
+                .map(|&(key, child)| (key, &self.counts[child]))
+                .filter(|(_, child)| child.words > 0)
+                .peekable();
+            // Rounding can leave the position just past the last subtree,
+            // which then takes it.
+            let (key, child) = loop {
+                let (key, child) = drawable.next().expect("a drawable child");
+                if position < child.total || drawable.peek().is_none() {
+                    break (key, child);
+                }
+                position -= child.total;
+            };
+            keys.push(key.clone());
+            counted = child;
+        }
+    }
+}
+
+fn count<'a, K: TrieKey, V>(
+    root: &'a Node<K, V>,
+    buffer: &mut Vec<K>,
+    weight: &impl Fn(&str, &V) -> f64,
+) -> Vec<Counted<'a, K, V>> {
+    let counted = |node: &'a Node<K, V>, buffer: &[K]| Counted {
+        node,
+        own: match &node.value {
+            Some(value) => weight(&K::join(buffer), value).max(0.0),
+            None => 0.0,
+        },
+        total: 0.0,
+        words: 0,
+        children: Vec::new(),
+    };
+    let mut counts = vec![counted(root, buffer)];
+    let base = buffer.len();
+    let mut stack = vec![(0, root.children.iter())];
+    while let Some((parent, children)) = stack.last_mut() {
+        let parent = *parent;
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        buffer.truncate(base + stack.len() - 1);
+        buffer.push(key.clone());
+        let id = counts.len();
+        counts.push(counted(child, buffer));
+        counts[parent].children.push((key, id));
+        stack.push((id, child.children.iter()));
+    }
+    buffer.truncate(base);
+    // Children come after their parent, so going backwards sums each
+    // subtree before the one above it.
+    for id in (0..counts.len()).rev() {
+        let (total, words) =
+            counts[id]
+                .children
+                .iter()
+                .fold((0.0, 0), |(total, words), &(_, child)| {
+                    (total + counts[child].total, words + counts[child].words)
+                });
+        let counted = &mut counts[id];
+        counted.total = counted.own + total;
+        counted.words = u64::from(counted.own > 0.0) + words;
+    }
+    counts
+}
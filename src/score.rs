@@ -0,0 +1,335 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    time::Duration,
+};
+
+use crate::{
+    complete::{Completer, Suggestion},
+    key::TrieKey,
+    trie::{now, Node, Trie, WordInfo},
+};
+
+/// Orders completions for [`Trie::scored`]: every candidate word gets a
+/// score, and the highest scores are suggested first. A candidate scoring NaN
+/// is left out.
+///
+/// Closures taking a [`Candidate`] are scorers too, which makes blending the
+/// built-in ones a one-liner:
+///
+/// ```
+/// use prefix_tree::{Candidate, Completer, Frequency, Length, Scorer, Trie};
+///
+/// let trie: Trie = ["apple", "apricot", "ape"].into_iter().collect();
+/// let shortest = trie.scored(Length);
+/// assert_eq!(shortest.complete("ap", 1)[0].word, "ape");
+///
+/// let blended = |c: &Candidate| Frequency.score(c) + 2.0 * Length.score(c);
+/// assert_eq!(trie.scored(blended).complete("ap", 3).len(), 3);
+/// ```
+pub trait Scorer {
+    fn score(&self, candidate: &Candidate) -> f64;
+}
+
+/// A word being ranked, with what is known about it.
+#[derive(Debug, Clone, Copy)]
+pub struct Candidate<'a> {
+    /// The word as stored, before any display casing.
+    pub word: &'a str,
+    pub weight: u64,
+    /// The prefix being completed.
+    pub prefix: &'a str,
+    pub info: &'a WordInfo,
+}
+
+impl<F: Fn(&Candidate) -> f64> Scorer for F {
+    fn score(&self, candidate: &Candidate) -> f64 {
+        self(candidate)
+    }
+}
+
+/// Heaviest words first, the order plain completion uses.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Frequency;
+
+impl Scorer for Frequency {
+    fn score(&self, candidate: &Candidate) -> f64 {
+        candidate.weight as f64
+    }
+}
+
+/// Shortest words first, the fewest keystrokes to finish.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Length;
+
+impl Scorer for Length {
+    fn score(&self, candidate: &Candidate) -> f64 {
+        -(candidate.word.chars().count() as f64)
+    }
+}
+
+/// Most recently used words first, e.g. the completions a user accepted last;
+/// words never used score 0 and come after them.
+#[derive(Debug, Clone, Default)]
+pub struct Recency {
+    // When each word was last used, on a clock ticking once per use.
+    used: HashMap<String, u64>,
+    clock: u64,
+}
+
+impl Recency {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks `word`, as stored, as just used.
+    pub fn touch(&mut self, word: &str) {
+        self.clock += 1;
+        self.used.insert(word.to_string(), self.clock);
+    }
+}
+
+impl Scorer for Recency {
+    fn score(&self, candidate: &Candidate) -> f64 {
+        self.used
+            .get(candidate.word)
+            .map_or(0.0, |&used| used as f64)
+    }
+}
+
+/// How often each completion was picked, so the words a user keeps choosing
+/// rise above the rest: a word picked `n` times scores `n + 1` times its
+/// weight (taken as at least 1).
+///
+/// With a [half-life](History::half_life), a pick counts half as much every
+/// time that long has passed since it was made, so old habits fade. The decay
+/// is worked out when counts are read, and folded into the saved counts when
+/// a word is picked again.
+///
+/// The counts are saved as lines of a word, its count and when it was last
+/// picked in seconds since the Unix epoch, separated by tabs, so a frontend
+/// can report picks as they happen and rankings adapt across sessions. Lines
+/// without the time, as older versions wrote, never decay until the word is
+/// picked again.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use prefix_tree::{Completer, History, Trie};
+///
+/// let trie: Trie = ["apple", "apricot", "ape"].into_iter().collect();
+/// let mut history = History::new();
+/// history.pick("apricot");
+///
+/// let mut saved = vec![];
+/// history.write(&mut saved).unwrap();
+/// let history = History::read(&saved[..]).unwrap();
+/// assert_eq!(trie.scored(history).complete("ap", 1)[0].word, "apricot");
+///
+/// // Two picks a day apart, with a half-life of a day.
+/// let day = 24 * 60 * 60;
+/// let mut history = History::new().half_life(Duration::from_secs(day));
+/// history.pick_at("ape", 0);
+/// history.pick_at("ape", day);
+/// assert_eq!(history.picks_at("ape", day), 1.5);
+/// assert_eq!(history.picks_at("ape", 2 * day), 0.75);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    picks: HashMap<String, Picks>,
+    // In seconds.
+    half_life: Option<f64>,
+}
+
+// A word's pick count as of the time of its last pick, in seconds since the
+// Unix epoch, if known.
+#[derive(Debug, Clone, Copy, Default)]
+struct Picks {
+    count: f64,
+    last: Option<u64>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Halves the weight of every pick each time `half_life` passes.
+    pub fn half_life(mut self, half_life: Duration) -> Self {
+        self.half_life = Some(half_life.as_secs_f64());
+        self
+    }
+
+    /// Reads counts saved by [`History::write`]; malformed lines are skipped.
+    pub fn read(input: impl BufRead) -> io::Result<Self> {
+        let mut history = Self::new();
+        for line in input.lines() {
+            let line = line?;
+            let mut fields = line.split('\t');
+            let (Some(word), Some(count)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            let Ok(count) = count.parse::<f64>() else {
+                continue;
+            };
+            if count.is_nan() || count < 0.0 {
+                continue;
+            }
+            let last = fields.next().and_then(|last| last.parse().ok());
+            history.add(word, Picks { count, last });
+        }
+        Ok(history)
+    }
+
+    /// Writes the counts, most picked first.
+    pub fn write(&self, mut output: impl Write) -> io::Result<()> {
+        let mut picks: Vec<_> = self.picks.iter().collect();
+        picks.sort_by(|a, b| b.1.count.total_cmp(&a.1.count).then_with(|| a.0.cmp(b.0)));
+        for (word, picks) in picks {
+            match picks.last {
+                Some(last) => writeln!(output, "{}\t{}\t{}", word, picks.count, last)?,
+                None => writeln!(output, "{}\t{}", word, picks.count)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Records that `word`, as stored, was picked from the completions just
+    /// now.
+    pub fn pick(&mut self, word: &str) {
+        self.pick_at(word, now());
+    }
+
+    /// Records that `word` was picked `at` seconds after the Unix epoch.
+    pub fn pick_at(&mut self, word: &str, at: u64) {
+        self.add(
+            word,
+            Picks {
+                count: 1.0,
+                last: Some(at),
+            },
+        );
+    }
+
+    /// How often `word` was picked, older picks counting less under a
+    /// half-life.
+    pub fn picks(&self, word: &str) -> f64 {
+        self.picks_at(word, now())
+    }
+
+    /// [`History::picks`] as of `at` seconds after the Unix epoch.
+    pub fn picks_at(&self, word: &str, at: u64) -> f64 {
+        self.picks
+            .get(word)
+            .map_or(0.0, |picks| self.decayed(*picks, at))
+    }
+
+    /// The weight of `word` raised by its picks, the weight it scores with.
+    pub fn boosted(&self, word: &str, weight: u64) -> u64 {
+        // The cast saturates.
+        (weight.max(1) as f64 * (self.picks(word) + 1.0)) as u64
+    }
+
+    // Adds picks to those of `word`, both counted as of the later of their
+    // times.
+    fn add(&mut self, word: &str, picks: Picks) {
+        let old = self.picks.get(word).copied().unwrap_or_default();
+        let last = match (old.last, picks.last) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        let count = match last {
+            Some(last) => self.decayed(old, last) + self.decayed(picks, last),
+            None => old.count + picks.count,
+        };
+        self.picks.insert(word.to_string(), Picks { count, last });
+    }
+
+    // The count of `picks` as of `at`.
+    fn decayed(&self, picks: Picks, at: u64) -> f64 {
+        match (self.half_life, picks.last) {
+            (Some(half_life), Some(last)) if at > last => {
+                picks.count * 0.5f64.powf((at - last) as f64 / half_life)
+            }
+            _ => picks.count,
+        }
+    }
+}
+
+impl Scorer for History {
+    fn score(&self, candidate: &Candidate) -> f64 {
+        candidate.weight.max(1) as f64 * (self.picks(candidate.word) + 1.0)
+    }
+}
+
+impl<K: TrieKey> Trie<K> {
+    /// Completes with `scorer` deciding the order instead of the weights.
+    pub fn scored<S: Scorer>(&self, scorer: S) -> Scored<'_, K, S> {
+        Scored { trie: self, scorer }
+    }
+}
+
+/// Completions of a trie ranked by a [`Scorer`], see [`Trie::scored`].
+pub struct Scored<'a, K, S> {
+    trie: &'a Trie<K>,
+    scorer: S,
+}
+
+impl<K: TrieKey, S: Scorer> Completer for Scored<'_, K, S> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        let Some(node) = self.trie.get_node(prefix) else {
+            return vec![];
+        };
+        let mut words = vec![];
+        collect(node, &mut K::segment(prefix), &mut words);
+        let mut scored: Vec<(f64, &WordInfo, String)> = words
+            .into_iter()
+            .map(|(word, info)| {
+                let candidate = Candidate {
+                    word: &word,
+                    weight: info.weight,
+                    prefix,
+                    info,
+                };
+                (self.scorer.score(&candidate), info, word)
+            })
+            .filter(|(score, _, _)| !score.is_nan())
+            .collect();
+        // Alphabetical among equals, like plain completion.
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.2.cmp(&b.2)));
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, info, word)| Suggestion {
+                word: info.display.clone().unwrap_or(word),
+                weight: info.weight,
+            })
+            .collect()
+    }
+}
+
+fn collect<'a, K: TrieKey>(
+    root: &'a Node<K>,
+    buffer: &mut Vec<K>,
+    out: &mut Vec<(String, &'a WordInfo)>,
+) {
+    let mut push = |node: &'a Node<K>, buffer: &[K]| {
+        if let Some(info) = node.value.as_ref().filter(|info| !info.expired()) {
+            out.push((K::join(buffer), info));
+        }
+    };
+    push(root, buffer);
+    let base = buffer.len();
+    let mut stack = vec![root.children.iter()];
+    while let Some(children) = stack.last_mut() {
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        buffer.truncate(base + stack.len() - 1);
+        buffer.push(key.clone());
+        push(child, buffer);
+        stack.push(child.children.iter());
+    }
+    buffer.truncate(base);
+}
@@ -0,0 +1,91 @@
+use std::cell::RefCell;
+
+use rhai::{Array, Dynamic, Engine, Scope, AST};
+
+use crate::score::{Candidate, Scorer};
+
+/// A [`Scorer`] running a [Rhai](https://rhai.rs) script once per candidate,
+/// for ranking rules that change without recompiling.
+///
+/// The script sees `word`, `weight`, `prefix` and `tags` and evaluates to the
+/// candidate's score: a number ranks it, `true` keeps it at its weight, and
+/// `false` or nothing leaves it out.
+///
+/// ```
+/// use prefix_tree::{Completer, ScriptScorer, Trie};
+///
+/// let trie: Trie = ["apple", "apricot", "ape"].into_iter().collect();
+/// let script = ScriptScorer::new("if word.len() > 3 { -word.len() }").unwrap();
+/// let words = trie.scored(script).complete("ap", 10);
+/// assert_eq!(words[0].word, "apple");
+/// assert_eq!(words.len(), 2);
+/// ```
+pub struct ScriptScorer {
+    engine: Engine,
+    ast: AST,
+    // The first error the script ran into; its candidate was left out.
+    error: RefCell<Option<String>>,
+}
+
+impl ScriptScorer {
+    /// Compiles `source`, returning the syntax error if it doesn't parse.
+    pub fn new(source: &str) -> Result<Self, String> {
+        let engine = Engine::new();
+        let ast = engine.compile(source).map_err(|err| err.to_string())?;
+        Ok(Self {
+            engine,
+            ast,
+            error: RefCell::new(None),
+        })
+    }
+
+    /// The first error raised while scoring, if any.
+    pub fn error(&self) -> Option<String> {
+        self.error.borrow().clone()
+    }
+}
+
+impl Scorer for ScriptScorer {
+    fn score(&self, candidate: &Candidate) -> f64 {
+        let mut scope = Scope::new();
+        scope.push("word", candidate.word.to_string());
+        scope.push(
+            "weight",
+            i64::try_from(candidate.weight).unwrap_or(i64::MAX),
+        );
+        scope.push("prefix", candidate.prefix.to_string());
+        let tags: Array = candidate
+            .info
+            .tags
+            .iter()
+            .cloned()
+            .map(Dynamic::from)
+            .collect();
+        scope.push("tags", tags);
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast)
+            .map_err(|err| err.to_string())
+            .and_then(|value| {
+                if let Ok(score) = value.as_float() {
+                    Ok(score)
+                } else if let Ok(score) = value.as_int() {
+                    Ok(score as f64)
+                } else if let Ok(keep) = value.as_bool() {
+                    Ok(if keep {
+                        candidate.weight as f64
+                    } else {
+                        f64::NAN
+                    })
+                } else if value.is_unit() {
+                    Ok(f64::NAN)
+                } else {
+                    Err(format!("script returned a {}", value.type_name()))
+                }
+            });
+        result.unwrap_or_else(|err| {
+            self.error.borrow_mut().get_or_insert(err);
+            f64::NAN
+        })
+    }
+}
@@ -0,0 +1,45 @@
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{key::TrieKey, trie::Trie};
+
+/// A trie serializes as a map from its words, in sorted order, to their
+/// values, rather than as its nested nodes.
+impl<K: TrieKey, V: Serialize> Serialize for Trie<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (word, value) in self.iter() {
+            map.serialize_entry(&word, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de, K: TrieKey, V: Deserialize<'de>> Deserialize<'de> for Trie<K, V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(TrieVisitor(PhantomData))
+    }
+}
+
+struct TrieVisitor<K, V>(PhantomData<Trie<K, V>>);
+
+impl<'de, K: TrieKey, V: Deserialize<'de>> Visitor<'de> for TrieVisitor<K, V> {
+    type Value = Trie<K, V>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map from words to values")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut trie = Trie::new();
+        while let Some((word, value)) = map.next_entry::<String, V>()? {
+            trie.insert(&word, value);
+        }
+        Ok(trie)
+    }
+}
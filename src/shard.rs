@@ -0,0 +1,146 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    panic, thread,
+    time::Instant,
+};
+
+use crate::{
+    complete::{Completer, Suggestion},
+    fuzzy::{self, Costs, Match, Stats},
+    key::TrieKey,
+    trie::{Trie, WordInfo},
+};
+
+/// A dictionary split by word hash across several tries, which can be built
+/// side by side and are queried in parallel, one thread per shard.
+///
+/// Every word lives in the shard [`Sharded::shard_of`] picks for it, so a
+/// lookup reads a single shard while completion and fuzzy search ask all of
+/// them and merge what they find. On a huge dictionary each shard is a
+/// fraction of the size, which keeps the slowest queries short.
+///
+/// ```
+/// use prefix_tree::{Completer, Sharded, Trie, WordInfo};
+///
+/// let mut shards: Vec<Trie> = vec![Trie::new(), Trie::new()];
+/// for word in ["apple", "apricot", "banana"] {
+///     shards[Sharded::<char>::shard_of(word, 2)].insert(word, WordInfo::default());
+/// }
+/// let sharded = Sharded::from_shards(shards);
+/// assert!(sharded.contains("apricot"));
+/// assert_eq!(sharded.complete("ap", 10).len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct Sharded<K = char> {
+    shards: Vec<Trie<K>>,
+}
+
+impl<K: TrieKey> Sharded<K> {
+    /// Which of `count` shards `word` belongs in. The same word always lands
+    /// in the same shard, across runs too.
+    pub fn shard_of(word: &str, count: usize) -> usize {
+        // `DefaultHasher::new` always uses the same keys.
+        let mut hasher = DefaultHasher::new();
+        word.hash(&mut hasher);
+        (hasher.finish() % count as u64) as usize
+    }
+
+    /// Puts together shards whose words were placed with
+    /// [`Sharded::shard_of`].
+    pub fn from_shards(shards: Vec<Trie<K>>) -> Self {
+        assert!(!shards.is_empty(), "at least one shard is needed");
+        Self { shards }
+    }
+
+    pub fn shards(&self) -> &[Trie<K>] {
+        &self.shards
+    }
+
+    /// Merges the shards back into a single trie, see [`Trie::merge`].
+    pub fn into_trie(self) -> Trie<K> {
+        let mut shards = self.shards.into_iter();
+        let mut trie = shards.next().expect("at least one shard");
+        for shard in shards {
+            trie.merge(shard);
+        }
+        trie
+    }
+
+    /// The number of words stored, across every shard.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(Trie::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, word: &str) -> Option<&WordInfo> {
+        self.shards[Self::shard_of(word, self.shards.len())].get(word)
+    }
+
+    /// Whether `word` itself is stored, see [`Trie::contains`].
+    pub fn contains(&self, word: &str) -> bool {
+        self.get(word).is_some()
+    }
+}
+
+impl<K: TrieKey + Sync> Sharded<K> {
+    /// [`fuzzy::search_explained`] on every shard, with the matches of all of
+    /// them, whether any search was cut short, and their stats added up.
+    pub fn search_explained(
+        &self,
+        word: &str,
+        max_dist: f64,
+        costs: &Costs,
+        deadline: Option<Instant>,
+    ) -> (Vec<Match>, bool, Stats) {
+        let mut matches = vec![];
+        let mut truncated = false;
+        let mut stats = Stats::default();
+        for (found, cut, shard_stats) in
+            self.fan_out(|shard| fuzzy::search_explained(shard, word, max_dist, costs, deadline))
+        {
+            matches.extend(found);
+            truncated |= cut;
+            stats.nodes_visited += shard_stats.nodes_visited;
+            stats.branches_pruned += shard_stats.branches_pruned;
+        }
+        (matches, truncated, stats)
+    }
+
+    // Runs `f` on every shard, each on its own thread, with the results in
+    // shard order.
+    fn fan_out<T: Send>(&self, f: impl Fn(&Trie<K>) -> T + Sync) -> Vec<T> {
+        if let [shard] = &self.shards[..] {
+            return vec![f(shard)];
+        }
+        thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .shards
+                .iter()
+                .map(|shard| scope.spawn(|| f(shard)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        })
+    }
+}
+
+impl<K: TrieKey + Sync> Completer for Sharded<K> {
+    fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        // No word is in two shards, so the best `limit` overall are among
+        // the best `limit` of each.
+        let mut suggestions: Vec<Suggestion> = self
+            .fan_out(|shard| shard.complete(prefix, limit))
+            .into_iter()
+            .flatten()
+            .collect();
+        suggestions.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.word.cmp(&b.word)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
@@ -0,0 +1,94 @@
+//! Draining a server before it exits: once [`Shutdown::request`]ed, the
+//! servers refuse to start on new requests, and whoever asked waits for those
+//! in flight to be answered with [`Shutdown::wait_idle`].
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use prefix_tree::shutdown::Shutdown;
+//!
+//! let shutdown = Shutdown::new();
+//! let request = shutdown.begin().expect("still serving");
+//! shutdown.request();
+//! assert!(shutdown.begin().is_none());
+//! assert!(!shutdown.wait_idle(Duration::from_millis(10)));
+//! drop(request);
+//! assert!(shutdown.wait_idle(Duration::from_millis(10)));
+//! ```
+
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Whether a server is to stop, and how many requests it is still answering.
+/// Clones share both.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    state: Arc<(Mutex<State>, Condvar)>,
+}
+
+#[derive(Default)]
+struct State {
+    requested: bool,
+    in_flight: usize,
+}
+
+/// A request being answered, until dropped.
+pub struct InFlight {
+    shutdown: Shutdown,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a request in until the returned guard is dropped, or refuses
+    /// it with `None` once a shutdown was requested.
+    pub fn begin(&self) -> Option<InFlight> {
+        let mut state = self.state.0.lock().unwrap();
+        if state.requested {
+            return None;
+        }
+        state.in_flight += 1;
+        Some(InFlight {
+            shutdown: self.clone(),
+        })
+    }
+
+    pub fn request(&self) {
+        self.state.0.lock().unwrap().requested = true;
+    }
+
+    pub fn requested(&self) -> bool {
+        self.state.0.lock().unwrap().requested
+    }
+
+    /// Waits up to `timeout` for the requests in flight to be answered,
+    /// returning whether they all were.
+    pub fn wait_idle(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let (lock, idle) = &*self.state;
+        let mut state = lock.lock().unwrap();
+        while state.in_flight > 0 {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return false;
+            }
+            state = idle.wait_timeout(state, left).unwrap().0;
+        }
+        true
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let (lock, idle) = &*self.shutdown.state;
+        let mut state = lock.lock().unwrap();
+        state.in_flight -= 1;
+        if state.in_flight == 0 {
+            idle.notify_all();
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Word lists kept in SQLite databases: read as dictionary entries, and
+//! written back from a trie, for vocabularies that already live in one.
+
+use rusqlite::{params_from_iter, types::Value, Connection};
+
+use crate::{
+    key::TrieKey,
+    trie::{Entry, Trie},
+};
+
+/// Where the words are in a database: a table, the column of the words and,
+/// optionally, the columns of their weights and of their comma-separated
+/// tags.
+///
+/// ```
+/// use prefix_tree::{sqlite::Table, Trie};
+/// use rusqlite::Connection;
+///
+/// let mut db = Connection::open_in_memory().unwrap();
+/// let mut table = Table::new("words", "word");
+/// table.weight = Some("frequency".to_string());
+///
+/// let mut trie: Trie = Trie::new();
+/// trie.insert_entry(&prefix_tree::Entry::parse("apple\t9"));
+/// assert_eq!(table.write(&mut db, &trie).unwrap(), 1);
+///
+/// let mut words = vec![];
+/// table.read(&db, |entry| words.push((entry.word.to_string(), entry.weight))).unwrap();
+/// assert_eq!(words, [("apple".to_string(), Some(9))]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub name: String,
+    pub word: String,
+    pub weight: Option<String>,
+    pub tags: Option<String>,
+}
+
+impl Table {
+    /// The words of column `word` of table `name`, without weights or tags.
+    pub fn new(name: &str, word: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            word: word.to_string(),
+            weight: None,
+            tags: None,
+        }
+    }
+
+    /// Calls `f` with every row of the table as a dictionary entry. Rows
+    /// without a word are skipped, and a weight that isn't a non-negative
+    /// number is left out.
+    pub fn read(&self, db: &Connection, mut f: impl FnMut(&Entry)) -> rusqlite::Result<()> {
+        let column = |name: &Option<String>| name.as_deref().map_or("NULL".to_string(), quote);
+        let sql = format!(
+            "SELECT {}, {}, {} FROM {}",
+            quote(&self.word),
+            column(&self.weight),
+            column(&self.tags),
+            quote(&self.name)
+        );
+        let mut statement = db.prepare(&sql)?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let Value::Text(word) = row.get(0)? else {
+                continue;
+            };
+            let weight = match row.get(1)? {
+                Value::Integer(weight) => u64::try_from(weight).ok(),
+                Value::Real(weight) if weight >= 0.0 => Some(weight as u64),
+                Value::Text(weight) => weight.trim().parse().ok(),
+                _ => None,
+            };
+            let tags = match row.get(2)? {
+                Value::Text(tags) => tags,
+                _ => String::new(),
+            };
+            f(&Entry {
+                word: &word,
+                weight,
+                tags: tags
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .collect(),
+                expires: None,
+            });
+        }
+        Ok(())
+    }
+
+    /// Writes every word of `trie` as a row of the table, in one transaction,
+    /// and returns how many were written. A missing table is created with the
+    /// word column as its primary key, so that writing again replaces the
+    /// rows of words already there.
+    pub fn write<K: TrieKey>(
+        &self,
+        db: &mut Connection,
+        trie: &Trie<K>,
+    ) -> rusqlite::Result<usize> {
+        let mut columns = vec![quote(&self.word)];
+        let mut definitions = vec![format!("{} TEXT PRIMARY KEY NOT NULL", columns[0])];
+        if let Some(weight) = &self.weight {
+            columns.push(quote(weight));
+            definitions.push(format!("{} INTEGER", quote(weight)));
+        }
+        if let Some(tags) = &self.tags {
+            columns.push(quote(tags));
+            definitions.push(format!("{} TEXT", quote(tags)));
+        }
+        let transaction = db.transaction()?;
+        transaction.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} ({})",
+                quote(&self.name),
+                definitions.join(", ")
+            ),
+            [],
+        )?;
+        {
+            let placeholders: Vec<_> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+            let mut statement = transaction.prepare(&format!(
+                "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+                quote(&self.name),
+                columns.join(", "),
+                placeholders.join(", ")
+            ))?;
+            for (word, info) in trie.iter() {
+                let mut values = vec![Value::Text(word)];
+                if self.weight.is_some() {
+                    values.push(Value::Integer(
+                        i64::try_from(info.weight).unwrap_or(i64::MAX),
+                    ));
+                }
+                if self.tags.is_some() {
+                    values.push(Value::Text(info.tags.join(",")));
+                }
+                statement.execute(params_from_iter(values))?;
+            }
+        }
+        transaction.commit()?;
+        Ok(trie.len())
+    }
+}
+
+// Quotes an SQL identifier, so table and column names can be anything.
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
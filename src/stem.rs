@@ -0,0 +1,218 @@
+/// Martin Porter's suffix-stripping stemmer ("running" -> "run",
+/// "connections" -> "connect"). Words are lowercased first; words containing
+/// anything but ASCII letters are returned lowercased but otherwise untouched.
+pub fn stem(word: &str) -> String {
+    let word = word.to_lowercase();
+    if word.len() <= 2 || !word.bytes().all(|b| b.is_ascii_lowercase()) {
+        return word;
+    }
+    let mut w = word.into_bytes();
+    step1a(&mut w);
+    step1b(&mut w);
+    step1c(&mut w);
+    step2(&mut w);
+    step3(&mut w);
+    step4(&mut w);
+    step5(&mut w);
+    String::from_utf8(w).expect("stemming keeps words ASCII")
+}
+
+fn is_consonant(w: &[u8], i: usize) -> bool {
+    match w[i] {
+        b'a' | b'e' | b'i' | b'o' | b'u' => false,
+        b'y' => i == 0 || !is_consonant(w, i - 1),
+        _ => true,
+    }
+}
+
+/// The number of vowel-consonant sequences in `w`, written m in the paper.
+fn measure(w: &[u8]) -> usize {
+    let mut m = 0;
+    let mut i = 0;
+    while i < w.len() && is_consonant(w, i) {
+        i += 1;
+    }
+    loop {
+        while i < w.len() && !is_consonant(w, i) {
+            i += 1;
+        }
+        if i == w.len() {
+            return m;
+        }
+        while i < w.len() && is_consonant(w, i) {
+            i += 1;
+        }
+        m += 1;
+    }
+}
+
+fn has_vowel(w: &[u8]) -> bool {
+    (0..w.len()).any(|i| !is_consonant(w, i))
+}
+
+fn ends_double_consonant(w: &[u8]) -> bool {
+    let n = w.len();
+    n >= 2 && w[n - 1] == w[n - 2] && is_consonant(w, n - 1)
+}
+
+/// Consonant-vowel-consonant ending where the last consonant isn't w, x or y,
+/// e.g. "hop" but not "snow".
+fn ends_cvc(w: &[u8]) -> bool {
+    let n = w.len();
+    n >= 3
+        && is_consonant(w, n - 3)
+        && !is_consonant(w, n - 2)
+        && is_consonant(w, n - 1)
+        && !matches!(w[n - 1], b'w' | b'x' | b'y')
+}
+
+/// Replaces the first listed suffix that `w` ends with, provided the remaining
+/// stem satisfies `condition`. The lists are ordered so that the first match is
+/// also the longest one the algorithm would pick.
+fn replace_suffix(
+    w: &mut Vec<u8>,
+    rules: &[(&str, &str)],
+    condition: impl Fn(&[u8], &str) -> bool,
+) {
+    for (suffix, replacement) in rules {
+        if w.ends_with(suffix.as_bytes()) {
+            let stem = w.len() - suffix.len();
+            if condition(&w[..stem], suffix) {
+                w.truncate(stem);
+                w.extend_from_slice(replacement.as_bytes());
+            }
+            return;
+        }
+    }
+}
+
+fn step1a(w: &mut Vec<u8>) {
+    replace_suffix(
+        w,
+        &[("sses", "ss"), ("ies", "i"), ("ss", "ss"), ("s", "")],
+        |_, _| true,
+    );
+}
+
+fn step1b(w: &mut Vec<u8>) {
+    if w.ends_with(b"eed") {
+        if measure(&w[..w.len() - 3]) > 0 {
+            w.pop();
+        }
+        return;
+    }
+    let suffix = if w.ends_with(b"ed") {
+        2
+    } else if w.ends_with(b"ing") {
+        3
+    } else {
+        return;
+    };
+    if !has_vowel(&w[..w.len() - suffix]) {
+        return;
+    }
+    w.truncate(w.len() - suffix);
+    if w.ends_with(b"at") || w.ends_with(b"bl") || w.ends_with(b"iz") {
+        w.push(b'e');
+    } else if ends_double_consonant(w) && !matches!(w[w.len() - 1], b'l' | b's' | b'z') {
+        w.pop();
+    } else if measure(w) == 1 && ends_cvc(w) {
+        w.push(b'e');
+    }
+}
+
+fn step1c(w: &mut [u8]) {
+    let n = w.len();
+    if w[n - 1] == b'y' && has_vowel(&w[..n - 1]) {
+        w[n - 1] = b'i';
+    }
+}
+
+fn step2(w: &mut Vec<u8>) {
+    replace_suffix(
+        w,
+        &[
+            ("ational", "ate"),
+            ("tional", "tion"),
+            ("enci", "ence"),
+            ("anci", "ance"),
+            ("izer", "ize"),
+            ("abli", "able"),
+            ("alli", "al"),
+            ("entli", "ent"),
+            ("eli", "e"),
+            ("ousli", "ous"),
+            ("ization", "ize"),
+            ("ation", "ate"),
+            ("ator", "ate"),
+            ("alism", "al"),
+            ("iveness", "ive"),
+            ("fulness", "ful"),
+            ("ousness", "ous"),
+            ("aliti", "al"),
+            ("iviti", "ive"),
+            ("biliti", "ble"),
+        ],
+        |stem, _| measure(stem) > 0,
+    );
+}
+
+fn step3(w: &mut Vec<u8>) {
+    replace_suffix(
+        w,
+        &[
+            ("icate", "ic"),
+            ("ative", ""),
+            ("alize", "al"),
+            ("iciti", "ic"),
+            ("ical", "ic"),
+            ("ful", ""),
+            ("ness", ""),
+        ],
+        |stem, _| measure(stem) > 0,
+    );
+}
+
+fn step4(w: &mut Vec<u8>) {
+    replace_suffix(
+        w,
+        &[
+            ("al", ""),
+            ("ance", ""),
+            ("ence", ""),
+            ("er", ""),
+            ("ic", ""),
+            ("able", ""),
+            ("ible", ""),
+            ("ant", ""),
+            ("ement", ""),
+            ("ment", ""),
+            ("ent", ""),
+            ("ion", ""),
+            ("ou", ""),
+            ("ism", ""),
+            ("ate", ""),
+            ("iti", ""),
+            ("ous", ""),
+            ("ive", ""),
+            ("ize", ""),
+        ],
+        |stem, suffix| {
+            // "ion" is only stripped after an s or a t.
+            measure(stem) > 1 && (suffix != "ion" || matches!(stem.last(), Some(b's' | b't')))
+        },
+    );
+}
+
+fn step5(w: &mut Vec<u8>) {
+    if w.ends_with(b"e") {
+        let stem = &w[..w.len() - 1];
+        let m = measure(stem);
+        if m > 1 || (m == 1 && !ends_cvc(stem)) {
+            w.pop();
+        }
+    }
+    if measure(w) > 1 && ends_double_consonant(w) && w.ends_with(b"l") {
+        w.pop();
+    }
+}
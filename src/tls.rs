@@ -0,0 +1,161 @@
+//! HTTPS in front of the GraphQL server, so small deployments can expose it
+//! without a reverse proxy: [`terminate`] takes the TLS connections and relays
+//! each, decrypted, to the plain HTTP server listening on a loopback port.
+//!
+//! The server only speaks HTTP/1.1, which is what it offers by ALPN; a client
+//! insisting on another protocol, like one failing the handshake any other
+//! way, is told so with an alert, reported on stderr and dropped, without
+//! the HTTP server ever seeing it.
+
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use rustls::{
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    ServerConfig, ServerConnection,
+};
+
+// How long a client may take over its part of the handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The configuration of a server presenting the certificate chain in the PEM
+/// file `cert`, leaf first, with the private key in the PEM file `key`.
+pub fn config(cert: &Path, key: &Path) -> io::Result<Arc<ServerConfig>> {
+    let chain = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| invalid(cert, err))?;
+    if chain.is_empty() {
+        return Err(invalid(cert, "no certificate found"));
+    }
+    let private_key = PrivateKeyDer::from_pem_file(key).map_err(|err| invalid(key, err))?;
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_no_client_auth()
+        .with_single_cert(chain, private_key)
+        .map_err(|err| invalid(key, err))?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+fn invalid(path: &Path, err: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), err),
+    )
+}
+
+/// Accepts TLS connections on `listener` until it fails, relaying each to the
+/// HTTP server at `backend` on a thread of its own.
+pub fn terminate(
+    listener: TcpListener,
+    config: Arc<ServerConfig>,
+    backend: SocketAddr,
+) -> io::Result<()> {
+    for client in listener.incoming() {
+        let client = client?;
+        let config = Arc::clone(&config);
+        thread::spawn(move || {
+            let peer = client
+                .peer_addr()
+                .map_or_else(|_| "a client".to_string(), |peer| peer.to_string());
+            match handshake(&client, config) {
+                Ok(tls) => {
+                    // Either side going away mid-exchange is its own business.
+                    let _ = relay(client, tls, backend);
+                }
+                // Hanging up before a word, as port probes do, is no failure.
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => {}
+                Err(err) => {
+                    let _ = writeln!(
+                        io::stderr(),
+                        "warning: TLS handshake with {} failed: {}",
+                        peer,
+                        err
+                    );
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handshake(mut client: &TcpStream, config: Arc<ServerConfig>) -> io::Result<ServerConnection> {
+    let mut tls = ServerConnection::new(config).map_err(io::Error::other)?;
+    client.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    while tls.is_handshaking() {
+        // Sends an alert telling the client what went wrong before failing.
+        tls.complete_io(&mut client)?;
+    }
+    client.set_read_timeout(None)?;
+    Ok(tls)
+}
+
+// Passes requests on to the server and its responses back until either side
+// closes. The connection is shared by the two directions, each locking it
+// only to turn what it read into what it writes, never while it waits.
+fn relay(mut client: TcpStream, tls: ServerConnection, backend: SocketAddr) -> io::Result<()> {
+    let mut server = TcpStream::connect(backend)?;
+    let tls = Arc::new(Mutex::new(tls));
+    let responses = {
+        let tls = Arc::clone(&tls);
+        let (mut server, mut client) = (server.try_clone()?, client.try_clone()?);
+        thread::spawn(move || -> io::Result<()> {
+            let mut buffer = [0; 16 * 1024];
+            loop {
+                let read = server.read(&mut buffer)?;
+                let mut tls = tls.lock().unwrap();
+                if read == 0 {
+                    tls.send_close_notify();
+                } else {
+                    tls.writer().write_all(&buffer[..read])?;
+                }
+                while tls.wants_write() {
+                    tls.write_tls(&mut client)?;
+                }
+                if read == 0 {
+                    return client.shutdown(Shutdown::Write);
+                }
+            }
+        })
+    };
+    let mut buffer = [0; 16 * 1024];
+    loop {
+        let read = client.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        let mut requests = vec![];
+        let mut closed = false;
+        {
+            let mut tls = tls.lock().unwrap();
+            let mut records = &buffer[..read];
+            while !records.is_empty() {
+                tls.read_tls(&mut records)?;
+                tls.process_new_packets().map_err(io::Error::other)?;
+                match tls.reader().read_to_end(&mut requests) {
+                    Ok(_) => closed = true,
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            while tls.wants_write() {
+                tls.write_tls(&mut client)?;
+            }
+        }
+        server.write_all(&requests)?;
+        if closed {
+            break;
+        }
+    }
+    // The server answers what it has, then closes, ending the responses.
+    server.shutdown(Shutdown::Write)?;
+    responses.join().expect("the responses relay doesn't panic")
+}
@@ -0,0 +1,160 @@
+//! The heaviest words of a dictionary, overall or under a prefix, for
+//! auditing what ranking will promote.
+//!
+//! Every node of a [`TopIndex`] knows the heaviest weight below it, so the
+//! words come out heaviest first from a best-first walk that only opens the
+//! subtrees that can still hold one of them.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::{
+    complete::Suggestion,
+    key::TrieKey,
+    order::ChildOrder,
+    trie::{Node, Trie},
+};
+
+/// A [`Trie`]'s words with every node augmented by the heaviest weight at or
+/// below it. Expired words are left out.
+pub struct TopIndex<K = char> {
+    // The root is the first node.
+    nodes: Vec<TopNode<K>>,
+}
+
+struct TopNode<K> {
+    // The parent and the key leading here from it, `None` for the root.
+    up: Option<(u32, K)>,
+    // The weight of the word ending here, if one does.
+    weight: Option<u64>,
+    // The heaviest weight at or below the node, 0 for a subtree without
+    // words.
+    max: u64,
+    children: Vec<(K, u32)>,
+}
+
+// What an entry of the walk stands for. Entries pop heaviest first, then in
+// key order with a word before the subtree below it, so words of equal
+// weight come out in key order. The nodes are numbered in key order, a
+// prefix before the words below it, so their numbers stand in for their
+// paths, which would make the walk copy a path per subtree.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Word,
+    Subtree,
+}
+
+impl<K: TrieKey> TopIndex<K> {
+    pub fn new(trie: &Trie<K>) -> Self {
+        let mut nodes = vec![];
+        build(&trie.root, &mut nodes);
+        Self { nodes }
+    }
+
+    /// The `n` heaviest words starting with `prefix`, heaviest first, and in
+    /// key order among equal weights.
+    ///
+    /// ```
+    /// use prefix_tree::{top::TopIndex, Trie};
+    ///
+    /// let mut trie: Trie = ["apple", "apricot", "banana", "avocado"].into_iter().collect();
+    /// trie.get_mut("banana").unwrap().weight = 9;
+    /// trie.get_mut("apricot").unwrap().weight = 4;
+    /// let index = TopIndex::new(&trie);
+    /// let words: Vec<_> = index.top("", 3).into_iter().map(|s| s.word).collect();
+    /// assert_eq!(words, ["banana", "apricot", "apple"]);
+    /// assert_eq!(index.top("av", 3)[0].word, "avocado");
+    /// ```
+    pub fn top(&self, prefix: &str, n: usize) -> Vec<Suggestion> {
+        let mut found = vec![];
+        let keys = K::segment(prefix);
+        let Some(start) = self.find(&keys) else {
+            return found;
+        };
+        let mut heap = BinaryHeap::new();
+        heap.push((self.nodes[start].max, Reverse((start, Kind::Subtree))));
+        while found.len() < n {
+            let Some((weight, Reverse((id, kind)))) = heap.pop() else {
+                break;
+            };
+            let node = &self.nodes[id];
+            if kind == Kind::Word {
+                found.push(Suggestion {
+                    word: self.word(id),
+                    weight,
+                });
+                continue;
+            }
+            if let Some(weight) = node.weight {
+                heap.push((weight, Reverse((id, Kind::Word))));
+            }
+            for &(_, child) in &node.children {
+                let max = self.nodes[child as usize].max;
+                heap.push((max, Reverse((child as usize, Kind::Subtree))));
+            }
+        }
+        found
+    }
+
+    // The word of the path down to `id`.
+    fn word(&self, mut id: usize) -> String {
+        let mut keys = vec![];
+        while let Some((parent, key)) = &self.nodes[id].up {
+            keys.push(key.clone());
+            id = *parent as usize;
+        }
+        keys.reverse();
+        K::join(&keys)
+    }
+
+    fn find(&self, keys: &[K]) -> Option<usize> {
+        let mut id = 0;
+        for key in keys {
+            let (_, child) = self.nodes[id].children.iter().find(|(k, _)| k == key)?;
+            id = *child as usize;
+        }
+        Some(id)
+    }
+}
+
+// Appends the node and its subtree in key order, the node first.
+fn build<K: TrieKey>(root: &Node<K>, nodes: &mut Vec<TopNode<K>>) {
+    let top = |up, node: &Node<K>| {
+        let weight = node
+            .value
+            .as_ref()
+            .filter(|info| !info.expired())
+            .map(|info| info.weight);
+        TopNode {
+            up,
+            weight,
+            max: weight.unwrap_or(0),
+            children: Vec::with_capacity(node.children.len()),
+        }
+    };
+    let start = nodes.len();
+    nodes.push(top(None, root));
+    let order = ChildOrder::keys();
+    let mut stack = vec![(start, order.sorted(root).into_iter())];
+    while let Some((parent, children)) = stack.last_mut() {
+        let parent = *parent;
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        let id = nodes.len();
+        nodes.push(top(Some((parent as u32, key.clone())), child));
+        nodes[parent].children.push((key.clone(), id as u32));
+        stack.push((id, order.sorted(child).into_iter()));
+    }
+    // Children come after their parent, so going backwards has each
+    // subtree's heaviest weight before the one above it needs it.
+    for id in (start..nodes.len()).rev() {
+        let below = nodes[id]
+            .children
+            .iter()
+            .map(|&(_, child)| nodes[child as usize].max)
+            .max()
+            .unwrap_or(0);
+        nodes[id].max = nodes[id].max.max(below);
+    }
+}
@@ -0,0 +1,60 @@
+use std::io::{self, Write};
+
+use crate::{key::TrieKey, order::ChildOrder, trie::Node, trie::Trie};
+
+/// Writes the part of the trie below `prefix` with box-drawing characters,
+/// like the `tree` utility, down to `max_depth` keys. Nodes ending a word are
+/// marked with `*`, and children come in the trie's
+/// [child order](Trie::with_child_order). Nothing is written if no word starts
+/// with `prefix`.
+pub fn write_tree<W: Write, K: TrieKey, V>(
+    out: &mut W,
+    trie: &Trie<K, V>,
+    prefix: &str,
+    max_depth: usize,
+) -> io::Result<()> {
+    let Some(node) = trie.get_node(prefix) else {
+        return Ok(());
+    };
+    let label = if prefix.is_empty() { "." } else { prefix };
+    writeln!(out, "{}{}", label, marker(node))?;
+    dump_tree(out, node, &trie.order, max_depth)
+}
+
+fn dump_tree<W: Write, K: TrieKey, V>(
+    out: &mut W,
+    node: &Node<K, V>,
+    order: &ChildOrder<K, V>,
+    max_depth: usize,
+) -> io::Result<()> {
+    if max_depth == 0 {
+        return Ok(());
+    }
+    let mut indent = String::new();
+    // The children of each level still to write, with the length of the
+    // indent in front of them.
+    let mut stack = vec![(order.sorted(node).into_iter().peekable(), 0)];
+    while let Some((children, len)) = stack.last_mut() {
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        let last = children.peek().is_none();
+        indent.truncate(*len);
+        let branch = if last { "└── " } else { "├── " };
+        writeln!(out, "{}{}{}{}", indent, branch, key, marker(child))?;
+        if stack.len() < max_depth {
+            indent.push_str(if last { "    " } else { "│   " });
+            stack.push((order.sorted(child).into_iter().peekable(), indent.len()));
+        }
+    }
+    Ok(())
+}
+
+fn marker<K, V>(node: &Node<K, V>) -> &'static str {
+    if node.value.is_some() {
+        " *"
+    } else {
+        ""
+    }
+}
@@ -0,0 +1,1097 @@
+use std::collections::{hash_map, HashMap};
+
+use crate::{
+    key::{KeyHasher, TrieKey},
+    order::ChildOrder,
+};
+
+/// Weight of a word whose dictionary line doesn't carry one.
+pub const DEFAULT_WEIGHT: u64 = 1;
+
+#[derive(Debug)]
+pub struct Node<K = char, V = WordInfo> {
+    pub(crate) value: Option<V>,
+    pub(crate) children: HashMap<K, Node<K, V>, KeyHasher>,
+    // Words at or below the node, its own included, kept up to date by every
+    // change to the trie.
+    pub(crate) words: usize,
+}
+
+impl<K: TrieKey, V> Node<K, V> {
+    pub(crate) fn new() -> Self {
+        Self {
+            value: None,
+            children: HashMap::default(),
+            words: 0,
+        }
+    }
+
+    /// The value of the word ending at this node, if one does.
+    pub fn value(&self) -> Option<&V> {
+        self.value.as_ref()
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = (&K, &Node<K, V>)> {
+        self.children.iter()
+    }
+
+    /// The number of words at or below this node, its own included.
+    pub fn words(&self) -> usize {
+        self.words
+    }
+
+    // The value and the children, which can't be moved out of a node that
+    // drops itself.
+    pub(crate) fn into_parts(mut self) -> (Option<V>, HashMap<K, Node<K, V>, KeyHasher>) {
+        (self.value.take(), std::mem::take(&mut self.children))
+    }
+}
+
+// Dropping a node would drop its children before it, and theirs before them,
+// one stack frame per key of the longest word. Their maps are taken apart on
+// a stack of our own instead.
+impl<K, V> Drop for Node<K, V> {
+    fn drop(&mut self) {
+        if self.children.is_empty() {
+            return;
+        }
+        let mut pending = vec![std::mem::take(&mut self.children)];
+        while let Some(children) = pending.pop() {
+            for (_, mut child) in children {
+                if !child.children.is_empty() {
+                    pending.push(std::mem::take(&mut child.children));
+                }
+            }
+        }
+    }
+}
+
+// Cloning a node would clone its children before returning, and theirs
+// before them, one stack frame per key of the longest word. The copies are
+// put together on a stack of our own instead.
+impl<K: TrieKey, V: Clone> Clone for Node<K, V> {
+    fn clone(&self) -> Self {
+        let shallow = |node: &Node<K, V>| Node {
+            value: node.value.clone(),
+            children: HashMap::with_capacity_and_hasher(node.children.len(), KeyHasher),
+            words: node.words,
+        };
+        let mut stack = vec![(None, shallow(self), self.children.iter())];
+        loop {
+            let (_, _, children) = stack.last_mut().expect("the root's copy");
+            if let Some((key, child)) = children.next() {
+                stack.push((Some(key), shallow(child), child.children.iter()));
+                continue;
+            }
+            let (key, copy, _) = stack.pop().expect("the root's copy");
+            match (key, stack.last_mut()) {
+                (Some(key), Some((_, parent, _))) => {
+                    parent.children.insert(key.clone(), copy);
+                }
+                _ => return copy,
+            }
+        }
+    }
+}
+
+/// What the dictionary knows about a word: its weight (e.g. its corpus
+/// frequency) and its tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct WordInfo {
+    pub weight: u64,
+    pub tags: Vec<String>,
+    /// How completions spell the word when it differs from its key, e.g. the
+    /// usual casing of a case-folded word.
+    pub display: Option<String>,
+    /// When the word stops being suggested, in seconds since the Unix epoch,
+    /// e.g. for an identifier a service only needs for a while.
+    pub expires: Option<u64>,
+}
+
+impl Default for WordInfo {
+    fn default() -> Self {
+        Self {
+            weight: DEFAULT_WEIGHT,
+            tags: Vec::new(),
+            display: None,
+            expires: None,
+        }
+    }
+}
+
+impl WordInfo {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Whether the word's expiry time has passed.
+    pub fn expired(&self) -> bool {
+        self.expires.is_some_and(|expires| expires <= now())
+    }
+
+    /// Folds another dictionary line of the same word in: a weight or an
+    /// expiry replaces the current one and tags are added to the ones already
+    /// carried.
+    pub fn apply(&mut self, entry: &Entry) {
+        if let Some(weight) = entry.weight {
+            self.weight = weight;
+        }
+        if entry.expires.is_some() {
+            self.expires = entry.expires;
+        }
+        for tag in &entry.tags {
+            if !self.has_tag(tag) {
+                self.tags.push(tag.to_string());
+            }
+        }
+    }
+}
+
+/// A dictionary line: a word with an optional weight, tags and expiry.
+#[derive(Debug, Default)]
+pub struct Entry<'a> {
+    pub word: &'a str,
+    pub weight: Option<u64>,
+    pub tags: Vec<&'a str>,
+    /// See [`WordInfo::expires`].
+    pub expires: Option<u64>,
+}
+
+impl<'a> Entry<'a> {
+    /// Parses a word optionally followed by tab-separated columns: a numeric
+    /// column is the word's weight (e.g. its corpus frequency), a column of
+    /// `expires=` and a Unix time is when the word expires, and any other
+    /// column is a comma-separated list of tags, e.g. "Apple\t120\tnoun,fruit".
+    pub fn parse(line: &'a str) -> Self {
+        let mut columns = line.split('\t');
+        let mut entry = Entry {
+            word: columns.next().unwrap_or_default(),
+            ..Entry::default()
+        };
+        for column in columns {
+            let expires = column.trim().strip_prefix("expires=");
+            if let Ok(weight) = column.trim().parse() {
+                entry.weight = Some(weight);
+            } else if let Some(Ok(expires)) = expires.map(str::parse) {
+                entry.expires = Some(expires);
+            } else {
+                entry
+                    .tags
+                    .extend(column.split(',').map(str::trim).filter(|t| !t.is_empty()));
+            }
+        }
+        entry
+    }
+}
+
+/// A prefix tree mapping words, split into `K` keys (`char`s by default), to
+/// values of type `V`. Dictionary tries store a [`WordInfo`] per word; any
+/// other value type makes the trie a general string map.
+///
+/// Every node counts the words below it, so [`Trie::count_prefix`],
+/// [`Trie::select`] and [`Trie::rank`] walk a single path.
+#[derive(Debug)]
+pub struct Trie<K = char, V = WordInfo> {
+    pub(crate) root: Node<K, V>,
+    pub(crate) order: ChildOrder<K, V>,
+}
+
+impl<K: TrieKey, V: Clone> Clone for Trie<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            order: self.order.clone(),
+        }
+    }
+}
+
+impl<K: TrieKey, V> Default for Trie<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: TrieKey, V> Trie<K, V> {
+    pub fn new() -> Self {
+        Self {
+            root: Node::new(),
+            order: ChildOrder::keys(),
+        }
+    }
+
+    /// Wraps a tree assembled elsewhere, e.g. by the builder, counting the
+    /// words below every node once.
+    pub(crate) fn from_root(mut root: Node<K, V>) -> Self {
+        recount(&mut root);
+        Self {
+            root,
+            order: ChildOrder::keys(),
+        }
+    }
+
+    /// Visits the children of every node in `order` from now on: the
+    /// iterators, [`Trie::walk`], completion among words of equal weight and
+    /// the [`tree`](crate::tree) and [`dot`](crate::dot) exports, and those of
+    /// the trie [`frozen`](Trie::freeze) from this one. What is
+    /// defined by sorted position, like [`Trie::range`], [`Trie::select`] and
+    /// [`Trie::next_after`], stays in key order.
+    pub fn with_child_order(mut self, order: ChildOrder<K, V>) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// The order the children of every node are visited in, see
+    /// [`Trie::with_child_order`].
+    pub fn child_order(&self) -> &ChildOrder<K, V> {
+        &self.order
+    }
+
+    /// The number of words stored.
+    pub fn len(&self) -> usize {
+        self.root.words
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes every word, dropping all nodes.
+    pub fn clear(&mut self) {
+        self.root = Node::new();
+    }
+
+    /// Stores `value` under `word`, returning the value it replaces.
+    pub fn insert(&mut self, word: &str, value: V) -> Option<V> {
+        self.entry(word).slot().replace(value)
+    }
+
+    /// How many stored words start with `prefix`, read off the node of the
+    /// prefix.
+    ///
+    /// ```
+    /// use prefix_tree::Trie;
+    ///
+    /// let trie: Trie = ["apple", "apply", "banana"].into_iter().collect();
+    /// assert_eq!(trie.count_prefix("app"), 2);
+    /// assert_eq!(trie.count_prefix("c"), 0);
+    /// ```
+    pub fn count_prefix(&self, prefix: &str) -> usize {
+        self.get_node(prefix).map_or(0, |node| node.words)
+    }
+
+    /// The `k`-th word starting with `prefix` in key order, counting from 0,
+    /// found by following the subtree counts down a single path.
+    ///
+    /// ```
+    /// use prefix_tree::Trie;
+    ///
+    /// let trie: Trie = ["apple", "apply", "banana"].into_iter().collect();
+    /// assert_eq!(trie.select(1, "").as_deref(), Some("apply"));
+    /// assert_eq!(trie.select(0, "b").as_deref(), Some("banana"));
+    /// assert_eq!(trie.select(3, ""), None);
+    /// ```
+    pub fn select(&self, mut k: usize, prefix: &str) -> Option<String> {
+        let mut node = self.get_node(prefix)?;
+        let mut keys = K::segment(prefix);
+        if k >= node.words {
+            return None;
+        }
+        loop {
+            if node.value.is_some() {
+                if k == 0 {
+                    return Some(K::join(&keys));
+                }
+                k -= 1;
+            }
+            let mut children: Vec<_> = node.children.iter().collect();
+            children.sort_by_key(|(key, _)| *key);
+            for (key, child) in children {
+                if k < child.words {
+                    keys.push(key.clone());
+                    node = child;
+                    break;
+                }
+                k -= child.words;
+            }
+        }
+    }
+
+    /// How many stored words sort strictly before `word`, which needn't be
+    /// stored itself; the counterpart of [`Trie::select`].
+    ///
+    /// ```
+    /// use prefix_tree::Trie;
+    ///
+    /// let trie: Trie = ["apple", "apply", "banana"].into_iter().collect();
+    /// assert_eq!(trie.rank("apply"), 1);
+    /// assert_eq!(trie.rank("b"), 2);
+    /// ```
+    pub fn rank(&self, word: &str) -> usize {
+        let mut rank = 0;
+        let mut node = &self.root;
+        for key in K::segment(word) {
+            // A proper prefix of the word sorts before it.
+            rank += usize::from(node.value.is_some());
+            rank += node
+                .children
+                .iter()
+                .filter(|(k, _)| **k < key)
+                .map(|(_, child)| child.words)
+                .sum::<usize>();
+            match node.children.get(&key) {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+        rank
+    }
+
+    pub fn get(&self, word: &str) -> Option<&V> {
+        self.get_node(word)?.value.as_ref()
+    }
+
+    pub fn get_mut(&mut self, word: &str) -> Option<&mut V> {
+        let mut node = &mut self.root;
+        for key in K::segment(word) {
+            node = node.children.get_mut(&key)?;
+        }
+        node.value.as_mut()
+    }
+
+    /// Whether `word` itself is stored; a word merely starting with it is not
+    /// enough, see [`Trie::starts_with`].
+    pub fn contains(&self, word: &str) -> bool {
+        self.get(word).is_some()
+    }
+
+    /// [`Trie::contains`] for many words at once, in their order. The words
+    /// are looked up in sorted order, each walk starting from where the
+    /// previous word's parted from it, so shared prefixes are walked once.
+    ///
+    /// ```
+    /// use prefix_tree::Trie;
+    ///
+    /// let trie: Trie = ["apple", "apply", "banana"].into_iter().collect();
+    /// let found = trie.contains_many(&["apply", "ape", "apple", "banana"]);
+    /// assert_eq!(found, [true, false, true, true]);
+    /// ```
+    pub fn contains_many(&self, words: &[&str]) -> Vec<bool> {
+        let keys: Vec<Vec<K>> = words.iter().map(|word| K::segment(word)).collect();
+        let mut order: Vec<usize> = (0..words.len()).collect();
+        order.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+        let mut found = vec![false; words.len()];
+        // The nodes along the previous word, as far as they went.
+        let mut path = vec![&self.root];
+        let mut previous: &[K] = &[];
+        for i in order {
+            let word = &keys[i];
+            let shared = previous
+                .iter()
+                .zip(word)
+                .take_while(|(a, b)| a == b)
+                .count();
+            path.truncate(shared + 1);
+            for key in &word[path.len() - 1..] {
+                match path[path.len() - 1].children.get(key) {
+                    Some(child) => path.push(child),
+                    None => break,
+                }
+            }
+            found[i] = path.len() == word.len() + 1 && path[word.len()].value.is_some();
+            previous = word;
+        }
+        found
+    }
+
+    /// Whether any stored word starts with `prefix`.
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        // Removals prune emptied branches, so every node leads to a word.
+        self.get_node(prefix).is_some()
+    }
+
+    /// The entry for `word`, to be read, updated or filled in. Filling it in
+    /// walks down the trie a second time, counting the new word in every
+    /// node along its path.
+    ///
+    /// ```
+    /// use prefix_tree::Trie;
+    ///
+    /// let mut counts = Trie::<char, usize>::new();
+    /// for word in ["to", "be", "or", "not", "to", "be"] {
+    ///     *counts.entry(word).or_insert(0) += 1;
+    /// }
+    /// assert_eq!(counts.get("be"), Some(&2));
+    /// ```
+    pub fn entry(&mut self, word: &str) -> WordEntry<'_, K, V> {
+        let keys = K::segment(word);
+        let mut node = &self.root;
+        let mut stored = true;
+        for key in &keys {
+            match node.children.get(key) {
+                Some(child) => node = child,
+                None => {
+                    stored = false;
+                    break;
+                }
+            }
+        }
+        WordEntry {
+            stored: stored && node.value.is_some(),
+            root: &mut self.root,
+            keys,
+        }
+    }
+
+    pub(crate) fn get_node(&self, text: &str) -> Option<&Node<K, V>> {
+        let mut node = &self.root;
+        for key in K::segment(text) {
+            node = node.children.get(&key)?;
+        }
+        Some(node)
+    }
+
+    /// Removes `word`, returning its value if it was stored, along with the
+    /// nodes no other word needs.
+    ///
+    /// ```
+    /// use prefix_tree::Trie;
+    ///
+    /// let mut trie: Trie = ["apple", "apply"].into_iter().collect();
+    /// assert!(trie.remove("apple").is_some());
+    /// assert!(trie.remove("app").is_none());
+    /// assert_eq!(trie.len(), 1);
+    /// assert!(trie.starts_with("appl"));
+    /// ```
+    pub fn remove(&mut self, word: &str) -> Option<V> {
+        remove(&mut self.root, &K::segment(word))
+    }
+
+    /// Keeps only the words for which `f` returns `true`, pruning the branches
+    /// left empty.
+    pub fn retain(&mut self, mut f: impl FnMut(&str, &mut V) -> bool) {
+        retain(&mut self.root, &mut f);
+    }
+
+    /// Removes every word starting with `prefix`, yielding the removed words
+    /// and their values.
+    pub fn drain_prefix(&mut self, prefix: &str) -> impl Iterator<Item = (String, V)> {
+        let keys = K::segment(prefix);
+        let mut words = Vec::new();
+        let drained = if keys.is_empty() {
+            Some(std::mem::replace(&mut self.root, Node::new()))
+        } else {
+            detach(&mut self.root, &keys)
+        };
+        if let Some(node) = drained {
+            into_words(node, &mut keys.clone(), &mut words);
+        }
+        words.into_iter()
+    }
+
+    /// Moves every word starting with `from` to start with `to` instead, by
+    /// re-rooting the whole subtree, and returns how many words moved. A moved
+    /// word replaces any value already stored under its new name.
+    pub fn rename_prefix(&mut self, from: &str, to: &str) -> usize {
+        let keys = K::segment(from);
+        let subtree = if keys.is_empty() {
+            Some(std::mem::replace(&mut self.root, Node::new()))
+        } else {
+            detach(&mut self.root, &keys)
+        };
+        // Only the root is left without words, by an empty trie.
+        let Some(subtree) = subtree.filter(|subtree| subtree.words > 0) else {
+            return 0;
+        };
+        let moved = subtree.words;
+        let keys = K::segment(to);
+        let mut node = &mut self.root;
+        for key in &keys {
+            node = node.children.entry(key.clone()).or_insert_with(Node::new);
+        }
+        let added = moved - graft(node, subtree);
+        // The node at `to` counted its own; count the words above it too.
+        let mut node = &mut self.root;
+        for key in &keys {
+            node.words += added;
+            node = node.children.get_mut(key).expect("grafted along the path");
+        }
+        moved
+    }
+
+    /// Moves every word of `other` into the trie, its values replacing those
+    /// of words stored in both. Subtrees only `other` has are moved over
+    /// whole; only the prefixes both share are walked.
+    ///
+    /// ```
+    /// use prefix_tree::Trie;
+    ///
+    /// let mut trie: Trie<char, u32> = [("apple", 1), ("apply", 2)].into_iter().collect();
+    /// let other: Trie<char, u32> = [("apply", 3), ("banana", 4)].into_iter().collect();
+    /// trie.merge(other);
+    /// assert_eq!(trie.len(), 3);
+    /// assert_eq!(trie.get("apply"), Some(&3));
+    /// assert_eq!(trie.get("banana"), Some(&4));
+    /// ```
+    pub fn merge(&mut self, other: Trie<K, V>) {
+        graft(&mut self.root, other.root);
+    }
+
+    /// Stores every word of `pairs` with its value, as [`Trie::insert`]
+    /// does, much faster when the words come sorted.
+    ///
+    /// As with [`TrieBuilder`](crate::TrieBuilder), the nodes along the path
+    /// of the last word are held out of the trie and each is only handed back
+    /// to its parent once a word leaves its subtree, so consecutive words
+    /// sharing a prefix never re-walk it from the root. Unsorted words are
+    /// still stored correctly, only without the saving.
+    ///
+    /// ```
+    /// use prefix_tree::Trie;
+    ///
+    /// let mut trie: Trie<char, u32> = [("apply", 1), ("banana", 2)].into_iter().collect();
+    /// trie.extend_sorted([("apple", 3), ("apply", 4), ("apricot", 5)]);
+    /// assert_eq!(trie.len(), 4);
+    /// assert_eq!(trie.get("apply"), Some(&4));
+    /// assert_eq!(trie.count_prefix("ap"), 3);
+    /// ```
+    pub fn extend_sorted<S: AsRef<str>>(&mut self, pairs: impl IntoIterator<Item = (S, V)>) {
+        // Each node held out with the key it hangs off its parent by, and
+        // the words it held when taken.
+        let mut path: Vec<(K, Node<K, V>, usize)> = Vec::new();
+        for (word, value) in pairs {
+            let keys = K::segment(word.as_ref());
+            let common = path
+                .iter()
+                .zip(&keys)
+                .take_while(|((key, _, _), next)| key == *next)
+                .count();
+            while path.len() > common {
+                hand_back(&mut self.root, &mut path);
+            }
+            for key in keys.into_iter().skip(common) {
+                let parent = match path.last_mut() {
+                    Some((_, parent, _)) => parent,
+                    None => &mut self.root,
+                };
+                let node = parent.children.remove(&key).unwrap_or_else(Node::new);
+                let words = node.words;
+                path.push((key, node, words));
+            }
+            let node = match path.last_mut() {
+                Some((_, node, _)) => node,
+                None => &mut self.root,
+            };
+            if node.value.replace(value).is_none() {
+                node.words += 1;
+            }
+        }
+        while !path.is_empty() {
+            hand_back(&mut self.root, &mut path);
+        }
+    }
+
+    /// Roughly how many bytes the trie takes: its nodes with their keys and
+    /// values, but not what the values own in turn, e.g. the tags of a
+    /// [`WordInfo`].
+    pub fn heap_size(&self) -> usize {
+        std::mem::size_of::<Self>() + heap_size(&self.root)
+    }
+
+    /// Shrinks the child map of every node to what it holds, handing the
+    /// memory left over by removed words back to the allocator. Worth it for
+    /// a long-lived trie after many words were removed, e.g. with
+    /// [`Trie::remove`] or [`Trie::retain`].
+    pub fn shrink_to_fit(&mut self) {
+        shrink_to_fit(&mut self.root);
+    }
+
+    /// Calls `f` with every word stored in the trie and its value.
+    pub fn for_each_word(&self, mut f: impl FnMut(&str, &V)) {
+        for_each_word(&self.root, &mut Vec::new(), &mut f);
+    }
+
+    /// Checks the invariants every change to the trie keeps: each node
+    /// counts the words at and below it, each node but the root leads to a
+    /// word, and each stored word segments back into the keys it is stored
+    /// under, so looking it up finds it. Meant for tests and fuzz targets;
+    /// the nodes are visited on a stack of their own.
+    ///
+    /// ```
+    /// use prefix_tree::Trie;
+    ///
+    /// let mut trie: Trie = ["apple", "apply", "banana"].into_iter().collect();
+    /// trie.remove("banana");
+    /// trie.rename_prefix("app", "ap");
+    /// assert_eq!(trie.debug_validate(), Ok(()));
+    ///
+    /// let mut empty: Trie = Trie::new();
+    /// empty.rename_prefix("", "a");
+    /// assert_eq!(empty.debug_validate(), Ok(()));
+    /// ```
+    pub fn debug_validate(&self) -> Result<(), String> {
+        // Every node with its parent's index and the key into it, parents
+        // first.
+        let mut nodes: Vec<(&Node<K, V>, usize, Option<&K>)> = vec![(&self.root, 0, None)];
+        let mut i = 0;
+        while let Some(&(node, _, _)) = nodes.get(i) {
+            nodes.extend(
+                node.children
+                    .iter()
+                    .map(|(key, child)| (child, i, Some(key))),
+            );
+            i += 1;
+        }
+        let keys_of = |mut i: usize| {
+            let mut keys = Vec::new();
+            while let (_, parent, Some(key)) = nodes[i] {
+                keys.push(key.clone());
+                i = parent;
+            }
+            keys.reverse();
+            keys
+        };
+        // Children come after their parent, so each is counted before it.
+        let mut words = vec![0; nodes.len()];
+        for i in (0..nodes.len()).rev() {
+            let (node, parent, _) = nodes[i];
+            words[i] += usize::from(node.value.is_some());
+            if node.words != words[i] {
+                return Err(format!(
+                    "the node of {:?} counts {} words, but {} are stored at or below it",
+                    K::join(&keys_of(i)),
+                    node.words,
+                    words[i]
+                ));
+            }
+            if i == 0 {
+                continue;
+            }
+            if words[i] == 0 {
+                return Err(format!(
+                    "the node of {:?} leads to no word",
+                    K::join(&keys_of(i))
+                ));
+            }
+            words[parent] += words[i];
+            if node.value.is_some() {
+                let keys = keys_of(i);
+                if K::segment(&K::join(&keys)) != keys {
+                    return Err(format!(
+                        "{:?} is stored under keys it doesn't segment into",
+                        K::join(&keys)
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K: TrieKey> Trie<K> {
+    /// Inserts a dictionary entry, merging it into an earlier entry of the
+    /// same word as [`WordInfo::apply`] describes.
+    pub fn insert_entry(&mut self, entry: &Entry) {
+        self.entry(entry.word).or_default().apply(entry);
+    }
+
+    /// Removes the words whose expiry time has passed, which completion
+    /// already leaves out, and returns how many there were.
+    ///
+    /// ```
+    /// use prefix_tree::{Entry, Trie};
+    ///
+    /// let mut trie: Trie = Trie::new();
+    /// trie.insert_entry(&Entry::parse("session-1\texpires=0"));
+    /// trie.insert_entry(&Entry::parse("session-2"));
+    /// assert_eq!(prefix_tree::Completer::complete(&trie, "session", 10).len(), 1);
+    /// assert_eq!(trie.remove_expired(), 1);
+    /// assert_eq!(trie.len(), 1);
+    /// ```
+    pub fn remove_expired(&mut self) -> usize {
+        let len = self.len();
+        self.retain(|_, info| !info.expired());
+        len - self.len()
+    }
+}
+
+// Seconds since the Unix epoch.
+pub(crate) fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// Collects plain words into a dictionary trie, each with a default
+/// [`WordInfo`].
+impl<'a, K: TrieKey> FromIterator<&'a str> for Trie<K> {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(words: I) -> Self {
+        let mut trie = Trie::new();
+        trie.extend(words);
+        trie
+    }
+}
+
+impl<K: TrieKey> FromIterator<String> for Trie<K> {
+    fn from_iter<I: IntoIterator<Item = String>>(words: I) -> Self {
+        let mut trie = Trie::new();
+        trie.extend(words);
+        trie
+    }
+}
+
+impl<'a, K: TrieKey> Extend<&'a str> for Trie<K> {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, words: I) {
+        for word in words {
+            self.entry(word).or_default();
+        }
+    }
+}
+
+impl<K: TrieKey> Extend<String> for Trie<K> {
+    fn extend<I: IntoIterator<Item = String>>(&mut self, words: I) {
+        for word in words {
+            self.entry(&word).or_default();
+        }
+    }
+}
+
+impl<K: TrieKey, V, S: AsRef<str>> FromIterator<(S, V)> for Trie<K, V> {
+    fn from_iter<I: IntoIterator<Item = (S, V)>>(pairs: I) -> Self {
+        let mut trie = Trie::new();
+        trie.extend(pairs);
+        trie
+    }
+}
+
+impl<K: TrieKey, V, S: AsRef<str>> Extend<(S, V)> for Trie<K, V> {
+    fn extend<I: IntoIterator<Item = (S, V)>>(&mut self, pairs: I) {
+        for (word, value) in pairs {
+            self.insert(word.as_ref(), value);
+        }
+    }
+}
+
+/// A word's slot in a [`Trie`], see [`Trie::entry`].
+pub struct WordEntry<'a, K, V> {
+    root: &'a mut Node<K, V>,
+    keys: Vec<K>,
+    // Whether the word is already stored.
+    stored: bool,
+}
+
+impl<'a, K: TrieKey, V> WordEntry<'a, K, V> {
+    pub fn get(&self) -> Option<&V> {
+        if !self.stored {
+            return None;
+        }
+        let mut node = &*self.root;
+        for key in &self.keys {
+            node = node.children.get(key)?;
+        }
+        node.value.as_ref()
+    }
+
+    pub fn and_modify(self, f: impl FnOnce(&mut V)) -> Self {
+        if self.stored {
+            let mut node = &mut *self.root;
+            for key in &self.keys {
+                node = node.children.get_mut(key).expect("the word is stored");
+            }
+            if let Some(value) = &mut node.value {
+                f(value);
+            }
+        }
+        self
+    }
+
+    pub fn or_insert(self, value: V) -> &'a mut V {
+        self.or_insert_with(|| value)
+    }
+
+    pub fn or_insert_with(self, f: impl FnOnce() -> V) -> &'a mut V {
+        self.slot().get_or_insert_with(f)
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    // Walks down to the word's value slot, creating the missing nodes. A
+    // word not yet stored is counted in every node on the way, as the caller
+    // fills the slot in.
+    fn slot(self) -> &'a mut Option<V> {
+        let added = usize::from(!self.stored);
+        let mut node = self.root;
+        for key in self.keys {
+            node.words += added;
+            node = node.children.entry(key).or_insert_with(Node::new);
+        }
+        node.words += added;
+        &mut node.value
+    }
+}
+
+// Calls `f` with every word at or below `root`, whose path is `buffer`. The
+// walk keeps its own stack, one iterator per level, so a long word can't
+// overflow the thread's.
+pub(crate) fn for_each_word<K: TrieKey, V>(
+    root: &Node<K, V>,
+    buffer: &mut Vec<K>,
+    f: &mut impl FnMut(&str, &V),
+) {
+    if let Some(value) = &root.value {
+        f(&K::join(buffer), value);
+    }
+    let base = buffer.len();
+    let mut stack = vec![root.children.iter()];
+    while let Some(children) = stack.last_mut() {
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        buffer.truncate(base + stack.len() - 1);
+        buffer.push(key.clone());
+        if let Some(value) = &child.value {
+            f(&K::join(buffer), value);
+        }
+        stack.push(child.children.iter());
+    }
+    buffer.truncate(base);
+}
+
+// Calls `leave` with every node at or below `root` once it is done with its
+// children, along with the keys down to it, and unlinks the nodes below the
+// root it returns `false` for. The nodes on the way down are taken out of
+// their parents and put back on the way up, on a stack of our own rather
+// than the thread's.
+fn post_order<K: TrieKey, V>(
+    root: &mut Node<K, V>,
+    leave: &mut impl FnMut(&[K], &mut Node<K, V>) -> bool,
+) {
+    let mut keys = Vec::new();
+    let pending: Vec<K> = root.children.keys().cloned().collect();
+    let mut stack = vec![(std::mem::replace(root, Node::new()), pending)];
+    while let Some((node, pending)) = stack.last_mut() {
+        if let Some(key) = pending.pop() {
+            let child = node.children.remove(&key).expect("a pending child");
+            let pending = child.children.keys().cloned().collect();
+            keys.push(key);
+            stack.push((child, pending));
+            continue;
+        }
+        let (mut node, _) = stack.pop().expect("a node on the stack");
+        let keep = leave(&keys, &mut node);
+        match (stack.last_mut(), keys.pop()) {
+            (Some((parent, _)), Some(key)) => {
+                if keep {
+                    parent.children.insert(key, node);
+                }
+            }
+            _ => *root = node,
+        }
+    }
+}
+
+// The words a node counts, once its children count theirs.
+fn words_of<K: TrieKey, V>(node: &Node<K, V>) -> usize {
+    usize::from(node.value.is_some())
+        + node
+            .children
+            .values()
+            .map(|child| child.words)
+            .sum::<usize>()
+}
+
+fn retain<K: TrieKey, V>(root: &mut Node<K, V>, f: &mut impl FnMut(&str, &mut V) -> bool) {
+    post_order(root, &mut |keys, node| {
+        if let Some(value) = &mut node.value {
+            if !f(&K::join(keys), value) {
+                node.value = None;
+            }
+        }
+        node.words = words_of(node);
+        node.words > 0
+    });
+}
+
+// What the children of the nodes at and below `root` take, each one in its
+// parent's map.
+fn heap_size<K: TrieKey, V>(root: &Node<K, V>) -> usize {
+    let mut size = 0;
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        // A hashbrown map keeps a control byte per bucket next to the entry.
+        size += node.children.capacity() * (std::mem::size_of::<(K, Node<K, V>)>() + 1);
+        stack.extend(node.children.values());
+    }
+    size
+}
+
+fn shrink_to_fit<K: TrieKey, V>(root: &mut Node<K, V>) {
+    post_order(root, &mut |_, node| {
+        node.children.shrink_to_fit();
+        true
+    });
+}
+
+// Sets the word count of every node.
+fn recount<K: TrieKey, V>(root: &mut Node<K, V>) {
+    post_order(root, &mut |_, node| {
+        node.words = words_of(node);
+        true
+    });
+}
+
+// Takes the value of the word at `keys` below `root`, unlinking the nodes it
+// leaves without words.
+fn remove<K: TrieKey, V>(root: &mut Node<K, V>, keys: &[K]) -> Option<V> {
+    let mut node = &*root;
+    for key in keys {
+        node = node.children.get(key)?;
+    }
+    node.value.as_ref()?;
+    let mut node = root;
+    for (i, key) in keys.iter().enumerate() {
+        node.words -= 1;
+        // The word is the only one below the child, so the whole branch goes.
+        if node.children[key].words == 1 {
+            let mut branch = node.children.remove(key).expect("along the word");
+            let mut end = &mut branch;
+            for key in &keys[i + 1..] {
+                end = end.children.get_mut(key).expect("along the word");
+            }
+            return end.value.take();
+        }
+        node = node.children.get_mut(key).expect("along the word");
+    }
+    node.words -= 1;
+    node.value.take()
+}
+
+// Unlinks the subtree at `keys` below `root`, along with the ancestors it
+// leaves without words, uncounting its words on the way.
+fn detach<K: TrieKey, V>(root: &mut Node<K, V>, keys: &[K]) -> Option<Node<K, V>> {
+    let last = keys.last()?;
+    let mut node = &*root;
+    for key in keys {
+        node = node.children.get(key)?;
+    }
+    let words = node.words;
+    let mut node = root;
+    for (i, key) in keys.iter().enumerate() {
+        node.words -= words;
+        // Nothing but the subtree below the child, so the whole branch goes.
+        if i + 1 == keys.len() || node.children[key].words == words {
+            let mut branch = node.children.remove(key).expect("along the keys");
+            if i + 1 == keys.len() {
+                return Some(branch);
+            }
+            let mut parent = &mut branch;
+            for key in &keys[i + 1..keys.len() - 1] {
+                parent = parent.children.get_mut(key).expect("along the keys");
+            }
+            return parent.children.remove(last);
+        }
+        node = node.children.get_mut(key).expect("along the keys");
+    }
+    unreachable!("the subtree is unlinked at the last key at the latest")
+}
+
+// Merges `from` into `into`, its values replacing those already there, and
+// returns how many were replaced. The counts of `into` and below are kept.
+// Where both have a child, the child of `into` is taken out of its parent
+// and put back once merged, on a stack of our own rather than the thread's.
+fn graft<K: TrieKey, V>(into: &mut Node<K, V>, from: Node<K, V>) -> usize {
+    struct Frame<K, V> {
+        // The key the node is put back under, `None` for `into` itself.
+        key: Option<K>,
+        node: Node<K, V>,
+        // The children of `from`'s node still to merge in.
+        from: hash_map::IntoIter<K, Node<K, V>>,
+        words: usize,
+        replaced: usize,
+    }
+    fn frame<K: TrieKey, V>(key: Option<K>, mut node: Node<K, V>, from: Node<K, V>) -> Frame<K, V> {
+        let words = from.words;
+        let (value, children) = from.into_parts();
+        let mut replaced = 0;
+        if let Some(value) = value {
+            replaced += usize::from(node.value.replace(value).is_some());
+        }
+        Frame {
+            key,
+            node,
+            from: children.into_iter(),
+            words,
+            replaced,
+        }
+    }
+
+    let mut stack = vec![frame(None, std::mem::replace(into, Node::new()), from)];
+    while let Some(top) = stack.last_mut() {
+        if let Some((key, child)) = top.from.next() {
+            match top.node.children.remove(&key) {
+                Some(node) => stack.push(frame(Some(key), node, child)),
+                None => {
+                    top.node.children.insert(key, child);
+                }
+            }
+            continue;
+        }
+        let mut done = stack.pop().expect("a frame on the stack");
+        done.node.words += done.words - done.replaced;
+        match (stack.last_mut(), done.key) {
+            (Some(parent), Some(key)) => {
+                parent.node.children.insert(key, done.node);
+                parent.replaced += done.replaced;
+            }
+            _ => {
+                *into = done.node;
+                return done.replaced;
+            }
+        }
+    }
+    unreachable!("`into` is put back last")
+}
+
+// Links the last node `path` holds out back into its parent, counting the
+// words it gained there.
+fn hand_back<K: TrieKey, V>(root: &mut Node<K, V>, path: &mut Vec<(K, Node<K, V>, usize)>) {
+    let (key, node, taken) = path.pop().expect("path is not empty");
+    let parent = match path.last_mut() {
+        Some((_, parent, _)) => parent,
+        None => root,
+    };
+    parent.words += node.words - taken;
+    parent.children.insert(key, node);
+}
+
+// Moves the words at or below `node`, whose path is `buffer`, into `out`,
+// one iterator per level on a stack of our own.
+fn into_words<K: TrieKey, V>(node: Node<K, V>, buffer: &mut Vec<K>, out: &mut Vec<(String, V)>) {
+    let (value, children) = node.into_parts();
+    if let Some(value) = value {
+        out.push((K::join(buffer), value));
+    }
+    let base = buffer.len();
+    let mut stack = vec![children.into_iter()];
+    while let Some(children) = stack.last_mut() {
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        buffer.truncate(base + stack.len() - 1);
+        buffer.push(key);
+        let (value, children) = child.into_parts();
+        if let Some(value) = value {
+            out.push((K::join(buffer), value));
+        }
+        stack.push(children.into_iter());
+    }
+    buffer.truncate(base);
+}
@@ -0,0 +1,537 @@
+use std::{cmp::Ordering, collections::VecDeque};
+
+use crate::{
+    key::TrieKey,
+    order::ChildOrder,
+    trie::{Node, Trie},
+};
+
+/// What [`Trie::walk`] does after visiting a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    /// Go on into the node's children.
+    Continue,
+    /// Leave the node's children out and go on with its siblings.
+    Skip,
+    /// End the walk.
+    Stop,
+}
+
+impl<K: TrieKey, V> Trie<K, V> {
+    /// Visits the nodes depth-first, starting at the root, calling `visitor`
+    /// with each node and the prefix spelled out on the way to it. Children
+    /// are visited in the trie's [child order](Trie::with_child_order).
+    ///
+    /// ```
+    /// use prefix_tree::{Trie, Walk};
+    ///
+    /// let mut trie = Trie::<char, ()>::new();
+    /// trie.insert("apple", ());
+    /// trie.insert("banana", ());
+    /// let mut prefixes = vec![];
+    /// trie.walk(|prefix, _| {
+    ///     prefixes.push(prefix.to_string());
+    ///     if prefix.len() < 2 { Walk::Continue } else { Walk::Skip }
+    /// });
+    /// prefixes.sort();
+    /// assert_eq!(prefixes, ["", "a", "ap", "b", "ba"]);
+    /// ```
+    pub fn walk(&self, mut visitor: impl FnMut(&str, &Node<K, V>) -> Walk) {
+        walk(&self.root, &self.order, &mut Vec::new(), &mut visitor);
+    }
+
+    /// The stored words and their values, in sorted key order, or the
+    /// trie's [child order](Trie::with_child_order) if set.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter::new(
+            &self.root,
+            Vec::new(),
+            &self.order,
+            Order::DepthFirst,
+            usize::MAX,
+        )
+    }
+
+    /// The stored words in sorted key order.
+    pub fn keys(&self) -> impl Iterator<Item = String> + '_ {
+        self.iter().map(|(word, _)| word)
+    }
+
+    /// The values of the stored words, in the words' sorted key order.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// The stored words and their values, breadth-first: shorter words (in
+    /// keys) before longer ones, in child order among equal lengths.
+    pub fn iter_bfs(&self) -> Iter<'_, K, V> {
+        Iter::new(
+            &self.root,
+            Vec::new(),
+            &self.order,
+            Order::BreadthFirst,
+            usize::MAX,
+        )
+    }
+
+    /// The stored words at most `max_depth` keys long, in sorted key order,
+    /// without descending any deeper into the trie.
+    pub fn iter_with_max_depth(&self, max_depth: usize) -> Iter<'_, K, V> {
+        Iter::new(
+            &self.root,
+            Vec::new(),
+            &self.order,
+            Order::DepthFirst,
+            max_depth,
+        )
+    }
+
+    /// The stored words from `low` to `high`, both included, in sorted key
+    /// order. Branches sorting entirely outside the bounds are never entered.
+    pub fn range(&self, low: &str, high: &str) -> impl Iterator<Item = (String, &V)> {
+        let mut words = Vec::new();
+        range(
+            &self.root,
+            &mut Vec::new(),
+            Some(&K::segment(low)),
+            Some(&K::segment(high)),
+            &mut words,
+        );
+        words.into_iter()
+    }
+
+    /// The first stored word sorting strictly after `word`, which needn't be
+    /// stored itself.
+    pub fn next_after(&self, word: &str) -> Option<String> {
+        let keys = K::segment(word);
+        let path = self.path(&keys);
+        // From the deepest node back up, the smallest branch after the word.
+        for depth in (0..path.len()).rev() {
+            let bound = keys.get(depth);
+            let next = path[depth]
+                .children
+                .iter()
+                .filter(|(key, _)| bound.is_none_or(|bound| *key > bound))
+                .min_by_key(|(key, _)| *key);
+            if let Some((key, child)) = next {
+                let mut prefix = keys[..depth].to_vec();
+                prefix.push(key.clone());
+                return Some(first_word(child, prefix));
+            }
+        }
+        None
+    }
+
+    /// The last stored word sorting strictly before `word`, which needn't be
+    /// stored itself.
+    pub fn prev_before(&self, word: &str) -> Option<String> {
+        let keys = K::segment(word);
+        let path = self.path(&keys);
+        // Below the word itself, everything sorts after it.
+        for depth in (0..path.len().min(keys.len())).rev() {
+            let prev = path[depth]
+                .children
+                .iter()
+                .filter(|(key, _)| *key < &keys[depth])
+                .max_by_key(|(key, _)| *key);
+            if let Some((key, child)) = prev {
+                let mut prefix = keys[..depth].to_vec();
+                prefix.push(key.clone());
+                return Some(last_word(child, prefix));
+            }
+            // A proper prefix of the word sorts before it.
+            if path[depth].value.is_some() {
+                return Some(K::join(&keys[..depth]));
+            }
+        }
+        None
+    }
+
+    /// The shortest prefix of `word` that no other word starts with, e.g. to
+    /// derive CLI short flags; the word itself if it is the prefix of another
+    /// word, and `None` if it isn't stored.
+    ///
+    /// ```
+    /// use prefix_tree::Trie;
+    ///
+    /// let trie: Trie = ["apple", "apricot", "ban", "banana"].into_iter().collect();
+    /// assert_eq!(trie.shortest_unique_prefix("apricot").as_deref(), Some("apr"));
+    /// assert_eq!(trie.shortest_unique_prefix("banana").as_deref(), Some("bana"));
+    /// assert_eq!(trie.shortest_unique_prefix("ban").as_deref(), Some("ban"));
+    /// assert_eq!(trie.shortest_unique_prefix("cherry"), None);
+    /// ```
+    pub fn shortest_unique_prefix(&self, word: &str) -> Option<String> {
+        let keys = K::segment(word);
+        let path = self.path(&keys);
+        path.get(keys.len()).filter(|node| node.value.is_some())?;
+        let depth = (keys.len().min(1)..=keys.len())
+            .find(|&depth| path[depth].words == 1)
+            .unwrap_or(keys.len());
+        Some(K::join(&keys[..depth]))
+    }
+
+    // The nodes along `keys` from the root, as far as they go.
+    fn path(&self, keys: &[K]) -> Vec<&Node<K, V>> {
+        let mut path = vec![&self.root];
+        for key in keys {
+            match path[path.len() - 1].children.get(key) {
+                Some(child) => path.push(child),
+                None => break,
+            }
+        }
+        path
+    }
+
+    /// Every distinct prefix of `len` keys, with how many words start with
+    /// it, in sorted key order. Words are counted, never spelled out.
+    pub fn prefix_counts(&self, len: usize) -> Vec<(String, usize)> {
+        let mut counts = Vec::new();
+        prefix_counts(&self.root, &mut Vec::new(), len, &mut counts);
+        counts
+    }
+
+    /// The shortest word (in keys) starting with `prefix`, the first in
+    /// child order among equals. Only the levels above it are visited.
+    pub fn shortest_word(&self, prefix: &str) -> Option<String> {
+        let node = self.get_node(prefix)?;
+        let mut iter = Iter::new(
+            node,
+            K::segment(prefix),
+            &self.order,
+            Order::BreadthFirst,
+            usize::MAX,
+        );
+        iter.next().map(|(word, _)| word)
+    }
+
+    /// The longest word (in keys) starting with `prefix`, the first in sorted
+    /// order among equals. Words are only spelled out along the way to the
+    /// deepest one, not for every word below the prefix.
+    pub fn longest_word(&self, prefix: &str) -> Option<String> {
+        let mut keys = K::segment(prefix);
+        keys.extend(deepest(self.get_node(prefix)?)?);
+        Some(K::join(&keys))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Order {
+    DepthFirst,
+    BreadthFirst,
+}
+
+/// An iterator over the words of a [`Trie`], see [`Trie::iter`],
+/// [`Trie::iter_bfs`] and [`Trie::iter_with_max_depth`].
+pub struct Iter<'a, K, V> {
+    children: &'a ChildOrder<K, V>,
+    max_depth: usize,
+    pending: Pending<'a, K, V>,
+}
+
+// What is left to visit. Neither copies the path for every node, which a
+// long word would make take the square of its length.
+enum Pending<'a, K, V> {
+    DepthFirst {
+        // The node started from, until visited.
+        start: Option<&'a Node<K, V>>,
+        // The children still to visit on every level below it.
+        levels: Vec<std::vec::IntoIter<(&'a K, &'a Node<K, V>)>>,
+        // The path to the node last visited, the first `base` keys the
+        // start's.
+        keys: Vec<K>,
+        base: usize,
+    },
+    BreadthFirst {
+        // The nodes to visit with their depths and the entries of `paths`
+        // holding the keys leading to them, `None` for the start.
+        queue: VecDeque<(Option<usize>, usize, &'a Node<K, V>)>,
+        // Each a key with the entry of the one before it.
+        paths: Vec<(Option<usize>, K)>,
+        start: Vec<K>,
+    },
+}
+
+impl<'a, K: TrieKey, V> Iter<'a, K, V> {
+    fn new(
+        node: &'a Node<K, V>,
+        keys: Vec<K>,
+        children: &'a ChildOrder<K, V>,
+        order: Order,
+        max_depth: usize,
+    ) -> Self {
+        let pending = match order {
+            Order::DepthFirst => Pending::DepthFirst {
+                start: Some(node),
+                levels: Vec::new(),
+                base: keys.len(),
+                keys,
+            },
+            Order::BreadthFirst => Pending::BreadthFirst {
+                queue: VecDeque::from([(None, keys.len(), node)]),
+                paths: Vec::new(),
+                start: keys,
+            },
+        };
+        Self {
+            children,
+            max_depth,
+            pending,
+        }
+    }
+}
+
+impl<'a, K: TrieKey, V> Iterator for Iter<'a, K, V> {
+    type Item = (String, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.pending {
+            Pending::DepthFirst {
+                start,
+                levels,
+                keys,
+                base,
+            } => {
+                if let Some(node) = start.take() {
+                    if keys.len() < self.max_depth {
+                        levels.push(self.children.sorted(node).into_iter());
+                    }
+                    if let Some(value) = &node.value {
+                        return Some((K::join(keys), value));
+                    }
+                }
+                loop {
+                    let depth = levels.len();
+                    let Some((key, child)) = levels.last_mut()?.next() else {
+                        levels.pop();
+                        continue;
+                    };
+                    keys.truncate(*base + depth - 1);
+                    keys.push(key.clone());
+                    if keys.len() < self.max_depth {
+                        levels.push(self.children.sorted(child).into_iter());
+                    }
+                    if let Some(value) = &child.value {
+                        return Some((K::join(keys), value));
+                    }
+                }
+            }
+            Pending::BreadthFirst {
+                queue,
+                paths,
+                start,
+            } => loop {
+                let (path, depth, node) = queue.pop_front()?;
+                if depth < self.max_depth {
+                    for (key, child) in self.children.sorted(node) {
+                        paths.push((path, key.clone()));
+                        queue.push_back((Some(paths.len() - 1), depth + 1, child));
+                    }
+                }
+                if let Some(value) = &node.value {
+                    let mut keys = vec![];
+                    let mut at = path;
+                    while let Some(i) = at {
+                        let (parent, key) = &paths[i];
+                        keys.push(key.clone());
+                        at = *parent;
+                    }
+                    keys.extend(start.iter().rev().cloned());
+                    keys.reverse();
+                    return Some((K::join(&keys), value));
+                }
+            },
+        }
+    }
+}
+
+// `low` and `high` are what's left of each bound while the path is still
+// equal to its prefix, and `None` once it sorts strictly inside of it. The
+// nodes are visited on a stack of our own, one level of children per key.
+fn range<'a, K: TrieKey, V>(
+    root: &'a Node<K, V>,
+    buffer: &mut Vec<K>,
+    low: Option<&[K]>,
+    high: Option<&[K]>,
+    out: &mut Vec<(String, &'a V)>,
+) {
+    type Level<'a, 'b, K, V> = (
+        std::vec::IntoIter<(&'a K, &'a Node<K, V>)>,
+        Option<&'b [K]>,
+        Option<&'b [K]>,
+    );
+    // Takes the word of `node`, if in range, and the children to go on with,
+    // if any can be.
+    fn enter<'a, 'b, K: TrieKey, V>(
+        node: &'a Node<K, V>,
+        buffer: &[K],
+        low: Option<&'b [K]>,
+        high: Option<&'b [K]>,
+        out: &mut Vec<(String, &'a V)>,
+    ) -> Option<Level<'a, 'b, K, V>> {
+        // A proper prefix of `low` sorts before it; a proper prefix of `high`
+        // still sorts before `high`.
+        if let Some(value) = &node.value {
+            if low.is_none_or(|low| low.is_empty()) {
+                out.push((K::join(buffer), value));
+            }
+        }
+        // Anything longer than `high` itself sorts after it.
+        if high.is_some_and(|high| high.is_empty()) {
+            return None;
+        }
+        Some((ChildOrder::keys().sorted(node).into_iter(), low, high))
+    }
+
+    let base = buffer.len();
+    let mut stack: Vec<Level<K, V>> = enter(root, buffer, low, high, out).into_iter().collect();
+    while let Some((children, low, high)) = stack.last_mut() {
+        let (low, high) = (*low, *high);
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        let child_low = match low.and_then(|low| low.split_first()) {
+            Some((first, rest)) => match key.cmp(first) {
+                Ordering::Less => continue,
+                Ordering::Equal => Some(rest),
+                Ordering::Greater => None,
+            },
+            None => None,
+        };
+        let child_high = match high.and_then(|high| high.split_first()) {
+            Some((first, rest)) => match key.cmp(first) {
+                Ordering::Less => None,
+                Ordering::Equal => Some(rest),
+                Ordering::Greater => {
+                    stack.pop();
+                    continue;
+                }
+            },
+            None => None,
+        };
+        buffer.truncate(base + stack.len() - 1);
+        buffer.push(key.clone());
+        stack.extend(enter(child, buffer, child_low, child_high, out));
+    }
+    buffer.truncate(base);
+}
+
+fn prefix_counts<K: TrieKey, V>(
+    root: &Node<K, V>,
+    buffer: &mut Vec<K>,
+    len: usize,
+    out: &mut Vec<(String, usize)>,
+) {
+    if buffer.len() == len {
+        out.push((K::join(buffer), root.words));
+        return;
+    }
+    let base = buffer.len();
+    let mut stack = vec![ChildOrder::keys().sorted(root).into_iter()];
+    while let Some(children) = stack.last_mut() {
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        buffer.truncate(base + stack.len() - 1);
+        buffer.push(key.clone());
+        if buffer.len() == len {
+            out.push((K::join(buffer), child.words));
+        } else {
+            stack.push(ChildOrder::keys().sorted(child).into_iter());
+        }
+    }
+    buffer.truncate(base);
+}
+
+// The first word at or below `node`, whose path is `keys`.
+fn first_word<K: TrieKey, V>(mut node: &Node<K, V>, mut keys: Vec<K>) -> String {
+    while node.value.is_none() {
+        let Some((key, child)) = node.children.iter().min_by_key(|(key, _)| *key) else {
+            break;
+        };
+        keys.push(key.clone());
+        node = child;
+    }
+    K::join(&keys)
+}
+
+// The last word at or below `node`, whose path is `keys`; removals prune
+// emptied branches, so that is the end of its rightmost path.
+fn last_word<K: TrieKey, V>(mut node: &Node<K, V>, mut keys: Vec<K>) -> String {
+    while let Some((key, child)) = node.children.iter().max_by_key(|(key, _)| *key) {
+        keys.push(key.clone());
+        node = child;
+    }
+    K::join(&keys)
+}
+
+// The keys from `node` down to its deepest word, the first in key order
+// among equals: the depth of the deepest word is found first, then the first
+// word that deep, both on stacks of our own.
+fn deepest<K: TrieKey, V>(node: &Node<K, V>) -> Option<Vec<K>> {
+    let mut depth = None;
+    let mut stack = vec![(node, 0)];
+    while let Some((node, level)) = stack.pop() {
+        if node.value.is_some() && depth.is_none_or(|depth| level > depth) {
+            depth = Some(level);
+        }
+        stack.extend(node.children.values().map(|child| (child, level + 1)));
+    }
+    let depth = depth?;
+    let mut keys = Vec::new();
+    let mut stack = vec![ChildOrder::keys().sorted(node).into_iter()];
+    if node.value.is_some() && depth == 0 {
+        return Some(keys);
+    }
+    while let Some(children) = stack.last_mut() {
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        keys.truncate(stack.len() - 1);
+        keys.push(key.clone());
+        if keys.len() == depth && child.value.is_some() {
+            return Some(keys);
+        }
+        if keys.len() < depth {
+            stack.push(ChildOrder::keys().sorted(child).into_iter());
+        }
+    }
+    None
+}
+
+// Returns false once the visitor has asked to stop. The nodes are visited on
+// a stack of our own, one level of children per key.
+fn walk<K: TrieKey, V>(
+    root: &Node<K, V>,
+    order: &ChildOrder<K, V>,
+    buffer: &mut Vec<K>,
+    visitor: &mut impl FnMut(&str, &Node<K, V>) -> Walk,
+) -> bool {
+    match visitor(&K::join(buffer), root) {
+        Walk::Stop => return false,
+        Walk::Skip => return true,
+        Walk::Continue => {}
+    }
+    let base = buffer.len();
+    let mut stack = vec![order.sorted(root).into_iter()];
+    while let Some(children) = stack.last_mut() {
+        let Some((key, child)) = children.next() else {
+            stack.pop();
+            continue;
+        };
+        buffer.truncate(base + stack.len() - 1);
+        buffer.push(key.clone());
+        match visitor(&K::join(buffer), child) {
+            Walk::Stop => {
+                buffer.truncate(base);
+                return false;
+            }
+            Walk::Skip => {}
+            Walk::Continue => stack.push(order.sorted(child).into_iter()),
+        }
+    }
+    buffer.truncate(base);
+    true
+}
@@ -0,0 +1,120 @@
+//! Wordle-style solving: the words of a given length that fit what earlier
+//! guesses revealed, letters known in place (green), letters in the word but
+//! elsewhere (yellow) and letters not in it (gray). All three are checked as
+//! the trie is walked, so a branch is left as soon as it breaks one.
+
+use crate::{
+    complete::Suggestion,
+    key::TrieKey,
+    trie::{Node, Trie},
+};
+
+/// What is known about the word sought. Letters match regardless of case.
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    /// The letter known at each position, `None` where it isn't. Words are
+    /// as long as this.
+    pub green: Vec<Option<char>>,
+    /// Letters in the word, but not at the position each comes with,
+    /// counting from 0.
+    pub yellow: Vec<(char, usize)>,
+    /// Letters that appear nowhere but where they are green. A letter both
+    /// yellow and gray, as after guessing it twice, counts as yellow only.
+    pub gray: Vec<char>,
+}
+
+/// The words of `trie` meeting `constraints`, heaviest first.
+///
+/// ```
+/// use prefix_tree::{wordle::{self, Constraints}, Trie};
+///
+/// let trie: Trie = ["cater", "baker", "maker", "stare", "wafer"].into_iter().collect();
+/// let constraints = Constraints {
+///     green: vec![None, Some('a'), None, Some('e'), None],
+///     yellow: vec![('r', 1)],
+///     gray: vec!['s', 't', 'm'],
+/// };
+/// let words: Vec<_> = wordle::solve(&trie, &constraints).into_iter().map(|s| s.word).collect();
+/// assert_eq!(words, ["baker", "wafer"]);
+/// ```
+pub fn solve<K: TrieKey>(trie: &Trie<K>, constraints: &Constraints) -> Vec<Suggestion> {
+    let folded = Constraints {
+        green: constraints
+            .green
+            .iter()
+            .map(|letter| letter.map(fold))
+            .collect(),
+        yellow: constraints
+            .yellow
+            .iter()
+            .map(|&(letter, position)| (fold(letter), position))
+            .collect(),
+        gray: constraints.gray.iter().copied().map(fold).collect(),
+    };
+    let mut found = vec![];
+    walk(&trie.root, &folded, &mut vec![], &mut vec![], &mut found);
+    found.sort_unstable_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.word.cmp(&b.word)));
+    found
+}
+
+fn walk<K: TrieKey>(
+    node: &Node<K>,
+    constraints: &Constraints,
+    path: &mut Vec<K>,
+    letters: &mut Vec<char>,
+    found: &mut Vec<Suggestion>,
+) {
+    let position = path.len();
+    if position == constraints.green.len() {
+        if let Some(info) = node.value.as_ref().filter(|info| !info.expired()) {
+            found.push(Suggestion {
+                word: K::join(path),
+                weight: info.weight,
+            });
+        }
+        return;
+    }
+    for (key, child) in &node.children {
+        let Some(letter) = key.as_char().filter(|c| c.is_alphabetic()).map(fold) else {
+            continue;
+        };
+        match constraints.green[position] {
+            Some(green) if green != letter => continue,
+            Some(_) => {}
+            None => {
+                let yellow = constraints.yellow.iter().any(|&(y, _)| y == letter);
+                if !yellow && constraints.gray.contains(&letter) {
+                    continue;
+                }
+                if constraints.yellow.contains(&(letter, position)) {
+                    continue;
+                }
+            }
+        }
+        letters.push(letter);
+        // Every yellow letter still missing needs a position of its own.
+        let left = constraints.green.len() - position - 1;
+        if missing(&constraints.yellow, letters) <= left {
+            path.push(key.clone());
+            walk(child, constraints, path, letters, found);
+            path.pop();
+        }
+        letters.pop();
+    }
+}
+
+// The number of distinct yellow letters not among `letters`.
+fn missing(yellow: &[(char, usize)], letters: &[char]) -> usize {
+    let mut missing: Vec<char> = yellow
+        .iter()
+        .map(|&(letter, _)| letter)
+        .filter(|letter| !letters.contains(letter))
+        .collect();
+    missing.sort_unstable();
+    missing.dedup();
+    missing.len()
+}
+
+fn fold(letter: char) -> char {
+    letter.to_lowercase().next().unwrap_or(letter)
+}
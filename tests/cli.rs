@@ -0,0 +1,338 @@
+//! The `prefix-tree` binary, run on dictionaries written for each test.
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+// A directory of its own for the test `name`, holding `dictionary.txt` with
+// `lines`, so no history, profile or dictionary of the checkout is read.
+fn dictionary(name: &str, lines: &[&str]) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("dictionary.txt"), lines.join("\n") + "\n").unwrap();
+    dir
+}
+
+// Runs the binary in `dir` and returns its stdout, failing the test with
+// its stderr unless it succeeds.
+fn run(dir: &Path, args: &[&str]) -> String {
+    let (code, stdout, stderr) = output(dir, args);
+    assert_eq!(code, 0, "prefix-tree {:?}: {}", args, stderr);
+    stdout
+}
+
+// Runs the binary in `dir`, failing the test unless it exits within a
+// minute, and returns its exit code, stdout and stderr.
+fn output(dir: &Path, args: &[&str]) -> (i32, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_prefix-tree"))
+        .current_dir(dir)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Drained as it comes, so a long output can't fill the pipe and stall
+    // the child.
+    let drain = |mut pipe: Box<dyn Read + Send>| {
+        thread::spawn(move || {
+            let mut text = String::new();
+            pipe.read_to_string(&mut text).unwrap();
+            text
+        })
+    };
+    let stdout = drain(Box::new(child.stdout.take().unwrap()));
+    let stderr = drain(Box::new(child.stderr.take().unwrap()));
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("prefix-tree {:?} didn't finish", args);
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    (
+        status.code().unwrap(),
+        stdout.join().unwrap(),
+        stderr.join().unwrap(),
+    )
+}
+
+#[test]
+fn loads_words_of_a_million_keys() {
+    let long = "a".repeat(1_000_000);
+    let dir = dictionary("long_words", &[&long, "ab"]);
+    assert_eq!(run(&dir, &["count", "a"]), "2\n");
+    assert_eq!(run(&dir, &["list"]), format!("{}\nab\n", long));
+}
+
+#[test]
+fn completes_multi_word_entries_whole() {
+    let dir = dictionary(
+        "multi_word",
+        &["Custard apple", "Star Trek", "Star Wars", "banana"],
+    );
+    assert_eq!(run(&dir, &["complete", "Custard a"]), "Custard apple\n");
+    assert_eq!(run(&dir, &["complete", "Star "]), "Star Trek\nStar Wars\n");
+    // Text no entry starts with still completes its last word.
+    assert_eq!(run(&dir, &["complete", "ripe ba"]), "ripe banana\n");
+}
+
+#[test]
+fn completes_ascii_and_other_dictionaries_alike() {
+    let dir = dictionary("ascii", &["cafe", "cab"]);
+    assert_eq!(run(&dir, &["complete", "ca"]), "cab\ncafe\n");
+    let dir = dictionary("not_ascii", &["café", "cafe", "cab"]);
+    assert_eq!(run(&dir, &["complete", "caf"]), "cafe\ncafé\n");
+    assert_eq!(run(&dir, &["count", "caf"]), "2\n");
+}
+
+// A named pipe reads through once, and opening one blocks until something
+// writes to it.
+#[cfg(unix)]
+fn pipe(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    let _ = fs::remove_file(&path);
+    assert!(Command::new("mkfifo")
+        .arg(&path)
+        .status()
+        .unwrap()
+        .success());
+    path
+}
+
+#[cfg(unix)]
+#[test]
+fn reads_the_dictionary_once() {
+    let dir = dictionary("read_once", &[]);
+    let path = pipe(&dir, "pipe.txt");
+    let writer = thread::spawn(move || fs::write(path, "apple\napricot\nbanana\n").unwrap());
+    assert_eq!(run(&dir, &["--dict", "pipe.txt", "count", "ap"]), "2\n");
+    writer.join().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn leaves_the_dictionary_unread_for_an_index() {
+    let dir = dictionary("index_only", &["apple", "apricot", "banana"]);
+    run(&dir, &["compact", "--output", "words.idx"]);
+    // Nothing ever writes to the pipe, so reading it would never finish.
+    pipe(&dir, "pipe.txt");
+    assert_eq!(
+        run(
+            &dir,
+            &[
+                "--dict",
+                "pipe.txt",
+                "complete",
+                "ap",
+                "--index",
+                "words.idx"
+            ]
+        ),
+        "apple\napricot\n"
+    );
+}
+
+#[test]
+fn draws_passphrases_from_words_only() {
+    let dir = dictionary("passphrase", &["apple", "   "]);
+    assert_eq!(
+        run(&dir, &["passphrase", "--words", "3", "--separator", "-"]),
+        "apple-apple-apple\n"
+    );
+    let (code, _, _) = output(&dir, &["passphrase", "--word", "3"]);
+    assert_eq!(code, 2);
+
+    let dir = dictionary("passphrase_empty", &["   "]);
+    let (code, stdout, stderr) = output(&dir, &["passphrase"]);
+    assert_eq!((code, stdout.as_str()), (3, ""));
+    assert!(!stderr.contains("NaN"), "{}", stderr);
+}
+
+#[test]
+fn rejects_unknown_options() {
+    let dir = dictionary("unknown_options", &["apple", "apricot"]);
+    let mut cases = vec![
+        &["dot", "--bogus"][..],
+        &["compact", "--bogus"],
+        &["prefixes", "--len", "2", "--bogus"],
+        &["complete", "ap", "--bogus"],
+        &["random", "--bogus"],
+        &["lsp", "--bogus"],
+        &["nvim", "--bogus"],
+        &["resp", "--listen", "127.0.0.1:0", "--bogus"],
+        &["bench", "--bogus", "10"],
+        &["gen", "--bogus", "3"],
+        &["sounds-like", "--bogus", "smith"],
+    ];
+    if cfg!(feature = "graphql") {
+        cases.push(&["graphql", "--listen", "127.0.0.1:0", "--bogus"]);
+    }
+    for args in cases {
+        let (code, stdout, stderr) = output(&dir, args);
+        assert_eq!((code, stdout.as_str()), (2, ""), "{:?}", args);
+        assert!(
+            stderr.contains("unknown") && stderr.contains("--bogus"),
+            "{}",
+            stderr
+        );
+    }
+    assert!(!dir.join("trie.dot").exists() && !dir.join("trie.idx").exists());
+    assert_eq!(run(&dir, &["complete", "apr"]), "apricot\n");
+    let (code, _, stderr) = output(&dir, &["sounds-like"]);
+    assert_eq!(code, 2, "{}", stderr);
+}
+
+// Starts `prefix-tree resp` and returns it with what it says on stderr up
+// to the line about its auth token.
+fn start_resp(dir: &Path, args: &[&str]) -> (Child, Vec<String>) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_prefix-tree"))
+        .current_dir(dir)
+        .args(["resp", "--listen", "127.0.0.1:0"])
+        .args(args)
+        .env_remove("PREFIX_TREE_AUTH_TOKEN")
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap()).lines();
+    let mut lines = vec![];
+    while !lines
+        .last()
+        .is_some_and(|line: &String| line.contains("PT.ADD"))
+    {
+        lines.push(stderr.next().expect("a line about the auth token").unwrap());
+    }
+    (child, lines)
+}
+
+// Sends `commands` to the resp server that said `lines` on starting, and
+// returns its replies up to the end of the connection.
+fn send(lines: &[String], commands: &str) -> String {
+    let address = lines
+        .iter()
+        .find_map(|line| line.strip_prefix("serving RESP on "))
+        .unwrap();
+    let mut stream = std::net::TcpStream::connect(address).unwrap();
+    std::io::Write::write_all(&mut stream, commands.as_bytes()).unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    reply
+}
+
+// The line of what `prefix-tree resp` says about its auth token.
+fn resp_auth(dir: &Path, args: &[&str]) -> String {
+    let (mut child, mut lines) = start_resp(dir, args);
+    child.kill().unwrap();
+    child.wait().unwrap();
+    lines.pop().unwrap()
+}
+
+#[test]
+fn says_whether_writes_are_authenticated() {
+    let dir = dictionary("resp_auth", &["apple"]);
+    assert!(resp_auth(&dir, &[]).starts_with(
+        "WARNING: PT.ADD, PT.DEL, PT.SHRINK, PT.SNAPSHOT and PT.RESTORE are open to anyone"
+    ));
+    assert_eq!(
+        resp_auth(&dir, &["--auth-token", "s3cret"]),
+        "PT.ADD, PT.DEL, PT.SHRINK, PT.SNAPSHOT and PT.RESTORE need the auth token"
+    );
+}
+
+#[cfg(feature = "graphql")]
+#[test]
+fn refuses_bodies_over_max_body() {
+    use std::{io::Write, net::TcpStream};
+
+    let dir = dictionary("graphql_body", &["apple"]);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_prefix-tree"))
+        .current_dir(&dir)
+        .args(["graphql", "--listen", "127.0.0.1:0"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stderr = BufReader::new(child.stderr.take().unwrap());
+    let address = stderr
+        .lines()
+        .map(Result::unwrap)
+        .find_map(|line| Some(line.strip_prefix("serving GraphQL on http://")?.to_string()))
+        .unwrap();
+    let status = |body: String| {
+        let mut stream = TcpStream::connect(&address).unwrap();
+        let head = format!(
+            "POST /complete/batch HTTP/1.1\r\nHost: test\r\nConnection: close\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        );
+        let mut writer = stream.try_clone().unwrap();
+        // Written aside, as the server stops reading a body too big.
+        let written = thread::spawn(move || {
+            let _ = writer.write_all(head.as_bytes());
+            let _ = writer.write_all(body.as_bytes());
+        });
+        let mut reply = String::new();
+        let _ = stream.read_to_string(&mut reply);
+        let _ = written.join();
+        reply.lines().next().unwrap_or_default().to_string()
+    };
+    let big = format!("[\"{}\"]", "a".repeat(prefix_tree::graphql::MAX_BODY));
+    // Answered with 503 until the dictionary is loaded.
+    let mut small = status("[\"ap\"]".to_string());
+    while small.contains(" 503 ") {
+        thread::sleep(Duration::from_millis(50));
+        small = status("[\"ap\"]".to_string());
+    }
+    let big = status(big);
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(small.contains(" 200 "), "{}", small);
+    assert!(big.contains(" 413 "), "{}", big);
+}
+
+#[test]
+fn completes_nothing_below_min_prefix_in_resp() {
+    let dir = dictionary("resp_min_prefix", &["apple"]);
+    let (mut child, lines) = start_resp(&dir, &["--min-prefix", "2"]);
+    let reply = send(&lines, "PT.COMPLETE a\r\nPT.COMPLETE ap\r\nQUIT\r\n");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert_eq!(reply, "*0\r\n*1\r\n$5\r\napple\r\n+OK\r\n");
+}
+
+#[test]
+fn keeps_added_words_across_restarts_in_a_snapshot() {
+    let dir = dictionary("resp_snapshot", &["apple"]);
+    let _ = fs::remove_file(dir.join("words.snapshot"));
+    let (mut child, lines) = start_resp(&dir, &["--snapshot", "words.snapshot"]);
+    let reply = send(&lines, "PT.ADD apricot 3\r\nPT.SNAPSHOT\r\nQUIT\r\n");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert_eq!(reply, ":1\r\n:2\r\n+OK\r\n");
+
+    fs::write(dir.join("dictionary.txt"), "banana\n").unwrap();
+    let (mut child, lines) = start_resp(&dir, &["--snapshot", "words.snapshot"]);
+    assert_eq!(lines[0], "restored 2 words from words.snapshot");
+    let reply = send(
+        &lines,
+        "PT.COMPLETE a\r\nPT.DEL apple\r\nPT.RESTORE\r\nPT.EXISTS apple\r\nQUIT\r\n",
+    );
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert_eq!(
+        reply,
+        "*2\r\n$7\r\napricot\r\n$5\r\napple\r\n:1\r\n:2\r\n:1\r\n+OK\r\n"
+    );
+
+    let (mut child, lines) = start_resp(&dir, &[]);
+    let reply = send(&lines, "PT.SNAPSHOT\r\nQUIT\r\n");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert_eq!(reply, "-ERR the server has no --snapshot file\r\n+OK\r\n");
+}
@@ -0,0 +1,86 @@
+//! Words of a million keys, which any walk taking a stack frame per key
+//! would overflow the test thread's stack with.
+
+use prefix_tree::{
+    dot::{self, DotStyle},
+    Entry, Trie, TrieBuilder,
+};
+
+const LEN: usize = 1_000_000;
+
+fn long_word() -> String {
+    "a".repeat(LEN)
+}
+
+fn trie() -> Trie {
+    [long_word().as_str(), "ab"].into_iter().collect()
+}
+
+fn lengths(words: impl Iterator<Item = String>) -> Vec<usize> {
+    words.map(|word| word.len()).collect()
+}
+
+#[test]
+fn builds() {
+    let long = long_word();
+    let mut builder = TrieBuilder::<char>::new();
+    // Out of order, so the second copy is merged into the first.
+    for line in [long.as_str(), "ab", long.as_str()] {
+        builder.push(&Entry::parse(line));
+    }
+    let trie = builder.build();
+    assert_eq!(trie.len(), 2);
+    assert!(trie.contains(&long));
+    assert_eq!(trie.count_prefix("a"), 2);
+}
+
+#[test]
+fn iterates() {
+    let trie = trie();
+    assert_eq!(lengths(trie.keys()), [LEN, 2]);
+    assert_eq!(lengths(trie.iter_bfs().map(|(word, _)| word)), [2, LEN]);
+    assert_eq!(trie.longest_word("").map(|word| word.len()), Some(LEN));
+}
+
+#[test]
+fn removes() {
+    let mut trie = trie();
+    assert!(trie.remove(&long_word()).is_some());
+    assert_eq!(trie.keys().collect::<Vec<_>>(), ["ab"]);
+    assert_eq!(trie.count_prefix("aa"), 0);
+}
+
+#[test]
+fn retains() {
+    let mut trie = trie();
+    trie.retain(|word, _| word.len() == LEN);
+    assert_eq!(lengths(trie.keys()), [LEN]);
+    trie.retain(|_, _| false);
+    assert!(trie.is_empty());
+}
+
+#[test]
+fn clones() {
+    let trie = trie();
+    let copy = trie.clone();
+    drop(trie);
+    assert_eq!(copy.len(), 2);
+    assert!(copy.contains(&long_word()));
+}
+
+#[test]
+fn draws_dot() {
+    // Named after their paths, the nodes of the whole word would take the
+    // square of its length to write; merged, its tail is one node.
+    let style = DotStyle {
+        merge_chains: true,
+        ..DotStyle::default()
+    };
+    let mut out = vec![];
+    dot::write_dot_with(&mut out, &trie(), &style).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    // The root, "a", the rest of the long word and "ab", with the edges into
+    // the last three, between the braces.
+    assert_eq!(out.lines().count(), 1 + 4 + 3 + 1);
+    assert!(out.contains(&format!("label=\"{}\"", "a".repeat(LEN - 1))));
+}